@@ -1,2 +1,3 @@
 mkdir output
-cargo run --release -- --fresh -vv vrps -o ./output/ROA.csv -f csv > >(tee ./output/ROV.json) 2> >(tee ./output/ROV.err >&2)
\ No newline at end of file
+cargo run --release -- --fresh -vv vrps -o ./output/ROA.csv -f csv 2> >(tee ./output/ROV.err >&2)
+cargo run --release -- -vv tree -n -o ./output/ROV.json 2> >(tee -a ./output/ROV.err >&2)
\ No newline at end of file
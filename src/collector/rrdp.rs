@@ -19,7 +19,7 @@
 //! if this file is not found before an update is started, the repository is
 //! considered not present even if there are actually files.
 
-use std::{cmp, error, fmt, fs, io};
+use std::{cmp, error, fmt, fs, io, slice};
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{Seek, SeekFrom, Write};
@@ -74,6 +74,9 @@ pub struct Collector {
     /// The maximum allowed size for published objects.
     max_object_size: Option<u64>,
 
+    /// The maximum allowed size of a repository’s objects taken together.
+    max_repository_size: Option<u64>,
+
     /// The maximum number of deltas we process before using a snapshot.
     max_delta_count: usize,
 }
@@ -123,6 +126,7 @@ impl Collector {
             filter_dubious: !config.allow_dubious_hosts,
             fallback_time: FallbackTime::from_config(config),
             max_object_size: config.max_object_size,
+            max_repository_size: config.max_repository_size,
             max_delta_count: config.rrdp_max_delta_count,
         }))
     }
@@ -753,6 +757,16 @@ impl Repository {
             return Ok(Some(SnapshotReason::TooManyDeltas))
         }
 
+        // We only keep track of the size if there is a limit. If the last
+        // update didn’t, we have to determine it once.
+        let mut size = match run.collector.max_repository_size {
+            Some(_) => match state.size {
+                Some(size) => Some(size),
+                None => Some(fatal::dir_size(&self.object_base())?),
+            }
+            None => None,
+        };
+
         if !deltas.is_empty() {
             let count = deltas.len();
             for (i, info) in deltas.iter().enumerate() {
@@ -762,7 +776,7 @@ impl Repository {
                 );
                 if let Some(reason) = DeltaUpdate::new(
                     run.collector, self, notify.content.session_id(),
-                    info, metrics
+                    info, metrics, &mut size,
                 ).try_update()? {
                     info!(
                         "RRDP {}: Delta update failed, \
@@ -780,7 +794,8 @@ impl Repository {
         RepositoryState::from_notify(
             self.rpki_notify.clone(),
             notify,
-            run.collector.fallback_time
+            run.collector.fallback_time,
+            size,
         ).write(self)?;
 
         debug!("RRDP {}: Delta update completed.", self.rpki_notify);
@@ -865,6 +880,11 @@ struct SnapshotUpdate<'a> {
 
     /// The metrics for the update.
     metrics: &'a mut RrdpRepositoryMetrics,
+
+    /// The size of all objects published so far.
+    ///
+    /// This is only tracked if there is a size limit.
+    size: Option<u64>,
 }
 
 impl<'a> SnapshotUpdate<'a> {
@@ -874,7 +894,10 @@ impl<'a> SnapshotUpdate<'a> {
         notify: &'a Notification,
         metrics: &'a mut RrdpRepositoryMetrics,
     ) -> Self {
-        SnapshotUpdate { collector, repository, notify, metrics }
+        SnapshotUpdate {
+            collector, repository, notify, metrics,
+            size: collector.max_repository_size.map(|_| 0),
+        }
     }
     
     pub fn try_update(mut self) -> Result<(), SnapshotError> {
@@ -939,7 +962,8 @@ impl<'a> SnapshotUpdate<'a> {
                 RepositoryState::from_notify(
                     self.repository.rpki_notify.clone(),
                     self.notify,
-                    self.collector.fallback_time
+                    self.collector.fallback_time,
+                    self.size,
                 ).write(self.repository)?;
 
                 Ok(())
@@ -1015,7 +1039,17 @@ impl<'a> ProcessSnapshot for SnapshotUpdate<'a> {
                     SnapshotError::Fatal
                 }
             }
-        })
+        })?;
+        if let (Some(max_size), Some(size)) = (
+            self.collector.max_repository_size, self.size.as_mut()
+        ) {
+            *size = size.saturating_add(RepositoryObject::size(&path)?);
+            if *size > max_size {
+                self.metrics.oversized = true;
+                return Err(SnapshotError::LargeRepository)
+            }
+        }
+        Ok(())
     }
 }
 
@@ -1049,6 +1083,11 @@ struct DeltaUpdate<'a> {
 
     /// The URIs of objects to be deleted.
     withdraw: HashSet<uri::Rsync>,
+
+    /// The size of the repository’s objects after applying the delta.
+    ///
+    /// This is only tracked if there is a size limit.
+    size: &'a mut Option<u64>,
 }
 
 impl<'a> DeltaUpdate<'a> {
@@ -1059,10 +1098,12 @@ impl<'a> DeltaUpdate<'a> {
         session_id: Uuid,
         info: &'a DeltaInfo,
         metrics: &'a mut RrdpRepositoryMetrics,
+        size: &'a mut Option<u64>,
     ) -> Self {
         DeltaUpdate {
             collector, repository, session_id, info, metrics,
             publish: Default::default(), withdraw: Default::default(),
+            size,
         }
     }
 
@@ -1130,7 +1171,8 @@ impl<'a> DeltaUpdate<'a> {
             self.repository.rpki_notify.clone(),
             self.session_id,
             self.info.serial(),
-            self.collector.fallback_time
+            self.collector.fallback_time,
+            *self.size,
         ).write(self.repository)?;
         Ok(())
     }
@@ -1263,6 +1305,19 @@ impl<'a> ProcessDelta for DeltaUpdate<'a> {
                 }
             }
         })?;
+        if let (Some(max_size), Some(size)) = (
+            self.collector.max_repository_size, self.size.as_mut()
+        ) {
+            *size = size.saturating_add(
+                RepositoryObject::size(&path)?
+            ).saturating_sub(
+                RepositoryObject::size(&self.repository.object_path(&uri))?
+            );
+            if *size > max_size {
+                self.metrics.oversized = true;
+                return Err(DeltaError::LargeRepository)
+            }
+        }
         if !self.publish.insert(uri.clone()) {
             return Err(DeltaError::ObjectRepeated { uri })
         }
@@ -1275,6 +1330,11 @@ impl<'a> ProcessDelta for DeltaUpdate<'a> {
         hash: rrdp::Hash
     ) -> Result<(), Self::Err> {
         self.check_hash(&uri, Some(hash))?;
+        if let Some(size) = self.size.as_mut() {
+            *size = size.saturating_sub(
+                RepositoryObject::size(&self.repository.object_path(&uri))?
+            );
+        }
         if !self.withdraw.insert(uri.clone()) {
             return Err(DeltaError::ObjectRepeated { uri })
         }
@@ -1750,6 +1810,12 @@ struct RepositoryState {
     /// This is the complete tag including the quotation marks and possibly
     /// the weak prefix.
     pub etag: Option<Bytes>,
+
+    /// The combined size of the repository’s objects in bytes.
+    ///
+    /// This is only known if it was tracked during the last update because
+    /// of a size limit.
+    pub size: Option<u64>,
 }
 
 impl RepositoryState {
@@ -1759,6 +1825,7 @@ impl RepositoryState {
         session: Uuid,
         serial: u64,
         fallback: FallbackTime,
+        size: Option<u64>,
     ) -> Self {
         RepositoryState {
             rpki_notify,
@@ -1767,7 +1834,8 @@ impl RepositoryState {
             updated_ts: Utc::now().timestamp(),
             best_before_ts: fallback.best_before().timestamp(),
             last_modified_ts: None,
-            etag: None
+            etag: None,
+            size,
         }
     }
 
@@ -1776,6 +1844,7 @@ impl RepositoryState {
         rpki_notify: uri::Https,
         notify: &Notification,
         fallback: FallbackTime,
+        size: Option<u64>,
     ) -> Self {
         RepositoryState {
             rpki_notify,
@@ -1785,6 +1854,7 @@ impl RepositoryState {
             best_before_ts: fallback.best_before().timestamp(),
             last_modified_ts: notify.last_modified.map(|x| x.timestamp()),
             etag: notify.etag.clone(),
+            size,
         }
     }
 
@@ -1876,9 +1946,33 @@ impl RepositoryState {
             best_before_ts: Parse::parse(reader)?,
             last_modified_ts: Parse::parse(reader)?,
             etag: Parse::parse(reader)?,
+            size: Self::read_size(reader)?,
         })
     }
 
+    /// Reads the size from an IO reader.
+    ///
+    /// The size was added later. It is encoded like an option at the end
+    /// of the state since older versions ignore any trailing data. If it
+    /// is missing, the state was written by such an older version.
+    fn read_size(
+        reader: &mut impl io::Read
+    ) -> Result<Option<u64>, io::Error> {
+        let mut marker = 0u8;
+        if reader.read(slice::from_mut(&mut marker))? == 0 {
+            return Ok(None)
+        }
+        match marker {
+            0 => Ok(None),
+            1 => Ok(Some(Parse::parse(reader)?)),
+            _ => {
+                Err(io::Error::new(
+                    io::ErrorKind::Other, "illegally encoded size"
+                ))
+            }
+        }
+    }
+
     /// Writes the state file of a repository.
     pub fn write(&self, repo: &Repository) -> Result<(), Failed> {
         let path = Self::file_path(repo);
@@ -1913,6 +2007,13 @@ impl RepositoryState {
         self.best_before_ts.compose(writer)?;
         self.last_modified_ts.compose(writer)?;
         self.etag.compose(writer)?;
+        match self.size {
+            Some(size) => {
+                1u8.compose(writer)?;
+                size.compose(writer)?;
+            }
+            None => 0u8.compose(writer)?,
+        }
         Ok(())
     }
 
@@ -1965,6 +2066,20 @@ impl RepositoryObject {
         })
     }
 
+    /// Returns the size of a stored object.
+    ///
+    /// Returns zero if the object doesn’t exist.
+    pub fn size(path: &Path) -> Result<u64, Failed> {
+        match fs::metadata(path) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(err) => {
+                error!("Fatal: failed to open {}: {}", path.display(), err);
+                Err(Failed)
+            }
+        }
+    }
+
     /// Opens the file for a repository object.
     fn open(path: &Path) -> Result<Option<File>, Failed> {
         match File::open(path) {
@@ -2377,6 +2492,7 @@ enum SnapshotError {
     },
     HashMismatch,
     LargeObject(uri::Rsync),
+    LargeRepository,
     Fatal,
 }
 
@@ -2453,6 +2569,9 @@ impl fmt::Display for SnapshotError {
             SnapshotError::LargeObject(ref uri) => {
                 write!(f, "object exceeds size limit: {}", uri)
             }
+            SnapshotError::LargeRepository => {
+                write!(f, "repository exceeds size limit")
+            }
             SnapshotError::Fatal => Ok(())
         }
     }
@@ -2495,6 +2614,7 @@ enum DeltaError {
     },
     DeltaHashMismatch,
     LargeObject(uri::Rsync),
+    LargeRepository,
     Fatal,
 }
 
@@ -2592,6 +2712,9 @@ impl fmt::Display for DeltaError {
             DeltaError::LargeObject(ref uri) => {
                 write!(f, "object exceeds size limit: {}", uri)
             }
+            DeltaError::LargeRepository => {
+                write!(f, "repository exceeds size limit")
+            }
             DeltaError::DeltaHashMismatch => {
                 write!(f, "delta file hash value mismatch")
             }
@@ -2623,7 +2746,8 @@ mod test {
             updated_ts: 28,
             best_before_ts: 892,
             last_modified_ts: Some(23),
-            etag: Some(Bytes::copy_from_slice(b"23890"))
+            etag: Some(Bytes::copy_from_slice(b"23890")),
+            size: Some(1234),
         };
         let mut written = Vec::new();
         orig._write(&mut written).unwrap();
@@ -2632,5 +2756,28 @@ mod test {
         assert!(slice.is_empty());
         assert_eq!(orig, decoded);
     }
+
+    #[test]
+    fn read_repository_state_without_size() {
+        let orig = RepositoryState {
+            rpki_notify: uri::Https::from_str(
+                "https://foo.bar/bla/blubb"
+            ).unwrap(),
+            session: Uuid::nil(),
+            serial: 12,
+            updated_ts: 28,
+            best_before_ts: 892,
+            last_modified_ts: None,
+            etag: None,
+            size: None,
+        };
+        let mut written = Vec::new();
+        orig._write(&mut written).unwrap();
+
+        // Drop the size marker to get what older versions wrote.
+        written.pop();
+        let decoded = RepositoryState::_read(&mut written.as_slice()).unwrap();
+        assert_eq!(orig, decoded);
+    }
 }
 
@@ -51,6 +51,9 @@ pub struct Collector {
 
     /// Whether to filter dubious authorities in rsync URIs.
     filter_dubious: bool,
+
+    /// The maximum size of a module in bytes.
+    max_repository_size: Option<u64>,
 }
  
 
@@ -102,7 +105,8 @@ impl Collector {
                     Self::create_working_dir(config)?
                 ),
                 command: Some(RsyncCommand::new(config)?),
-                filter_dubious: !config.allow_dubious_hosts
+                filter_dubious: !config.allow_dubious_hosts,
+                max_repository_size: config.max_repository_size,
             }))
         }
     }
@@ -281,11 +285,22 @@ impl<'a> Run<'a> {
         }
         else {
             // Run the actual update.
-            let metrics = command.update(
-                module.as_ref(),
-                &self.collector.working_dir.module_path(module.as_ref())
+            let path = self.collector.working_dir.module_path(
+                module.as_ref()
+            );
+            let mut metrics = command.update(
+                module.as_ref(), &path, self.collector.max_repository_size
             );
 
+            // Drop the module if it has grown too large. If the transfer
+            // was stopped because of the limit, this removes the partial
+            // update, too.
+            if let Some(max_size) = self.collector.max_repository_size {
+                metrics.oversized = Self::remove_oversized(
+                    module.as_ref(), &path, max_size
+                );
+            }
+
             // Insert into updated map and metrics.
             self.metrics.lock().push(metrics);
         }
@@ -297,6 +312,27 @@ impl<'a> Run<'a> {
         self.updated.write().insert(module.into_owned());
     }
 
+    /// Removes a module if its local copy exceeds the size limit.
+    ///
+    /// Returns whether the module was too large. If so, all its objects
+    /// will be missing from the local copy and its publication points
+    /// will fall back to the data in the store.
+    fn remove_oversized(module: &Module, path: &Path, max_size: u64) -> bool {
+        let size = match fatal::dir_size(path) {
+            Ok(size) => size,
+            Err(_) => return false,
+        };
+        if size <= max_size {
+            return false
+        }
+        warn!(
+            "{}: module size of {} bytes exceeds limit. Removing.",
+            module, size
+        );
+        let _ = fatal::remove_dir_all(path);
+        true
+    }
+
     /// Loads the file for the given URI.
     ///
     /// Does _not_ attempt to update the corresponding module first. You need
@@ -421,6 +457,9 @@ impl<'a> Run<'a> {
 
 //------------ RsyncCommand --------------------------------------------------
 
+/// How often to check the size of a module while rsync is running.
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 /// The command to run rsync.
 #[derive(Debug)]
 struct RsyncCommand {
@@ -481,19 +520,24 @@ impl RsyncCommand {
     }
 
     /// Updates a module by running rsync.
+    ///
+    /// If `max_size` is given, the transfer is stopped once the size of
+    /// the local copy exceeds it.
     pub fn update(
         &self,
         source: &Module,
-        destination: &Path
+        destination: &Path,
+        max_size: Option<u64>,
     ) -> RsyncModuleMetrics {
         let start = SystemTime::now();
         let status = self.command(
             source, destination
-        ).and_then(|cmd| self.run(source, cmd));
+        ).and_then(|cmd| self.run(source, cmd, destination, max_size));
         RsyncModuleMetrics {
             module: source.to_uri(),
             status,
             duration: SystemTime::now().duration_since(start),
+            oversized: false,
         }
     }
 
@@ -501,7 +545,9 @@ impl RsyncCommand {
     fn run(
         &self,
         source: &Module,
-        mut command: AsyncCommand
+        mut command: AsyncCommand,
+        destination: &Path,
+        max_size: Option<u64>,
     ) -> Result<ExitStatus, io::Error> {
         // Because we can’t have a timeout on a child process with just std,
         // we resort to Tokio here: We fire up a current-thread runtime and
@@ -521,20 +567,28 @@ impl RsyncCommand {
             let stderr_pipe = child.stderr.take();
             let mut stdout = Vec::new();
             let mut stderr = Vec::new();
+            let wait = match self.timeout {
+                None => Either::Left(child.wait().map(Ok)),
+                Some(timeout) => {
+                    Either::Right(
+                        tokio::time::timeout(
+                            timeout, child.wait()
+                        ).map_err(|_| {
+                            io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                "rsync process reached time out"
+                            )
+                        })
+                    )
+                }
+            };
             let res = tokio::try_join!(
-                match self.timeout {
-                    None => Either::Left(child.wait().map(Ok)),
-                    Some(timeout) => {
-                        Either::Right(
-                            tokio::time::timeout(
-                                timeout, child.wait()
-                            ).map_err(|_| {
-                                io::Error::new(
-                                    io::ErrorKind::TimedOut,
-                                    "rsync process reached time out"
-                                )
-                            })
-                        )
+                async {
+                    tokio::select! {
+                        res = wait => res,
+                        err = Self::watch_size(destination, max_size) => {
+                            Err(err)
+                        }
                     }
                 },
                 async {
@@ -586,6 +640,28 @@ impl RsyncCommand {
         })
     }
 
+    /// Watches the size of the local copy during a transfer.
+    ///
+    /// Resolves into an error once the size exceeds `max_size`. If there
+    /// is no limit, never resolves.
+    async fn watch_size(path: &Path, max_size: Option<u64>) -> io::Error {
+        let max_size = match max_size {
+            Some(max_size) => max_size,
+            None => return futures::future::pending().await,
+        };
+        loop {
+            tokio::time::sleep(SIZE_CHECK_INTERVAL).await;
+            if let Ok(size) = fatal::dir_size(path) {
+                if size > max_size {
+                    return io::Error::new(
+                        io::ErrorKind::Other,
+                        "module exceeds size limit"
+                    )
+                }
+            }
+        }
+    }
+
     /// Creates the rsync command.
     fn command(
         &self,
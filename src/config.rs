@@ -86,6 +86,18 @@ const DEFAULT_MAX_OBJECT_SIZE: u64 = 20_000_000;
 /// The default maximum CA depth.
 const DEFAULT_MAX_CA_DEPTH: usize = 32;
 
+/// The default maximum number of entries in a manifest.
+const DEFAULT_MAX_MANIFEST_ENTRIES: usize = 100_000;
+
+/// The default maximum number of child CAs of a CA.
+const DEFAULT_MAX_CHILD_CAS: usize = 100_000;
+
+/// The default maximum number of repositories per TAL.
+const DEFAULT_MAX_TAL_REPOSITORIES: usize = 5_000;
+
+/// The default maximum size of a repository.
+const DEFAULT_MAX_REPOSITORY_SIZE: u64 = 10_000_000_000;
+
 /// The default syslog facility.
 #[cfg(unix)]
 const DEFAULT_SYSLOG_FACILITY: Facility = Facility::LOG_DAEMON;
@@ -247,6 +259,18 @@ pub struct Config {
     /// Maxium length of the CA chain.
     pub max_ca_depth: usize,
 
+    /// Optional limit for the number of entries in a manifest.
+    pub max_manifest_entries: Option<usize>,
+
+    /// Optional limit for the number of child CAs of a CA.
+    pub max_child_cas: Option<usize>,
+
+    /// Optional limit for the number of repositories used by a TAL.
+    pub max_tal_repositories: Option<usize>,
+
+    /// Optional limit for the size of an RRDP repository or rsync module.
+    pub max_repository_size: Option<u64>,
+
     /// Whether to process BGPsec router keys.
     pub enable_bgpsec: bool,
 
@@ -261,6 +285,13 @@ pub struct Config {
     /// deleted.
     pub dirty_repository: bool,
 
+    /// Whether to keep using stored trust anchor certificates.
+    ///
+    /// If this is `true`, a downloaded trust anchor certificate that differs
+    /// from the stored one in its key or resources is not used until it has
+    /// been accepted explicitly.
+    pub pin_ta_certs: bool,
+
     /// Number of threads used during validation.
     pub validation_threads: usize,
 
@@ -311,6 +342,19 @@ pub struct Config {
     /// Path to the HTTP TLS server certificate.
     pub http_tls_cert: Option<PathBuf>,
 
+    /// Should we serve the tree of validated objects via HTTP?
+    pub http_tree: bool,
+
+    /// Path to a file to append tree changes to.
+    pub tree_changes_file: Option<PathBuf>,
+
+    /// Whether to not keep validated publication points between runs.
+    ///
+    /// If this is `false`, the server keeps the validated objects of each
+    /// publication point in memory and reuses them in the next run if the
+    /// point has not changed.
+    pub disable_validation_cache: bool,
+
     /// The log levels to be logged.
     pub log_level: LevelFilter,
 
@@ -334,6 +378,9 @@ pub struct Config {
 
     /// A mapping of TAL file names to TAL labels.
     pub tal_labels: HashMap<String, String>,
+
+    /// A mapping of TAL names to the paths of their constraints files.
+    pub tal_constraints: HashMap<String, PathBuf>,
 }
 
 
@@ -590,6 +637,46 @@ impl Config {
             self.max_ca_depth = value;
         }
 
+        // max_manifest_entries
+        if let Some(value) = args.max_manifest_entries {
+            if value == 0 {
+                self.max_manifest_entries = None
+            }
+            else {
+                self.max_manifest_entries = Some(value)
+            }
+        }
+
+        // max_child_cas
+        if let Some(value) = args.max_child_cas {
+            if value == 0 {
+                self.max_child_cas = None
+            }
+            else {
+                self.max_child_cas = Some(value)
+            }
+        }
+
+        // max_tal_repositories
+        if let Some(value) = args.max_tal_repositories {
+            if value == 0 {
+                self.max_tal_repositories = None
+            }
+            else {
+                self.max_tal_repositories = Some(value)
+            }
+        }
+
+        // max_repository_size
+        if let Some(value) = args.max_repository_size {
+            if value == 0 {
+                self.max_repository_size = None
+            }
+            else {
+                self.max_repository_size = Some(value)
+            }
+        }
+
         // enable_bgpsec
         if args.enable_bgpsec {
             self.enable_bgpsec = true
@@ -606,6 +693,11 @@ impl Config {
             self.dirty_repository = true
         }
 
+        // pin_ta_certs
+        if args.pin_ta_certs {
+            self.pin_ta_certs = true
+        }
+
         // validation_threads
         if let Some(value) = args.validation_threads {
             self.validation_threads = value
@@ -780,6 +872,21 @@ impl Config {
             self.http_tls_cert = Some(cur_dir.join(path))
         }
 
+        // http_tree
+        if args.http_tree {
+            self.http_tree = true
+        }
+
+        // tree_changes_file
+        if let Some(path) = args.tree_changes_file {
+            self.tree_changes_file = Some(cur_dir.join(path))
+        }
+
+        // disable_validation_cache
+        if args.disable_validation_cache {
+            self.disable_validation_cache = true
+        }
+
         // pid_file
         if let Some(pid_file) = args.pid_file {
             self.pid_file = Some(cur_dir.join(pid_file))
@@ -945,6 +1052,34 @@ impl Config {
                 file.take_usize("max-ca-depth")?
                     .unwrap_or(DEFAULT_MAX_CA_DEPTH)
             },
+            max_manifest_entries: {
+                match file.take_usize("max-manifest-entries")? {
+                    Some(0) => None,
+                    Some(value) => Some(value),
+                    None => Some(DEFAULT_MAX_MANIFEST_ENTRIES),
+                }
+            },
+            max_child_cas: {
+                match file.take_usize("max-child-cas")? {
+                    Some(0) => None,
+                    Some(value) => Some(value),
+                    None => Some(DEFAULT_MAX_CHILD_CAS),
+                }
+            },
+            max_tal_repositories: {
+                match file.take_usize("max-tal-repositories")? {
+                    Some(0) => None,
+                    Some(value) => Some(value),
+                    None => Some(DEFAULT_MAX_TAL_REPOSITORIES),
+                }
+            },
+            max_repository_size: {
+                match file.take_u64("max-repository-size")? {
+                    Some(0) => None,
+                    Some(value) => Some(value),
+                    None => Some(DEFAULT_MAX_REPOSITORY_SIZE),
+                }
+            },
             enable_bgpsec: file.take_bool("enable-bgpsec")?.unwrap_or(false),
 
             #[cfg(feature = "aspa")]
@@ -954,6 +1089,9 @@ impl Config {
             enable_aspa: false,
 
             dirty_repository: file.take_bool("dirty")?.unwrap_or(false),
+            pin_ta_certs: {
+                file.take_bool("pin-ta-certs")?.unwrap_or(false)
+            },
             validation_threads: {
                 file.take_small_usize("validation-threads")?
                     .unwrap_or_else(::num_cpus::get)
@@ -1006,6 +1144,11 @@ impl Config {
             rtr_tls_cert: file.take_path("rtr-tls-cert")?,
             http_tls_key: file.take_path("http-tls-key")?,
             http_tls_cert: file.take_path("http-tls-cert")?,
+            http_tree: file.take_bool("http-tree")?.unwrap_or(false),
+            tree_changes_file: file.take_path("tree-changes-file")?,
+            disable_validation_cache: {
+                file.take_bool("disable-validation-cache")?.unwrap_or(false)
+            },
             log_level: {
                 file.take_from_str("log-level")?.unwrap_or(LevelFilter::Warn)
             },
@@ -1016,6 +1159,9 @@ impl Config {
             user: file.take_string("user")?,
             group: file.take_string("group")?,
             tal_labels: file.take_string_map("tal-labels")?.unwrap_or_default(),
+            tal_constraints: {
+                file.take_path_map("tal-constraints")?.unwrap_or_default()
+            },
         };
 
         if file.take_path("tal-dir")?.is_some() {
@@ -1152,9 +1298,14 @@ impl Config {
             rrdp_keep_responses: None,
             max_object_size: Some(DEFAULT_MAX_OBJECT_SIZE),
             max_ca_depth: DEFAULT_MAX_CA_DEPTH,
+            max_manifest_entries: Some(DEFAULT_MAX_MANIFEST_ENTRIES),
+            max_child_cas: Some(DEFAULT_MAX_CHILD_CAS),
+            max_tal_repositories: Some(DEFAULT_MAX_TAL_REPOSITORIES),
+            max_repository_size: Some(DEFAULT_MAX_REPOSITORY_SIZE),
             enable_bgpsec: false,
             enable_aspa: false,
             dirty_repository: DEFAULT_DIRTY_REPOSITORY,
+            pin_ta_certs: false,
             validation_threads: ::num_cpus::get(),
             refresh: Duration::from_secs(DEFAULT_REFRESH),
             retry: Duration::from_secs(DEFAULT_RETRY),
@@ -1171,6 +1322,9 @@ impl Config {
             rtr_tls_cert: None,
             http_tls_key: None,
             http_tls_cert: None,
+            http_tree: false,
+            tree_changes_file: None,
+            disable_validation_cache: false,
             log_level: LevelFilter::Warn,
             log_target: LogTarget::default(),
             pid_file: None,
@@ -1179,6 +1333,7 @@ impl Config {
             user: None,
             group: None,
             tal_labels: HashMap::new(),
+            tal_constraints: HashMap::new(),
         }
     }
 
@@ -1370,10 +1525,27 @@ impl Config {
             self.max_object_size.unwrap_or(0),
         );
         insert_int(&mut res, "max-ca-depth", self.max_ca_depth);
+        insert_int(
+            &mut res, "max-manifest-entries",
+            self.max_manifest_entries.unwrap_or(0),
+        );
+        insert_int(
+            &mut res, "max-child-cas",
+            self.max_child_cas.unwrap_or(0),
+        );
+        insert_int(
+            &mut res, "max-tal-repositories",
+            self.max_tal_repositories.unwrap_or(0),
+        );
+        insert_int(
+            &mut res, "max-repository-size",
+            self.max_repository_size.unwrap_or(0),
+        );
         insert(&mut res, "enable-bgpsec", self.enable_bgpsec);
         #[cfg(feature = "aspa")]
         insert(&mut res, "enable-aspa", self.enable_aspa);
         insert(&mut res, "dirty", self.dirty_repository);
+        insert(&mut res, "pin-ta-certs", self.pin_ta_certs);
         insert_int(&mut res, "validation-threads", self.validation_threads);
         insert_int(&mut res, "refresh", self.refresh.as_secs());
         insert_int(&mut res, "retry", self.retry.as_secs());
@@ -1432,6 +1604,16 @@ impl Config {
         if let Some(ref path) = self.http_tls_cert {
             insert(&mut res, "http-tls-cert", path.display().to_string());
         }
+        insert(&mut res, "http-tree", self.http_tree);
+        if let Some(ref path) = self.tree_changes_file {
+            insert(
+                &mut res, "tree-changes-file", path.display().to_string()
+            );
+        }
+        insert(
+            &mut res, "disable-validation-cache",
+            self.disable_validation_cache
+        );
         insert(&mut res, "log-level", self.log_level.to_string());
         match self.log_target {
             #[cfg(unix)]
@@ -1484,6 +1666,19 @@ impl Config {
                 )
             );
         }
+        if !self.tal_constraints.is_empty() {
+            insert(
+                &mut res, "tal-constraints",
+                toml::Value::Array(
+                    self.tal_constraints.iter().map(|(left, right)| {
+                        toml::Value::Array([
+                            toml::Value::from(left.clone()),
+                            toml::Value::from(right.display().to_string()),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
+        }
         res
     }
 }
@@ -1818,6 +2013,22 @@ struct GlobalArgs {
     #[arg(long, value_name = "COUNT")]
     max_ca_depth: Option<usize>,
 
+    /// Maximum number of entries in a manifest (0 for no limit)
+    #[arg(long, value_name = "COUNT")]
+    max_manifest_entries: Option<usize>,
+
+    /// Maximum number of child CAs of a CA (0 for no limit)
+    #[arg(long, value_name = "COUNT")]
+    max_child_cas: Option<usize>,
+
+    /// Maximum number of repositories per TAL (0 for no limit)
+    #[arg(long, value_name = "COUNT")]
+    max_tal_repositories: Option<usize>,
+
+    /// Maximum size of a repository (0 for no limit)
+    #[arg(long, value_name = "BYTES")]
+    max_repository_size: Option<u64>,
+
     /// Include BGPsec router keys in the data set
     #[arg(long)]
     enable_bgpsec: bool,
@@ -1831,6 +2042,10 @@ struct GlobalArgs {
     #[arg(long)]
     dirty_repository: bool,
 
+    /// Keep using stored TA certificates until changes are accepted
+    #[arg(long)]
+    pin_ta_certs: bool,
+
     /// Number of threads for validation
     #[arg(long, value_name = "COUNT")]
     validation_threads: Option<usize>,
@@ -1924,6 +2139,18 @@ struct ServerArgs {
     #[arg(long, value_name = "PATH")]
     http_tls_cert: Option<PathBuf>,
 
+    /// Serve the tree of validated objects via HTTP
+    #[arg(long)]
+    http_tree: bool,
+
+    /// Append changes to the tree of validated objects to this file
+    #[arg(long, value_name = "PATH")]
+    tree_changes_file: Option<PathBuf>,
+
+    /// Do not keep validated publication points between runs
+    #[arg(long)]
+    disable_validation_cache: bool,
+
     /// The file for keep the daemon process's PID in
     #[arg(long, value_name = "PATH")]
     pid_file: Option<PathBuf>,
@@ -2486,6 +2713,21 @@ impl ConfigFile {
         }
     }
 
+    /// Takes a string-to-path hashmap from the config file.
+    ///
+    /// The paths are interpreted relative to the directory of the config
+    /// file.
+    fn take_path_map(
+        &mut self,
+        key: &str
+    ) -> Result<Option<HashMap<String, PathBuf>>, Failed> {
+        Ok(self.take_string_map(key)?.map(|map| {
+            map.into_iter().map(|(left, right)| {
+                (left, self.dir.join(right))
+            }).collect()
+        }))
+    }
+
     /// Checks whether the config file is now empty.
     ///
     /// If it isn’t, logs a complaint and returns an error.
@@ -2718,6 +2960,26 @@ mod test {
         assert_eq!(config.log_target, LogTarget::Syslog(Facility::LOG_AUTH));
     }
 
+    #[test]
+    fn resource_limit_args() {
+        let config = process_basic_args(&["routinator"]);
+        assert_eq!(
+            config.max_manifest_entries, Some(DEFAULT_MAX_MANIFEST_ENTRIES)
+        );
+        assert_eq!(
+            config.max_repository_size, Some(DEFAULT_MAX_REPOSITORY_SIZE)
+        );
+        let config = process_basic_args(&[
+            "routinator", "--max-manifest-entries", "0",
+            "--max-child-cas", "10", "--max-tal-repositories", "20",
+            "--max-repository-size", "0",
+        ]);
+        assert_eq!(config.max_manifest_entries, None);
+        assert_eq!(config.max_child_cas, Some(10));
+        assert_eq!(config.max_tal_repositories, Some(20));
+        assert_eq!(config.max_repository_size, None);
+    }
+
     #[test]
     fn verbosity() {
         let config = process_basic_args(&["routinator"]);
@@ -0,0 +1,309 @@
+//! Constraints on the resources a trust anchor may certify.
+//!
+//! A trust anchor certificate typically claims all resources. In order to
+//! limit the damage a misbehaving trust anchor can do, the resources it is
+//! allowed to certify can be restricted through a constraints file. Payload
+//! for resources outside of these constraints is then dropped.
+//!
+//! A constraints file is a simple text file. Each line contains either the
+//! keyword `allow` or `deny` followed by a resource. A resource is an IPv4
+//! or IPv6 prefix, an address range in the form `<min>-<max>` or a single
+//! address, an AS number or range of AS numbers in the form
+//! `AS<min>-AS<max>`, or the word `all` for all resources of all kinds.
+//! Empty lines and everything after a `#` are ignored.
+//!
+//! The resources a trust anchor may certify are those listed as allowed
+//! minus those listed as denied. Resources of a kind, i.e., IPv4 prefixes,
+//! IPv6 prefixes, or AS numbers, that aren’t listed as allowed are denied,
+//! so a file that only denies resources needs to start with `allow all`.
+//! Only a file without any resources at all allows everything.
+
+use std::{error, fmt, fs};
+use std::path::Path;
+use std::str::FromStr;
+use log::error;
+use rpki::repository::resources::{AsBlock, AsBlocks, IpBlock, IpBlocks};
+use rpki::resources::{Asn, Prefix};
+use crate::error::Failed;
+
+
+//------------ TalConstraints ------------------------------------------------
+
+/// The resources a trust anchor is allowed to certify.
+#[derive(Clone, Debug)]
+pub struct TalConstraints {
+    /// The allowed IPv4 addresses.
+    v4: IpBlocks,
+
+    /// The allowed IPv6 addresses.
+    v6: IpBlocks,
+
+    /// The allowed AS numbers.
+    asns: AsBlocks,
+}
+
+impl TalConstraints {
+    /// Loads the constraints from a file.
+    ///
+    /// Logs an error and fails if the file cannot be read or is invalid.
+    pub fn load(path: &Path) -> Result<Self, Failed> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                error!(
+                    "Failed to read TAL constraints file {}: {}",
+                    path.display(), err
+                );
+                return Err(Failed)
+            }
+        };
+        content.parse().map_err(|err| {
+            error!(
+                "Failed to load TAL constraints file {}: {}",
+                path.display(), err
+            );
+            Failed
+        })
+    }
+
+    /// Returns whether the given prefix is allowed in its entirety.
+    pub fn allows_prefix(&self, prefix: Prefix) -> bool {
+        let raw = rpki::repository::resources::Prefix::new(
+            prefix.addr(), prefix.len()
+        );
+        if prefix.is_v4() {
+            self.v4.contains_block(raw)
+        }
+        else {
+            self.v6.contains_block(raw)
+        }
+    }
+
+    /// Returns whether the given AS number is allowed.
+    pub fn allows_asn(&self, asn: Asn) -> bool {
+        self.asns.contains_asn(asn)
+    }
+
+    /// Returns the part of the given AS numbers that is allowed.
+    pub fn clip_asns(&self, asns: &AsBlocks) -> AsBlocks {
+        asns.intersection(&self.asns)
+    }
+}
+
+
+//--- FromStr
+
+impl FromStr for TalConstraints {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut allow = Resources::default();
+        let mut deny = Resources::default();
+        let mut empty = true;
+        for (idx, line) in s.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(pos) => &line[..pos],
+                None => line
+            };
+            let mut words = line.split_whitespace();
+            let target = match words.next() {
+                Some("allow") => &mut allow,
+                Some("deny") => &mut deny,
+                Some(_) => {
+                    return Err(ParseError::new(idx, "unknown keyword"))
+                }
+                None => continue,
+            };
+
+            // Allow for whitespace around the dash in ranges.
+            let value: String = words.collect();
+            if value.is_empty() {
+                return Err(ParseError::new(idx, "missing resource"))
+            }
+            target.push(&value).map_err(|msg| ParseError::new(idx, msg))?;
+            empty = false;
+        }
+        if empty {
+            allow.all = true;
+        }
+        Ok(TalConstraints {
+            v4: allow.v4().difference(&deny.v4()),
+            v6: allow.v6().difference(&deny.v6()),
+            asns: allow.asns().difference(&deny.asns()),
+        })
+    }
+}
+
+
+//------------ Resources -----------------------------------------------------
+
+/// The resources listed under one keyword in a constraints file.
+#[derive(Default)]
+struct Resources {
+    all: bool,
+    v4: Vec<IpBlock>,
+    v6: Vec<IpBlock>,
+    asns: Vec<AsBlock>,
+}
+
+impl Resources {
+    /// Adds a resource given in its string representation.
+    fn push(&mut self, value: &str) -> Result<(), &'static str> {
+        if value == "all" {
+            self.all = true;
+        }
+        else if value.contains(':') {
+            self.v6.push(
+                IpBlock::from_v6_str(value).map_err(|_| "invalid IPv6 block")?
+            );
+        }
+        else if value.contains('.') {
+            self.v4.push(
+                IpBlock::from_v4_str(value).map_err(|_| "invalid IPv4 block")?
+            );
+        }
+        else {
+            self.asns.push(
+                AsBlock::from_str(value).map_err(|_| "invalid AS block")?
+            );
+        }
+        Ok(())
+    }
+
+    fn v4(&self) -> IpBlocks {
+        if self.all {
+            IpBlocks::all()
+        }
+        else {
+            self.v4.iter().copied().collect()
+        }
+    }
+
+    fn v6(&self) -> IpBlocks {
+        if self.all {
+            IpBlocks::all()
+        }
+        else {
+            self.v6.iter().copied().collect()
+        }
+    }
+
+    fn asns(&self) -> AsBlocks {
+        if self.all {
+            AsBlocks::all()
+        }
+        else {
+            self.asns.iter().copied().collect()
+        }
+    }
+}
+
+
+//------------ ParseError ----------------------------------------------------
+
+/// A constraints file was invalid.
+#[derive(Clone, Copy, Debug)]
+pub struct ParseError {
+    /// The line number of the offending line.
+    line: usize,
+
+    /// What was wrong with the line.
+    msg: &'static str,
+}
+
+impl ParseError {
+    fn new(idx: usize, msg: &'static str) -> Self {
+        ParseError { line: idx + 1, msg }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl error::Error for ParseError { }
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn prefix(s: &str) -> Prefix {
+        Prefix::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn parse_and_check() {
+        let constraints = TalConstraints::from_str(
+            "# Only some resources.\n\
+             allow 10.0.0.0/8\n\
+             allow 2001:db8::/32  # documentation\n\
+             \n\
+             allow AS64496 - AS64511\n\
+             deny 10.1.0.0-10.1.255.255\n\
+             deny AS64500\n"
+        ).unwrap();
+        assert!(constraints.allows_prefix(prefix("10.0.0.0/16")));
+        assert!(!constraints.allows_prefix(prefix("10.1.0.0/24")));
+        assert!(!constraints.allows_prefix(prefix("10.0.0.0/8")));
+        assert!(!constraints.allows_prefix(prefix("192.0.2.0/24")));
+        assert!(constraints.allows_prefix(prefix("2001:db8:1::/48")));
+        assert!(!constraints.allows_prefix(prefix("2001:db9::/32")));
+        assert!(constraints.allows_asn(Asn::from_u32(64496)));
+        assert!(!constraints.allows_asn(Asn::from_u32(64500)));
+        assert!(!constraints.allows_asn(Asn::from_u32(65000)));
+        assert_eq!(
+            constraints.clip_asns(
+                &"AS64510-AS64520".parse::<AsBlock>().map(|block| {
+                    [block].into_iter().collect::<AsBlocks>()
+                }).unwrap()
+            ).asn_count(),
+            2
+        );
+    }
+
+    #[test]
+    fn missing_family_denies_all() {
+        let constraints = TalConstraints::from_str(
+            "allow 10.0.0.0/8\n"
+        ).unwrap();
+        assert!(constraints.allows_prefix(prefix("10.0.0.0/8")));
+        assert!(!constraints.allows_prefix(prefix("2001:db8::/32")));
+        assert!(!constraints.allows_asn(Asn::from_u32(64496)));
+
+        let constraints = TalConstraints::from_str(
+            "deny 192.0.2.0/24\n"
+        ).unwrap();
+        assert!(!constraints.allows_prefix(prefix("10.0.0.0/8")));
+        assert!(!constraints.allows_prefix(prefix("2001:db8::/32")));
+        assert!(!constraints.allows_asn(Asn::from_u32(64496)));
+    }
+
+    #[test]
+    fn allow_all() {
+        let constraints = TalConstraints::from_str(
+            "allow all\ndeny 192.0.2.0/24\n"
+        ).unwrap();
+        assert!(constraints.allows_prefix(prefix("10.0.0.0/8")));
+        assert!(!constraints.allows_prefix(prefix("192.0.2.128/25")));
+        assert!(constraints.allows_prefix(prefix("2001:db8::/32")));
+        assert!(constraints.allows_asn(Asn::from_u32(64496)));
+
+        let constraints = TalConstraints::from_str("# Nothing.\n").unwrap();
+        assert!(constraints.allows_prefix(prefix("10.0.0.0/8")));
+        assert!(constraints.allows_prefix(prefix("2001:db8::/32")));
+        assert!(constraints.allows_asn(Asn::from_u32(64496)));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(TalConstraints::from_str("permit 10.0.0.0/8").is_err());
+        assert!(TalConstraints::from_str("allow").is_err());
+        assert!(TalConstraints::from_str("allow 10.0.0.0/33").is_err());
+        assert!(TalConstraints::from_str("allow ASfoo").is_err());
+    }
+}
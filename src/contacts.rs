@@ -0,0 +1,330 @@
+//! Finding the contacts responsible for resources.
+//!
+//! CAs can publish contact information for their operators through
+//! Ghostbuster records. The payload snapshot keeps these as a list of
+//! [`Contact`]s together with the resources of the CA. This module allows
+//! to select the contacts for an IP prefix or AS number via a
+//! [`ContactQuery`] and to output the result as a [`ContactList`].
+
+use std::io;
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use log::error;
+use rpki::resources::{Asn, Prefix};
+use serde::Serialize;
+use crate::error::Failed;
+use crate::payload::{Contact, PayloadSnapshot, VCard};
+use crate::utils::date::format_iso_date;
+
+
+//------------ ContactQuery --------------------------------------------------
+
+/// The resources to find contacts for.
+///
+/// If neither a prefix nor an AS number is given, all contacts are
+/// selected. Otherwise, contacts are selected if their CA holds the
+/// prefix or the AS number.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ContactQuery {
+    /// The prefix to look for.
+    prefix: Option<Prefix>,
+
+    /// The AS number to look for.
+    asn: Option<Asn>,
+}
+
+impl ContactQuery {
+    /// Creates a new query from its parts.
+    pub fn new(prefix: Option<Prefix>, asn: Option<Asn>) -> Self {
+        ContactQuery { prefix, asn }
+    }
+
+    /// Creates the query from the query of an HTTP request.
+    ///
+    /// The query can contain the `prefix` and `asn` parameters, each at
+    /// most once.
+    pub fn from_query(query: Option<&str>) -> Result<Self, Failed> {
+        let mut res = ContactQuery::default();
+        for (key, value) in form_urlencoded::parse(
+            query.unwrap_or_default().as_ref()
+        ) {
+            if key == "prefix" && res.prefix.is_none() {
+                res.prefix = Some(
+                    Prefix::from_str(&value).map_err(|_| Failed)?
+                );
+            }
+            else if key == "asn" && res.asn.is_none() {
+                res.asn = Some(Asn::from_str(&value).map_err(|_| Failed)?);
+            }
+            else {
+                return Err(Failed)
+            }
+        }
+        Ok(res)
+    }
+
+    /// Returns whether the query selects the given contact.
+    fn matches(&self, contact: &Contact) -> bool {
+        if self.prefix.is_none() && self.asn.is_none() {
+            return true
+        }
+        if let Some(prefix) = self.prefix {
+            if contact.holds_prefix(prefix) {
+                return true
+            }
+        }
+        if let Some(asn) = self.asn {
+            if contact.holds_asn(asn) {
+                return true
+            }
+        }
+        false
+    }
+}
+
+
+//------------ ContactList ---------------------------------------------------
+
+/// The contacts selected by a query.
+///
+/// The contacts are ordered so that contacts of CAs further down the
+/// validation chain – i.e., those closest to the actual holder of the
+/// resources – come first.
+#[derive(Clone, Debug)]
+pub struct ContactList<'a> {
+    /// The selected contacts.
+    contacts: Vec<&'a Contact>,
+
+    /// The time the snapshot was created.
+    created: DateTime<Utc>,
+}
+
+impl<'a> ContactList<'a> {
+    /// Selects the contacts from a snapshot.
+    pub fn new(snapshot: &'a PayloadSnapshot, query: &ContactQuery) -> Self {
+        let mut contacts: Vec<_> = snapshot.contacts().iter().filter(
+            |contact| query.matches(contact)
+        ).collect();
+        contacts.sort_by(|left, right| {
+            right.chain_len().cmp(&left.chain_len())
+        });
+        ContactList {
+            contacts,
+            created: snapshot.created(),
+        }
+    }
+
+    /// Returns whether no contacts were selected.
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+
+    /// Writes the list as JSON.
+    ///
+    /// Contrary to text output, this includes the resources of each CA.
+    pub fn write_json(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer_pretty(
+            &mut *target,
+            &JsonList {
+                contacts: self.contacts.iter().map(|contact| {
+                    JsonContact::new(contact)
+                }).collect(),
+                generated_time: format_iso_date(self.created).to_string(),
+            }
+        )?;
+        target.write_all(b"\n")
+    }
+
+    /// Writes the list as human-readable text.
+    pub fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        let mut first = true;
+        for contact in &self.contacts {
+            if first {
+                first = false;
+            }
+            else {
+                writeln!(target)?;
+            }
+            let vcard = contact.vcard();
+            match (vcard.full_name.as_ref(), vcard.org.as_ref()) {
+                (Some(name), Some(org)) => {
+                    writeln!(target, "{} ({})", name, org)?
+                }
+                (Some(name), None) => writeln!(target, "{}", name)?,
+                (None, Some(org)) => writeln!(target, "{}", org)?,
+                (None, None) => writeln!(target, "(unnamed contact)")?,
+            }
+            for addr in &vcard.addresses {
+                writeln!(target, "  Address: {}", addr.replace('\n', ", "))?;
+            }
+            for phone in &vcard.phones {
+                writeln!(target, "  Phone:   {}", phone)?;
+            }
+            for email in &vcard.emails {
+                writeln!(target, "  Email:   {}", email)?;
+            }
+            writeln!(target, "  CA:      {}", contact.ca_uri())?;
+            writeln!(target, "  Record:  {}", contact.uri())?;
+            writeln!(target, "  TAL:     {}", contact.tal_name())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the list in the given format.
+    pub fn write(
+        &self, format: ContactsFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            ContactsFormat::Json => self.write_json(target),
+            ContactsFormat::Text => self.write_text(target),
+        }
+    }
+}
+
+
+//------------ JsonList and JsonContact --------------------------------------
+
+/// The JSON representation of a contact list.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonList<'a> {
+    contacts: Vec<JsonContact<'a>>,
+    generated_time: String,
+}
+
+/// The JSON representation of a single contact.
+#[derive(Serialize)]
+struct JsonContact<'a> {
+    tal: &'a str,
+    ca: String,
+    uri: &'a str,
+    resources: JsonResources,
+
+    #[serde(flatten)]
+    vcard: &'a VCard,
+}
+
+impl<'a> JsonContact<'a> {
+    fn new(contact: &'a Contact) -> Self {
+        JsonContact {
+            tal: contact.tal_name(),
+            ca: contact.ca_uri().to_string(),
+            uri: contact.uri().as_str(),
+            resources: JsonResources {
+                asn: contact.as_resources().iter().map(|block| {
+                    block.to_string()
+                }).collect(),
+                ipv4: contact.v4_resources().iter().map(|block| {
+                    block.display_v4().to_string()
+                }).collect(),
+                ipv6: contact.v6_resources().iter().map(|block| {
+                    block.display_v6().to_string()
+                }).collect(),
+            },
+            vcard: contact.vcard(),
+        }
+    }
+}
+
+/// The JSON representation of the resources of a CA.
+#[derive(Serialize)]
+struct JsonResources {
+    asn: Vec<String>,
+    ipv4: Vec<String>,
+    ipv6: Vec<String>,
+}
+
+
+//------------ ContactsFormat ------------------------------------------------
+
+/// The output format for contacts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContactsFormat {
+    /// A JSON object with a list of contacts and the resources of their CA.
+    Json,
+
+    /// Human-readable text.
+    Text,
+}
+
+impl ContactsFormat {
+    /// All known contacts format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("json", ContactsFormat::Json),
+        ("text", ContactsFormat::Text),
+    ];
+
+    /// The default contacts format name.
+    pub const DEFAULT_VALUE: &'static str = "text";
+}
+
+
+//--- FromStr
+
+impl FromStr for ContactsFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown contacts format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dump::testing::BuiltTree;
+
+    #[test]
+    fn query() {
+        let query = ContactQuery::from_query(
+            Some("prefix=192.0.2.0/24&asn=AS64496")
+        ).unwrap();
+        assert_eq!(
+            query.prefix, Some(Prefix::from_str("192.0.2.0/24").unwrap())
+        );
+        assert_eq!(query.asn, Some(Asn::from_u32(64496)));
+        assert!(ContactQuery::from_query(None).is_ok());
+        assert!(ContactQuery::from_query(Some("asn=1&asn=2")).is_err());
+        assert!(ContactQuery::from_query(Some("prefix=foo")).is_err());
+        assert!(ContactQuery::from_query(Some("foo=bar")).is_err());
+    }
+
+    #[test]
+    fn built_tree() {
+        let tree = BuiltTree::new();
+        let list = ContactList::new(
+            &tree.snapshot,
+            &ContactQuery::from_query(Some("prefix=10.0.0.0/24")).unwrap()
+        );
+        assert_eq!(list.contacts.len(), 1);
+        assert_eq!(
+            list.contacts[0].vcard().full_name.as_deref(), Some("CA Contact")
+        );
+        assert!(list.contacts[0].uri().ends_with("/contact.gbr"));
+
+        let list = ContactList::new(
+            &tree.snapshot,
+            &ContactQuery::from_query(Some("asn=AS64499")).unwrap()
+        );
+        assert_eq!(list.contacts.len(), 1);
+
+        let list = ContactList::new(
+            &tree.snapshot,
+            &ContactQuery::from_query(Some("asn=AS64500")).unwrap()
+        );
+        assert!(list.is_empty());
+    }
+}
@@ -0,0 +1,528 @@
+//! Auditing the resources of a tree dump.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+use log::error;
+use rpki::crypto::keys::KeyIdentifier;
+use rpki::repository::resources::{
+    Addr, AsBlock, AsBlocks, AsBlocksBuilder, Asn, IpBlocks, IpBlocksBuilder,
+};
+use serde::Serialize;
+use crate::engine::ObjectKind;
+use crate::error::Failed;
+use super::{CaCertDump, DBDump, ROADump};
+
+
+//------------ TreeAudit -----------------------------------------------------
+
+/// The result of auditing the resources of a tree dump.
+///
+/// The audit checks every CA certificate, ROA, router certificate, and
+/// ASPA object against the resources of the CA certificate that issued it
+/// and reports any resources claimed beyond those. Objects issued directly
+/// by a trust anchor are not checked since the resources of trust anchor
+/// certificates are not part of the tree. Objects whose issuing CA is
+/// missing from the tree are reported as orphans.
+///
+/// In addition, the audit lists all CA certificates that inherit resources
+/// from their issuer together with the CA the resources actually come
+/// from, and all the resources of each CA that are neither used by one of
+/// its child CAs nor by any object it issued.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TreeAudit {
+    /// Objects claiming resources their issuer doesn’t hold.
+    over_claims: Vec<ResourceReport>,
+
+    /// Objects whose issuer is missing from the tree.
+    orphans: Vec<Orphan>,
+
+    /// CA certificates inheriting resources from their issuer.
+    inherit_chains: Vec<InheritChain>,
+
+    /// CA certificates with resources that are not used by anything.
+    unused: Vec<ResourceReport>,
+}
+
+impl TreeAudit {
+    /// Audits the given tree.
+    pub fn new(tree: &DBDump) -> Self {
+        let mut res = Self::default();
+        let cas: HashMap<_, _> = tree.ca_certs.iter().map(|cert| {
+            (cert.id, cert)
+        }).collect();
+        let is_ta = |id: &KeyIdentifier| {
+            tree.tals.iter().any(|tal| tal.id == *id)
+        };
+
+        // The resources used by each CA so far.
+        let mut used: HashMap<KeyIdentifier, Resources> = HashMap::new();
+
+        // Check every object against its parent. This also collects the
+        // used resources.
+        let mut check = |
+            kind: ObjectKind, id: KeyIdentifier, uri: &str,
+            parent: KeyIdentifier, claimed: Resources,
+        | {
+            match cas.get(&parent) {
+                Some(ca) => {
+                    let excess = claimed.difference(&Resources::of_ca(ca));
+                    if !excess.is_empty() {
+                        res.over_claims.push(ResourceReport {
+                            kind, id, uri: uri.into(), parent, resources: excess
+                        });
+                    }
+                }
+                None => {
+                    if !is_ta(&parent) {
+                        res.orphans.push(Orphan {
+                            kind, id, uri: uri.into(), parent
+                        });
+                    }
+                }
+            }
+            used.entry(parent).or_default().add(&claimed);
+        };
+        for cert in &tree.ca_certs {
+            check(
+                ObjectKind::CaCert, cert.id, cert.uri.as_str(), cert.parent,
+                Resources::of_ca(cert)
+            );
+        }
+        for roa in &tree.roas {
+            check(
+                ObjectKind::Roa, roa.id, roa.uri.as_str(), roa.parent,
+                Resources::of_roa(roa)
+            );
+        }
+        for cert in &tree.router_certs {
+            check(
+                ObjectKind::RouterCert, cert.id, cert.uri.as_str(),
+                cert.parent,
+                Resources {
+                    as_resources: cert.as_resources.clone(),
+                    .. Default::default()
+                }
+            );
+        }
+        for aspa in &tree.aspas {
+            check(
+                ObjectKind::Aspa, aspa.id, aspa.uri.as_str(), aspa.parent,
+                Resources {
+                    as_resources: {
+                        let mut res = AsBlocksBuilder::new();
+                        res.push(AsBlock::from(Asn::from(aspa.customer_as)));
+                        res.finalize()
+                    },
+                    .. Default::default()
+                }
+            );
+        }
+
+        // Inherit chains and unused resources.
+        for cert in &tree.ca_certs {
+            for (family, inherited) in [
+                (Family::Ipv4, cert.v4_inherited),
+                (Family::Ipv6, cert.v6_inherited),
+                (Family::As, cert.as_inherited),
+            ] {
+                if inherited {
+                    res.inherit_chains.push(
+                        InheritChain::new(cert, family, &cas)
+                    );
+                }
+            }
+
+            let unused = match used.get(&cert.id) {
+                Some(used) => Resources::of_ca(cert).difference(used),
+                None => Resources::of_ca(cert),
+            };
+            if !unused.is_empty() {
+                res.unused.push(ResourceReport {
+                    kind: ObjectKind::CaCert,
+                    id: cert.id,
+                    uri: cert.uri.to_string(),
+                    parent: cert.parent,
+                    resources: unused,
+                });
+            }
+        }
+
+        res.over_claims.sort_by(|left, right| left.uri.cmp(&right.uri));
+        res.orphans.sort_by(|left, right| left.uri.cmp(&right.uri));
+        res.inherit_chains.sort_by(|left, right| {
+            (&left.uri, left.family).cmp(&(&right.uri, right.family))
+        });
+        res.unused.sort_by(|left, right| left.uri.cmp(&right.uri));
+        res
+    }
+
+    /// Returns whether any over-claims or orphans were found.
+    pub fn has_problems(&self) -> bool {
+        !self.over_claims.is_empty() || !self.orphans.is_empty()
+    }
+
+    /// Writes the audit as pretty-printed JSON to the given target.
+    pub fn write_json(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer_pretty(&mut *target, self)?;
+        target.write_all(b"\n")
+    }
+
+    /// Writes the audit as human-readable text to the given target.
+    pub fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(target, "Over-claims: {}", self.over_claims.len())?;
+        for item in &self.over_claims {
+            item.write_text(target)?;
+        }
+        writeln!(target, "Orphans: {}", self.orphans.len())?;
+        for item in &self.orphans {
+            writeln!(
+                target, "  {} {} (missing parent {})",
+                kind_name(item.kind), item.uri, item.parent
+            )?;
+        }
+        writeln!(target, "Inherit chains: {}", self.inherit_chains.len())?;
+        for item in &self.inherit_chains {
+            writeln!(
+                target, "  {} {} from {} over {} level{}",
+                item.uri, item.family.name(), item.source,
+                item.length, if item.length == 1 { "" } else { "s" }
+            )?;
+        }
+        writeln!(target, "Unused resources: {}", self.unused.len())?;
+        for item in &self.unused {
+            item.write_text(target)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the audit in the given format to the given target.
+    pub fn write(
+        &self, format: TreeAuditFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            TreeAuditFormat::Json => self.write_json(target),
+            TreeAuditFormat::Text => self.write_text(target),
+        }
+    }
+}
+
+
+//------------ ResourceReport ------------------------------------------------
+
+/// Resources of an object that are reported by the audit.
+#[derive(Clone, Debug, Serialize)]
+struct ResourceReport {
+    kind: ObjectKind,
+    id: KeyIdentifier,
+    uri: String,
+    parent: KeyIdentifier,
+
+    /// The reported resources.
+    #[serde(flatten)]
+    resources: Resources,
+}
+
+impl ResourceReport {
+    /// Writes the report as human-readable text.
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(
+            target, "  {} {} (parent {})",
+            kind_name(self.kind), self.uri, self.parent
+        )?;
+        if !self.resources.v4_resources.is_empty() {
+            writeln!(
+                target, "      IPv4: {}", self.resources.v4_resources.as_v4()
+            )?;
+        }
+        if !self.resources.v6_resources.is_empty() {
+            writeln!(
+                target, "      IPv6: {}", self.resources.v6_resources.as_v6()
+            )?;
+        }
+        if !self.resources.as_resources.is_empty() {
+            writeln!(target, "      AS: {}", self.resources.as_resources)?;
+        }
+        Ok(())
+    }
+}
+
+
+//------------ Orphan --------------------------------------------------------
+
+/// An object whose issuing CA is missing from the tree.
+#[derive(Clone, Debug, Serialize)]
+struct Orphan {
+    kind: ObjectKind,
+    id: KeyIdentifier,
+    uri: String,
+    parent: KeyIdentifier,
+}
+
+
+//------------ InheritChain --------------------------------------------------
+
+/// A CA certificate inheriting resources of one family from its issuer.
+#[derive(Clone, Debug, Serialize)]
+struct InheritChain {
+    id: KeyIdentifier,
+    uri: String,
+    family: Family,
+
+    /// The key identifier of the CA the resources are taken from.
+    ///
+    /// This is the closest ancestor that doesn’t inherit the resources
+    /// itself. If the chain ends at a trust anchor or a CA missing from
+    /// the tree, this is the key identifier of that CA.
+    source: KeyIdentifier,
+
+    /// The number of issuers between the CA and the source.
+    length: usize,
+}
+
+impl InheritChain {
+    /// Follows the inherit chain of `cert` for the given family.
+    fn new(
+        cert: &CaCertDump,
+        family: Family,
+        cas: &HashMap<KeyIdentifier, &CaCertDump>,
+    ) -> Self {
+        let mut source = cert.parent;
+        let mut length = 1;
+        while let Some(parent) = cas.get(&source) {
+            // Stop at loops which can only happen in broken trees.
+            if !family.is_inherited(parent) || length > cas.len() {
+                break
+            }
+            source = parent.parent;
+            length += 1;
+        }
+        InheritChain {
+            id: cert.id,
+            uri: cert.uri.to_string(),
+            family, source, length,
+        }
+    }
+}
+
+
+//------------ Family --------------------------------------------------------
+
+/// A resource family.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Family {
+    Ipv4,
+    Ipv6,
+    As,
+}
+
+impl Family {
+    /// Returns whether the CA certificate inherits the family.
+    fn is_inherited(self, cert: &CaCertDump) -> bool {
+        match self {
+            Family::Ipv4 => cert.v4_inherited,
+            Family::Ipv6 => cert.v6_inherited,
+            Family::As => cert.as_inherited,
+        }
+    }
+
+    /// Returns a human-readable name of the family.
+    fn name(self) -> &'static str {
+        match self {
+            Family::Ipv4 => "IPv4",
+            Family::Ipv6 => "IPv6",
+            Family::As => "AS",
+        }
+    }
+}
+
+
+//------------ Resources -----------------------------------------------------
+
+/// A set of resources of all families.
+#[derive(Clone, Debug, Default, Serialize)]
+struct Resources {
+    #[serde(with = "super::v4_blocks")]
+    v4_resources: IpBlocks,
+
+    #[serde(with = "super::v6_blocks")]
+    v6_resources: IpBlocks,
+
+    as_resources: AsBlocks,
+}
+
+impl Resources {
+    /// Returns the resources of a CA certificate.
+    fn of_ca(cert: &CaCertDump) -> Self {
+        Resources {
+            v4_resources: cert.v4_resources.clone(),
+            v6_resources: cert.v6_resources.clone(),
+            as_resources: cert.as_resources.clone(),
+        }
+    }
+
+    /// Returns the address resources claimed by the prefixes of a ROA.
+    fn of_roa(roa: &ROADump) -> Self {
+        let mut v4 = IpBlocksBuilder::new();
+        for addr in &roa.v4_resources {
+            v4.push((Addr::from_v4(addr.start), Addr::from_v4(addr.end)));
+        }
+        let mut v6 = IpBlocksBuilder::new();
+        for addr in &roa.v6_resources {
+            v6.push((Addr::from_v6(addr.start), Addr::from_v6(addr.end)));
+        }
+        Resources {
+            v4_resources: v4.finalize(),
+            v6_resources: v6.finalize(),
+            as_resources: AsBlocks::empty(),
+        }
+    }
+
+    /// Returns whether there are no resources at all.
+    fn is_empty(&self) -> bool {
+        self.v4_resources.is_empty()
+            && self.v6_resources.is_empty()
+            && self.as_resources.is_empty()
+    }
+
+    /// Adds the resources of `other`.
+    fn add(&mut self, other: &Self) {
+        self.v4_resources = self.v4_resources.union(&other.v4_resources);
+        self.v6_resources = self.v6_resources.union(&other.v6_resources);
+        self.as_resources = self.as_resources.union(&other.as_resources);
+    }
+
+    /// Returns the resources not contained in `other`.
+    fn difference(&self, other: &Self) -> Self {
+        Resources {
+            v4_resources: self.v4_resources.difference(&other.v4_resources),
+            v6_resources: self.v6_resources.difference(&other.v6_resources),
+            as_resources: self.as_resources.difference(&other.as_resources),
+        }
+    }
+}
+
+
+//------------ TreeAuditFormat -----------------------------------------------
+
+/// The output format for the tree audit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TreeAuditFormat {
+    /// A single JSON object with a list for each kind of finding.
+    Json,
+
+    /// Human-readable text.
+    Text,
+}
+
+impl TreeAuditFormat {
+    /// All known tree audit format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("json", TreeAuditFormat::Json),
+        ("text", TreeAuditFormat::Text),
+    ];
+
+    /// The default tree audit format name.
+    pub const DEFAULT_VALUE: &'static str = "text";
+}
+
+
+//--- FromStr
+
+impl FromStr for TreeAuditFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown tree audit format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Returns a human-readable name for the kind of an object.
+fn kind_name(kind: ObjectKind) -> &'static str {
+    match kind {
+        ObjectKind::CaCert => "CA",
+        ObjectKind::Roa => "ROA",
+        ObjectKind::RouterCert => "router cert",
+        ObjectKind::Aspa => "ASPA",
+        _ => "object",
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::testing::BuiltTree;
+
+    #[test]
+    fn built_tree() {
+        let audit = TreeAudit::new(&BuiltTree::new().dump);
+        assert!(!audit.has_problems());
+        assert!(audit.inherit_chains.is_empty());
+
+        // The ROA uses part of the CA’s addresses but no AS numbers.
+        let unused = audit.unused.iter().find(|report| {
+            report.uri.ends_with("/ca.cer")
+        }).unwrap();
+        assert!(matches!(unused.kind, ObjectKind::CaCert));
+        assert!(
+            !unused.resources.v4_resources.as_v4().to_string().contains(
+                "10.0.0.0/24"
+            )
+        );
+        assert!(!unused.resources.v4_resources.is_empty());
+        assert_eq!(
+            unused.resources.as_resources,
+            AsBlocks::from_str("AS64496-AS64499").unwrap()
+        );
+    }
+
+    #[test]
+    fn resources_difference() {
+        let held = Resources {
+            v4_resources: IpBlocks::from_str("10.0.0.0/8").unwrap(),
+            as_resources: AsBlocks::from_str("AS64496-AS64511").unwrap(),
+            .. Default::default()
+        };
+        let claimed = Resources {
+            v4_resources: IpBlocks::from_str(
+                "10.1.0.0/16, 192.0.2.0/24"
+            ).unwrap(),
+            as_resources: AsBlocks::from_str("AS64500").unwrap(),
+            .. Default::default()
+        };
+        let excess = claimed.difference(&held);
+        assert_eq!(
+            excess.v4_resources.as_v4().to_string(), "192.0.2.0/24"
+        );
+        assert!(excess.v6_resources.is_empty());
+        assert!(excess.as_resources.is_empty());
+
+        let mut used = Resources::default();
+        used.add(&claimed);
+        let unused = held.difference(&used);
+        assert!(!unused.v4_resources.is_empty());
+        assert!(!unused.as_resources.is_empty());
+        assert!(claimed.difference(&claimed).is_empty());
+    }
+}
@@ -0,0 +1,913 @@
+//! Comparing two tree dumps.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::str::FromStr;
+use log::error;
+use rpki::crypto::keys::{KeyIdentifier, PublicKey};
+use rpki::repository::resources::{AsBlocks, IpBlocks};
+use rpki::repository::x509::{Name, Time};
+use rpki::uri;
+use serde::Serialize;
+use crate::error::Failed;
+use super::{
+    AspaDump, CaCertDump, DBDump, GbrDump, PointDump, ROADump,
+    RouterCertDump, TALDump, V4Roa, V6Roa,
+};
+
+
+//------------ TreeDiff ------------------------------------------------------
+
+/// The differences between two tree dumps.
+///
+/// TALs are matched by their name while CA certificates and ROAs are
+/// matched by their TAL and URI, so that a certificate reissued with a new
+/// key shows up as modified. All other objects are matched by their
+/// subject key identifier and URI. Objects only present in the new dump
+/// are added, objects only present in the old dump are removed. Objects
+/// present in both with differing content are modified. For TALs, CA
+/// certificates, and ROAs they are described by a field-level breakdown
+/// of what has changed, for all other objects by their old and new
+/// versions.
+///
+/// Manifests and CRLs are not compared since they are reissued regularly
+/// without any change to the tree. Neither is whether a publication point
+/// was collected or taken from the store. Rejected objects and publication
+/// points are not compared either.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TreeDiff {
+    /// The changes to the trust anchors.
+    tals: ObjectsDiff<TALDump, TalChanges>,
+
+    /// The changes to the CA certificates.
+    ca_certs: ObjectsDiff<CaCertDump, CaCertChanges>,
+
+    /// The changes to the ROAs.
+    roas: ObjectsDiff<ROADump, RoaChanges>,
+
+    /// The changes to the ASPA objects.
+    aspas: ObjectsDiff<AspaDump, Change<AspaDump>>,
+
+    /// The changes to the router certificates.
+    router_certs: ObjectsDiff<RouterCertDump, Change<RouterCertDump>>,
+
+    /// The changes to the Ghostbuster records.
+    gbrs: ObjectsDiff<GbrDump, Change<GbrDump>>,
+}
+
+impl TreeDiff {
+    /// Creates the differences between the `old` and `new` dumps.
+    pub fn new(old: &DBDump, new: &DBDump) -> Self {
+        TreeDiff {
+            tals: ObjectsDiff::new(
+                &old.tals, &new.tals,
+                |tal| tal.name.clone(), TalChanges::new,
+            ),
+            ca_certs: ObjectsDiff::new(
+                &old.ca_certs, &new.ca_certs,
+                |cert| (cert.point.tal.clone(), cert.uri.to_string()),
+                CaCertChanges::new,
+            ),
+            roas: ObjectsDiff::new(
+                &old.roas, &new.roas,
+                |roa| (roa.point.tal.clone(), roa.uri.to_string()),
+                RoaChanges::new,
+            ),
+            aspas: ObjectsDiff::new(
+                &old.aspas, &new.aspas,
+                |aspa| (aspa.id, aspa.uri.to_string()), Change::new,
+            ),
+            router_certs: ObjectsDiff::new(
+                &old.router_certs, &new.router_certs,
+                |cert| (cert.id, cert.uri.to_string()), Change::new,
+            ),
+            gbrs: ObjectsDiff::new(
+                &old.gbrs, &new.gbrs,
+                |gbr| (gbr.id, gbr.uri.to_string()), Change::new,
+            ),
+        }
+    }
+
+    /// Returns whether there are no differences at all.
+    pub fn is_empty(&self) -> bool {
+        self.tals.is_empty()
+            && self.ca_certs.is_empty()
+            && self.roas.is_empty()
+            && self.aspas.is_empty()
+            && self.router_certs.is_empty()
+            && self.gbrs.is_empty()
+    }
+
+    /// Writes the differences as pretty-printed JSON to the given target.
+    pub fn write_json(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer_pretty(&mut *target, self)?;
+        target.write_all(b"\n")
+    }
+
+    /// Writes the differences as human-readable text to the given target.
+    pub fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        self.tals.write_summary("TALs", target)?;
+        for tal in &self.tals.added {
+            writeln!(target, "  + {} ({})", tal.name, tal.id)?;
+        }
+        for tal in &self.tals.removed {
+            writeln!(target, "  - {} ({})", tal.name, tal.id)?;
+        }
+        for tal in &self.tals.modified {
+            writeln!(target, "  ~ {}", tal.name)?;
+            write_change(target, "key", &tal.id, |id| id.to_string())?;
+        }
+
+        self.ca_certs.write_summary("CA certificates", target)?;
+        for cert in &self.ca_certs.added {
+            writeln!(
+                target, "  + {} {} (parent {})",
+                cert.id, cert.uri, cert.parent
+            )?;
+        }
+        for cert in &self.ca_certs.removed {
+            writeln!(
+                target, "  - {} {} (parent {})",
+                cert.id, cert.uri, cert.parent
+            )?;
+        }
+        for cert in &self.ca_certs.modified {
+            writeln!(target, "  ~ {} {}", cert.id, cert.uri)?;
+            cert.cert.write_text(target)?;
+            cert.resources.write_text(target)?;
+        }
+
+        self.roas.write_summary("ROAs", target)?;
+        for roa in &self.roas.added {
+            writeln!(
+                target, "  + {} AS{} {} (parent {})",
+                roa.id, roa.as_number, roa.uri, roa.parent
+            )?;
+        }
+        for roa in &self.roas.removed {
+            writeln!(
+                target, "  - {} AS{} {} (parent {})",
+                roa.id, roa.as_number, roa.uri, roa.parent
+            )?;
+        }
+        for roa in &self.roas.modified {
+            writeln!(target, "  ~ {} {}", roa.id, roa.uri)?;
+            roa.cert.write_text(target)?;
+            if let Some(as_number) = roa.as_number.as_ref() {
+                write_change(
+                    target, "AS number", as_number,
+                    |asn| format!("AS{}", asn)
+                )?;
+            }
+            write_list(target, "IPv4 prefixes gained", &roa.v4_gained)?;
+            write_list(target, "IPv4 prefixes lost", &roa.v4_lost)?;
+            write_list(target, "IPv6 prefixes gained", &roa.v6_gained)?;
+            write_list(target, "IPv6 prefixes lost", &roa.v6_lost)?;
+        }
+
+        self.aspas.write_summary("ASPA objects", target)?;
+        for aspa in &self.aspas.added {
+            writeln!(
+                target, "  + {} AS{} {} (parent {})",
+                aspa.id, aspa.customer_as, aspa.uri, aspa.parent
+            )?;
+        }
+        for aspa in &self.aspas.removed {
+            writeln!(
+                target, "  - {} AS{} {} (parent {})",
+                aspa.id, aspa.customer_as, aspa.uri, aspa.parent
+            )?;
+        }
+        for aspa in &self.aspas.modified {
+            writeln!(target, "  ~ {}", aspa.new.id)?;
+        }
+
+        self.router_certs.write_summary("Router certificates", target)?;
+        for cert in &self.router_certs.added {
+            writeln!(
+                target, "  + {} {} (parent {})",
+                cert.id, cert.uri, cert.parent
+            )?;
+        }
+        for cert in &self.router_certs.removed {
+            writeln!(
+                target, "  - {} {} (parent {})",
+                cert.id, cert.uri, cert.parent
+            )?;
+        }
+        for cert in &self.router_certs.modified {
+            writeln!(target, "  ~ {}", cert.new.id)?;
+        }
+
+        self.gbrs.write_summary("Ghostbuster records", target)?;
+        for gbr in &self.gbrs.added {
+            writeln!(
+                target, "  + {} {} (parent {})",
+                gbr.id, gbr.uri, gbr.parent
+            )?;
+        }
+        for gbr in &self.gbrs.removed {
+            writeln!(
+                target, "  - {} {} (parent {})",
+                gbr.id, gbr.uri, gbr.parent
+            )?;
+        }
+        for gbr in &self.gbrs.modified {
+            writeln!(target, "  ~ {}", gbr.new.id)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the differences in the given format to the given target.
+    pub fn write(
+        &self, format: TreeDiffFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            TreeDiffFormat::Json => self.write_json(target),
+            TreeDiffFormat::Text => self.write_text(target),
+        }
+    }
+}
+
+
+//------------ TreeDelta -----------------------------------------------------
+
+/// The changes to the tree between two consecutive validation runs.
+#[derive(Clone, Debug, Serialize)]
+pub struct TreeDelta {
+    /// The serial number of the tree after the changes were applied.
+    serial: u64,
+
+    /// The time the changes were detected.
+    time: Time,
+
+    /// The changes themselves.
+    #[serde(flatten)]
+    diff: TreeDiff,
+}
+
+impl TreeDelta {
+    /// Creates a new delta resulting in the tree with the given serial.
+    pub fn new(serial: u64, diff: TreeDiff) -> Self {
+        TreeDelta { serial, time: Time::now(), diff }
+    }
+
+    /// Returns the serial number of the tree after the changes.
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// Returns the changes.
+    pub fn diff(&self) -> &TreeDiff {
+        &self.diff
+    }
+
+    /// Writes the delta as a single line of JSON to the given target.
+    pub fn write_json_line(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer(&mut *target, self)?;
+        target.write_all(b"\n")
+    }
+}
+
+
+//------------ ObjectsDiff ---------------------------------------------------
+
+/// The differences for one kind of object.
+#[derive(Clone, Debug, Serialize)]
+struct ObjectsDiff<T, C> {
+    /// The objects only present in the new dump.
+    added: Vec<T>,
+
+    /// The objects only present in the old dump.
+    removed: Vec<T>,
+
+    /// The changes to objects present in both dumps.
+    modified: Vec<C>,
+}
+
+impl<T: Clone, C> ObjectsDiff<T, C> {
+    /// Creates the differences between two lists of objects.
+    ///
+    /// Objects are matched via the key returned by `key`. For objects
+    /// present in both lists, `changes` determines the changes or returns
+    /// `None` if the objects are identical. A broken repository may
+    /// contain several objects with the same key. These are first matched
+    /// with identical objects. If a single object on each side remains,
+    /// it is considered modified, otherwise all remaining objects are
+    /// added or removed. All lists are ordered by key.
+    fn new<K: Ord>(
+        old: &[T], new: &[T],
+        key: impl Fn(&T) -> K,
+        changes: impl Fn(&T, &T) -> Option<C>,
+    ) -> Self {
+        let mut items = BTreeMap::<_, (Vec<&T>, Vec<&T>)>::new();
+        for item in old {
+            items.entry(key(item)).or_default().0.push(item)
+        }
+        for item in new {
+            items.entry(key(item)).or_default().1.push(item)
+        }
+        let mut res = Self::default();
+        for (mut old, mut new) in items.into_values() {
+            old.retain(|old_item| {
+                match new.iter().position(|new_item| {
+                    changes(old_item, new_item).is_none()
+                }) {
+                    Some(pos) => {
+                        new.remove(pos);
+                        false
+                    }
+                    None => true
+                }
+            });
+            if let ([old_item], [new_item]) = (old.as_slice(), new.as_slice()) {
+                if let Some(change) = changes(old_item, new_item) {
+                    res.modified.push(change)
+                }
+                continue
+            }
+            res.removed.extend(old.into_iter().cloned());
+            res.added.extend(new.into_iter().cloned());
+        }
+        res
+    }
+
+    /// Returns whether there are no differences.
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+    }
+
+    /// Writes a summary line for the objects.
+    fn write_summary(
+        &self, title: &str, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(
+            target, "{}: {} added, {} removed, {} modified",
+            title, self.added.len(), self.removed.len(), self.modified.len()
+        )
+    }
+}
+
+impl<T, C> Default for ObjectsDiff<T, C> {
+    fn default() -> Self {
+        ObjectsDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            modified: Vec::new(),
+        }
+    }
+}
+
+
+//------------ TalChanges ----------------------------------------------------
+
+/// The changes to a trust anchor.
+#[derive(Clone, Debug, Serialize)]
+struct TalChanges {
+    /// The name of the TAL.
+    name: String,
+
+    /// The change of the trust anchor’s key identifier.
+    id: Change<KeyIdentifier>,
+}
+
+impl TalChanges {
+    /// Returns the changes between two TALs with the same name.
+    fn new(old: &TALDump, new: &TALDump) -> Option<Self> {
+        Some(TalChanges {
+            name: new.name.clone(),
+            id: Change::new(&old.id, &new.id)?,
+        })
+    }
+}
+
+
+//------------ CertChanges ---------------------------------------------------
+
+/// The changes to the certificate fields shared by CAs and ROAs.
+#[derive(Clone, Debug, Default, Serialize)]
+struct CertChanges {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<Change<KeyIdentifier>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    point: Option<Change<PointDump>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issuer: Option<Change<Name>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: Option<Change<Name>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pubkey: Option<Change<PublicKey>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_before: Option<Change<Time>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_after: Option<Change<Time>>,
+}
+
+impl CertChanges {
+    /// Creates the changes from the individual fields.
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        old_parent: &KeyIdentifier, new_parent: &KeyIdentifier,
+        old_point: &PointDump, new_point: &PointDump,
+        old_issuer: &Name, new_issuer: &Name,
+        old_subject: &Name, new_subject: &Name,
+        old_pubkey: &PublicKey, new_pubkey: &PublicKey,
+        old_validity: (&Time, &Time), new_validity: (&Time, &Time),
+    ) -> Self {
+        CertChanges {
+            parent: Change::new(old_parent, new_parent),
+            point: Change::new(old_point, new_point),
+            issuer: Change::new(old_issuer, new_issuer),
+            subject: Change::new(old_subject, new_subject),
+            pubkey: Change::new(old_pubkey, new_pubkey),
+            not_before: Change::new(old_validity.0, new_validity.0),
+            not_after: Change::new(old_validity.1, new_validity.1),
+        }
+    }
+
+    /// Returns whether nothing has changed.
+    fn is_empty(&self) -> bool {
+        self.parent.is_none()
+            && self.point.is_none()
+            && self.issuer.is_none()
+            && self.subject.is_none()
+            && self.pubkey.is_none()
+            && self.not_before.is_none()
+            && self.not_after.is_none()
+    }
+
+    /// Writes the changes as human-readable text.
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        if let Some(parent) = self.parent.as_ref() {
+            write_change(target, "parent", parent, |id| id.to_string())?;
+        }
+        if let Some(point) = self.point.as_ref() {
+            write_change(
+                target, "repository", point,
+                |point| point.ca_repository.to_string()
+            )?;
+        }
+        if self.issuer.is_some() {
+            writeln!(target, "      issuer changed")?;
+        }
+        if self.subject.is_some() {
+            writeln!(target, "      subject changed")?;
+        }
+        if self.pubkey.is_some() {
+            writeln!(target, "      key changed")?;
+        }
+        if let Some(time) = self.not_before.as_ref() {
+            write_change(target, "not before", time, |t| t.to_rfc3339())?;
+        }
+        if let Some(time) = self.not_after.as_ref() {
+            write_change(target, "not after", time, |t| t.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
+
+//------------ CaCertChanges -------------------------------------------------
+
+/// The changes to a CA certificate.
+#[derive(Clone, Debug, Serialize)]
+struct CaCertChanges {
+    /// The subject key identifier of the new CA certificate.
+    id: KeyIdentifier,
+
+    /// The URI of the CA certificate.
+    uri: uri::Rsync,
+
+    /// The changes to the certificate fields.
+    #[serde(flatten)]
+    cert: CertChanges,
+
+    /// The changes to the resources.
+    #[serde(flatten)]
+    resources: ResourceChanges,
+}
+
+impl CaCertChanges {
+    /// Returns the changes between two CA certificates with the same TAL
+    /// and URI.
+    fn new(old: &CaCertDump, new: &CaCertDump) -> Option<Self> {
+        let cert = CertChanges::new(
+            &old.parent, &new.parent,
+            &old.point, &new.point,
+            &old.issuer, &new.issuer,
+            &old.subject, &new.subject,
+            &old.pubkey, &new.pubkey,
+            (&old.not_before, &old.not_after),
+            (&new.not_before, &new.not_after),
+        );
+        let resources = ResourceChanges {
+            v4_gained: new.v4_resources.difference(&old.v4_resources),
+            v4_lost: old.v4_resources.difference(&new.v4_resources),
+            v6_gained: new.v6_resources.difference(&old.v6_resources),
+            v6_lost: old.v6_resources.difference(&new.v6_resources),
+            as_gained: new.as_resources.difference(&old.as_resources),
+            as_lost: old.as_resources.difference(&new.as_resources),
+        };
+        if cert.is_empty() && resources.is_empty() {
+            return None
+        }
+        Some(CaCertChanges {
+            id: new.id, uri: new.uri.clone(), cert, resources
+        })
+    }
+}
+
+
+//------------ ResourceChanges -----------------------------------------------
+
+/// The resources gained and lost by a CA certificate.
+#[derive(Clone, Debug, Serialize)]
+struct ResourceChanges {
+    #[serde(with = "super::v4_blocks")]
+    v4_gained: IpBlocks,
+
+    #[serde(with = "super::v4_blocks")]
+    v4_lost: IpBlocks,
+
+    #[serde(with = "super::v6_blocks")]
+    v6_gained: IpBlocks,
+
+    #[serde(with = "super::v6_blocks")]
+    v6_lost: IpBlocks,
+
+    as_gained: AsBlocks,
+
+    as_lost: AsBlocks,
+}
+
+impl ResourceChanges {
+    /// Returns whether no resources were gained or lost.
+    fn is_empty(&self) -> bool {
+        self.v4_gained.is_empty()
+            && self.v4_lost.is_empty()
+            && self.v6_gained.is_empty()
+            && self.v6_lost.is_empty()
+            && self.as_gained.is_empty()
+            && self.as_lost.is_empty()
+    }
+
+    /// Writes the changes as human-readable text.
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        if !self.v4_gained.is_empty() {
+            writeln!(target, "      IPv4 gained: {}", self.v4_gained.as_v4())?;
+        }
+        if !self.v4_lost.is_empty() {
+            writeln!(target, "      IPv4 lost: {}", self.v4_lost.as_v4())?;
+        }
+        if !self.v6_gained.is_empty() {
+            writeln!(target, "      IPv6 gained: {}", self.v6_gained.as_v6())?;
+        }
+        if !self.v6_lost.is_empty() {
+            writeln!(target, "      IPv6 lost: {}", self.v6_lost.as_v6())?;
+        }
+        if !self.as_gained.is_empty() {
+            writeln!(target, "      AS gained: {}", self.as_gained)?;
+        }
+        if !self.as_lost.is_empty() {
+            writeln!(target, "      AS lost: {}", self.as_lost)?;
+        }
+        Ok(())
+    }
+}
+
+
+//------------ RoaChanges ----------------------------------------------------
+
+/// The changes to a ROA.
+#[derive(Clone, Debug, Serialize)]
+struct RoaChanges {
+    /// The subject key identifier of the new ROA’s EE certificate.
+    id: KeyIdentifier,
+
+    /// The URI of the ROA.
+    uri: uri::Rsync,
+
+    /// The changes to the certificate fields.
+    #[serde(flatten)]
+    cert: CertChanges,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    as_number: Option<Change<u32>>,
+
+    v4_gained: Vec<V4Roa>,
+
+    v4_lost: Vec<V4Roa>,
+
+    v6_gained: Vec<V6Roa>,
+
+    v6_lost: Vec<V6Roa>,
+}
+
+impl RoaChanges {
+    /// Returns the changes between two ROAs with the same TAL and URI.
+    fn new(old: &ROADump, new: &ROADump) -> Option<Self> {
+        let res = RoaChanges {
+            id: new.id,
+            uri: new.uri.clone(),
+            cert: CertChanges::new(
+                &old.parent, &new.parent,
+                &old.point, &new.point,
+                &old.issuer, &new.issuer,
+                &old.subject, &new.subject,
+                &old.pubkey, &new.pubkey,
+                (&old.not_before, &old.not_after),
+                (&new.not_before, &new.not_after),
+            ),
+            as_number: Change::new(&old.as_number, &new.as_number),
+            v4_gained: missing(&new.v4_resources, &old.v4_resources),
+            v4_lost: missing(&old.v4_resources, &new.v4_resources),
+            v6_gained: missing(&new.v6_resources, &old.v6_resources),
+            v6_lost: missing(&old.v6_resources, &new.v6_resources),
+        };
+        if
+            res.cert.is_empty() && res.as_number.is_none()
+            && res.v4_gained.is_empty() && res.v4_lost.is_empty()
+            && res.v6_gained.is_empty() && res.v6_lost.is_empty()
+        {
+            return None
+        }
+        Some(res)
+    }
+}
+
+
+//------------ Change --------------------------------------------------------
+
+/// A value that has changed.
+#[derive(Clone, Debug, Serialize)]
+struct Change<T> {
+    old: T,
+    new: T,
+}
+
+impl<T: Clone + PartialEq> Change<T> {
+    /// Returns a change if the two values differ.
+    fn new(old: &T, new: &T) -> Option<Self> {
+        if old == new {
+            None
+        }
+        else {
+            Some(Change { old: old.clone(), new: new.clone() })
+        }
+    }
+}
+
+
+//------------ TreeDiffFormat ------------------------------------------------
+
+/// The output format for the tree differences.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TreeDiffFormat {
+    /// A single JSON object with the changes per object kind.
+    Json,
+
+    /// Human-readable text.
+    Text,
+}
+
+impl TreeDiffFormat {
+    /// All known tree diff format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("json", TreeDiffFormat::Json),
+        ("text", TreeDiffFormat::Text),
+    ];
+
+    /// The default tree diff format name.
+    pub const DEFAULT_VALUE: &'static str = "text";
+}
+
+
+//--- FromStr
+
+impl FromStr for TreeDiffFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown tree diff format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Returns the items of `left` that are not in `right`.
+fn missing<T: Copy + Eq + Ord>(left: &[T], right: &[T]) -> Vec<T> {
+    let mut res: Vec<_> = left.iter().filter(|item| {
+        !right.contains(item)
+    }).copied().collect();
+    res.sort();
+    res
+}
+
+/// Writes a changed value as human-readable text.
+fn write_change<T>(
+    target: &mut impl io::Write,
+    title: &str,
+    change: &Change<T>,
+    display: impl Fn(&T) -> String,
+) -> Result<(), io::Error> {
+    writeln!(
+        target, "      {}: {} -> {}",
+        title, display(&change.old), display(&change.new)
+    )
+}
+
+/// Writes a non-empty list of items as human-readable text.
+fn write_list<T: std::fmt::Display>(
+    target: &mut impl io::Write,
+    title: &str,
+    items: &[T],
+) -> Result<(), io::Error> {
+    if items.is_empty() {
+        return Ok(())
+    }
+    write!(target, "      {}:", title)?;
+    for item in items {
+        write!(target, " {}", item)?;
+    }
+    writeln!(target)
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::PointSource;
+    use crate::utils::testing::TestRepository;
+    use super::super::TreeReport;
+    use super::super::testing::{dump, key, point, tal};
+
+    /// Returns the tree of a validation run over the repository.
+    fn tree(repo: &TestRepository) -> DBDump {
+        let (report, _) = TreeReport::new().process(&repo.engine()).unwrap();
+        report.into_dump()
+    }
+
+    #[test]
+    fn empty_diff() {
+        let old = dump(vec![tal("a", 1), tal("b", 2)]);
+        let new = dump(vec![tal("b", 2), tal("a", 1)]);
+        assert!(TreeDiff::new(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn tal_diff() {
+        let old = dump(vec![tal("a", 1), tal("b", 2)]);
+        let new = dump(vec![tal("b", 3), tal("c", 4)]);
+        let diff = TreeDiff::new(&old, &new);
+        assert_eq!(diff.tals.added.len(), 1);
+        assert_eq!(diff.tals.added[0].name, "c");
+        assert_eq!(diff.tals.removed.len(), 1);
+        assert_eq!(diff.tals.removed[0].name, "a");
+        assert_eq!(diff.tals.modified.len(), 1);
+        assert_eq!(diff.tals.modified[0].name, "b");
+        assert_eq!(diff.tals.modified[0].id.old, key(2));
+        assert_eq!(diff.tals.modified[0].id.new, key(3));
+    }
+
+    #[test]
+    fn ca_cert_rekey() {
+        let (repo, mut ta) = TestRepository::new();
+        let mut ca = repo.child(&mut ta, "ca", "10.0.0.0/8", "AS64496");
+        repo.publish(&mut ca);
+        repo.publish(&mut ta);
+        let old = tree(&repo);
+
+        repo.rekey(&mut ta, &mut ca);
+        repo.publish(&mut ca);
+        repo.publish(&mut ta);
+        let diff = TreeDiff::new(&old, &tree(&repo));
+        assert!(diff.ca_certs.added.is_empty());
+        assert!(diff.ca_certs.removed.is_empty());
+        assert_eq!(diff.ca_certs.modified.len(), 1);
+        let modified = &diff.ca_certs.modified[0];
+        assert_eq!(modified.id, ca.key_id());
+        assert_eq!(&modified.uri, ca.uri());
+        assert_eq!(
+            modified.cert.pubkey.as_ref().map(|key| &key.new),
+            Some(ca.key_info())
+        );
+    }
+
+    /// Returns a Ghostbuster record sharing its key with all others.
+    fn gbr(n: u8, vcard: &str) -> GbrDump {
+        GbrDump {
+            parent: key(1),
+            id: key(2),
+            uri: uri::Rsync::from_str(
+                &format!("rsync://a.example/repo/{}.gbr", n)
+            ).unwrap(),
+            point: point(PointSource::Collected),
+            vcard: vcard.into(),
+            not_before: Time::utc(2024, 1, 1, 0, 0, 0),
+            not_after: Time::utc(2025, 1, 1, 0, 0, 0),
+        }
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let old = DBDump {
+            gbrs: vec![gbr(1, "FN:a"), gbr(1, "FN:b")],
+            .. Default::default()
+        };
+        let new = DBDump {
+            gbrs: vec![gbr(1, "FN:b")], .. Default::default()
+        };
+        let diff = TreeDiff::new(&old, &new);
+        assert!(diff.gbrs.added.is_empty());
+        assert_eq!(diff.gbrs.removed, vec![gbr(1, "FN:a")]);
+        assert!(diff.gbrs.modified.is_empty());
+
+        // Without an identical object, all are added and removed.
+        let new = DBDump {
+            gbrs: vec![gbr(1, "FN:c")], .. Default::default()
+        };
+        let diff = TreeDiff::new(&old, &new);
+        assert_eq!(diff.gbrs.added, vec![gbr(1, "FN:c")]);
+        assert_eq!(diff.gbrs.removed.len(), 2);
+        assert!(diff.gbrs.modified.is_empty());
+
+        // A single remaining object on each side is modified.
+        let new = DBDump {
+            gbrs: vec![gbr(1, "FN:c"), gbr(1, "FN:b")],
+            .. Default::default()
+        };
+        let diff = TreeDiff::new(&old, &new);
+        assert!(diff.gbrs.added.is_empty());
+        assert!(diff.gbrs.removed.is_empty());
+        assert_eq!(diff.gbrs.modified.len(), 1);
+    }
+
+    #[test]
+    fn same_key_objects() {
+        let gbr = |n: u8| gbr(n, &format!("FN:{}", n));
+        let old = DBDump {
+            gbrs: vec![gbr(1), gbr(2)], .. Default::default()
+        };
+        let new = DBDump {
+            gbrs: vec![gbr(2), gbr(3)], .. Default::default()
+        };
+        let diff = TreeDiff::new(&old, &new);
+        assert_eq!(diff.gbrs.added, vec![gbr(3)]);
+        assert_eq!(diff.gbrs.removed, vec![gbr(1)]);
+        assert!(diff.gbrs.modified.is_empty());
+    }
+
+    #[test]
+    fn point_source_ignored() {
+        assert!(Change::new(
+            &point(PointSource::Collected), &point(PointSource::Stored)
+        ).is_none());
+        let mut moved = point(PointSource::Stored);
+        moved.tal = "b".into();
+        assert!(Change::new(&point(PointSource::Stored), &moved).is_some());
+    }
+
+    #[test]
+    fn missing_prefixes() {
+        let roa = |max_length| V4Roa {
+            start: [10, 0, 0, 0].into(),
+            end: [10, 0, 255, 255].into(),
+            max_length,
+        };
+        assert_eq!(
+            missing(&[roa(16), roa(24)], &[roa(24), roa(20)]),
+            vec![roa(16)]
+        );
+        assert_eq!(roa(24).to_string(), "10.0.0.0/16-24");
+        assert_eq!(roa(0).to_string(), "10.0.0.0/16");
+    }
+}
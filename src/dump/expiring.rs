@@ -0,0 +1,560 @@
+//! Forecasting which objects expire soon.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::io;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use chrono::Duration;
+use log::error;
+use rpki::crypto::keys::KeyIdentifier;
+use rpki::repository::x509::Time;
+use serde::Serialize;
+use crate::error::Failed;
+use crate::payload::{PayloadInfo, PayloadSnapshot};
+use crate::utils::date::format_iso_date;
+use super::DBDump;
+
+
+//------------ Expiring ------------------------------------------------------
+
+/// The objects that will expire or go stale within a time window.
+///
+/// VRPs, router keys, and ASPAs are taken from a payload snapshot and
+/// expire when the first certificate of their validation chain expires.
+/// CA certificates and manifests are taken from the tree. CA certificates
+/// expire at the end of their own validity while manifests go stale at
+/// their next update time.
+///
+/// The objects are grouped by the TAL they were derived from and then by
+/// the CA whose publication point they were published at. Trust anchor
+/// certificates are listed under their own key.
+#[derive(Clone, Debug, Serialize)]
+pub struct Expiring {
+    /// The start of the window.
+    now: Time,
+
+    /// The end of the window.
+    until: Time,
+
+    /// The objects grouped by TAL.
+    tals: Vec<TalExpiring>,
+}
+
+impl Expiring {
+    /// Collects all objects expiring between `now` and `now + within`.
+    pub fn new(
+        snapshot: &PayloadSnapshot,
+        tree: &DBDump,
+        now: Time,
+        within: ExpiryWindow,
+    ) -> Self {
+        let until = now + within.0;
+        let mut collector = Collector::new(tree, now, until);
+
+        for (origin, info) in snapshot.origins() {
+            collector.add_payload(
+                info, ObjectType::Vrp,
+                format!(
+                    "{}-{} => {}",
+                    origin.prefix.prefix(), origin.prefix.resolved_max_len(),
+                    origin.asn,
+                ),
+            );
+        }
+        for (key, info) in snapshot.router_keys() {
+            collector.add_payload(
+                info, ObjectType::RouterKey,
+                format!("{} => {}", key.key_identifier, key.asn),
+            );
+        }
+        for (aspa, info) in snapshot.aspas() {
+            collector.add_payload(
+                info, ObjectType::Aspa,
+                format!("customer {}", aspa.customer),
+            );
+        }
+        for tal in &tree.tals {
+            if let Some(not_after) = tal.not_after {
+                collector.add(
+                    &tal.name, Some(tal.id), ObjectType::CaCert, &tal.uri,
+                    Event::Expires, not_after, None,
+                );
+            }
+        }
+        for cert in &tree.ca_certs {
+            collector.add(
+                &cert.point.tal, Some(cert.parent), ObjectType::CaCert,
+                cert.uri.as_str(), Event::Expires, cert.not_after, None,
+            );
+        }
+        for manifest in &tree.manifests {
+            collector.add(
+                &manifest.point.tal, Some(manifest.parent),
+                ObjectType::Manifest, manifest.uri.as_str(),
+                Event::Stale, manifest.next_update, None,
+            );
+        }
+
+        collector.finalize()
+    }
+
+    /// Writes the report as pretty-printed JSON to the given target.
+    pub fn write_json(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer_pretty(&mut *target, self)?;
+        target.write_all(b"\n")
+    }
+
+    /// Writes the report as human-readable text to the given target.
+    pub fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(
+            target, "Objects expiring between {} and {}:",
+            format_iso_date(self.now.into()),
+            format_iso_date(self.until.into()),
+        )?;
+        if self.tals.is_empty() {
+            writeln!(target, "None.")?;
+        }
+        for tal in &self.tals {
+            writeln!(target, "TAL {}", tal.name)?;
+            for ca in &tal.cas {
+                match (ca.id, ca.uri.as_ref()) {
+                    (Some(id), Some(uri)) => {
+                        writeln!(target, "  CA {} ({})", id, uri)?
+                    }
+                    (Some(id), None) => writeln!(target, "  CA {}", id)?,
+                    _ => writeln!(target, "  unknown CA")?,
+                }
+                for object in &ca.objects {
+                    object.write_text(target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the report in the given format to the given target.
+    pub fn write(
+        &self, format: ExpiringFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            ExpiringFormat::Json => self.write_json(target),
+            ExpiringFormat::Text => self.write_text(target),
+        }
+    }
+}
+
+
+//------------ Collector -----------------------------------------------------
+
+/// Collects the expiring objects while building the report.
+struct Collector<'a> {
+    /// The CAs and their URIs by key identifier.
+    cas: HashMap<KeyIdentifier, &'a str>,
+
+    /// The issuing CA of payload objects by their URI.
+    parents: HashMap<&'a str, KeyIdentifier>,
+
+    /// The start of the window.
+    now: Time,
+
+    /// The end of the window.
+    until: Time,
+
+    /// The objects collected so far by TAL name and issuing CA.
+    tals: BTreeMap<String, BTreeMap<Option<KeyIdentifier>, Vec<Object>>>,
+}
+
+impl<'a> Collector<'a> {
+    /// Creates a new collector for the given tree and window.
+    fn new(tree: &'a DBDump, now: Time, until: Time) -> Self {
+        let mut cas: HashMap<_, _> = tree.ca_certs.iter().map(|cert| {
+            (cert.id, cert.uri.as_str())
+        }).collect();
+        cas.extend(tree.tals.iter().map(|tal| (tal.id, tal.uri.as_str())));
+        let parents = tree.roas.iter().map(|roa| {
+            (roa.uri.as_str(), roa.parent)
+        }).chain(tree.router_certs.iter().map(|cert| {
+            (cert.uri.as_str(), cert.parent)
+        })).chain(tree.aspas.iter().map(|aspa| {
+            (aspa.uri.as_str(), aspa.parent)
+        })).collect();
+        Collector {
+            cas, parents, now, until,
+            tals: BTreeMap::new(),
+        }
+    }
+
+    /// Adds all published sources of a payload item.
+    fn add_payload(
+        &mut self, info: &PayloadInfo, kind: ObjectType, payload: String,
+    ) {
+        for info in info {
+            let info = match info.publish_info() {
+                Some(info) => info,
+                None => continue,
+            };
+            let uri = info.uri.as_ref().map(|uri| uri.as_str());
+            let parent = uri.and_then(|uri| self.parents.get(uri).copied());
+            self.add(
+                info.tal.name(), parent, kind, uri.unwrap_or_default(),
+                Event::Expires, info.chain_validity.not_after(),
+                Some(payload.clone()),
+            );
+        }
+    }
+
+    /// Adds an object if its time is within the window.
+    #[allow(clippy::too_many_arguments)]
+    fn add(
+        &mut self,
+        tal: &str,
+        parent: Option<KeyIdentifier>,
+        kind: ObjectType,
+        uri: &str,
+        event: Event,
+        time: Time,
+        payload: Option<String>,
+    ) {
+        if time < self.now || time > self.until {
+            return
+        }
+        self.tals.entry(tal.into()).or_default().entry(
+            parent
+        ).or_default().push(Object {
+            kind, uri: uri.into(), event, time, payload
+        });
+    }
+
+    /// Converts the collected objects into the final report.
+    fn finalize(self) -> Expiring {
+        let cas = self.cas;
+        let tals = self.tals.into_iter().map(|(name, items)| {
+            TalExpiring {
+                name,
+                cas: items.into_iter().map(|(id, mut objects)| {
+                    objects.sort_by(|left, right| {
+                        left.time.cmp(&right.time).then_with(|| {
+                            left.uri.cmp(&right.uri)
+                        })
+                    });
+                    CaExpiring {
+                        id,
+                        uri: id.and_then(|id| {
+                            cas.get(&id).map(|uri| uri.to_string())
+                        }),
+                        objects,
+                    }
+                }).collect(),
+            }
+        }).collect();
+        Expiring { now: self.now, until: self.until, tals }
+    }
+}
+
+
+//------------ TalExpiring ---------------------------------------------------
+
+/// The expiring objects of a TAL.
+#[derive(Clone, Debug, Serialize)]
+struct TalExpiring {
+    /// The name of the TAL.
+    name: String,
+
+    /// The objects grouped by issuing CA.
+    cas: Vec<CaExpiring>,
+}
+
+
+//------------ CaExpiring ----------------------------------------------------
+
+/// The expiring objects published by a CA.
+#[derive(Clone, Debug, Serialize)]
+struct CaExpiring {
+    /// The subject key identifier of the CA.
+    ///
+    /// This is `None` if the objects couldn’t be found in the tree.
+    id: Option<KeyIdentifier>,
+
+    /// The URI of the CA’s certificate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+
+    /// The expiring objects ordered by time.
+    objects: Vec<Object>,
+}
+
+
+//------------ Object --------------------------------------------------------
+
+/// A single expiring object.
+#[derive(Clone, Debug, Serialize)]
+struct Object {
+    #[serde(rename = "type")]
+    kind: ObjectType,
+    uri: String,
+
+    /// What happens to the object at `time`.
+    event: Event,
+    time: Time,
+
+    /// A description of the payload for VRPs, router keys, and ASPAs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<String>,
+}
+
+impl Object {
+    /// Writes the object as text.
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        write!(
+            target, "    {} {} {}",
+            format_iso_date(self.time.into()), self.event.name(),
+            self.kind.name(),
+        )?;
+        if let Some(payload) = self.payload.as_ref() {
+            write!(target, " {}", payload)?;
+        }
+        writeln!(target)?;
+        writeln!(target, "      {}", self.uri)
+    }
+}
+
+
+//------------ ObjectType ----------------------------------------------------
+
+/// The type of an expiring object.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ObjectType {
+    Vrp,
+    RouterKey,
+    Aspa,
+    CaCert,
+    Manifest,
+}
+
+impl ObjectType {
+    /// Returns the name used in text output.
+    fn name(self) -> &'static str {
+        match self {
+            ObjectType::Vrp => "VRP",
+            ObjectType::RouterKey => "router key",
+            ObjectType::Aspa => "ASPA",
+            ObjectType::CaCert => "CA certificate",
+            ObjectType::Manifest => "manifest",
+        }
+    }
+}
+
+
+//------------ Event ---------------------------------------------------------
+
+/// What happens to an object.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Event {
+    /// The object or its validation chain expires.
+    Expires,
+
+    /// The manifest goes stale.
+    Stale,
+}
+
+impl Event {
+    /// Returns the name used in text output.
+    fn name(self) -> &'static str {
+        match self {
+            Event::Expires => "expires",
+            Event::Stale => "goes stale",
+        }
+    }
+}
+
+
+//------------ ExpiryWindow --------------------------------------------------
+
+/// The length of the time window for expiring objects.
+///
+/// The window is given as a number followed by an optional unit which can
+/// be `s` for seconds – the default –, `m` for minutes, `h` for hours, `d`
+/// for days, or `w` for weeks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ExpiryWindow(Duration);
+
+impl ExpiryWindow {
+    /// The default window.
+    pub const DEFAULT_VALUE: &'static str = "48h";
+
+    /// Creates the window from the query of an HTTP request.
+    ///
+    /// The query can contain the `within` parameter. If it is missing, the
+    /// default window is used.
+    pub fn from_query(query: Option<&str>) -> Result<Self, Failed> {
+        let mut res = None;
+        for (key, value) in form_urlencoded::parse(
+            query.unwrap_or_default().as_ref()
+        ) {
+            if key == "within" && res.is_none() {
+                res = Some(Self::from_str(&value)?);
+            }
+            else {
+                return Err(Failed)
+            }
+        }
+        match res {
+            Some(res) => Ok(res),
+            None => Self::from_str(Self::DEFAULT_VALUE),
+        }
+    }
+}
+
+
+//--- FromStr
+
+impl FromStr for ExpiryWindow {
+    type Err = Failed;
+
+    /// Parses the window.
+    ///
+    /// Doesn’t log an error since this is also used for HTTP queries.
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        let (number, unit) = match value.find(|ch: char| {
+            !ch.is_ascii_digit()
+        }) {
+            Some(pos) => value.split_at(pos),
+            None => (value, ""),
+        };
+        let number = i64::from_str(number).map_err(|_| Failed)?;
+        let seconds = match unit {
+            "" | "s" => Some(number),
+            "m" => number.checked_mul(60),
+            "h" => number.checked_mul(3600),
+            "d" => number.checked_mul(86400),
+            "w" => number.checked_mul(7 * 86400),
+            _ => None,
+        }.ok_or(Failed)?;
+        if seconds > Duration::max_value().num_seconds() {
+            return Err(Failed)
+        }
+        Ok(ExpiryWindow(Duration::seconds(seconds)))
+    }
+}
+
+
+//------------ ExpiringFormat ------------------------------------------------
+
+/// The output format for expiring objects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExpiringFormat {
+    /// A single JSON object with the objects grouped by TAL and CA.
+    Json,
+
+    /// Human-readable text.
+    Text,
+}
+
+impl ExpiringFormat {
+    /// All known expiring format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("json", ExpiringFormat::Json),
+        ("text", ExpiringFormat::Text),
+    ];
+
+    /// The default expiring format name.
+    pub const DEFAULT_VALUE: &'static str = "text";
+}
+
+
+//--- FromStr
+
+impl FromStr for ExpiringFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown expiring format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::testing::BuiltTree;
+
+    #[test]
+    fn built_tree() {
+        let tree = BuiltTree::new();
+
+        // Manifests go stale after a day, everything else lasts a year.
+        let expiring = Expiring::new(
+            &tree.snapshot, &tree.dump, Time::now(),
+            ExpiryWindow::from_str("2d").unwrap(),
+        );
+        assert_eq!(expiring.tals.len(), 1);
+        let objects: Vec<_> = expiring.tals[0].cas.iter().flat_map(|ca| {
+            ca.objects.iter()
+        }).collect();
+        assert_eq!(objects.len(), 2);
+        assert!(objects.iter().all(|object| {
+            matches!(object.kind, ObjectType::Manifest)
+            && matches!(object.event, Event::Stale)
+        }));
+
+        let expiring = Expiring::new(
+            &tree.snapshot, &tree.dump, Time::now(),
+            ExpiryWindow::from_str("400d").unwrap(),
+        );
+        let objects: Vec<_> = expiring.tals[0].cas.iter().flat_map(|ca| {
+            ca.objects.iter()
+        }).collect();
+        assert!(objects.iter().any(|object| {
+            matches!(object.kind, ObjectType::Vrp)
+            && object.payload.as_deref() == Some("10.0.0.0/24-24 => AS64496")
+        }));
+        assert!(objects.iter().any(|object| {
+            matches!(object.kind, ObjectType::CaCert)
+            && object.uri.ends_with("/ca.cer")
+        }));
+    }
+
+    #[test]
+    fn window() {
+        assert_eq!(
+            ExpiryWindow::from_str("48h").unwrap().0,
+            Duration::hours(48)
+        );
+        assert_eq!(
+            ExpiryWindow::from_str("90").unwrap().0,
+            Duration::seconds(90)
+        );
+        assert_eq!(
+            ExpiryWindow::from_str("2w").unwrap().0,
+            Duration::days(14)
+        );
+        assert!(ExpiryWindow::from_str("h").is_err());
+        assert!(ExpiryWindow::from_str("3y").is_err());
+        assert_eq!(
+            ExpiryWindow::from_query(Some("within=15m")).unwrap().0,
+            Duration::minutes(15)
+        );
+        assert_eq!(
+            ExpiryWindow::from_query(None).unwrap().0,
+            Duration::hours(48)
+        );
+        assert!(ExpiryWindow::from_query(Some("within=1h&x=2")).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! Exporting the CA hierarchy of a tree dump as a graph.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::str::FromStr;
+use log::error;
+use rpki::crypto::keys::KeyIdentifier;
+use crate::error::Failed;
+use super::{CaCertDump, DBDump, ROADump, TALDump};
+
+
+//------------ TreeGraph -----------------------------------------------------
+
+/// The CA hierarchy of a tree dump as a graph.
+///
+/// The graph contains a node for each trust anchor, CA certificate, and
+/// ROA with edges from each issuing CA to the objects it issued. It can
+/// be restricted to the subtree rooted at a given CA and to a maximum
+/// depth below the roots. The ROAs of each CA can be collapsed into a
+/// single node giving only their number.
+#[derive(Clone, Debug, Default)]
+pub struct TreeGraph {
+    /// The nodes of the graph.
+    nodes: Vec<Node>,
+
+    /// The edges of the graph as indexes into `nodes`.
+    edges: Vec<(usize, usize)>,
+}
+
+impl TreeGraph {
+    /// Creates the graph for a tree dump with the given options.
+    ///
+    /// Logs an error and fails if the root of the subtree is given but
+    /// is neither a trust anchor nor a CA in the tree.
+    pub fn new(
+        tree: &DBDump, options: &TreeGraphOptions
+    ) -> Result<Self, Failed> {
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        for cert in &tree.ca_certs {
+            children.entry(cert.parent).or_default().push(cert)
+        }
+        let mut roas: HashMap<_, Vec<_>> = HashMap::new();
+        for roa in &tree.roas {
+            roas.entry(roa.parent).or_default().push(roa)
+        }
+
+        let mut res = Self::default();
+        let mut queue = Vec::new();
+        match options.subtree {
+            Some(root) => {
+                if let Some(tal) = tree.tals.iter().find(|tal| {
+                    tal.id == root
+                }) {
+                    queue.push((res.push(Node::tal(tal)), root, 0));
+                }
+                else if let Some(cert) = tree.ca_certs.iter().find(|cert| {
+                    cert.id == root
+                }) {
+                    queue.push((res.push(Node::ca(cert)), root, 0));
+                }
+                else {
+                    error!("Subtree root {} not found in tree.", root);
+                    return Err(Failed)
+                }
+            }
+            None => {
+                for tal in &tree.tals {
+                    queue.push((res.push(Node::tal(tal)), tal.id, 0));
+                }
+            }
+        }
+
+        // Walk the tree depth-first. Since a broken tree may contain loops,
+        // we only ever visit a CA once.
+        let mut seen: HashSet<_> = queue.iter().map(|item| item.1).collect();
+        while let Some((index, id, depth)) = queue.pop() {
+            if options.max_depth.map(|max| depth >= max).unwrap_or(false) {
+                continue
+            }
+            for cert in children.get(&id).into_iter().flatten() {
+                if !seen.insert(cert.id) {
+                    continue
+                }
+                let child = res.push(Node::ca(cert));
+                res.edges.push((index, child));
+                queue.push((child, cert.id, depth + 1));
+            }
+            let roas = match roas.get(&id) {
+                Some(roas) => roas,
+                None => continue,
+            };
+            if options.collapse_roas {
+                let child = res.push(Node::roa_count(id, roas.len()));
+                res.edges.push((index, child));
+            }
+            else {
+                for roa in roas {
+                    let child = res.push(Node::roa(roa));
+                    res.edges.push((index, child));
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Adds a node and returns its index.
+    fn push(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Writes the graph in Graphviz DOT format to the given target.
+    pub fn write_dot(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(target, "digraph rpki {{")?;
+        writeln!(target, "  rankdir=LR;")?;
+        for node in &self.nodes {
+            writeln!(
+                target, "  \"{}\" [label=\"{}\", shape={}];",
+                node.id, dot_escape(&node.label), node.kind.dot_shape(),
+            )?;
+        }
+        for &(from, to) in &self.edges {
+            writeln!(
+                target, "  \"{}\" -> \"{}\";",
+                self.nodes[from].id, self.nodes[to].id
+            )?;
+        }
+        writeln!(target, "}}")
+    }
+
+    /// Writes the graph in GraphML format to the given target.
+    pub fn write_graphml(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(target, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(
+            target,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+        )?;
+        writeln!(
+            target,
+            "  <key id=\"kind\" for=\"node\" attr.name=\"kind\" \
+             attr.type=\"string\"/>"
+        )?;
+        writeln!(
+            target,
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" \
+             attr.type=\"string\"/>"
+        )?;
+        writeln!(target, "  <graph id=\"rpki\" edgedefault=\"directed\">")?;
+        for node in &self.nodes {
+            writeln!(target, "    <node id=\"{}\">", node.id)?;
+            writeln!(
+                target, "      <data key=\"kind\">{}</data>",
+                node.kind.name()
+            )?;
+            writeln!(
+                target, "      <data key=\"label\">{}</data>",
+                xml_escape(&node.label)
+            )?;
+            writeln!(target, "    </node>")?;
+        }
+        for &(from, to) in &self.edges {
+            writeln!(
+                target, "    <edge source=\"{}\" target=\"{}\"/>",
+                self.nodes[from].id, self.nodes[to].id
+            )?;
+        }
+        writeln!(target, "  </graph>")?;
+        writeln!(target, "</graphml>")
+    }
+
+    /// Writes the graph in the given format to the given target.
+    pub fn write(
+        &self, format: TreeGraphFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            TreeGraphFormat::Dot => self.write_dot(target),
+            TreeGraphFormat::Graphml => self.write_graphml(target),
+        }
+    }
+}
+
+
+//------------ TreeGraphOptions ----------------------------------------------
+
+/// The options for creating a [`TreeGraph`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TreeGraphOptions {
+    /// Replace the ROAs of each CA with a single node giving their number.
+    collapse_roas: bool,
+
+    /// The maximum number of CA levels below the roots.
+    max_depth: Option<usize>,
+
+    /// The key identifier of the CA to use as the only root.
+    subtree: Option<KeyIdentifier>,
+}
+
+impl TreeGraphOptions {
+    /// Creates the default options for the complete tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collapses the ROAs of each CA into a single node.
+    pub fn with_collapsed_roas(mut self) -> Self {
+        self.collapse_roas = true;
+        self
+    }
+
+    /// Limits the graph to the given number of CA levels below the roots.
+    ///
+    /// With a depth of zero, only the roots are included.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Restricts the graph to the subtree rooted at the given CA.
+    pub fn with_subtree(mut self, id: KeyIdentifier) -> Self {
+        self.subtree = Some(id);
+        self
+    }
+}
+
+
+//------------ Node ----------------------------------------------------------
+
+/// A node of the graph.
+#[derive(Clone, Debug)]
+struct Node {
+    /// The identifier of the node in the graph.
+    id: String,
+
+    kind: NodeKind,
+
+    /// The human-readable label of the node.
+    label: String,
+}
+
+impl Node {
+    /// Creates the node for a trust anchor.
+    fn tal(tal: &TALDump) -> Self {
+        Node {
+            id: tal.id.to_string(),
+            kind: NodeKind::TrustAnchor,
+            label: tal.name.clone(),
+        }
+    }
+
+    /// Creates the node for a CA certificate.
+    fn ca(cert: &CaCertDump) -> Self {
+        Node {
+            id: cert.id.to_string(),
+            kind: NodeKind::CaCert,
+            label: format!("{}\n{}", file_name(cert.uri.as_str()), cert.id),
+        }
+    }
+
+    /// Creates the node for a ROA.
+    fn roa(roa: &ROADump) -> Self {
+        let mut label = format!("AS{}", roa.as_number);
+        for prefix in &roa.v4_resources {
+            label.push_str(&format!("\n{}", prefix));
+        }
+        for prefix in &roa.v6_resources {
+            label.push_str(&format!("\n{}", prefix));
+        }
+        Node {
+            id: roa.id.to_string(),
+            kind: NodeKind::Roa,
+            label,
+        }
+    }
+
+    /// Creates the node for the collapsed ROAs of a CA.
+    fn roa_count(parent: KeyIdentifier, count: usize) -> Self {
+        Node {
+            id: format!("{}-roas", parent),
+            kind: NodeKind::RoaCount,
+            label: if count == 1 {
+                String::from("1 ROA")
+            }
+            else {
+                format!("{} ROAs", count)
+            },
+        }
+    }
+}
+
+
+//------------ NodeKind ------------------------------------------------------
+
+/// The kind of object a node represents.
+#[derive(Clone, Copy, Debug)]
+enum NodeKind {
+    TrustAnchor,
+    CaCert,
+    Roa,
+    RoaCount,
+}
+
+impl NodeKind {
+    /// Returns the name of the kind used in GraphML.
+    fn name(self) -> &'static str {
+        match self {
+            NodeKind::TrustAnchor => "trust-anchor",
+            NodeKind::CaCert => "ca-cert",
+            NodeKind::Roa => "roa",
+            NodeKind::RoaCount => "roa-count",
+        }
+    }
+
+    /// Returns the node shape used in DOT.
+    fn dot_shape(self) -> &'static str {
+        match self {
+            NodeKind::TrustAnchor => "doubleoctagon",
+            NodeKind::CaCert => "box",
+            NodeKind::Roa => "ellipse",
+            NodeKind::RoaCount => "note",
+        }
+    }
+}
+
+
+//------------ TreeGraphFormat -----------------------------------------------
+
+/// The output format for the tree graph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TreeGraphFormat {
+    /// The Graphviz DOT language.
+    Dot,
+
+    /// GraphML.
+    Graphml,
+}
+
+impl TreeGraphFormat {
+    /// All known tree graph format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("dot", TreeGraphFormat::Dot),
+        ("graphml", TreeGraphFormat::Graphml),
+    ];
+
+    /// The default tree graph format name.
+    pub const DEFAULT_VALUE: &'static str = "dot";
+}
+
+
+//--- FromStr
+
+impl FromStr for TreeGraphFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown tree graph format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Returns the last path component of a URI.
+fn file_name(uri: &str) -> &str {
+    uri.rsplit('/').next().unwrap_or(uri)
+}
+
+/// Escapes a string for use in a quoted DOT identifier.
+///
+/// Line feeds are turned into DOT’s line breaks.
+fn dot_escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            _ => res.push(ch),
+        }
+    }
+    res
+}
+
+/// Escapes a string for use in XML character data.
+fn xml_escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            _ => res.push(ch),
+        }
+    }
+    res
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::testing::{dump, key, tal};
+
+    #[test]
+    fn write_formats() {
+        let tree = dump(vec![tal("a\"b", 1), tal("c&d", 2)]);
+        let graph = TreeGraph::new(
+            &tree, &TreeGraphOptions::new()
+        ).unwrap();
+
+        let mut out = Vec::new();
+        graph.write_dot(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("label=\"a\\\"b\", shape=doubleoctagon"));
+
+        let mut out = Vec::new();
+        graph.write_graphml(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("<data key=\"label\">c&amp;d</data>"));
+
+        let options = TreeGraphOptions::new().with_subtree(key(2));
+        let graph = TreeGraph::new(&tree, &options).unwrap();
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].label, "c&d");
+
+        let options = TreeGraphOptions::new().with_subtree(key(3));
+        assert!(TreeGraph::new(&tree, &options).is_err());
+    }
+}
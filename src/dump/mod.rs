@@ -0,0 +1,1448 @@
+//! Dumping the tree of validated RPKI objects.
+//!
+//! The tree is collected during a validation run by [`TreeReport`] which
+//! implements [`ProcessRun`] and can therefore be used with the
+//! [`Engine`] either on its own or alongside other processors. Each
+//! publication point collects its data in a [`TreePubPoint`] which only
+//! contributes it to the report if the point is committed.
+//!
+//! Once the run has finished, the report can be converted into a
+//! [`DBDump`] which can then be written in one of the formats defined
+//! by [`TreeFormat`]. Alternatively, the report can write the tree into a
+//! [`TreeStream`] as it is collected to avoid keeping it in memory. Dumps
+//! written as JSON can be read back and two of them compared via
+//! [`TreeDiff`], have their resources audited via [`TreeAudit`], or be
+//! exported as a graph via [`TreeGraph`]. Parts of a dump can be selected
+//! via [`TreeSelection`]. Together with the payload of the same run, a
+//! dump can be used to trace VRPs back to their trust anchor via
+//! [`Provenance`] or to list objects about to expire via [`Expiring`].
+
+pub use self::audit::{TreeAudit, TreeAuditFormat};
+pub use self::diff::{TreeDelta, TreeDiff, TreeDiffFormat};
+pub use self::expiring::{Expiring, ExpiringFormat, ExpiryWindow};
+pub use self::graph::{TreeGraph, TreeGraphFormat, TreeGraphOptions};
+pub use self::provenance::{
+    Provenance, ProvenanceFormat, ProvenanceSelection
+};
+pub use self::select::{SelectionError, TreeSelection};
+pub use self::stream::TreeStream;
+
+mod audit;
+mod diff;
+mod expiring;
+mod graph;
+mod provenance;
+mod select;
+mod stream;
+
+#[cfg(test)]
+pub(crate) mod testing;
+
+use std::{fmt, fs, io};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use bytes::Bytes;
+use crossbeam_queue::SegQueue;
+use log::{error, warn};
+use rpki::uri;
+use rpki::repository::resources::{
+    AsBlocks, IpBlocks,
+};
+use rpki::repository::aspa::AsProviderAttestation;
+use rpki::repository::cert::{Cert, ResourceCert};
+use rpki::repository::crl::Crl;
+use rpki::repository::manifest::ManifestContent;
+use rpki::repository::roa::RouteOriginAttestation;
+use rpki::repository::tal::{Tal, TalUri};
+use rpki::crypto::keys::{KeyIdentifier, PublicKey};
+use rpki::repository::x509::{Name, Serial, Time, Validity};
+use rpki::resources::{Asn, Prefix};
+use rpki::resources::addr::MaxLenPrefix;
+use rpki::rtr::payload::RouteOrigin;
+use serde::{Deserialize, Serialize};
+use crate::constraints::TalConstraints;
+use crate::engine::{
+    CaCert, Engine, ObjectKind, PointSource, ProcessPubPoint, ProcessRun
+};
+use crate::error::Failed;
+use crate::metrics::Metrics;
+
+
+//------------ CaCertDump ----------------------------------------------------
+
+/// The dump of a validated CA certificate.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CaCertDump {
+    parent: KeyIdentifier,
+    id: KeyIdentifier,
+    uri: uri::Rsync,
+    point: PointDump,
+    issuer: Name,
+    subject: Name,
+    pubkey: PublicKey,
+    #[serde(with = "v4_blocks")]
+    v4_resources: IpBlocks,
+    #[serde(with = "v6_blocks")]
+    v6_resources: IpBlocks,
+    as_resources: AsBlocks,
+
+    /// Whether the IPv4 resources are inherited from the issuer.
+    #[serde(default)]
+    v4_inherited: bool,
+
+    /// Whether the IPv6 resources are inherited from the issuer.
+    #[serde(default)]
+    v6_inherited: bool,
+
+    /// Whether the AS resources are inherited from the issuer.
+    #[serde(default)]
+    as_inherited: bool,
+    not_before: Time,
+    not_after: Time,
+}
+
+impl CaCertDump {
+    /// Creates the dump of a CA certificate issued by `parent`.
+    fn new(
+        parent: KeyIdentifier,
+        uri: &uri::Rsync,
+        point: &PointDump,
+        rcert: &ResourceCert,
+    ) -> Self {
+        check_parent(parent, rcert);
+        let validity = rcert.validity();
+        CaCertDump {
+            parent,
+            id: rcert.subject_key_identifier(),
+            uri: uri.clone(),
+            point: point.clone(),
+            issuer: rcert.issuer().clone(),
+            subject: rcert.subject().clone(),
+            pubkey: rcert.subject_public_key_info().clone(),
+            v4_resources: rcert.v4_resources().clone(),
+            v6_resources: rcert.v6_resources().clone(),
+            as_resources: rcert.as_resources().clone(),
+            v4_inherited: rcert.as_cert().v4_resources().is_inherited(),
+            v6_inherited: rcert.as_cert().v6_resources().is_inherited(),
+            as_inherited: rcert.as_cert().as_resources().is_inherited(),
+            not_before: validity.not_before(),
+            not_after: validity.not_after(),
+        }
+    }
+
+    /// Returns the key identifier of the issuing CA.
+    pub fn parent(&self) -> KeyIdentifier {
+        self.parent
+    }
+
+    /// Returns the subject key identifier of the certificate.
+    pub fn id(&self) -> KeyIdentifier {
+        self.id
+    }
+
+    /// Returns the validity of the certificate.
+    pub fn validity(&self) -> Validity {
+        Validity::new(self.not_before, self.not_after)
+    }
+}
+
+
+//------------ ROADump -------------------------------------------------------
+
+/// The dump of a validated ROA.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ROADump {
+    parent: KeyIdentifier,
+    id: KeyIdentifier,
+    uri: uri::Rsync,
+    point: PointDump,
+    issuer: Name,
+    subject: Name,
+    pubkey: PublicKey,
+    as_number: u32,
+    v4_resources: Vec<V4Roa>,
+    v6_resources: Vec<V6Roa>,
+    not_before: Time,
+    not_after: Time,
+
+    /// Whether prefixes were dropped due to the TAL’s constraints.
+    #[serde(default)]
+    constrained: bool,
+}
+
+impl ROADump {
+    /// Creates the dump of a ROA issued by `parent`.
+    ///
+    /// Prefixes outside of `constraints` are left out.
+    fn new(
+        parent: KeyIdentifier,
+        uri: &uri::Rsync,
+        point: &PointDump,
+        rcert: &ResourceCert,
+        route: &RouteOriginAttestation,
+        constraints: Option<&TalConstraints>,
+    ) -> Self {
+        check_parent(parent, rcert);
+        let validity = rcert.validity();
+        let mut res = ROADump {
+            parent,
+            id: rcert.subject_key_identifier(),
+            uri: uri.clone(),
+            point: point.clone(),
+            issuer: rcert.issuer().clone(),
+            subject: rcert.subject().clone(),
+            pubkey: rcert.subject_public_key_info().clone(),
+            as_number: route.as_id().into(),
+            v4_resources: route.v4_addrs().iter().map(|addr| {
+                let (start, end) = addr.range();
+                V4Roa {
+                    start: start.to_v4(),
+                    end: end.to_v4(),
+                    max_length: addr.max_length().unwrap_or(0),
+                }
+            }).collect(),
+            v6_resources: route.v6_addrs().iter().map(|addr| {
+                let (start, end) = addr.range();
+                V6Roa {
+                    start: start.to_v6(),
+                    end: end.to_v6(),
+                    max_length: addr.max_length().unwrap_or(0),
+                }
+            }).collect(),
+            not_before: validity.not_before(),
+            not_after: validity.not_after(),
+            constrained: false,
+        };
+        if let Some(constraints) = constraints {
+            let len = res.v4_resources.len() + res.v6_resources.len();
+            let allowed = |prefix: Option<MaxLenPrefix>| {
+                prefix.is_some_and(|prefix| {
+                    constraints.allows_prefix(prefix.prefix())
+                })
+            };
+            res.v4_resources.retain(|roa| allowed(roa.prefix()));
+            res.v6_resources.retain(|roa| allowed(roa.prefix()));
+            res.constrained = {
+                res.v4_resources.len() + res.v6_resources.len() < len
+            };
+        }
+        res
+    }
+
+    /// Returns the key identifier of the issuing CA.
+    pub fn parent(&self) -> KeyIdentifier {
+        self.parent
+    }
+
+    /// Returns the URI of the ROA.
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    /// Returns the publication point the ROA was published at.
+    pub fn point(&self) -> &PointDump {
+        &self.point
+    }
+
+    /// Returns the validity of the ROA’s EE certificate.
+    pub fn validity(&self) -> Validity {
+        Validity::new(self.not_before, self.not_after)
+    }
+
+    /// Returns whether prefixes were dropped due to the TAL’s constraints.
+    pub fn constrained(&self) -> bool {
+        self.constrained
+    }
+
+    /// Returns an iterator over the route origins of the ROA.
+    ///
+    /// Prefixes that cannot be expressed as route origins are skipped.
+    pub fn origins(&self) -> impl Iterator<Item = RouteOrigin> + '_ {
+        let asn = Asn::from_u32(self.as_number);
+        self.v4_resources.iter().filter_map(V4Roa::prefix).chain(
+            self.v6_resources.iter().filter_map(V6Roa::prefix)
+        ).map(move |prefix| RouteOrigin::new(prefix, asn))
+    }
+}
+
+
+//------------ V4Roa and V6Roa -----------------------------------------------
+
+/// An IPv4 prefix of a ROA.
+///
+/// A missing max length is given as zero.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd,
+    Serialize
+)]
+pub struct V4Roa {
+    start: Ipv4Addr,
+    end: Ipv4Addr,
+    max_length: u8,
+}
+
+/// An IPv6 prefix of a ROA.
+///
+/// A missing max length is given as zero.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd,
+    Serialize
+)]
+pub struct V6Roa {
+    start: Ipv6Addr,
+    end: Ipv6Addr,
+    max_length: u8,
+}
+
+impl V4Roa {
+    /// Returns the prefix with its max length.
+    ///
+    /// Returns `None` if the prefix or max length is invalid.
+    fn prefix(&self) -> Option<MaxLenPrefix> {
+        let len = (u32::from(self.start) ^ u32::from(self.end)).leading_zeros();
+        MaxLenPrefix::new(
+            Prefix::new(self.start.into(), len as u8).ok()?,
+            (self.max_length != 0).then_some(self.max_length),
+        ).ok()
+    }
+}
+
+impl V6Roa {
+    /// Returns the prefix with its max length.
+    ///
+    /// Returns `None` if the prefix or max length is invalid.
+    fn prefix(&self) -> Option<MaxLenPrefix> {
+        let len = (
+            u128::from(self.start) ^ u128::from(self.end)
+        ).leading_zeros();
+        MaxLenPrefix::new(
+            Prefix::new(self.start.into(), len as u8).ok()?,
+            (self.max_length != 0).then_some(self.max_length),
+        ).ok()
+    }
+}
+
+
+//--- Display
+
+impl fmt::Display for V4Roa {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let len = (u32::from(self.start) ^ u32::from(self.end)).leading_zeros();
+        write!(f, "{}/{}", self.start, len)?;
+        if self.max_length != 0 {
+            write!(f, "-{}", self.max_length)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for V6Roa {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let len = (
+            u128::from(self.start) ^ u128::from(self.end)
+        ).leading_zeros();
+        write!(f, "{}/{}", self.start, len)?;
+        if self.max_length != 0 {
+            write!(f, "-{}", self.max_length)?;
+        }
+        Ok(())
+    }
+}
+
+
+//------------ TALDump -------------------------------------------------------
+
+/// The dump of a trust anchor.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TALDump {
+    id: KeyIdentifier,
+    name: String,
+
+    /// The URI the trust anchor certificate was retrieved from.
+    uri: String,
+
+    /// The start of the validity of the trust anchor certificate.
+    ///
+    /// This is missing in dumps written by older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    not_before: Option<Time>,
+
+    /// The end of the validity of the trust anchor certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    not_after: Option<Time>,
+}
+
+impl TALDump {
+    /// Returns the subject key identifier of the trust anchor certificate.
+    pub fn id(&self) -> KeyIdentifier {
+        self.id
+    }
+
+    /// Returns the name of the TAL.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the validity of the trust anchor certificate if known.
+    pub fn validity(&self) -> Option<Validity> {
+        Some(Validity::new(self.not_before?, self.not_after?))
+    }
+}
+
+
+//------------ PointDump -----------------------------------------------------
+
+/// The publication point an object was published at.
+///
+/// This describes where the object came from: the trust anchor the point
+/// was derived from, the repository and manifest URIs of the CA, and
+/// whether the data was freshly collected or taken from the store during
+/// the run.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PointDump {
+    /// The label of the TAL the publication point was derived from.
+    tal: String,
+    ca_repository: uri::Rsync,
+    rpki_manifest: uri::Rsync,
+    rpki_notify: Option<uri::Https>,
+    source: PointSource,
+}
+
+impl PointDump {
+    /// Creates the dump of the publication point of the given CA.
+    ///
+    /// The source is only known once processing of the point starts and
+    /// is initially assumed to be the store.
+    fn new(tal: String, cert: &CaCert) -> Self {
+        PointDump {
+            tal,
+            ca_repository: cert.ca_repository().clone(),
+            rpki_manifest: cert.rpki_manifest().clone(),
+            rpki_notify: cert.rpki_notify().cloned(),
+            source: PointSource::Stored,
+        }
+    }
+
+    /// Returns the label of the TAL the point was derived from.
+    pub fn tal(&self) -> &str {
+        &self.tal
+    }
+}
+
+
+//--- PartialEq and Eq
+
+/// Compares everything but the source.
+///
+/// Whether a point was collected or taken from the store changes from run
+/// to run without any change to the objects published there.
+impl PartialEq for PointDump {
+    fn eq(&self, other: &Self) -> bool {
+        self.tal == other.tal
+            && self.ca_repository == other.ca_repository
+            && self.rpki_manifest == other.rpki_manifest
+            && self.rpki_notify == other.rpki_notify
+    }
+}
+
+impl Eq for PointDump { }
+
+
+//------------ RouterCertDump ------------------------------------------------
+
+/// The dump of a validated BGPsec router certificate.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RouterCertDump {
+    parent: KeyIdentifier,
+    id: KeyIdentifier,
+    uri: uri::Rsync,
+    point: PointDump,
+    issuer: Name,
+    subject: Name,
+    pubkey: PublicKey,
+    as_resources: AsBlocks,
+    not_before: Time,
+    not_after: Time,
+
+    /// Whether AS numbers were dropped due to the TAL’s constraints.
+    #[serde(default)]
+    constrained: bool,
+}
+
+impl RouterCertDump {
+    /// Creates the dump of a router certificate issued by `parent`.
+    ///
+    /// The AS numbers are clipped to `constraints`.
+    fn new(
+        parent: KeyIdentifier,
+        uri: &uri::Rsync,
+        point: &PointDump,
+        cert: &Cert,
+        constraints: Option<&TalConstraints>,
+    ) -> Self {
+        check_parent(parent, cert);
+        let validity = cert.validity();
+        let asns = cert.as_resources().to_blocks().unwrap_or_default();
+        let (as_resources, constrained) = match constraints {
+            Some(constraints) => {
+                let clipped = constraints.clip_asns(&asns);
+                let constrained = clipped.asn_count() < asns.asn_count();
+                (clipped, constrained)
+            }
+            None => (asns, false)
+        };
+        RouterCertDump {
+            parent,
+            id: cert.subject_key_identifier(),
+            uri: uri.clone(),
+            point: point.clone(),
+            issuer: cert.issuer().clone(),
+            subject: cert.subject().clone(),
+            pubkey: cert.subject_public_key_info().clone(),
+            as_resources,
+            not_before: validity.not_before(),
+            not_after: validity.not_after(),
+            constrained,
+        }
+    }
+
+    /// Returns the key identifier of the issuing CA.
+    pub fn parent(&self) -> KeyIdentifier {
+        self.parent
+    }
+
+    /// Returns the subject key identifier of the certificate.
+    pub fn id(&self) -> KeyIdentifier {
+        self.id
+    }
+
+    /// Returns the URI of the certificate.
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    /// Returns the publication point the certificate was published at.
+    pub fn point(&self) -> &PointDump {
+        &self.point
+    }
+
+    /// Returns the router key.
+    pub fn pubkey(&self) -> &PublicKey {
+        &self.pubkey
+    }
+
+    /// Returns the AS numbers of the certificate.
+    pub fn as_resources(&self) -> &AsBlocks {
+        &self.as_resources
+    }
+
+    /// Returns whether AS numbers were dropped due to the TAL’s constraints.
+    pub fn constrained(&self) -> bool {
+        self.constrained
+    }
+
+    /// Returns the validity of the certificate.
+    pub fn validity(&self) -> Validity {
+        Validity::new(self.not_before, self.not_after)
+    }
+}
+
+
+//------------ AspaDump ------------------------------------------------------
+
+/// The dump of a validated ASPA object.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AspaDump {
+    parent: KeyIdentifier,
+    id: KeyIdentifier,
+    uri: uri::Rsync,
+    point: PointDump,
+    customer_as: u32,
+    provider_as_set: Vec<u32>,
+    not_before: Time,
+    not_after: Time,
+}
+
+impl AspaDump {
+    /// Creates the dump of an ASPA object issued by `parent`.
+    fn new(
+        parent: KeyIdentifier,
+        uri: &uri::Rsync,
+        point: &PointDump,
+        rcert: &ResourceCert,
+        aspa: &AsProviderAttestation,
+    ) -> Self {
+        check_parent(parent, rcert);
+        let validity = rcert.validity();
+        AspaDump {
+            parent,
+            id: rcert.subject_key_identifier(),
+            uri: uri.clone(),
+            point: point.clone(),
+            customer_as: aspa.customer_as().into(),
+            provider_as_set: {
+                aspa.provider_as_set().iter().map(Into::into).collect()
+            },
+            not_before: validity.not_before(),
+            not_after: validity.not_after(),
+        }
+    }
+
+    /// Returns the key identifier of the issuing CA.
+    pub fn parent(&self) -> KeyIdentifier {
+        self.parent
+    }
+
+    /// Returns the URI of the ASPA object.
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    /// Returns the publication point the object was published at.
+    pub fn point(&self) -> &PointDump {
+        &self.point
+    }
+
+    /// Returns the customer AS number.
+    pub fn customer_as(&self) -> Asn {
+        Asn::from_u32(self.customer_as)
+    }
+
+    /// Returns an iterator over the provider AS numbers.
+    pub fn provider_as_set(&self) -> impl Iterator<Item = Asn> + '_ {
+        self.provider_as_set.iter().copied().map(Asn::from_u32)
+    }
+
+    /// Returns the validity of the object’s EE certificate.
+    pub fn validity(&self) -> Validity {
+        Validity::new(self.not_before, self.not_after)
+    }
+}
+
+
+//------------ GbrDump -------------------------------------------------------
+
+/// The dump of a validated Ghostbuster record.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GbrDump {
+    parent: KeyIdentifier,
+    id: KeyIdentifier,
+    uri: uri::Rsync,
+    point: PointDump,
+
+    /// The vCard of the record.
+    ///
+    /// Any invalid UTF-8 sequences have been replaced.
+    vcard: String,
+    not_before: Time,
+    not_after: Time,
+}
+
+impl GbrDump {
+    /// Creates the dump of a Ghostbuster record issued by `parent`.
+    fn new(
+        parent: KeyIdentifier,
+        uri: &uri::Rsync,
+        point: &PointDump,
+        rcert: &ResourceCert,
+        content: &[u8],
+    ) -> Self {
+        check_parent(parent, rcert);
+        let validity = rcert.validity();
+        GbrDump {
+            parent,
+            id: rcert.subject_key_identifier(),
+            uri: uri.clone(),
+            point: point.clone(),
+            vcard: String::from_utf8_lossy(content).into_owned(),
+            not_before: validity.not_before(),
+            not_after: validity.not_after(),
+        }
+    }
+}
+
+
+//------------ ManifestDump --------------------------------------------------
+
+/// The dump of the validated manifest of a CA.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ManifestDump {
+    parent: KeyIdentifier,
+
+    /// The key identifier of the manifest’s EE certificate.
+    id: KeyIdentifier,
+    uri: uri::Rsync,
+    point: PointDump,
+    manifest_number: Serial,
+    this_update: Time,
+    next_update: Time,
+
+    /// The number of files listed on the manifest.
+    entries: usize,
+}
+
+impl ManifestDump {
+    /// Creates the dump of a manifest issued by `parent`.
+    fn new(
+        parent: KeyIdentifier,
+        uri: &uri::Rsync,
+        point: &PointDump,
+        ee_cert: &ResourceCert,
+        manifest: &ManifestContent,
+    ) -> Self {
+        check_parent(parent, ee_cert);
+        ManifestDump {
+            parent,
+            id: ee_cert.subject_key_identifier(),
+            uri: uri.clone(),
+            point: point.clone(),
+            manifest_number: manifest.manifest_number(),
+            this_update: manifest.this_update(),
+            next_update: manifest.next_update(),
+            entries: manifest.len(),
+        }
+    }
+
+    /// Returns the key identifier of the issuing CA.
+    pub fn parent(&self) -> KeyIdentifier {
+        self.parent
+    }
+
+    /// Returns the time of the next update of the manifest.
+    pub fn next_update(&self) -> Time {
+        self.next_update
+    }
+}
+
+
+//------------ CrlDump -------------------------------------------------------
+
+/// The dump of the validated manifest CRL of a CA.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CrlDump {
+    parent: KeyIdentifier,
+    uri: uri::Rsync,
+    point: PointDump,
+    crl_number: Serial,
+    this_update: Time,
+    next_update: Time,
+
+    /// The number of revoked certificates.
+    revoked_certs: usize,
+}
+
+impl CrlDump {
+    /// Creates the dump of a CRL issued by `parent`.
+    fn new(
+        parent: KeyIdentifier,
+        uri: &uri::Rsync,
+        point: &PointDump,
+        crl: &Crl,
+    ) -> Self {
+        if *crl.authority_key_identifier() != parent {
+            warn!("Parent field mismatch in CRL of {}", parent);
+        }
+        CrlDump {
+            parent,
+            uri: uri.clone(),
+            point: point.clone(),
+            crl_number: crl.crl_number(),
+            this_update: crl.this_update(),
+            next_update: crl.next_update(),
+            revoked_certs: crl.revoked_certs().iter().count(),
+        }
+    }
+}
+
+
+//------------ RejectedObjectDump --------------------------------------------
+
+/// The dump of an object rejected during validation.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RejectedObjectDump {
+    parent: KeyIdentifier,
+    uri: uri::Rsync,
+    kind: ObjectKind,
+    point: PointDump,
+
+    /// The reason why the object was rejected.
+    reason: String,
+}
+
+
+//------------ RejectedPointDump ---------------------------------------------
+
+/// The dump of a publication point rejected during validation.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RejectedPointDump {
+    /// The key identifier of the publication point’s CA.
+    id: KeyIdentifier,
+
+    /// The URI of the CA certificate.
+    uri: String,
+    point: PointDump,
+
+    /// The reason why the publication point was rejected.
+    reason: String,
+}
+
+
+//------------ DBDump --------------------------------------------------------
+
+/// The tree of a complete validation run.
+///
+/// All objects are linked to the CA that issued them via the key
+/// identifier of that CA in their `parent` field. Apart from the TALs,
+/// they also carry their own URI and, in their `point` field, the
+/// publication point they were published at.
+///
+/// Rejected objects and publication points are only included if this was
+/// explicitly requested when creating the [`TreeReport`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DBDump {
+    tals: Vec<TALDump>,
+    ca_certs: Vec<CaCertDump>,
+    roas: Vec<ROADump>,
+    #[serde(default)]
+    aspas: Vec<AspaDump>,
+    #[serde(default)]
+    router_certs: Vec<RouterCertDump>,
+    #[serde(default)]
+    gbrs: Vec<GbrDump>,
+    #[serde(default)]
+    manifests: Vec<ManifestDump>,
+    #[serde(default)]
+    crls: Vec<CrlDump>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    rejected_objects: Vec<RejectedObjectDump>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    rejected_points: Vec<RejectedPointDump>,
+}
+
+impl DBDump {
+    /// Returns the trust anchors.
+    pub fn tals(&self) -> &[TALDump] {
+        &self.tals
+    }
+
+    /// Returns the CA certificates.
+    pub fn ca_certs(&self) -> &[CaCertDump] {
+        &self.ca_certs
+    }
+
+    /// Returns the ROAs.
+    pub fn roas(&self) -> &[ROADump] {
+        &self.roas
+    }
+
+    /// Returns the ASPA objects.
+    pub fn aspas(&self) -> &[AspaDump] {
+        &self.aspas
+    }
+
+    /// Returns the router certificates.
+    pub fn router_certs(&self) -> &[RouterCertDump] {
+        &self.router_certs
+    }
+
+    /// Returns the manifests.
+    pub fn manifests(&self) -> &[ManifestDump] {
+        &self.manifests
+    }
+
+    /// Loads a dump previously written as JSON from the given path.
+    ///
+    /// Logs an error and fails if the file cannot be read.
+    pub fn load(path: &Path) -> Result<Self, Failed> {
+        let file = match fs::File::open(path) {
+            Ok(file) => io::BufReader::new(file),
+            Err(err) => {
+                error!(
+                    "Failed to open tree dump '{}': {}",
+                    path.display(), err
+                );
+                return Err(Failed)
+            }
+        };
+        Self::read_json(file).map_err(|err| {
+            error!(
+                "Failed to read tree dump '{}': {}",
+                path.display(), err
+            );
+            Failed
+        })
+    }
+
+    /// Reads a dump previously written as JSON from the given source.
+    pub fn read_json(
+        source: impl io::Read
+    ) -> Result<Self, serde_json::Error> {
+        serde_json::from_reader(source)
+    }
+
+    /// Writes the dump as pretty-printed JSON to the given target.
+    pub fn write_json(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer_pretty(&mut *target, self)?;
+        target.write_all(b"\n")
+    }
+
+    /// Writes the dump as sorted newline-delimited JSON to the target.
+    ///
+    /// See [`TreeStream`] for details on the format.
+    pub fn write_ndjson(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        let stream = TreeStream::new()?;
+        stream.push(self);
+        stream.finish(target)
+    }
+
+    /// Writes the dump in the given format to the given target.
+    pub fn write(
+        &self, format: TreeFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            TreeFormat::Json => self.write_json(target),
+            TreeFormat::Ndjson => self.write_ndjson(target),
+        }
+    }
+
+    /// Moves all objects of `other` into `self`.
+    fn append(&mut self, mut other: DBDump) {
+        self.tals.append(&mut other.tals);
+        self.ca_certs.append(&mut other.ca_certs);
+        self.roas.append(&mut other.roas);
+        self.aspas.append(&mut other.aspas);
+        self.router_certs.append(&mut other.router_certs);
+        self.gbrs.append(&mut other.gbrs);
+        self.manifests.append(&mut other.manifests);
+        self.crls.append(&mut other.crls);
+        self.rejected_objects.append(&mut other.rejected_objects);
+        self.rejected_points.append(&mut other.rejected_points);
+    }
+}
+
+
+//------------ TreeReport ----------------------------------------------------
+
+/// Collects the tree of validated objects during a validation run.
+///
+/// By default, the report keeps the tree in memory. If a [`TreeStream`]
+/// is given via [`with_stream`][Self::with_stream], the tree is written
+/// to the stream as each publication point is completed instead.
+#[derive(Debug, Default)]
+pub struct TreeReport {
+    /// The objects from the trust anchors and committed publication points.
+    points: SegQueue<DBDump>,
+
+    /// The stream to write the objects to instead.
+    stream: Option<TreeStream>,
+
+    /// Should rejected objects and publication points be included?
+    rejected: bool,
+}
+
+impl TreeReport {
+    /// Creates a new, empty tree report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Includes rejected objects and publication points in the report.
+    pub fn with_rejected(mut self) -> Self {
+        self.rejected = true;
+        self
+    }
+
+    /// Writes the tree to the given stream rather than keeping it.
+    pub fn with_stream(mut self, stream: TreeStream) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Runs the engine for the report.
+    pub fn process(self, engine: &Engine) -> Result<(Self, Metrics), Failed> {
+        let mut run = engine.start(&self)?;
+        run.process()?;
+        run.cleanup()?;
+        let metrics = run.done();
+        Ok((self, metrics))
+    }
+
+    /// Writes the tree in the given format to the target.
+    ///
+    /// If the report was streaming, the stream is finished into the
+    /// target and `format` is ignored.
+    pub fn write(
+        self, format: TreeFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match self.stream {
+            Some(stream) => stream.finish(target),
+            None => self.into_dump().write(format, target),
+        }
+    }
+
+    /// Converts the report into the tree dump.
+    ///
+    /// If the report was streaming, the dump will be empty.
+    pub fn into_dump(self) -> DBDump {
+        let mut res = DBDump::default();
+        for point in self.points {
+            res.append(point)
+        }
+        res
+    }
+
+    /// Adds the objects of a trust anchor or publication point.
+    fn push(&self, dump: DBDump) {
+        match self.stream.as_ref() {
+            Some(stream) => stream.push(&dump),
+            None => self.points.push(dump),
+        }
+    }
+}
+
+impl<'a> ProcessRun for &'a TreeReport {
+    type PubPoint = TreePubPoint<'a>;
+
+    fn process_ta(
+        &self,
+        tal: &Tal, uri: &TalUri, cert: &CaCert,
+        _tal_index: usize,
+    ) -> Result<Option<Self::PubPoint>, Failed> {
+        let id = cert.cert().subject_key_identifier();
+        let validity = cert.cert().validity();
+        self.push(DBDump {
+            tals: vec![TALDump {
+                id,
+                name: tal.info().name().into(),
+                uri: uri.to_string(),
+                not_before: Some(validity.not_before()),
+                not_after: Some(validity.not_after()),
+            }],
+            .. Default::default()
+        });
+        Ok(Some(TreePubPoint::new(self, tal.info().name().into(), cert)))
+    }
+}
+
+
+//------------ TreePubPoint --------------------------------------------------
+
+/// Collects the tree data of a single publication point.
+///
+/// The data is only added to the report when the point is committed.
+#[derive(Debug)]
+pub struct TreePubPoint<'a> {
+    /// The report the data is contributed to.
+    report: &'a TreeReport,
+
+    /// The key identifier of the publication point’s CA.
+    parent: KeyIdentifier,
+
+    /// The publication point itself.
+    point: PointDump,
+
+    /// The objects published by the point.
+    dump: DBDump,
+
+    /// The reason why the publication point was rejected if it was.
+    rejected_reason: Option<String>,
+
+    /// The constraints on the resources of the TAL.
+    constraints: Option<Arc<TalConstraints>>,
+}
+
+impl<'a> TreePubPoint<'a> {
+    /// Creates a new processor for the given CA derived from a TAL.
+    fn new(report: &'a TreeReport, tal: String, cert: &CaCert) -> Self {
+        TreePubPoint {
+            report,
+            parent: cert.cert().subject_key_identifier(),
+            point: PointDump::new(tal, cert),
+            dump: DBDump::default(),
+            rejected_reason: None,
+            constraints: cert.constraints().cloned(),
+        }
+    }
+}
+
+impl<'a> ProcessPubPoint for TreePubPoint<'a> {
+    fn point_source(&mut self, source: PointSource) {
+        self.point.source = source;
+    }
+
+    fn want(&self, uri: &uri::Rsync) -> Result<bool, Failed> {
+        Ok(
+            uri.ends_with(".cer") || uri.ends_with(".roa")
+            || uri.ends_with(".asa") || uri.ends_with(".gbr")
+        )
+    }
+
+    fn rejected_object(
+        &mut self, uri: &uri::Rsync, kind: ObjectKind, reason: &str
+    ) {
+        if self.report.rejected {
+            self.dump.rejected_objects.push(RejectedObjectDump {
+                parent: self.parent,
+                uri: uri.clone(),
+                kind,
+                point: self.point.clone(),
+                reason: reason.into(),
+            })
+        }
+    }
+
+    fn rejected_point(&mut self, reason: &str) {
+        self.rejected_reason = Some(reason.into());
+    }
+
+    fn process_manifest(
+        &mut self,
+        uri: &uri::Rsync,
+        ee_cert: &ResourceCert,
+        manifest: &ManifestContent,
+        crl_uri: &uri::Rsync,
+        crl: &Crl,
+    ) -> Result<(), Failed> {
+        self.dump.manifests.push(ManifestDump::new(
+            self.parent, uri, &self.point, ee_cert, manifest
+        ));
+        self.dump.crls.push(
+            CrlDump::new(self.parent, crl_uri, &self.point, crl)
+        );
+        Ok(())
+    }
+
+    fn process_ca(
+        &mut self, uri: &uri::Rsync, cert: &CaCert,
+    ) -> Result<Option<Self>, Failed> {
+        self.dump.ca_certs.push(
+            CaCertDump::new(self.parent, uri, &self.point, cert.cert())
+        );
+        Ok(Some(Self::new(self.report, self.point.tal.clone(), cert)))
+    }
+
+    fn process_router_cert(
+        &mut self, uri: &uri::Rsync, cert: Cert, _ca_cert: &CaCert,
+    ) -> Result<(), Failed> {
+        self.dump.router_certs.push(RouterCertDump::new(
+            self.parent, uri, &self.point, &cert,
+            self.constraints.as_deref(),
+        ));
+        Ok(())
+    }
+
+    fn process_roa(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        route: RouteOriginAttestation
+    ) -> Result<(), Failed> {
+        self.dump.roas.push(ROADump::new(
+            self.parent, uri, &self.point, &cert, &route,
+            self.constraints.as_deref(),
+        ));
+        Ok(())
+    }
+
+    fn process_aspa(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        aspa: AsProviderAttestation,
+    ) -> Result<(), Failed> {
+        if let Some(constraints) = self.constraints.as_ref() {
+            if !constraints.allows_asn(aspa.customer_as()) {
+                self.rejected_object(
+                    uri, ObjectKind::Aspa,
+                    "customer AS outside of TAL constraints"
+                );
+                return Ok(())
+            }
+        }
+        self.dump.aspas.push(
+            AspaDump::new(self.parent, uri, &self.point, &cert, &aspa)
+        );
+        Ok(())
+    }
+
+    fn process_gbr(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        content: Bytes
+    ) -> Result<(), Failed> {
+        self.dump.gbrs.push(
+            GbrDump::new(self.parent, uri, &self.point, &cert, &content)
+        );
+        Ok(())
+    }
+
+    fn restart(&mut self) -> Result<(), Failed> {
+        // Keep the objects rejected so far since they explain why we fell
+        // back to the stored data.
+        self.dump = DBDump {
+            rejected_objects: std::mem::take(&mut self.dump.rejected_objects),
+            .. Default::default()
+        };
+        Ok(())
+    }
+
+    fn commit(self) {
+        self.report.push(self.dump)
+    }
+
+    fn cancel(self, cert: &CaCert) {
+        if !self.report.rejected {
+            return
+        }
+        self.report.push(DBDump {
+            rejected_objects: self.dump.rejected_objects,
+            rejected_points: vec![RejectedPointDump {
+                id: self.parent,
+                uri: cert.uri().to_string(),
+                point: self.point,
+                reason: self.rejected_reason.unwrap_or_else(|| {
+                    String::from("invalid publication point")
+                }),
+            }],
+            .. Default::default()
+        })
+    }
+}
+
+
+//------------ TreeFormat ----------------------------------------------------
+
+/// The output format for the tree dump.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TreeFormat {
+    /// A single JSON object with lists of all the objects.
+    Json,
+
+    /// One JSON object per line for each object, sorted by kind and URI.
+    Ndjson,
+}
+
+impl TreeFormat {
+    /// All known tree format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("json", TreeFormat::Json),
+        ("ndjson", TreeFormat::Ndjson),
+    ];
+
+    /// The default tree format name.
+    pub const DEFAULT_VALUE: &'static str = "json";
+}
+
+
+//--- FromStr
+
+impl FromStr for TreeFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown tree format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Checks that the certificate’s authority key identifier matches `parent`.
+///
+/// Only logs a warning if it doesn’t.
+fn check_parent(parent: KeyIdentifier, cert: &Cert) {
+    let id = cert.subject_key_identifier();
+    match cert.authority_key_identifier() {
+        Some(aki) => {
+            if aki != parent {
+                warn!("Parent field mismatch in {}", id);
+                warn!("  Expected: {}", parent);
+                warn!("  Got: {}", aki);
+            }
+        }
+        None => warn!("No parent field in {}", id)
+    }
+}
+
+
+//------------ Serializing IP Blocks -----------------------------------------
+
+/// Serializes IPv4 blocks as a sequence of start and end addresses.
+mod v4_blocks {
+    use std::net::Ipv4Addr;
+    use rpki::repository::resources::{Addr, IpBlocks, IpBlocksBuilder};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    struct V4Block {
+        start: Ipv4Addr,
+        end: Ipv4Addr,
+    }
+
+    pub fn serialize<S: Serializer>(
+        blocks: &IpBlocks, serializer: S
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(blocks.iter().map(|block| {
+            V4Block { start: block.min().to_v4(), end: block.max().to_v4() }
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<IpBlocks, D::Error> {
+        let mut res = IpBlocksBuilder::new();
+        for block in Vec::<V4Block>::deserialize(deserializer)? {
+            res.push((Addr::from_v4(block.start), Addr::from_v4(block.end)));
+        }
+        Ok(res.finalize())
+    }
+}
+
+/// Serializes IPv6 blocks as a sequence of start and end addresses.
+mod v6_blocks {
+    use std::net::Ipv6Addr;
+    use rpki::repository::resources::{Addr, IpBlocks, IpBlocksBuilder};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    struct V6Block {
+        start: Ipv6Addr,
+        end: Ipv6Addr,
+    }
+
+    pub fn serialize<S: Serializer>(
+        blocks: &IpBlocks, serializer: S
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(blocks.iter().map(|block| {
+            V6Block { start: block.min().to_v6(), end: block.max().to_v6() }
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D
+    ) -> Result<IpBlocks, D::Error> {
+        let mut res = IpBlocksBuilder::new();
+        for block in Vec::<V6Block>::deserialize(deserializer)? {
+            res.push((Addr::from_v6(block.start), Addr::from_v6(block.end)));
+        }
+        Ok(res.finalize())
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::testing::{TAL_NAME, TestRepository};
+
+    #[test]
+    fn cancelled_point() {
+        let (mut repo, mut ta) = TestRepository::new();
+        let mut good = repo.child(
+            &mut ta, "good", "10.1.0.0/16", "AS64496"
+        );
+        repo.roa(&mut good, "good", 64496, "10.1.0.0/24");
+        repo.publish(&mut good);
+
+        // The bad CA has more child CAs than allowed, so its point is
+        // rejected after all its objects have been processed.
+        let mut bad = repo.child(&mut ta, "bad", "10.2.0.0/16", "AS64497");
+        repo.roa(&mut bad, "bad", 64497, "10.2.0.0/24");
+        for name in ["one", "two", "three"] {
+            let mut child = repo.child(&mut bad, name, "10.2.0.0/24", "");
+            repo.publish(&mut child);
+        }
+        repo.publish(&mut bad);
+        repo.publish(&mut ta);
+        repo.config_mut().max_child_cas = Some(2);
+
+        let (report, _) = TreeReport::new().with_rejected().process(
+            &repo.engine()
+        ).unwrap();
+        let dump = report.into_dump();
+
+        // Only the committed points contribute objects.
+        assert_eq!(dump.roas().len(), 1);
+        assert!(dump.roas()[0].uri().ends_with("good.roa"));
+        let mut cas: Vec<_> = dump.ca_certs().iter().map(|cert| {
+            cert.uri.as_str().rsplit('/').next().unwrap()
+        }).collect();
+        cas.sort();
+        assert_eq!(cas, ["bad.cer", "good.cer"]);
+        assert_eq!(dump.manifests.len(), 2);
+        assert!(dump.manifests.iter().all(|manifest| {
+            !manifest.uri.as_str().contains("/bad/")
+        }));
+
+        // The cancelled point is reported with its reason.
+        assert_eq!(dump.rejected_points.len(), 1);
+        assert!(dump.rejected_points[0].uri.ends_with("bad.cer"));
+        assert_eq!(dump.rejected_points[0].reason, "too many child CAs");
+        assert!(dump.rejected_objects.iter().any(|object| {
+            object.reason == "exceeds limit of 2 child CAs"
+        }));
+
+        // Without rejected objects, nothing of the point remains.
+        let (report, _) = TreeReport::new().process(&repo.engine()).unwrap();
+        let dump = report.into_dump();
+        assert_eq!(dump.roas().len(), 1);
+        assert!(dump.rejected_points.is_empty());
+        assert!(dump.rejected_objects.is_empty());
+    }
+
+    #[test]
+    fn constrained_tree() {
+        let (repo, mut ta) = TestRepository::new();
+        fs::write(
+            repo.config().extra_tals_dir.as_ref().unwrap().join(
+                format!("{}.constraints", TAL_NAME)
+            ),
+            "allow 10.0.0.0/16\nallow AS64496\n"
+        ).unwrap();
+        let mut ca = repo.child(
+            &mut ta, "ca", "10.0.0.0/8", "AS64496-AS64499"
+        );
+        repo.roa(&mut ca, "a", 64496, "10.0.0.0/24");
+        repo.roa(&mut ca, "b", 64497, "10.1.0.0/24");
+        repo.aspa(&mut ca, "c", 64496, &[64497]);
+        repo.aspa(&mut ca, "d", 64497, &[64496]);
+        repo.publish(&mut ca);
+        repo.publish(&mut ta);
+
+        let (report, _) = TreeReport::new().with_rejected().process(
+            &repo.engine()
+        ).unwrap();
+        let dump = report.into_dump();
+        let roa = |name: &str| {
+            dump.roas().iter().find(|roa| roa.uri().ends_with(name)).unwrap()
+        };
+        assert!(!roa("a.roa").constrained());
+        assert_eq!(roa("a.roa").origins().count(), 1);
+        assert!(roa("b.roa").constrained());
+        assert_eq!(roa("b.roa").origins().count(), 0);
+        #[cfg(feature = "aspa")]
+        {
+            assert_eq!(dump.aspas().len(), 1);
+            assert_eq!(
+                dump.aspas()[0].customer_as(), Asn::from_u32(64496)
+            );
+            assert_eq!(dump.rejected_objects.len(), 1);
+            assert!(dump.rejected_objects[0].uri.ends_with("d.asa"));
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//! Tracing VRPs back to their trust anchor.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+use log::error;
+use rpki::crypto::keys::KeyIdentifier;
+use rpki::repository::x509::{Serial, Time};
+use rpki::resources::{Asn, Prefix};
+use rpki::rtr::payload::RouteOrigin;
+use serde::Serialize;
+use crate::error::Failed;
+use crate::payload::PayloadSnapshot;
+use crate::utils::date::format_iso_date;
+use super::{DBDump, PointDump};
+
+
+//------------ ProvenanceSelection -------------------------------------------
+
+/// The VRPs to trace.
+///
+/// A VRP is selected if it matches all the given conditions: its prefix
+/// covers the selected prefix – or is covered by it if more specifics are
+/// to be included – and its origin AS is the selected AS number.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProvenanceSelection {
+    /// The prefix the VRPs need to cover.
+    prefix: Option<Prefix>,
+
+    /// The origin AS number of the VRPs.
+    asn: Option<Asn>,
+
+    /// Include VRPs for prefixes covered by `prefix`, too.
+    more_specifics: bool,
+}
+
+impl ProvenanceSelection {
+    /// Creates a new selection.
+    pub fn new(
+        prefix: Option<Prefix>, asn: Option<Asn>, more_specifics: bool
+    ) -> Self {
+        ProvenanceSelection { prefix, asn, more_specifics }
+    }
+
+    /// Creates a selection from the query of an HTTP request.
+    ///
+    /// The query must contain at least one of the `prefix` and `asn`
+    /// parameters and can contain the `more-specifics` parameter with no
+    /// value. Returns an error if there are any other or duplicate
+    /// parameters or if a value is invalid.
+    pub fn from_query(query: Option<&str>) -> Result<Self, Failed> {
+        let mut res = Self::default();
+        for (key, value) in form_urlencoded::parse(
+            query.unwrap_or_default().as_ref()
+        ) {
+            if key == "prefix" && res.prefix.is_none() {
+                res.prefix = Some(
+                    Prefix::from_str(&value).map_err(|_| Failed)?
+                );
+            }
+            else if key == "asn" && res.asn.is_none() {
+                res.asn = Some(Asn::from_str(&value).map_err(|_| Failed)?);
+            }
+            else if key == "more-specifics" && value.is_empty() {
+                res.more_specifics = true;
+            }
+            else {
+                return Err(Failed)
+            }
+        }
+        if res.prefix.is_none() && res.asn.is_none() {
+            return Err(Failed)
+        }
+        Ok(res)
+    }
+
+    /// Returns whether the given route origin is selected.
+    pub fn include_origin(&self, origin: RouteOrigin) -> bool {
+        if let Some(asn) = self.asn {
+            if origin.asn != asn {
+                return false
+            }
+        }
+        if let Some(prefix) = self.prefix {
+            if !(
+                origin.prefix.prefix().covers(prefix)
+                || (
+                    self.more_specifics
+                    && prefix.covers(origin.prefix.prefix())
+                )
+            ) {
+                return false
+            }
+        }
+        true
+    }
+}
+
+
+//------------ Provenance ----------------------------------------------------
+
+/// The provenance of a set of VRPs.
+///
+/// For each VRP, all the sources it was derived from are listed. For a
+/// ROA, this is the complete chain of certificates from the trust anchor
+/// down to the ROA itself as far as it can be found in the tree. For
+/// local exceptions, it is the file and comment of the exception.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Provenance {
+    /// The selected VRPs.
+    vrps: Vec<VrpProvenance>,
+}
+
+impl Provenance {
+    /// Traces the selected VRPs of the snapshot through the tree.
+    pub fn new(
+        snapshot: &PayloadSnapshot,
+        tree: &DBDump,
+        selection: &ProvenanceSelection,
+    ) -> Self {
+        let roas: HashMap<_, _> = tree.roas.iter().map(|roa| {
+            (roa.uri.as_str(), roa)
+        }).collect();
+        let cas: HashMap<_, _> = tree.ca_certs.iter().map(|cert| {
+            (cert.id, cert)
+        }).collect();
+        let tals: HashMap<_, _> = tree.tals.iter().map(|tal| {
+            (tal.id, tal)
+        }).collect();
+        let manifests: HashMap<_, _> = tree.manifests.iter().map(|manifest| {
+            (manifest.parent, manifest.manifest_number)
+        }).collect();
+
+        let vrps = snapshot.origins().filter(|(origin, _)| {
+            selection.include_origin(*origin)
+        }).map(|(origin, info)| {
+            VrpProvenance {
+                asn: origin.asn.into_u32(),
+                prefix: origin.prefix.prefix().to_string(),
+                max_length: origin.prefix.resolved_max_len(),
+                sources: info.iter().filter_map(|info| {
+                    if let Some(info) = info.publish_info() {
+                        let uri = info.uri.as_ref()?;
+                        let mut chain = Vec::new();
+                        let mut parent = match roas.get(uri.as_str()) {
+                            Some(roa) => {
+                                chain.push(ChainLink {
+                                    kind: LinkKind::Roa,
+                                    id: roa.id,
+                                    uri: roa.uri.to_string(),
+                                    point: Some(roa.point.clone()),
+                                    manifest_number: None,
+                                    not_before: Some(roa.not_before),
+                                    not_after: Some(roa.not_after),
+                                });
+                                Some(roa.parent)
+                            }
+                            None => None
+                        };
+                        while let Some(id) = parent {
+                            if let Some(ca) = cas.get(&id) {
+                                chain.push(ChainLink {
+                                    kind: LinkKind::CaCert,
+                                    id,
+                                    uri: ca.uri.to_string(),
+                                    point: Some(ca.point.clone()),
+                                    manifest_number: manifests.get(
+                                        &id
+                                    ).copied(),
+                                    not_before: Some(ca.not_before),
+                                    not_after: Some(ca.not_after),
+                                });
+                                parent = Some(ca.parent);
+                                // Stop on loops which can only happen in
+                                // a broken tree.
+                                if chain.len() > cas.len() + 1 {
+                                    break
+                                }
+                            }
+                            else {
+                                if let Some(tal) = tals.get(&id) {
+                                    chain.push(ChainLink {
+                                        kind: LinkKind::TrustAnchor,
+                                        id,
+                                        uri: tal.uri.clone(),
+                                        point: None,
+                                        manifest_number: manifests.get(
+                                            &id
+                                        ).copied(),
+                                        not_before: tal.not_before,
+                                        not_after: tal.not_after,
+                                    });
+                                }
+                                parent = None;
+                            }
+                        }
+                        chain.reverse();
+                        Some(Source::Roa {
+                            tal: info.tal.name().into(),
+                            uri: uri.to_string(),
+                            chain,
+                        })
+                    }
+                    else {
+                        info.exception_info().map(|info| {
+                            Source::Exception {
+                                path: info.path.as_ref().map(|path| {
+                                    path.display().to_string()
+                                }),
+                                comment: info.comment.clone(),
+                            }
+                        })
+                    }
+                }).collect(),
+            }
+        }).collect();
+        Provenance { vrps }
+    }
+
+    /// Writes the provenance as pretty-printed JSON to the given target.
+    pub fn write_json(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer_pretty(&mut *target, self)?;
+        target.write_all(b"\n")
+    }
+
+    /// Writes the provenance as human-readable text to the given target.
+    pub fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        if self.vrps.is_empty() {
+            writeln!(target, "No matching VRPs.")?;
+        }
+        for vrp in &self.vrps {
+            writeln!(
+                target, "{}-{} => AS{}", vrp.prefix, vrp.max_length, vrp.asn
+            )?;
+            for source in &vrp.sources {
+                source.write_text(target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the provenance in the given format to the given target.
+    pub fn write(
+        &self, format: ProvenanceFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            ProvenanceFormat::Json => self.write_json(target),
+            ProvenanceFormat::Text => self.write_text(target),
+        }
+    }
+}
+
+
+//------------ VrpProvenance -------------------------------------------------
+
+/// The provenance of a single VRP.
+#[derive(Clone, Debug, Serialize)]
+struct VrpProvenance {
+    asn: u32,
+    prefix: String,
+    max_length: u8,
+
+    /// The sources the VRP was derived from.
+    sources: Vec<Source>,
+}
+
+
+//------------ Source --------------------------------------------------------
+
+/// A single source of a VRP.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Source {
+    /// The VRP was published in a ROA.
+    Roa {
+        /// The name of the TAL the ROA was derived from.
+        tal: String,
+
+        /// The URI of the ROA.
+        uri: String,
+
+        /// The certificates from the trust anchor to the ROA.
+        ///
+        /// The chain is empty if the ROA is missing from the tree and
+        /// stops short of the trust anchor if a CA is missing.
+        chain: Vec<ChainLink>,
+    },
+
+    /// The VRP was added by a local exception.
+    Exception {
+        /// The path of the local exception file.
+        path: Option<String>,
+
+        /// The comment of the exception.
+        comment: Option<String>,
+    }
+}
+
+impl Source {
+    /// Writes the source as text.
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match self {
+            Source::Roa { tal, uri, chain } => {
+                writeln!(target, "  ROA {} (TAL {})", uri, tal)?;
+                if chain.is_empty() {
+                    writeln!(target, "    chain not found in tree")?;
+                }
+                for link in chain {
+                    link.write_text(target)?;
+                }
+            }
+            Source::Exception { path, comment } => {
+                writeln!(
+                    target, "  local exception {}",
+                    path.as_deref().unwrap_or("(unknown file)")
+                )?;
+                if let Some(comment) = comment {
+                    writeln!(target, "    comment: {}", comment)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+
+//------------ ChainLink -----------------------------------------------------
+
+/// A certificate in the chain from a trust anchor to a ROA.
+#[derive(Clone, Debug, Serialize)]
+struct ChainLink {
+    kind: LinkKind,
+
+    /// The subject key identifier of the certificate.
+    id: KeyIdentifier,
+    uri: String,
+
+    /// The publication point the certificate was published at.
+    ///
+    /// This is missing for trust anchor certificates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    point: Option<PointDump>,
+
+    /// The number of the manifest of the CA.
+    ///
+    /// This is missing for ROAs and CAs without a manifest in the tree.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest_number: Option<Serial>,
+    not_before: Option<Time>,
+    not_after: Option<Time>,
+}
+
+impl ChainLink {
+    /// Writes the link as text.
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(target, "    {} {}", self.kind.name(), self.id)?;
+        writeln!(target, "      uri: {}", self.uri)?;
+        if let Some(point) = self.point.as_ref() {
+            writeln!(target, "      repository: {}", point.ca_repository)?;
+            if let Some(notify) = point.rpki_notify.as_ref() {
+                writeln!(target, "      rrdp: {}", notify)?;
+            }
+        }
+        if let Some(number) = self.manifest_number {
+            writeln!(target, "      manifest number: {}", number)?;
+        }
+        if let (Some(not_before), Some(not_after)) = (
+            self.not_before, self.not_after
+        ) {
+            writeln!(
+                target, "      validity: {} to {}",
+                format_iso_date(not_before.into()),
+                format_iso_date(not_after.into()),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+
+//------------ LinkKind ------------------------------------------------------
+
+/// The kind of a certificate in a chain.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum LinkKind {
+    TrustAnchor,
+    CaCert,
+    Roa,
+}
+
+impl LinkKind {
+    /// Returns the name used in text output.
+    fn name(self) -> &'static str {
+        match self {
+            LinkKind::TrustAnchor => "trust anchor",
+            LinkKind::CaCert => "CA certificate",
+            LinkKind::Roa => "ROA",
+        }
+    }
+}
+
+
+//------------ ProvenanceFormat ----------------------------------------------
+
+/// The output format for the provenance of VRPs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProvenanceFormat {
+    /// A single JSON object with a list of VRPs and their sources.
+    Json,
+
+    /// Human-readable text.
+    Text,
+}
+
+impl ProvenanceFormat {
+    /// All known provenance format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("json", ProvenanceFormat::Json),
+        ("text", ProvenanceFormat::Text),
+    ];
+
+    /// The default provenance format name.
+    pub const DEFAULT_VALUE: &'static str = "text";
+}
+
+
+//--- FromStr
+
+impl FromStr for ProvenanceFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown provenance format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::testing::BuiltTree;
+
+    #[test]
+    fn built_tree() {
+        let tree = BuiltTree::new();
+        let provenance = Provenance::new(
+            &tree.snapshot, &tree.dump,
+            &ProvenanceSelection::from_query(
+                Some("prefix=10.0.0.0/24&asn=AS64496")
+            ).unwrap()
+        );
+        assert_eq!(provenance.vrps.len(), 1);
+        let vrp = &provenance.vrps[0];
+        assert_eq!(vrp.asn, 64496);
+        assert_eq!(vrp.prefix, "10.0.0.0/24");
+        assert_eq!(vrp.sources.len(), 1);
+        let chain = match &vrp.sources[0] {
+            Source::Roa { uri, chain, .. } => {
+                assert!(uri.ends_with("/roa.roa"));
+                chain
+            }
+            _ => panic!("expected ROA source"),
+        };
+        assert_eq!(chain.len(), 3);
+        assert!(matches!(chain[0].kind, LinkKind::TrustAnchor));
+        assert!(matches!(chain[1].kind, LinkKind::CaCert));
+        assert!(chain[1].uri.ends_with("/ca.cer"));
+        assert!(chain[1].manifest_number.is_some());
+        assert!(matches!(chain[2].kind, LinkKind::Roa));
+
+        let provenance = Provenance::new(
+            &tree.snapshot, &tree.dump,
+            &ProvenanceSelection::from_query(Some("asn=AS64497")).unwrap()
+        );
+        assert!(provenance.vrps.is_empty());
+    }
+
+    fn origin(prefix: &str, asn: u32) -> RouteOrigin {
+        RouteOrigin::new(
+            rpki::resources::addr::MaxLenPrefix::new(
+                Prefix::from_str(prefix).unwrap(), None
+            ).unwrap(),
+            asn.into(),
+        )
+    }
+
+    #[test]
+    fn selection() {
+        let select = ProvenanceSelection::from_query(
+            Some("prefix=10.1.0.0/16&asn=AS64496")
+        ).unwrap();
+        assert!(select.include_origin(origin("10.0.0.0/8", 64496)));
+        assert!(select.include_origin(origin("10.1.0.0/16", 64496)));
+        assert!(!select.include_origin(origin("10.0.0.0/8", 64497)));
+        assert!(!select.include_origin(origin("10.1.1.0/24", 64496)));
+
+        let select = ProvenanceSelection::from_query(
+            Some("prefix=10.1.0.0/16&more-specifics")
+        ).unwrap();
+        assert!(select.include_origin(origin("10.1.1.0/24", 64497)));
+
+        assert!(ProvenanceSelection::from_query(None).is_err());
+        assert!(
+            ProvenanceSelection::from_query(Some("asn=1&asn=2")).is_err()
+        );
+    }
+}
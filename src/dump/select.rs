@@ -0,0 +1,181 @@
+//! Selecting parts of a tree dump.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use rpki::crypto::keys::KeyIdentifier;
+use super::DBDump;
+
+
+//------------ TreeSelection -------------------------------------------------
+
+/// A selection of parts of a tree dump.
+///
+/// A tree can be restricted to the trust anchors with given names, to
+/// the CAs with given key identifiers, or to the subtrees rooted at CAs
+/// with given key identifiers. If more than one of these are given, the
+/// union of the selected parts is used.
+#[derive(Clone, Debug, Default)]
+pub struct TreeSelection {
+    /// The names of the TALs to include with their complete tree.
+    tals: Vec<String>,
+
+    /// The key identifiers of CAs to include with their direct children.
+    cas: Vec<KeyIdentifier>,
+
+    /// The key identifiers of CAs to include with all their descendants.
+    subtrees: Vec<KeyIdentifier>,
+}
+
+impl TreeSelection {
+    /// Creates a new, empty selection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a selection from the query of an HTTP request.
+    ///
+    /// The query can contain any number of `tal`, `ca`, and `subtree`
+    /// parameters. Returns an error if there are any other parameters or
+    /// if a key identifier is invalid.
+    pub fn from_query(query: Option<&str>) -> Result<Self, SelectionError> {
+        let mut res = Self::new();
+        let query = match query {
+            Some(query) => query,
+            None => return Ok(res)
+        };
+        for (key, value) in form_urlencoded::parse(query.as_ref()) {
+            if key == "tal" {
+                res.tals.push(value.into_owned())
+            }
+            else if key == "ca" {
+                res.cas.push(
+                    KeyIdentifier::from_str(&value)
+                        .map_err(|_| SelectionError)?
+                )
+            }
+            else if key == "subtree" {
+                res.subtrees.push(
+                    KeyIdentifier::from_str(&value)
+                        .map_err(|_| SelectionError)?
+                )
+            }
+            else {
+                return Err(SelectionError)
+            }
+        }
+        Ok(res)
+    }
+
+    /// Returns whether the selection is empty and selects everything.
+    pub fn is_empty(&self) -> bool {
+        self.tals.is_empty() && self.cas.is_empty() && self.subtrees.is_empty()
+    }
+
+    /// Returns the selected part of the given tree.
+    ///
+    /// If the selection is empty, returns a copy of the complete tree.
+    pub fn apply(&self, tree: &DBDump) -> DBDump {
+        if self.is_empty() {
+            return tree.clone()
+        }
+
+        // The CAs whose issued objects are included.
+        let mut parents: HashSet<_> = self.cas.iter().copied().collect();
+
+        // Collect the subtree roots, including those of selected TALs, and
+        // add all their descendants to the parents.
+        let mut queue: Vec<_> = self.subtrees.clone();
+        queue.extend(tree.tals.iter().filter(|tal| {
+            self.tals.contains(&tal.name)
+        }).map(|tal| tal.id));
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        for cert in &tree.ca_certs {
+            children.entry(cert.parent).or_default().push(cert.id)
+        }
+        while let Some(id) = queue.pop() {
+            if parents.insert(id) {
+                if let Some(items) = children.get(&id) {
+                    queue.extend(items.iter().copied())
+                }
+            }
+        }
+
+        DBDump {
+            tals: tree.tals.iter().filter(|tal| {
+                self.tals.contains(&tal.name)
+            }).cloned().collect(),
+            ca_certs: tree.ca_certs.iter().filter(|cert| {
+                parents.contains(&cert.parent)
+                    || self.cas.contains(&cert.id)
+                    || self.subtrees.contains(&cert.id)
+            }).cloned().collect(),
+            roas: tree.roas.iter().filter(|roa| {
+                parents.contains(&roa.parent)
+            }).cloned().collect(),
+            aspas: tree.aspas.iter().filter(|aspa| {
+                parents.contains(&aspa.parent)
+            }).cloned().collect(),
+            router_certs: tree.router_certs.iter().filter(|cert| {
+                parents.contains(&cert.parent)
+            }).cloned().collect(),
+            gbrs: tree.gbrs.iter().filter(|gbr| {
+                parents.contains(&gbr.parent)
+            }).cloned().collect(),
+            manifests: tree.manifests.iter().filter(|manifest| {
+                parents.contains(&manifest.parent)
+            }).cloned().collect(),
+            crls: tree.crls.iter().filter(|crl| {
+                parents.contains(&crl.parent)
+            }).cloned().collect(),
+            rejected_objects: tree.rejected_objects.iter().filter(|obj| {
+                parents.contains(&obj.parent)
+            }).cloned().collect(),
+            rejected_points: tree.rejected_points.iter().filter(|point| {
+                parents.contains(&point.id)
+            }).cloned().collect(),
+        }
+    }
+}
+
+
+//------------ SelectionError ------------------------------------------------
+
+/// A tree selection could not be created from a query.
+#[derive(Clone, Copy, Debug)]
+pub struct SelectionError;
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::testing::{dump, key, tal};
+
+    #[test]
+    fn from_query() {
+        let selection = TreeSelection::from_query(Some(
+            "tal=ripe&ca=0101010101010101010101010101010101010101\
+             &subtree=0202020202020202020202020202020202020202"
+        )).unwrap();
+        assert_eq!(selection.tals, ["ripe"]);
+        assert_eq!(selection.cas, [key(1)]);
+        assert_eq!(selection.subtrees, [key(2)]);
+        assert!(TreeSelection::from_query(None).unwrap().is_empty());
+        assert!(TreeSelection::from_query(Some("ca=foo")).is_err());
+        assert!(TreeSelection::from_query(Some("foo=bar")).is_err());
+    }
+
+    #[test]
+    fn select_tals() {
+        let tree = dump(vec![tal("a", 1), tal("b", 2)]);
+        let mut selection = TreeSelection::new();
+        assert_eq!(selection.apply(&tree).tals.len(), 2);
+        selection.tals.push("b".into());
+        let selected = selection.apply(&tree);
+        assert_eq!(selected.tals.len(), 1);
+        assert_eq!(selected.tals[0].id, key(2));
+    }
+}
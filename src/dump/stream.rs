@@ -0,0 +1,258 @@
+//! Writing the tree as newline-delimited JSON.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::{fs, io};
+use std::io::{Seek, SeekFrom, Write};
+use std::sync::Mutex;
+use log::error;
+use ring::digest;
+use serde::Serialize;
+use super::{
+    AspaDump, CaCertDump, CrlDump, DBDump, GbrDump, ManifestDump,
+    RejectedObjectDump, RejectedPointDump, ROADump, RouterCertDump, TALDump,
+};
+
+
+//------------ TreeStream ----------------------------------------------------
+
+/// Writes the tree as newline-delimited JSON with bounded memory.
+///
+/// Each object of the tree is written as a single line containing a JSON
+/// object with a `type` field giving the kind of object. The lines are
+/// first written to a temporary file as parts of the tree are pushed to
+/// the stream so that only a small index needs to be kept in memory.
+///
+/// When the stream is finished, the lines are copied to the final target
+/// ordered by the kind of object, then by their URI – or name for TALs –,
+/// and finally by their content. This makes the output the same for the
+/// same tree so consecutive runs can be compared line by line.
+#[derive(Debug)]
+pub struct TreeStream {
+    /// The temporary file, the index of its lines, and any error.
+    inner: Mutex<StreamInner>,
+}
+
+#[derive(Debug)]
+struct StreamInner {
+    /// The temporary file holding all lines written so far.
+    file: io::BufWriter<fs::File>,
+
+    /// The length of the temporary file.
+    len: u64,
+
+    /// The index of all lines in the temporary file.
+    index: Vec<IndexItem>,
+
+    /// The first error that happened while writing.
+    err: Option<io::Error>,
+}
+
+impl TreeStream {
+    /// Creates a new, empty stream backed by a temporary file.
+    pub fn new() -> Result<Self, io::Error> {
+        Ok(TreeStream {
+            inner: Mutex::new(StreamInner {
+                file: io::BufWriter::new(tempfile::tempfile()?),
+                len: 0,
+                index: Vec::new(),
+                err: None,
+            })
+        })
+    }
+
+    /// Adds all objects of the given dump to the stream.
+    ///
+    /// The objects are serialized before the stream is locked, so this can
+    /// be called concurrently for different parts of the tree. Errors are
+    /// remembered and returned by [`finish`][Self::finish].
+    pub fn push(&self, dump: &DBDump) {
+        let mut lines = Vec::new();
+        let mut index = Vec::new();
+        for record in Record::iter_dump(dump) {
+            let start = lines.len();
+            if let Err(err) = serde_json::to_writer(&mut lines, &record) {
+                // Serializing into a vec shouldn’t fail. If it does
+                // anyway, we just skip the record.
+                error!("Failed to serialize tree record: {}", err);
+                lines.truncate(start);
+                continue
+            }
+            lines.push(b'\n');
+            index.push(IndexItem::new(
+                &record, start as u64, &lines[start..]
+            ));
+        }
+
+        let mut inner = self.inner.lock().expect("poisoned lock");
+        if inner.err.is_some() {
+            return
+        }
+        if let Err(err) = inner.file.write_all(&lines) {
+            inner.err = Some(err);
+            return
+        }
+        let offset = inner.len;
+        inner.index.extend(index.into_iter().map(|mut item| {
+            item.start += offset;
+            item
+        }));
+        inner.len += lines.len() as u64;
+    }
+
+    /// Writes the sorted lines to the target.
+    pub fn finish(self, target: &mut impl io::Write) -> Result<(), io::Error> {
+        let StreamInner { file, mut index, err, .. } = self.inner.into_inner(
+        ).expect("poisoned lock");
+        if let Some(err) = err {
+            return Err(err)
+        }
+        let mut file = file.into_inner().map_err(|err| err.into_error())?;
+        index.sort_unstable();
+        let mut buf = Vec::new();
+        for item in index {
+            buf.resize(item.len, 0);
+            file.seek(SeekFrom::Start(item.start))?;
+            io::Read::read_exact(&mut file, &mut buf)?;
+            target.write_all(&buf)?;
+        }
+        Ok(())
+    }
+}
+
+
+//------------ IndexItem -----------------------------------------------------
+
+/// The position and sort order of a line in the temporary file.
+///
+/// The derived ordering of the fields is the order of the lines.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct IndexItem {
+    /// The position of the kind of object.
+    order: u8,
+
+    /// The URI or name of the object.
+    key: String,
+
+    /// The start of the SHA-256 digest of the line.
+    ///
+    /// This orders objects with the same key by their content.
+    digest: [u8; 8],
+
+    /// The start of the line in the temporary file.
+    start: u64,
+
+    /// The length of the line including the line feed.
+    len: usize,
+}
+
+impl IndexItem {
+    /// Creates the index item for a record at `start` in the file.
+    fn new(record: &Record, start: u64, line: &[u8]) -> Self {
+        let mut digest_start = [0u8; 8];
+        digest_start.copy_from_slice(
+            &digest::digest(&digest::SHA256, line).as_ref()[..8]
+        );
+        IndexItem {
+            order: record.order(),
+            key: record.key().into(),
+            digest: digest_start,
+            start,
+            len: line.len(),
+        }
+    }
+}
+
+
+//------------ Record --------------------------------------------------------
+
+/// A single line of the stream.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Record<'a> {
+    Tal(&'a TALDump),
+    CaCert(&'a CaCertDump),
+    RouterCert(&'a RouterCertDump),
+    Roa(&'a ROADump),
+    Aspa(&'a AspaDump),
+    Gbr(&'a GbrDump),
+    Manifest(&'a ManifestDump),
+    Crl(&'a CrlDump),
+    RejectedObject(&'a RejectedObjectDump),
+    RejectedPoint(&'a RejectedPointDump),
+}
+
+impl<'a> Record<'a> {
+    /// Returns an iterator over all the records of a dump.
+    fn iter_dump(dump: &'a DBDump) -> impl Iterator<Item = Self> + 'a {
+        dump.tals.iter().map(Record::Tal)
+            .chain(dump.ca_certs.iter().map(Record::CaCert))
+            .chain(dump.router_certs.iter().map(Record::RouterCert))
+            .chain(dump.roas.iter().map(Record::Roa))
+            .chain(dump.aspas.iter().map(Record::Aspa))
+            .chain(dump.gbrs.iter().map(Record::Gbr))
+            .chain(dump.manifests.iter().map(Record::Manifest))
+            .chain(dump.crls.iter().map(Record::Crl))
+            .chain(dump.rejected_objects.iter().map(Record::RejectedObject))
+            .chain(dump.rejected_points.iter().map(Record::RejectedPoint))
+    }
+
+    /// Returns the position of the record’s kind in the output.
+    fn order(self) -> u8 {
+        match self {
+            Record::Tal(_) => 0,
+            Record::CaCert(_) => 1,
+            Record::RouterCert(_) => 2,
+            Record::Roa(_) => 3,
+            Record::Aspa(_) => 4,
+            Record::Gbr(_) => 5,
+            Record::Manifest(_) => 6,
+            Record::Crl(_) => 7,
+            Record::RejectedObject(_) => 8,
+            Record::RejectedPoint(_) => 9,
+        }
+    }
+
+    /// Returns the key to order records of the same kind by.
+    fn key(self) -> &'a str {
+        match self {
+            Record::Tal(item) => &item.name,
+            Record::CaCert(item) => item.uri.as_str(),
+            Record::RouterCert(item) => item.uri.as_str(),
+            Record::Roa(item) => item.uri.as_str(),
+            Record::Aspa(item) => item.uri.as_str(),
+            Record::Gbr(item) => item.uri.as_str(),
+            Record::Manifest(item) => item.uri.as_str(),
+            Record::Crl(item) => item.uri.as_str(),
+            Record::RejectedObject(item) => item.uri.as_str(),
+            Record::RejectedPoint(item) => &item.uri,
+        }
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::testing::{dump, tal};
+
+    #[test]
+    fn sorted_lines() {
+        let stream = TreeStream::new().unwrap();
+        stream.push(&dump(vec![tal("c", 3), tal("a", 1)]));
+        stream.push(&dump(vec![tal("b", 2)]));
+        let mut out = Vec::new();
+        stream.finish(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let names: Vec<_> = out.lines().map(|line| {
+            let value: serde_json::Value = serde_json::from_str(
+                line
+            ).unwrap();
+            assert_eq!(value["type"], "tal");
+            value["name"].as_str().unwrap().to_string()
+        }).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,98 @@
+//! Fixtures for the tests of the tree modules.
+
+use std::str::FromStr;
+use rpki::uri;
+use rpki::crypto::keys::KeyIdentifier;
+use crate::engine::PointSource;
+use crate::payload::{PayloadSnapshot, ValidationReport};
+use crate::slurm::LocalExceptions;
+use crate::utils::testing::TestRepository;
+use super::{DBDump, PointDump, TALDump, TreeReport};
+
+
+//------------ Dump Parts ----------------------------------------------------
+
+/// Returns a key identifier with all bytes set to `n`.
+pub fn key(n: u8) -> KeyIdentifier {
+    KeyIdentifier::from([n; 20])
+}
+
+/// Returns a trust anchor with the given name and key identifier.
+pub fn tal(name: &str, id: u8) -> TALDump {
+    TALDump {
+        id: key(id),
+        name: name.into(),
+        uri: format!("rsync://{}.example/ta.cer", name),
+        not_before: None,
+        not_after: None,
+    }
+}
+
+/// Returns a publication point of TAL “a” with the given source.
+pub fn point(source: PointSource) -> PointDump {
+    PointDump {
+        tal: "a".into(),
+        ca_repository: uri::Rsync::from_str(
+            "rsync://a.example/repo/"
+        ).unwrap(),
+        rpki_manifest: uri::Rsync::from_str(
+            "rsync://a.example/repo/ca.mft"
+        ).unwrap(),
+        rpki_notify: None,
+        source,
+    }
+}
+
+/// Returns a dump with only the given trust anchors.
+pub fn dump(tals: Vec<TALDump>) -> DBDump {
+    DBDump { tals, .. Default::default() }
+}
+
+
+//------------ BuiltTree -----------------------------------------------------
+
+/// The tree and payload of a validation run over a small repository.
+///
+/// The trust anchor has a single child CA `ca.cer` holding 10.0.0.0/8 and
+/// AS64496-AS64499. It publishes a ROA `roa.roa` for 10.0.0.0/24 and
+/// AS64496 and a Ghostbuster record `contact.gbr` with the full name
+/// “CA Contact”.
+pub struct BuiltTree {
+    /// The tree of the run.
+    pub dump: DBDump,
+
+    /// The payload of the run.
+    pub snapshot: PayloadSnapshot,
+}
+
+impl BuiltTree {
+    /// Creates the repository and validates it.
+    pub fn new() -> Self {
+        let (repo, mut ta) = TestRepository::new();
+        let mut ca = repo.child(
+            &mut ta, "ca", "10.0.0.0/8", "AS64496-AS64499"
+        );
+        repo.roa(&mut ca, "roa", 64496, "10.0.0.0/24");
+        repo.gbr(
+            &mut ca, "contact",
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:CA Contact\r\nEND:VCARD\r\n"
+        );
+        repo.publish(&mut ca);
+        repo.publish(&mut ta);
+
+        let engine = repo.engine();
+        let report = ValidationReport::new(repo.config());
+        let tree = TreeReport::new();
+        let mut metrics = {
+            let mut run = engine.start((&report, &tree)).unwrap();
+            run.process().unwrap();
+            run.done()
+        };
+        BuiltTree {
+            dump: tree.into_dump(),
+            snapshot: report.into_snapshot(
+                &LocalExceptions::empty(), &mut metrics
+            ),
+        }
+    }
+}
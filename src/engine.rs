@@ -18,38 +18,42 @@
 /// the accompanying trait [`ProcessPubPoint`] dealing with individual
 /// publication points.
 
-use std::{fmt, fs, thread};
+use std::{cmp, fmt, fs, mem, thread};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use bytes::Bytes;
-use crossbeam_queue::{ArrayQueue, SegQueue};
+use crossbeam_queue::SegQueue;
 use log::{debug, error, info, warn};
 use rpki::crypto::keys::KeyIdentifier;
 #[allow(unused_imports)]
 use rpki::repository::aspa::{Aspa, AsProviderAttestation};
 use rpki::repository::cert::{Cert, KeyUsage, ResourceCert};
 use rpki::repository::crl::Crl;
-use rpki::repository::error::{InspectionError, ValidationError};
+use rpki::repository::error::{
+    InspectionError, ValidationError, VerificationError,
+};
 use rpki::repository::manifest::{Manifest, ManifestContent, ManifestHash};
 use rpki::repository::roa::{Roa, RouteOriginAttestation};
 use rpki::repository::sigobj::SignedObject;
-use rpki::repository::tal::{Tal, TalInfo, TalUri};
-use rpki::repository::x509::{Time, Validity};
+use rpki::repository::tal::{Tal, TalUri};
+use rpki::repository::x509::{Serial, Time, Validity};
 use rpki::uri;
+use serde::{Deserialize, Serialize};
 use crate::{collector, store, tals};
 use crate::config::{Config, FilterPolicy};
 use crate::collector::Collector;
+use crate::constraints::TalConstraints;
 use crate::error::Failed;
 use crate::metrics::{
     Metrics, PublicationMetrics, RepositoryMetrics, TalMetrics
 };
 use crate::store::{Store, StoredManifest, StoredObject, StoredPoint};
+use crate::tak::{TaKey, TakContent, TakState};
 use crate::utils::str::str_from_ascii;
-use crate::dump::DB_DUMP;
 
 
 //------------ Configuration -------------------------------------------------
@@ -80,6 +84,10 @@ const CRL_CACHE_LIMIT: usize = 50;
 /// A run is started via the [`start`][Self::start] method, providing a
 /// processor that handles valid data. The method returns a [Run] value that
 /// drives the validation run.
+///
+/// If the engine is used for repeated runs, the validated objects of
+/// publication points can be kept between runs by enabling the point cache
+/// via [`enable_point_cache`][Self::enable_point_cache].
 #[derive(Debug)]
 pub struct Engine {
     /// A list of built-in TALs to use.
@@ -91,9 +99,17 @@ pub struct Engine {
     /// A mapping of TAL file names to TAL labels.
     tal_labels: HashMap<String, String>,
 
+    /// A mapping of TAL names to constraints files.
+    tal_constraints: HashMap<String, PathBuf>,
+
     /// The list of our TALs. 
     tals: Vec<Tal>,
 
+    /// The constraints for our TALs.
+    ///
+    /// This list has the same order as `tals`.
+    constraints: Vec<Option<Arc<TalConstraints>>>,
+
     /// The collector to load updated data from.
     ///
     /// If this is `None`, updates have been disabled.
@@ -114,8 +130,31 @@ pub struct Engine {
     /// Should we leave the repository dirty after a valiation run.
     dirty_repository: bool,
 
+    /// Should we keep using stored TA certificates until changes are
+    /// accepted?
+    pin_ta_certs: bool,
+
     /// Maximum depth of the CA chain.
     max_ca_depth: usize,
+
+    /// Maximum number of entries in a manifest.
+    max_manifest_entries: Option<usize>,
+
+    /// Maximum number of child CAs of a CA.
+    max_child_cas: Option<usize>,
+
+    /// Maximum number of repositories used by the CAs of a TAL.
+    max_tal_repositories: Option<usize>,
+
+    /// The validated publication points of the last run.
+    ///
+    /// If this is `None`, the cache has not been enabled.
+    point_cache: Option<PointCache>,
+
+    /// The time to evaluate the validity of objects at.
+    ///
+    /// If this is `None`, the current time is used.
+    validation_time: Option<Time>,
 }
 
 impl Engine {
@@ -153,14 +192,22 @@ impl Engine {
             bundled_tals: tals::collect_tals(config)?,
             extra_tals_dir: config.extra_tals_dir.clone(),
             tal_labels: config.tal_labels.clone(),
+            tal_constraints: config.tal_constraints.clone(),
             tals: Vec::new(),
+            constraints: Vec::new(),
             collector,
             store,
             strict: config.strict,
             stale: config.stale,
             validation_threads: config.validation_threads,
             dirty_repository: config.dirty_repository,
+            pin_ta_certs: config.pin_ta_certs,
             max_ca_depth: config.max_ca_depth,
+            max_manifest_entries: config.max_manifest_entries,
+            max_child_cas: config.max_child_cas,
+            max_tal_repositories: config.max_tal_repositories,
+            point_cache: None,
+            validation_time: None,
         };
         res.reload_tals()?;
         Ok(res)
@@ -175,8 +222,14 @@ impl Engine {
     ///
     /// It is not considered an error if there are no TAL files in the TAL
     /// directory. However, a warning will be logged in this case.
+    ///
+    /// The constraints for each TAL are loaded from the file configured for
+    /// the TAL’s name or, for TALs from the TAL directory, a file with the
+    /// same name but an extension of `constraints` if present.
     pub fn reload_tals(&mut self) -> Result<(), Failed> {
-        let mut res = self.bundled_tals.clone();
+        let mut res: Vec<_> = self.bundled_tals.iter().map(|tal| {
+            (tal.clone(), None)
+        }).collect();
         if let Some(extra_tals_dir) = self.extra_tals_dir.as_ref() {
             let dir = match fs::read_dir(extra_tals_dir) {
                 Ok(dir) => dir,
@@ -236,7 +289,13 @@ impl Engine {
                     }
                 };
                 tal.prefer_https();
-                res.push(tal);
+                let constraints = path.with_extension("constraints");
+                if constraints.is_file() {
+                    res.push((tal, Some(constraints)));
+                }
+                else {
+                    res.push((tal, None));
+                }
             }
         }
         if res.is_empty() {
@@ -245,13 +304,63 @@ impl Engine {
             );
         }
         res.sort_by(|left, right| {
-            left.info().name().cmp(right.info().name())
+            left.0.info().name().cmp(right.0.info().name())
         });
-        self.tals = res;
+        let mut tals = Vec::with_capacity(res.len());
+        let mut constraints = Vec::with_capacity(res.len());
+        for (tal, path) in res {
+            let path = match self.tal_constraints.get(tal.info().name()) {
+                Some(path) => Some(path.clone()),
+                None => path,
+            };
+            constraints.push(match path {
+                Some(path) => Some(Arc::new(TalConstraints::load(&path)?)),
+                None => None,
+            });
+            tals.push(tal);
+        }
+        self.tals = tals;
+        self.constraints = constraints;
+
+        // The cached points refer to the TALs by their index, so they are
+        // of no use anymore.
+        if let Some(cache) = self.point_cache.as_mut() {
+            *cache = Default::default();
+        }
 
         Ok(())
     }
 
+    /// Sets the time at which the validity of objects is evaluated.
+    ///
+    /// By default, the current time at the moment of validating an object
+    /// is used. With a different time, certificates, manifests, and CRLs
+    /// are considered valid, premature, or stale as of that time. This is
+    /// mostly useful together with the stored data only since the
+    /// collector will still fetch current data.
+    pub fn set_validation_time(&mut self, time: Time) {
+        self.validation_time = Some(time)
+    }
+
+    /// Returns the time at which to evaluate the validity of objects.
+    pub fn now(&self) -> Time {
+        self.validation_time.unwrap_or_else(Time::now)
+    }
+
+    /// Enables keeping validated publication points between runs.
+    ///
+    /// With the cache enabled, the validated objects of each publication
+    /// point are kept after a run. In the next run, they are handed to the
+    /// processor again instead of being decoded and validated if the
+    /// point’s CA certificate and manifest have not changed and all the
+    /// objects are still valid. This trades memory for processing time and
+    /// is only useful if the engine is used for more than one run.
+    pub fn enable_point_cache(&mut self) {
+        if self.point_cache.is_none() {
+            self.point_cache = Some(Default::default())
+        }
+    }
+
     /// Converts a path into a TAL label.
     ///
     /// This will be an explicitly configured TAL label if the file name
@@ -283,6 +392,9 @@ impl Engine {
     /// valid objects. It must implement the [`ProcessRun`] trait.
     ///
     /// The method returns a [`Run`] that drives the validation run.
+    ///
+    /// If the trust anchor of a TAL has rolled over to a new key via a
+    /// Trust Anchor Key object, the TAL for the new key is used instead.
     pub fn start<P: ProcessRun>(
         &self, processor: P
     ) -> Result<Run<P>, Failed> {
@@ -290,14 +402,53 @@ impl Engine {
         for tal in &self.tals {
             info!("  * {}", tal.info().name());
         }
+        let store = self.store.start();
+        let tals = self.tals.iter().map(|tal| {
+            TakState::new(&store, tal).effective_tal()
+        }).collect::<Result<_, _>>()?;
         Ok(Run::new(
             self,
             self.collector.as_ref().map(Collector::start),
-            self.store.start(),
+            store,
+            tals,
             processor
         ))
     }
 
+    /// Returns the constraints for the TAL with the given name, if any.
+    pub fn tal_constraints(
+        &self, name: &str
+    ) -> Option<Arc<TalConstraints>> {
+        self.tals.iter().position(|tal| {
+            tal.info().name() == name
+        }).and_then(|index| self.constraints[index].clone())
+    }
+
+    /// Accepts changed trust anchor certificates held back by pinning.
+    ///
+    /// If `name` is given, only the certificates of the TAL with this name
+    /// are accepted. Returns the URIs of the accepted certificates.
+    pub fn accept_ta_certs(
+        &self, name: Option<&str>
+    ) -> Result<Vec<TalUri>, Failed> {
+        let store = self.store.start();
+        let mut res = Vec::new();
+        for tal in &self.tals {
+            if let Some(name) = name {
+                if tal.info().name() != name {
+                    continue
+                }
+            }
+            let tal = TakState::new(&store, tal).effective_tal()?;
+            for uri in tal.uris() {
+                if store.accept_pending_ta(uri)? {
+                    res.push(uri.clone())
+                }
+            }
+        }
+        Ok(res)
+    }
+
     /// Dumps the content of the collector and store owned by the engine.
     pub fn dump(&self, dir: &Path) -> Result<(), Failed> {
         self.store.dump(dir)?;
@@ -327,9 +478,22 @@ pub struct Run<'a, P> {
     /// The runner for the store.
     store: store::Run<'a>,
 
+    /// The TALs used in this run.
+    ///
+    /// These are the engine’s TALs with any accepted key rollovers applied.
+    tals: Vec<Tal>,
+
+    /// The content of valid TAK objects of the trust anchors by TAL index.
+    taks: Mutex<HashMap<usize, TakContent>>,
+
     /// The processor for valid data.
     processor: P,
 
+    /// The repositories admitted or refused for each TAL so far.
+    ///
+    /// The vec is indexed by the TAL index.
+    tal_repositories: Mutex<Vec<TalRepositories>>,
+
     /// The metrics collected during the run.
     metrics: Metrics,
 }
@@ -340,10 +504,13 @@ impl<'a, P> Run<'a, P> {
         validation: &'a Engine,
         collector: Option<collector::Run<'a>>,
         store: store::Run<'a>,
+        tals: Vec<Tal>,
         processor: P,
     ) -> Self {
         Run {
-            validation, collector, store, processor,
+            validation, collector, store, tals, processor,
+            taks: Default::default(),
+            tal_repositories: Default::default(),
             metrics: Default::default()
         }
     }
@@ -373,51 +540,85 @@ impl<'a, P> Run<'a, P> {
             collector.done(&mut metrics)
         }
         self.store.done(&mut metrics);
+        Self::mark_oversized(&mut metrics);
         metrics
     }
+
+    /// Marks the repositories the collector found to be too large.
+    fn mark_oversized(metrics: &mut Metrics) {
+        let oversized: HashSet<_> = metrics.rrdp.iter().filter(|rrdp| {
+            rrdp.oversized
+        }).map(|rrdp| rrdp.notify_uri.to_string()).chain(
+            metrics.rsync.iter().filter(|rsync| {
+                rsync.oversized
+            }).map(|rsync| rsync.module.to_string())
+        ).collect();
+        if oversized.is_empty() {
+            return
+        }
+        for repository in &mut metrics.repositories {
+            if oversized.contains(&repository.uri) {
+                repository.oversized = true
+            }
+        }
+    }
 }
 
 impl<'a, P: ProcessRun> Run<'a, P> {
     /// Performs the validation run.
     pub fn process(&mut self) -> Result<(), Failed> {
         // If we don’t have any TALs, we ain’t got nothing to do.
-        if self.validation.tals.is_empty() {
+        if self.tals.is_empty() {
             return Ok(())
         }
 
+        if let Some(cache) = self.validation.point_cache.as_ref() {
+            cache.start();
+        }
+
         // Initialize our task queue with all the TALs.
         let metrics = RunMetrics::default();
         let tasks = SegQueue::new();
-        for (index, tal) in self.validation.tals.iter().enumerate() {
+        for (index, tal) in self.tals.iter().enumerate() {
             tasks.push(Task::Tal(TalTask { tal, index }));
             self.metrics.tals.push(TalMetrics::new(tal.info().clone()));
         }
 
         // And off we trot.
+        //
+        // CAs in repositories not yet admitted for their TAL are parked.
+        // Once all other tasks are done, the parked repositories are
+        // admitted or refused and their CAs are processed in another round.
 
         // Keep a flag to cancel everything if something goes wrong.
         let had_err = AtomicBool::new(false);
-        let thread_metrics = ArrayQueue::new(
-            self.validation.validation_threads
-        );
-        thread::scope(|scope| {
-            for _ in 0 .. self.validation.validation_threads {
-                scope.spawn(|| {
-                    let mut metrics = metrics.fork();
-                    while let Some(task) = tasks.pop() {
-                        if self.process_task(
-                            task, &tasks, &mut metrics, &had_err,
-                        ).is_err() {
-                            break;
+        let parked = SegQueue::new();
+        let thread_metrics = SegQueue::new();
+        loop {
+            thread::scope(|scope| {
+                for _ in 0 .. self.validation.validation_threads {
+                    scope.spawn(|| {
+                        let mut metrics = metrics.fork();
+                        while let Some(task) = tasks.pop() {
+                            if self.process_task(
+                                task, &tasks, &parked, &mut metrics,
+                                &had_err,
+                            ).is_err() {
+                                break;
+                            }
                         }
-                    }
-                    thread_metrics.push(metrics).unwrap();
-                });
+                        thread_metrics.push(metrics);
+                    });
+                }
+            });
+
+            if had_err.load(Ordering::Relaxed) {
+                return Err(Failed);
             }
-        });
 
-        if had_err.load(Ordering::Relaxed) {
-            return Err(Failed);
+            if !self.admit_parked(&parked, &tasks, &metrics) {
+                break
+            }
         }
 
         metrics.prepare_final(&mut self.metrics);
@@ -425,23 +626,181 @@ impl<'a, P: ProcessRun> Run<'a, P> {
             metrics.collapse(&mut self.metrics);
         }
 
+        if let Some(cache) = self.validation.point_cache.as_ref() {
+            cache.finish();
+        }
+
+        self.update_taks()
+    }
+
+    /// Updates the key rollover state from the TAK objects of the run.
+    ///
+    /// The state records when a successor key was first seen, so it is
+    /// left alone if a validation time is set. It is also left alone if
+    /// the run doesn’t update the repository data since successor keys
+    /// need to be checked against their downloaded certificates.
+    fn update_taks(&self) -> Result<(), Failed> {
+        if self.validation.validation_time.is_some() {
+            debug!("Skipping key rollover update for validation time.");
+            return Ok(())
+        }
+        let collector = match self.collector.as_ref() {
+            Some(collector) => collector,
+            None => return Ok(())
+        };
+        self.apply_taks(Time::now(), |uri| collector.load_ta(uri))
+    }
+
+    /// Applies the TAK objects of the run to the key rollover state.
+    ///
+    /// Uses `now` as the current time and `load` to fetch the trust anchor
+    /// certificate of a successor key from one of its URIs.
+    fn apply_taks(
+        &self, now: Time, load: impl Fn(&TalUri) -> Option<Bytes>,
+    ) -> Result<(), Failed> {
+        let taks = self.taks.lock().unwrap();
+        for (&index, tak) in taks.iter() {
+            TakState::new(&self.store, &self.validation.tals[index]).update(
+                &self.tals[index], tak, now, |key| {
+                    self.check_successor(index, key, now, &load)
+                }
+            )?;
+        }
         Ok(())
     }
 
+    /// Checks that a successor key has a valid trust anchor certificate.
+    ///
+    /// Tries the certificate URIs of the key in order and returns whether
+    /// one of them provided a certificate for the key valid at `now`.
+    fn check_successor(
+        &self, tal: usize, key: &TaKey, now: Time,
+        load: impl Fn(&TalUri) -> Option<Bytes>,
+    ) -> bool {
+        for uri in key.uris() {
+            let cert = match load(uri).and_then(|bytes| {
+                Cert::decode(bytes).ok()
+            }) {
+                Some(cert) => cert,
+                None => {
+                    warn!("Successor trust anchor {}: unavailable.", uri);
+                    continue
+                }
+            };
+            if cert.subject_public_key_info() != key.key_info() {
+                warn!("Successor trust anchor {}: key doesn’t match.", uri);
+                continue
+            }
+            match cert.validate_ta_at(
+                self.tals[tal].info().clone(), self.validation.strict, now
+            ) {
+                Ok(_) => return true,
+                Err(err) => {
+                    warn!("Successor trust anchor {}: {}.", uri, err);
+                }
+            }
+        }
+        false
+    }
+
+    /// Admits or refuses the repositories of the parked CA tasks.
+    ///
+    /// For each TAL, the repositories are admitted in the order of their
+    /// URIs until the TAL’s limit is reached. The remaining ones are
+    /// refused. Since the parked tasks are the same no matter in which
+    /// order the previous round processed its tasks, this keeps the choice
+    /// independent of the number of validation threads.
+    ///
+    /// All parked tasks are then queued again. Returns whether there were
+    /// any.
+    fn admit_parked(
+        &self,
+        parked: &SegQueue<CaTask<P::PubPoint>>,
+        tasks: &SegQueue<Task<P::PubPoint>>,
+        metrics: &RunMetrics,
+    ) -> bool {
+        let max = match self.validation.max_tal_repositories {
+            Some(max) => max,
+            None => return false,
+        };
+        let mut parked_tasks = Vec::new();
+        while let Some(task) = parked.pop() {
+            parked_tasks.push(task)
+        }
+        if parked_tasks.is_empty() {
+            return false
+        }
+
+        let mut candidates: Vec<_> = parked_tasks.iter().map(|task| {
+            (task.cert.tal, task.cert.repository_uri())
+        }).collect();
+        candidates.sort();
+        candidates.dedup();
+
+        let mut tal_repositories = self.tal_repositories.lock().unwrap();
+        for (tal, uri) in candidates {
+            if tal_repositories.len() <= tal {
+                tal_repositories.resize_with(tal + 1, Default::default)
+            }
+            let index = metrics.repository_index_for(uri);
+            let repositories = &mut tal_repositories[tal];
+            if repositories.admitted.len() < max {
+                repositories.admitted.insert(index);
+            }
+            else {
+                repositories.refused.insert(index);
+            }
+        }
+        drop(tal_repositories);
+
+        for task in parked_tasks {
+            tasks.push(Task::Ca(task))
+        }
+        true
+    }
+
+    /// Checks the repository of a CA against its TAL’s limit.
+    fn check_tal_repository(
+        &self, task: &CaTask<P::PubPoint>, metrics: &RunMetrics,
+    ) -> TalRepository {
+        if self.validation.max_tal_repositories.is_none() {
+            return TalRepository::Admitted
+        }
+
+        // If we have a repository index, we stay in our parent’s
+        // repository which has been admitted already.
+        if task.repository_index.is_some() {
+            return TalRepository::Admitted
+        }
+        let index = metrics.repository_index(&task.cert);
+
+        let tal_repositories = self.tal_repositories.lock().unwrap();
+        match tal_repositories.get(task.cert.tal) {
+            Some(repositories) if repositories.admitted.contains(&index) => {
+                TalRepository::Admitted
+            }
+            Some(repositories) if repositories.refused.contains(&index) => {
+                TalRepository::Refused
+            }
+            _ => TalRepository::Pending
+        }
+    }
+
     /// Process a task. Any task.
     fn process_task(
         &self,
         task: Task<P::PubPoint>,
         tasks: &SegQueue<Task<P::PubPoint>>,
+        parked: &SegQueue<CaTask<P::PubPoint>>,
         metrics: &mut RunMetrics,
         had_err: &AtomicBool,
     ) -> Result<(), Failed> {
         match task {
             Task::Tal(task) => {
-                self.process_tal_task(task, tasks, metrics, had_err)
+                self.process_tal_task(task, tasks, parked, metrics, had_err)
             }
             Task::Ca(task) => {
-                self.process_ca_task(task, tasks, metrics, had_err)
+                self.process_ca_task(task, tasks, parked, metrics, had_err)
             }
         }
     }
@@ -450,11 +809,12 @@ impl<'a, P: ProcessRun> Run<'a, P> {
     fn process_tal_task(
         &self, task: TalTask,
         tasks: &SegQueue<Task<P::PubPoint>>,
+        parked: &SegQueue<CaTask<P::PubPoint>>,
         metrics: &mut RunMetrics,
         had_err: &AtomicBool,
     ) -> Result<(), Failed> {
         for uri in task.tal.uris() {
-            let cert = match self.load_ta(uri, task.tal.info())? {
+            let cert = match self.load_ta(uri, task.index, metrics)? {
                 Some(cert) => cert,
                 _ => continue,
             };
@@ -465,10 +825,16 @@ impl<'a, P: ProcessRun> Run<'a, P> {
                 );
                 continue;
             }
-            let cert = match cert.validate_ta(
-                task.tal.info().clone(), self.validation.strict
+            let cert = match cert.validate_ta_at(
+                task.tal.info().clone(), self.validation.strict,
+                self.validation.now(),
             ) {
-                Ok(cert) => CaCert::root(cert, uri.clone(), task.index),
+                Ok(cert) => {
+                    CaCert::root(
+                        cert, uri.clone(), task.index,
+                        self.validation.constraints[task.index].clone(),
+                    )
+                }
                 Err(err) => {
                     warn!("Trust anchor {}: {}.", uri, err);
                     continue;
@@ -480,8 +846,6 @@ impl<'a, P: ProcessRun> Run<'a, P> {
             };
             debug!("Found valid trust anchor {}. Processing.", uri);
 
-            let tal_pk = cert.cert().subject_key_identifier();
-            DB_DUMP.get().unwrap().lock().unwrap().add_tal(tal_pk, task.tal.info().name());
             match self.processor.process_ta(
                 task.tal, uri, &cert, cert.tal
             )? {
@@ -492,7 +856,7 @@ impl<'a, P: ProcessRun> Run<'a, P> {
                             repository_index: None,
                             defer: false,
                         },
-                        tasks, metrics, had_err
+                        tasks, parked, metrics, had_err
                     )
                 }
                 None => {
@@ -509,25 +873,93 @@ impl<'a, P: ProcessRun> Run<'a, P> {
     ///
     /// Attempts to download the certificate from upstream but falls back to
     /// the version in the store if available.
+    ///
+    /// If the downloaded certificate differs from the stored one in its key,
+    /// resources, or validity, the change is logged and counted in the
+    /// metrics of the TAL with index `tal`. If pinning is enabled, the
+    /// downloaded certificate is then kept as pending and the stored one
+    /// is used until the change has been accepted.
     fn load_ta(
         &self,
         uri: &TalUri,
-        _info: &TalInfo,
+        tal: usize,
+        metrics: &mut RunMetrics,
     ) -> Result<Option<Cert>, Failed> {
-        // Get the new version, store and return it if it decodes.
-        if let Some(collector) = self.collector.as_ref() {
-            if let Some(bytes) = collector.load_ta(uri) {
-                if let Ok(cert) = Cert::decode(bytes.clone()) {
-                    self.store.update_ta(uri, &bytes)?;
-                    return Ok(Some(cert))
+        let downloaded = self.collector.as_ref().and_then(|collector| {
+            collector.load_ta(uri)
+        });
+        self.select_ta(uri, tal, metrics, downloaded)
+    }
+
+    /// Selects the trust anchor certificate to use.
+    ///
+    /// If the `downloaded` certificate decodes, it is stored and returned
+    /// unless it needs to be accepted first. Otherwise the stored
+    /// certificate is returned if there is one.
+    fn select_ta(
+        &self,
+        uri: &TalUri,
+        tal: usize,
+        metrics: &mut RunMetrics,
+        downloaded: Option<Bytes>,
+    ) -> Result<Option<Cert>, Failed> {
+        let stored = self.store.load_ta(uri)?.and_then(|bytes| {
+            Cert::decode(bytes.clone()).ok().map(|cert| (bytes, cert))
+        });
+
+        // Store and return the new version if it decodes.
+        if let Some(bytes) = downloaded {
+            if let Ok(cert) = Cert::decode(bytes.clone()) {
+                if let Some((stored_bytes, stored_cert)) = stored {
+                    if
+                        stored_bytes != bytes
+                        && self.check_ta_change(
+                            uri, tal, metrics, &stored_cert, &cert, &bytes
+                        )?
+                    {
+                        return Ok(Some(stored_cert))
+                    }
                 }
+                self.store.update_ta(uri, &bytes)?;
+                self.store.remove_pending_ta(uri)?;
+                return Ok(Some(cert))
             }
         }
 
         // Get what we have in store.
-        self.store.load_ta(uri).map(|bytes| {
-            bytes.and_then(|bytes| Cert::decode(bytes).ok())
-        })
+        Ok(stored.map(|(_, cert)| cert))
+    }
+
+    /// Checks for changes between a stored and a downloaded TA certificate.
+    ///
+    /// Logs and counts any changes. Returns whether the stored certificate
+    /// should be used because the change needs to be accepted first. This
+    /// is only the case for changes to key or resources when pinning is
+    /// enabled. The downloaded certificate is then stored as pending.
+    fn check_ta_change(
+        &self,
+        uri: &TalUri,
+        tal: usize,
+        metrics: &mut RunMetrics,
+        stored: &Cert,
+        cert: &Cert,
+        bytes: &Bytes,
+    ) -> Result<bool, Failed> {
+        let change = TaCertChange::new(stored, cert);
+        let mut tal_metrics = PublicationMetrics::default();
+        change.apply(uri, &mut tal_metrics);
+        let pin = self.validation.pin_ta_certs && change.needs_acceptance();
+        if pin {
+            warn!(
+                "Trust anchor {}: using stored certificate until the change \
+                 is accepted.",
+                uri
+            );
+            tal_metrics.pinned_ta_certs += 1;
+            self.store.update_pending_ta(uri, bytes)?;
+        }
+        metrics.apply_tal(&tal_metrics, tal);
+        Ok(pin)
     }
 
     /// Processes a CA.
@@ -535,23 +967,33 @@ impl<'a, P: ProcessRun> Run<'a, P> {
         &self,
         task: CaTask<P::PubPoint>,
         tasks: &SegQueue<Task<P::PubPoint>>,
+        parked: &SegQueue<CaTask<P::PubPoint>>,
         metrics: &mut RunMetrics,
         had_err: &AtomicBool,
     ) -> Result<(), Failed> {
+        let admitted = match self.check_tal_repository(&task, metrics) {
+            TalRepository::Admitted => true,
+            TalRepository::Refused => false,
+            TalRepository::Pending => {
+                parked.push(task);
+                return Ok(())
+            }
+        };
         let more_tasks = PubPoint::new(
             self, &task.cert, task.processor, task.repository_index,
         ).and_then(|point| {
-            point.process(metrics)
+            if admitted {
+                point.process(metrics)
+            }
+            else {
+                point.reject_excess_repository(metrics);
+                Ok(Vec::new())
+            }
         }).map_err(|_| {
             had_err.store(true, Ordering::Relaxed);
             Failed
         })?;
-        let parent_pk = task.cert.cert().subject_key_identifier();
         for task in more_tasks {
-            let rcert = task.cert.cert();
-            DB_DUMP.get().unwrap().lock().unwrap().add_ca_cert(
-                parent_pk, rcert
-            );
             if had_err.load(Ordering::Relaxed) {
                 return Err(Failed)
             }
@@ -559,7 +1001,7 @@ impl<'a, P: ProcessRun> Run<'a, P> {
                 tasks.push(Task::Ca(task))
             }
             else {
-                self.process_ca_task(task, tasks, metrics, had_err)?;
+                self.process_ca_task(task, tasks, parked, metrics, had_err)?;
             }
         }
         Ok(())
@@ -590,6 +1032,12 @@ struct PubPoint<'a, P: ProcessRun> {
     /// collected during object processing via `ValidPointManifest` so we can
     /// drop it if the point gets cancelled.
     metrics: PublicationMetrics,
+
+    /// The valid objects of the point collected for the point cache.
+    ///
+    /// This is `None` if the cache is disabled or if the point can’t be
+    /// cached because some of its objects were rejected.
+    record: Option<CacheRecord>,
 }
 
 impl<'a, P: ProcessRun> PubPoint<'a, P> {
@@ -603,6 +1051,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         Ok(PubPoint {
             run, cert, processor, repository_index,
             metrics: Default::default(),
+            record: CacheRecord::start(run.validation, cert),
         })
     }
 
@@ -622,7 +1071,17 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 )? {
                     Ok(res) => return Ok(res),
                     Err(mut this) => {
-                        this.metrics = Default::default();
+                        // Keep counting a rolled back manifest since it is
+                        // the reason for falling back to the store.
+                        this.metrics = PublicationMetrics {
+                            rollback_manifests:
+                                this.metrics.rollback_manifests,
+                            .. Default::default()
+                        };
+                        this.record = CacheRecord::start(
+                            this.run.validation, this.cert
+                        );
+                        this.processor.restart()?;
                         return this.process_stored(store, metrics)
                     }
                 }
@@ -631,6 +1090,17 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         self.process_stored(store, metrics)
     }
 
+    /// Rejects the point because its TAL has too many repositories.
+    fn reject_excess_repository(mut self, metrics: &mut RunMetrics) {
+        warn!(
+            "{}: TAL {} exceeds limit of repositories. \
+             Rejecting publication point.",
+            self.cert.uri(), self.cert.cert().tal().name(),
+        );
+        self.metrics.excess_repository_points += 1;
+        self.reject_point("too many repositories for TAL", metrics);
+    }
+
     /// Tries to update the stored data and validate at the same time.
     ///
     /// Tries to fetch the updated manifest from the collector. If it differs
@@ -675,14 +1145,16 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
 
         // Validate the collected manifest. If it is no good, well, you know
         // the drill.
+        self.processor.point_source(PointSource::Collected);
         let mut collected = match self.validate_collected_manifest(
-            collected, &collector
+            collected, store.manifest(), &collector
         )? {
             Some(collected) => collected,
             None => {
                 return Ok(Err(self))
             }
         };
+        self.process_manifest(&collected)?;
 
         // The manifest is fine, so we can now look at the objects. The
         // objects are fine if they are present and match the hash. If they
@@ -719,9 +1191,13 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 let file = match str_from_ascii(item.file()) {
                     Ok(file) => file,
                     Err(_) => {
-                        warn!("{}: illegal file name '{}'.",
-                            self.cert.rpki_manifest(),
-                            String::from_utf8_lossy(item.file())
+                        let cert = self.cert;
+                        self.reject_object(
+                            cert.rpki_manifest(), ObjectKind::Manifest,
+                            format_args!(
+                                "illegal file name '{}'",
+                                String::from_utf8_lossy(item.file())
+                            )
                         );
                         return Err(store::UpdateError::Abort)
                     }
@@ -737,13 +1213,18 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 let content = match collector.load_object(&uri)? {
                     Some(content) => content,
                     None => {
-                        warn!("{}: failed to load.", uri);
+                        self.reject_object(
+                            &uri, ObjectKind::from_uri(&uri), "failed to load"
+                        );
                         return Err(store::UpdateError::Abort)
                     }
                 };
 
                 if hash.verify(&content).is_err() {
-                    warn!("{}: file has wrong manifest hash.", uri);
+                    self.reject_object(
+                        &uri, ObjectKind::from_uri(&uri),
+                        "file has wrong manifest hash"
+                    );
                     return Err(store::UpdateError::Abort)
                 }
 
@@ -762,14 +1243,19 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Ok(()) => {
                 // Update was successful. We have to accept whatever result
                 // we got.
-                if point_ok {
-                    self.accept_point(collected, metrics);
-                    Ok(Ok(ca_tasks))
+                if !point_ok {
+                    self.reject_point("rejected by processor", metrics);
+                    Ok(Ok(Vec::new()))
                 }
-                else {
-                    self.reject_point(metrics);
+                else if collected.excess_child_cas {
+                    self.metrics.excess_child_ca_points += 1;
+                    self.reject_point("too many child CAs", metrics);
                     Ok(Ok(Vec::new()))
                 }
+                else {
+                    self.accept_point(collected, metrics);
+                    Ok(Ok(ca_tasks))
+                }
             }
             Err(store::UpdateError::Abort) => {
                 // Update was aborted. We need to use the store.
@@ -785,12 +1271,14 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
     /// Tries to validate a manifest acquired from the collector.
     ///
     /// Checks that the manifest is correct itself and has been signed by the
-    /// publication point’s CA. Tries to load the associated CRL from the
-    /// collector, validates that against the CA and checks that the manifest
-    /// has not been revoked.
+    /// publication point’s CA. Checks that it doesn’t go backwards when
+    /// compared to the stored manifest if there is one. Tries to load the
+    /// associated CRL from the collector, validates that against the CA and
+    /// checks that the manifest has not been revoked.
     fn validate_collected_manifest(
         &mut self,
         manifest_bytes: Bytes,
+        stored: Option<&StoredManifest>,
         repository: &collector::Repository,
     ) -> Result<Option<ValidPointManifest>, Failed> {
         let manifest = match Manifest::decode(
@@ -799,32 +1287,37 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Ok(manifest) => manifest,
             Err(err) => {
                 self.metrics.invalid_manifests += 1;
-                warn!("{}: {}.", self.cert.rpki_manifest(), err);
+                self.reject_manifest(err);
                 return Ok(None)
             }
         };
-        let (ee_cert, content) = match manifest.validate(
-            self.cert.cert(), self.run.validation.strict
+        let (ee_cert, content) = match manifest.validate_at(
+            self.cert.cert(), self.run.validation.strict,
+            self.run.validation.now(),
         ) {
             Ok(some) => some,
             Err(err) => {
                 self.metrics.invalid_manifests += 1;
-                warn!("{}: {}.", self.cert.rpki_manifest(), err);
+                self.reject_manifest(err);
                 return Ok(None)
             }
         };
 
-        if content.this_update() > Time::now() {
+        if !self.check_manifest_entries(&content) {
+            return Ok(None)
+        }
+
+        if content.this_update() > self.run.validation.now() {
             self.metrics.premature_manifests += 1;
-            warn!("{}: premature manifest", self.cert.rpki_manifest());
+            self.reject_manifest("premature manifest");
             return Ok(None)
         }
 
-        if content.is_stale() {
+        if content.next_update() < self.run.validation.now() {
             self.metrics.stale_manifests += 1;
             match self.run.validation.stale {
                 FilterPolicy::Reject => {
-                    warn!("{}: stale manifest", self.cert.rpki_manifest());
+                    self.reject_manifest("stale manifest");
                     return Ok(None)
                 }
                 FilterPolicy::Warn => {
@@ -834,6 +1327,21 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             }
         }
 
+        // RFC 9286 requires the manifest number to increase with every new
+        // manifest. A manifest going backwards may be an attempt to replay
+        // old data, so we stick with what we have.
+        if let Some(previous) = stored.and_then(|stored| {
+            self.stored_manifest_sequence(stored)
+        }) {
+            if let Err(reason) = ManifestSequence::from_content(
+                &content
+            ).check_successor(previous) {
+                self.metrics.rollback_manifests += 1;
+                self.reject_manifest(reason);
+                return Ok(None)
+            }
+        }
+
         let (crl_uri, crl, crl_bytes) = match self.validate_collected_crl(
             &ee_cert, &content, repository
         )? {
@@ -846,9 +1354,50 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         Ok(Some(ValidPointManifest {
             ee_cert, content, crl_uri, crl, manifest_bytes, crl_bytes,
             metrics: Default::default(),
+            excess_child_cas: false,
         }))
     }
 
+    /// Checks that the manifest doesn’t have too many entries.
+    ///
+    /// Rejects the manifest and returns `false` if it does.
+    fn check_manifest_entries(&mut self, content: &ManifestContent) -> bool {
+        if let Some(max) = self.run.validation.max_manifest_entries {
+            if content.len() > max {
+                self.metrics.oversized_manifests += 1;
+                self.reject_manifest(format_args!(
+                    "manifest has {} entries, limit is {}",
+                    content.len(), max
+                ));
+                return false
+            }
+        }
+        true
+    }
+
+    /// Returns the sequence position of a stored manifest.
+    ///
+    /// Returns `None` if the stored manifest cannot be decoded or is not
+    /// for the current CA certificate’s repository and key, in which case
+    /// it cannot be compared with a newly collected manifest.
+    fn stored_manifest_sequence(
+        &self, stored: &StoredManifest
+    ) -> Option<ManifestSequence> {
+        if stored.ca_repository() != self.cert.ca_repository() {
+            return None
+        }
+        let manifest = Manifest::decode(
+            stored.manifest().clone(), self.run.validation.strict
+        ).ok()?;
+        if
+            manifest.cert().authority_key_identifier()
+                != Some(self.cert.cert().subject_key_identifier())
+        {
+            return None
+        }
+        Some(ManifestSequence::from_content(manifest.content()))
+    }
+
     /// Check the manifest CRL.
     ///
     /// Checks that there is exactly one CRL on the manifest, that it matches
@@ -870,7 +1419,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Some(some) if some.ends_with(".crl") => some.clone(),
             _ => {
                 self.metrics.invalid_manifests += 1;
-                warn!("{}: invalid CRL URI.", self.cert.rpki_manifest());
+                self.reject_manifest("invalid CRL URI");
                 return Ok(None)
             }
         };
@@ -878,10 +1427,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Some(name) => name,
             None => {
                 self.metrics.invalid_manifests += 1;
-                warn!(
-                    "{}: CRL URI outside repository directory.",
-                    self.cert.rpki_manifest()
-                );
+                self.reject_manifest("CRL URI outside repository directory");
                 return Ok(None)
             }
         };
@@ -896,14 +1442,18 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                     Some(bytes) => bytes,
                     None => {
                         self.metrics.invalid_crls += 1;
-                        warn!("{}: failed to load.", crl_uri);
+                        self.reject_object(
+                            &crl_uri, ObjectKind::Crl, "failed to load"
+                        );
                         return Ok(None)
                     }
                 };
                 let hash = ManifestHash::new(hash, manifest.file_hash_alg());
                 if hash.verify(&bytes).is_err() {
                     self.metrics.invalid_crls += 1;
-                    warn!("{}: file has wrong hash.", crl_uri);
+                    self.reject_object(
+                        &crl_uri, ObjectKind::Crl, "file has wrong hash"
+                    );
                     return Ok(None)
                 }
                 crl_bytes = Some(bytes);
@@ -913,10 +1463,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Some(some) => some,
             None => {
                 self.metrics.invalid_crls += 1;
-                warn!(
-                    "{}: CRL not listed on manifest.",
-                    self.cert.rpki_manifest()
-                );
+                self.reject_manifest("CRL not listed on manifest");
                 return Ok(None)
             }
         };
@@ -926,7 +1473,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Ok(crl) => crl,
             Err(err) => {
                 self.metrics.invalid_crls += 1;
-                warn!("{}: {}.", crl_uri, err);
+                self.reject_object(&crl_uri, ObjectKind::Crl, err);
                 return Ok(None)
             }
         };
@@ -934,14 +1481,14 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             self.cert.cert().subject_public_key_info()
         ) {
             self.metrics.invalid_crls += 1;
-            warn!("{}: {}.", crl_uri, err);
+            self.reject_object(&crl_uri, ObjectKind::Crl, err);
             return Ok(None)
         }
-        if crl.is_stale() {
+        if crl.next_update() < self.run.validation.now() {
             self.metrics.stale_crls += 1;
             match self.run.validation.stale {
                 FilterPolicy::Reject => {
-                    warn!("{}: stale CRL.", crl_uri);
+                    self.reject_object(&crl_uri, ObjectKind::Crl, "stale CRL");
                     return Ok(None)
                 }
                 FilterPolicy::Warn => {
@@ -959,10 +1506,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         // Finally: has the manifest’s cert been revoked?
         if crl.contains(ee_cert.serial_number()) {
             self.metrics.invalid_manifests += 1;
-            warn!(
-                "{}: certificate has been revoked.",
-                self.cert.rpki_manifest()
-            );
+            self.reject_manifest("certificate has been revoked");
             return Ok(None)
         }
 
@@ -982,6 +1526,10 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         mut store: StoredPoint,
         metrics: &mut RunMetrics,
     ) -> Result<Vec<CaTask<P::PubPoint>>, Failed> {
+        if let Some(cached) = self.cached_point(&store)? {
+            return self.process_cached(cached, metrics)
+        }
+
         let manifest = match store.take_manifest() {
             Some(manifest) => manifest,
             None => {
@@ -992,7 +1540,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                     self.cert.uri(), self.cert.rpki_manifest()
                 );
                 self.metrics.missing_manifests += 1;
-                self.reject_point(metrics);
+                self.reject_point("no valid manifest found", metrics);
                 return Ok(Vec::new())
             }
         };
@@ -1000,10 +1548,12 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         let mut manifest = match self.validate_stored_manifest(manifest) {
             Ok(manifest) => manifest,
             Err(_) => {
-                self.reject_point(metrics);
+                self.reject_point("invalid stored manifest", metrics);
                 return Ok(Vec::new())
             }
         };
+        self.processor.point_source(PointSource::Stored);
+        self.process_manifest(&manifest)?;
 
         let mut ca_tasks = Vec::new();
         for object in &mut store {
@@ -1023,7 +1573,9 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                              at {}: {}",
                             store.path().display(), err
                         );
-                        self.reject_point(metrics);
+                        self.reject_point(
+                            "invalid stored publication point", metrics
+                        );
                         return Ok(Vec::new())
                     }
                 }
@@ -1032,15 +1584,103 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 object.uri(), object.content().clone(),
                 &mut manifest, &mut ca_tasks
             )? {
-                self.reject_point(metrics);
+                self.reject_point("rejected by processor", metrics);
                 return Ok(Vec::new())
             }
         }
 
+        if manifest.excess_child_cas {
+            self.metrics.excess_child_ca_points += 1;
+            self.reject_point("too many child CAs", metrics);
+            return Ok(Vec::new())
+        }
+
         self.accept_point(manifest, metrics);
         Ok(ca_tasks)
     }
 
+    /// Returns the cached version of the point if it can be used.
+    ///
+    /// The cached point can be used if it was created for the same CA
+    /// certificate and stored manifest, none of its objects has expired
+    /// since, and the processor doesn’t want any of the objects it skipped
+    /// last time.
+    fn cached_point(
+        &self, store: &StoredPoint,
+    ) -> Result<Option<Arc<CachedPoint>>, Failed> {
+        let cache = match self.run.validation.point_cache.as_ref() {
+            Some(cache) => cache,
+            None => return Ok(None),
+        };
+        let stored = match store.manifest() {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        let cached = match cache.get(self.cert.rpki_manifest()) {
+            Some(cached) => cached,
+            None => return Ok(None),
+        };
+        if !cached.matches(self.cert, stored, self.run.validation.now()) {
+            return Ok(None)
+        }
+        for uri in &cached.unwanted {
+            if self.processor.want(uri)? {
+                return Ok(None)
+            }
+        }
+        Ok(Some(cached))
+    }
+
+    /// Processes the point using the objects from the point cache.
+    ///
+    /// Hands all the cached objects to the processor without validating
+    /// them again and keeps the cached point for the next run.
+    fn process_cached(
+        mut self,
+        cached: Arc<CachedPoint>,
+        metrics: &mut RunMetrics,
+    ) -> Result<Vec<CaTask<P::PubPoint>>, Failed> {
+        self.processor.point_source(PointSource::Stored);
+        self.process_manifest(&cached.manifest)?;
+
+        let mut ca_tasks = Vec::new();
+        for object in &cached.objects {
+            if !self.processor.want(object.uri())? {
+                continue
+            }
+            match object.clone() {
+                CachedObject::Ca(uri, cert) => {
+                    self.push_ca_task(&uri, cert, &mut ca_tasks)?;
+                }
+                CachedObject::RouterCert(uri, cert) => {
+                    self.processor.process_router_cert(&uri, cert, self.cert)?;
+                }
+                CachedObject::Roa(uri, cert, route) => {
+                    self.processor.process_roa(&uri, cert, route)?;
+                }
+                #[cfg(feature = "aspa")]
+                CachedObject::Aspa(uri, cert, aspa) => {
+                    self.processor.process_aspa(&uri, cert, aspa)?;
+                }
+                CachedObject::Gbr(uri, cert, content) => {
+                    self.processor.process_gbr(&uri, cert, content)?;
+                }
+                CachedObject::Tak(_, _, tak) => {
+                    self.register_tak(tak);
+                }
+            }
+        }
+
+        self.metrics = cached.metrics.clone();
+        self.metrics.cached_points += 1;
+        self.apply_metrics(metrics);
+        self.processor.commit();
+        if let Some(cache) = self.run.validation.point_cache.as_ref() {
+            cache.keep(self.cert.rpki_manifest(), cached);
+        }
+        Ok(ca_tasks)
+    }
+
     /// Tries to validate a stored manifest.
     ///
     /// This is similar to
@@ -1057,26 +1697,30 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         ) {
             Ok(manifest) => manifest,
             Err(err) => {
-                warn!("{}: {}.", self.cert.rpki_manifest(), err);
+                self.reject_manifest(err);
                 self.metrics.invalid_manifests += 1;
                 return Err(Failed);
             }
         };
-        let (ee_cert, content) = match manifest.validate(
-            self.cert.cert(), self.run.validation.strict
+        let (ee_cert, content) = match manifest.validate_at(
+            self.cert.cert(), self.run.validation.strict,
+            self.run.validation.now(),
         ) {
             Ok(some) => some,
             Err(err) => {
-                warn!("{}: {}.", self.cert.rpki_manifest(), err);
+                self.reject_manifest(err);
                 self.metrics.invalid_manifests += 1;
                 return Err(Failed);
             }
         };
-        if content.is_stale() {
+        if !self.check_manifest_entries(&content) {
+            return Err(Failed)
+        }
+        if content.next_update() < self.run.validation.now() {
             self.metrics.stale_manifests += 1;
             match self.run.validation.stale {
                 FilterPolicy::Reject => {
-                    warn!("{}: stale manifest", self.cert.rpki_manifest());
+                    self.reject_manifest("stale manifest");
                     self.metrics.invalid_manifests += 1;
                     return Err(Failed);
                 }
@@ -1092,10 +1736,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Some(uri) => uri.clone(),
             None => {
                 // This should have been ruled out in manifest validation.
-                warn!(
-                    "{}: manifest without CRL URI.",
-                    self.cert.rpki_manifest()
-                );
+                self.reject_manifest("manifest without CRL URI");
                 self.metrics.invalid_manifests += 1;
                 return Err(Failed)
             }
@@ -1105,7 +1746,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         let mut crl = match Crl::decode(stored_manifest.crl().clone()) {
             Ok(crl) => crl,
             Err(err) => {
-                warn!("{}: {}.", crl_uri, err);
+                self.reject_object(&crl_uri, ObjectKind::Crl, err);
                 self.metrics.invalid_manifests += 1;
                 self.metrics.invalid_crls += 1;
                 return Err(Failed)
@@ -1114,16 +1755,16 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         if let Err(err) = crl.verify_signature(
             self.cert.cert().subject_public_key_info()
         ) {
-            warn!("{}: {}.", crl_uri, err);
+            self.reject_object(&crl_uri, ObjectKind::Crl, err);
             self.metrics.invalid_manifests += 1;
             self.metrics.invalid_crls += 1;
             return Err(Failed)
         }
-        if crl.is_stale() {
+        if crl.next_update() < self.run.validation.now() {
             self.metrics.stale_crls += 1;
             match self.run.validation.stale {
                 FilterPolicy::Reject => {
-                    warn!("{}: stale CRL.", crl_uri);
+                    self.reject_object(&crl_uri, ObjectKind::Crl, "stale CRL");
                     self.metrics.invalid_manifests += 1;
                     self.metrics.invalid_crls += 1;
                     return Err(Failed)
@@ -1145,10 +1786,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         // XXX This shouldn’t really happen because if it were we would never
         //     have stored this manifest.
         if crl.contains(ee_cert.serial_number()) {
-            warn!(
-                "{}: certificate has been revoked.",
-                self.cert.rpki_manifest()
-            );
+            self.reject_manifest("certificate has been revoked");
             self.metrics.invalid_manifests += 1;
             return Err(Failed)
         }
@@ -1160,31 +1798,82 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             manifest_bytes: stored_manifest.manifest().clone(),
             crl_bytes: stored_manifest.crl().clone(),
             metrics: Default::default(),
+            excess_child_cas: false,
         })
     }
 
-    // XXX Check metrics generation and concentrate at one central point.
-
-    fn accept_point(
+    /// Hands the validated manifest and CRL to the processor.
+    fn process_manifest(
+        &mut self, manifest: &ValidPointManifest
+    ) -> Result<(), Failed> {
+        self.processor.process_manifest(
+            self.cert.rpki_manifest(), &manifest.ee_cert, &manifest.content,
+            &manifest.crl_uri, &manifest.crl,
+        )
+    }
+
+    // XXX Check metrics generation and concentrate at one central point.
+
+    fn accept_point(
         mut self,
         manifest: ValidPointManifest,
         metrics: &mut RunMetrics,
     ) {
         self.metrics.valid_points += 1;
-        self.metrics += manifest.metrics;
+        self.metrics += &manifest.metrics;
         self.apply_metrics(metrics);
+        if let (Some(cache), Some(record)) = (
+            self.run.validation.point_cache.as_ref(), self.record.take()
+        ) {
+            cache.insert(
+                CachedPoint::new(
+                    self.cert.clone(), manifest, record, self.metrics.clone()
+                ),
+                self.run.validation.now(),
+            );
+        }
         self.processor.commit();
     }
 
     fn reject_point(
         mut self,
+        reason: &str,
         metrics: &mut RunMetrics,
     ) {
         self.metrics.rejected_points += 1;
         self.apply_metrics(metrics);
+        self.processor.rejected_point(reason);
         self.processor.cancel(self.cert);
     }
 
+    /// Rejects an object of the publication point.
+    ///
+    /// Logs the reason as a warning and passes it on to the processor.
+    fn reject_object(
+        &mut self,
+        uri: &uri::Rsync,
+        kind: ObjectKind,
+        reason: impl fmt::Display,
+    ) {
+        let reason = reason.to_string();
+        warn!("{}: {}.", uri, reason);
+        self.record = None;
+        self.processor.rejected_object(uri, kind, &reason);
+    }
+
+    /// Adds a valid object to the record for the point cache.
+    fn record(&mut self, object: impl FnOnce() -> CachedObject) {
+        if let Some(record) = self.record.as_mut() {
+            record.push(object())
+        }
+    }
+
+    /// Rejects the manifest of the publication point.
+    fn reject_manifest(&mut self, reason: impl fmt::Display) {
+        let cert = self.cert;
+        self.reject_object(cert.rpki_manifest(), ObjectKind::Manifest, reason)
+    }
+
     fn apply_metrics(
         &mut self,
         metrics: &mut RunMetrics,
@@ -1212,6 +1901,9 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         ca_task: &mut Vec<CaTask<P::PubPoint>>,
     ) -> Result<bool, Failed> {
         if !self.processor.want(uri)? {
+            if let Some(record) = self.record.as_mut() {
+                record.unwanted.push(uri.clone())
+            }
             return Ok(true)
         }
 
@@ -1227,6 +1919,9 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         else if uri.ends_with(".gbr") {
             self.process_gbr(uri, content, manifest)?;
         }
+        else if uri.ends_with(".tak") {
+            self.process_tak(uri, content, manifest);
+        }
         else if uri.ends_with(".crl") {
             if *uri != manifest.crl_uri {
                 warn!("{}: stray CRL.", uri);
@@ -1251,7 +1946,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         let cert = match Cert::decode(content) {
             Ok(cert) => cert,
             Err(err) => {
-                warn!("{}: {}.", uri, err);
+                self.reject_object(uri, ObjectKind::Cert, err);
                 manifest.metrics.invalid_certs += 1;
                 return Ok(())
             }
@@ -1266,29 +1961,45 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
     }
 
     /// Processes a CA certificate.
+    ///
+    /// If the publication point has too many child CAs, marks the manifest
+    /// so the point will be rejected.
     #[allow(clippy::too_many_arguments)]
     fn process_ca_cer(
         &mut self, uri: &uri::Rsync, cert: Cert,
         manifest: &mut ValidPointManifest,
         ca_task: &mut Vec<CaTask<P::PubPoint>>,
     ) -> Result<(), Failed> {
+        if let Some(max) = self.run.validation.max_child_cas {
+            if manifest.metrics.valid_ca_certs as usize >= max {
+                self.reject_object(
+                    uri, ObjectKind::CaCert,
+                    format_args!("exceeds limit of {} child CAs", max)
+                );
+                manifest.excess_child_cas = true;
+                return Ok(())
+            }
+        }
         if self.cert.check_loop(&cert).is_err() {
-            warn!("{}: certificate loop detected.", uri);
+            self.reject_object(
+                uri, ObjectKind::CaCert, "certificate loop detected"
+            );
             manifest.metrics.invalid_certs += 1;
             return Ok(())
         }
-        let cert = match cert.validate_ca(
-            self.cert.cert(), self.run.validation.strict
+        let cert = match cert.validate_ca_at(
+            self.cert.cert(), self.run.validation.strict,
+            self.run.validation.now(),
         ) {
             Ok(cert) => cert,
             Err(err) => {
-                warn!("{}: {}.", uri, err);
+                self.reject_object(uri, ObjectKind::CaCert, err);
                 manifest.metrics.invalid_certs += 1;
                 return Ok(())
             }
         };
         if let Err(err) = manifest.check_crl(&cert) {
-            warn!("{}: {}.", uri, err);
+            self.reject_object(uri, ObjectKind::CaCert, err);
             manifest.metrics.invalid_certs += 1;
             return Ok(())
         }
@@ -1298,13 +2009,26 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         ) {
             Ok(cert) => cert,
             Err(_) => {
+                // The error has already been logged.
+                self.record = None;
+                self.processor.rejected_object(
+                    uri, ObjectKind::CaCert, "CA depth overrun"
+                );
                 manifest.metrics.invalid_certs += 1;
                 return Ok(())
             }
         };
 
         manifest.metrics.valid_ca_certs += 1;
+        self.record(|| CachedObject::Ca(uri.clone(), cert.clone()));
+        self.push_ca_task(uri, cert, ca_task)
+    }
 
+    /// Creates the processing task for a valid child CA.
+    fn push_ca_task(
+        &mut self, uri: &uri::Rsync, cert: Arc<CaCert>,
+        ca_task: &mut Vec<CaTask<P::PubPoint>>,
+    ) -> Result<(), Failed> {
         let mut processor = match self.processor.process_ca(
             uri, &cert
         )? {
@@ -1339,19 +2063,21 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         &mut self, uri: &uri::Rsync, cert: Cert,
         manifest: &mut ValidPointManifest,
     ) -> Result<(), Failed> {
-        if let Err(err) = cert.validate_router(
-            self.cert.cert(), self.run.validation.strict
+        if let Err(err) = cert.validate_router_at(
+            self.cert.cert(), self.run.validation.strict,
+            self.run.validation.now(),
         ) {
-            warn!("{}: {}.", uri, err);
+            self.reject_object(uri, ObjectKind::RouterCert, err);
             manifest.metrics.invalid_certs += 1;
             return Ok(())
         };
         if let Err(err) = manifest.check_crl(&cert) {
-            warn!("{}: {}.", uri, err);
+            self.reject_object(uri, ObjectKind::RouterCert, err);
             manifest.metrics.invalid_certs += 1;
             return Ok(())
         }
         manifest.metrics.valid_router_certs += 1;
+        self.record(|| CachedObject::RouterCert(uri.clone(), cert.clone()));
         self.processor.process_router_cert(uri, cert, self.cert)?;
         Ok(())
     }
@@ -1362,31 +2088,38 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         manifest: &mut ValidPointManifest,
     ) -> Result<(), Failed> {
         let roa = match Roa::decode(
-            content, self.run.validation.strict
+            content.clone(), self.run.validation.strict
         ) {
             Ok(roa) => roa,
             Err(err) => {
-                warn!("{}: {}.", uri, err);
+                self.reject_object(uri, ObjectKind::Roa, err);
                 manifest.metrics.invalid_roas += 1;
                 return Ok(())
             }
         };
-        match roa.process(
-            self.cert.cert(),
-            self.run.validation.strict,
-            |cert| manifest.check_crl(cert)
-        ) {
+        let res = match self.run.validation.validation_time {
+            Some(now) => process_roa_at(
+                roa, content, self.cert.cert(),
+                self.run.validation.strict, now,
+                |cert| manifest.check_crl(cert)
+            ),
+            None => roa.process(
+                self.cert.cert(),
+                self.run.validation.strict,
+                |cert| manifest.check_crl(cert)
+            ),
+        };
+        match res {
             Ok((cert, route)) => {
-                let parent_pk = self.cert.cert().subject_key_identifier();
-                DB_DUMP.get().unwrap().lock().unwrap().add_roa(
-                    parent_pk, &cert, &route
-                );
                 manifest.metrics.valid_roas += 1;
+                self.record(|| {
+                    CachedObject::Roa(uri.clone(), cert.clone(), route.clone())
+                });
                 self.processor.process_roa(uri, cert, route)?
             }
             Err(err) => {
                 manifest.metrics.invalid_roas += 1;
-                warn!("{}: {}.", uri, err)
+                self.reject_object(uri, ObjectKind::Roa, err)
             }
         }
         Ok(())
@@ -1400,27 +2133,38 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
     ) -> Result<(), Failed> {
         #[cfg(feature = "aspa")] {
             let aspa = match Aspa::decode(
-                content, self.run.validation.strict
+                content.clone(), self.run.validation.strict
             ) {
                 Ok(aspa) => aspa,
                 Err(err) => {
-                    warn!("{}: {}.", uri, err);
+                    self.reject_object(uri, ObjectKind::Aspa, err);
                     manifest.metrics.invalid_aspas += 1;
                     return Ok(())
                 }
             };
-            match aspa.process(
-                self.cert.cert(),
-                self.run.validation.strict,
-                |cert| manifest.check_crl(cert)
-            ) {
+            let res = match self.run.validation.validation_time {
+                Some(now) => process_aspa_at(
+                    aspa, content, self.cert.cert(),
+                    self.run.validation.strict, now,
+                    |cert| manifest.check_crl(cert)
+                ),
+                None => aspa.process(
+                    self.cert.cert(),
+                    self.run.validation.strict,
+                    |cert| manifest.check_crl(cert)
+                ),
+            };
+            match res {
                 Ok((cert, aspa)) => {
                     manifest.metrics.valid_aspas += 1;
+                    self.record(|| CachedObject::Aspa(
+                        uri.clone(), cert.clone(), aspa.clone()
+                    ));
                     self.processor.process_aspa(uri, cert, aspa)?
                 }
                 Err(err) => {
                     manifest.metrics.invalid_aspas += 1;
-                    warn!("{}: {}.", uri, err)
+                    self.reject_object(uri, ObjectKind::Aspa, err)
                 }
             }
         }
@@ -1437,27 +2181,98 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         ) {
             Ok(obj) => obj,
             Err(err) => {
-                warn!("{}: {}.", uri, err);
+                self.reject_object(uri, ObjectKind::Gbr, err);
                 manifest.metrics.invalid_gbrs += 1;
                 return Ok(())
             }
         };
-        match obj.process(
-            self.cert.cert(),
-            self.run.validation.strict,
+        let gbr_content = obj.content().to_bytes();
+        match validate_signed_at(
+            obj, self.cert.cert(),
+            self.run.validation.strict, self.run.validation.now(),
             |cert| manifest.check_crl(cert)
-        ) {
+        ).map(|cert| (cert, gbr_content)) {
             Ok((cert, content)) => {
                 manifest.metrics.valid_gbrs += 1;
+                self.record(|| CachedObject::Gbr(
+                    uri.clone(), cert.clone(), content.clone()
+                ));
                 self.processor.process_gbr(uri, cert, content)?
             }
             Err(err) => {
                 manifest.metrics.invalid_gbrs += 1;
-                warn!("{}: {}.", uri, err)
+                self.reject_object(uri, ObjectKind::Gbr, err)
             }
         }
         Ok(())
     }
+
+    /// Processes a Trust Anchor Key object.
+    ///
+    /// The object is only valid if published by a trust anchor and if its
+    /// current key is the trust anchor’s key. Its content is then kept for
+    /// updating the key rollover state at the end of the run.
+    fn process_tak(
+        &mut self, uri: &uri::Rsync, content: Bytes,
+        manifest: &mut ValidPointManifest,
+    ) {
+        if self.cert.chain_len() != 0 {
+            manifest.metrics.invalid_taks += 1;
+            self.reject_object(
+                uri, ObjectKind::Tak, "TAK object not issued by trust anchor"
+            );
+            return
+        }
+        let obj = match SignedObject::decode(
+            content, self.run.validation.strict
+        ) {
+            Ok(obj) => obj,
+            Err(err) => {
+                manifest.metrics.invalid_taks += 1;
+                self.reject_object(uri, ObjectKind::Tak, err);
+                return
+            }
+        };
+        let tak = match TakContent::from_signed_object(&obj) {
+            Ok(tak) => tak,
+            Err(err) => {
+                manifest.metrics.invalid_taks += 1;
+                self.reject_object(uri, ObjectKind::Tak, err);
+                return
+            }
+        };
+        let cert = match validate_signed_at(
+            obj, self.cert.cert(),
+            self.run.validation.strict, self.run.validation.now(),
+            |cert| manifest.check_crl(cert)
+        ) {
+            Ok(cert) => cert,
+            Err(err) => {
+                manifest.metrics.invalid_taks += 1;
+                self.reject_object(uri, ObjectKind::Tak, err);
+                return
+            }
+        };
+        if
+            tak.current().key_info()
+                != self.cert.cert().subject_public_key_info()
+        {
+            manifest.metrics.invalid_taks += 1;
+            self.reject_object(
+                uri, ObjectKind::Tak,
+                "current key in TAK object differs from trust anchor key"
+            );
+            return
+        }
+        manifest.metrics.valid_taks += 1;
+        self.record(|| CachedObject::Tak(uri.clone(), cert, tak.clone()));
+        self.register_tak(tak);
+    }
+
+    /// Keeps the content of a valid TAK object for the point’s TAL.
+    fn register_tak(&self, tak: TakContent) {
+        self.run.taks.lock().unwrap().insert(self.cert.tal, tak);
+    }
 }
 
 
@@ -1492,6 +2307,9 @@ struct ValidPointManifest {
     /// We keep them here separatedly, so we can just zero them out if
     /// processor decides to drop the publication point.
     metrics: PublicationMetrics,
+
+    /// Did the publication point exceed the limit of child CAs?
+    excess_child_cas: bool,
 }
 
 impl ValidPointManifest {
@@ -1523,6 +2341,351 @@ impl ValidPointManifest {
 }
 
 
+//------------ ManifestSequence ----------------------------------------------
+
+/// The position of a manifest in the sequence of a CA’s manifests.
+#[derive(Clone, Copy, Debug)]
+struct ManifestSequence {
+    /// The manifest number.
+    number: Serial,
+
+    /// The thisUpdate time of the manifest.
+    this_update: Time,
+}
+
+impl ManifestSequence {
+    /// Creates the sequence position from the content of a manifest.
+    fn from_content(content: &ManifestContent) -> Self {
+        ManifestSequence {
+            number: content.manifest_number(),
+            this_update: content.this_update(),
+        }
+    }
+
+    /// Checks whether `self` may follow a different manifest `previous`.
+    ///
+    /// The manifest number has to increase and the thisUpdate time must
+    /// not go backwards. If either is not the case, returns an error with
+    /// the reason.
+    fn check_successor(self, previous: Self) -> Result<(), &'static str> {
+        if self.number <= previous.number {
+            Err("manifest number not higher than that of stored manifest")
+        }
+        else if self.this_update < previous.this_update {
+            Err("thisUpdate earlier than that of stored manifest")
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+
+//------------ PointCache ----------------------------------------------------
+
+/// The validated publication points kept between runs.
+///
+/// The cache keeps two sets of points keyed by their manifest URI: those of
+/// the previous run which can be reused in the current run and those used
+/// or created during the current run. When a run finishes successfully,
+/// the latter replace the former, so points that have disappeared are
+/// dropped.
+#[derive(Debug, Default)]
+struct PointCache {
+    /// The points available from the previous run.
+    previous: Mutex<HashMap<uri::Rsync, Arc<CachedPoint>>>,
+
+    /// The points of the current run.
+    current: Mutex<HashMap<uri::Rsync, Arc<CachedPoint>>>,
+}
+
+impl PointCache {
+    /// Prepares the cache for a new run.
+    fn start(&self) {
+        self.current.lock().unwrap().clear()
+    }
+
+    /// Returns the cached point for the given manifest URI.
+    fn get(&self, uri: &uri::Rsync) -> Option<Arc<CachedPoint>> {
+        self.previous.lock().unwrap().get(uri).cloned()
+    }
+
+    /// Keeps a point from the previous run for the next run.
+    fn keep(&self, uri: &uri::Rsync, point: Arc<CachedPoint>) {
+        self.current.lock().unwrap().insert(uri.clone(), point);
+    }
+
+    /// Adds a newly validated point.
+    ///
+    /// Points that have expired already at `now` are quietly dropped.
+    fn insert(&self, point: CachedPoint, now: Time) {
+        if point.expires <= now {
+            return
+        }
+        self.current.lock().unwrap().insert(
+            point.ca.rpki_manifest().clone(), Arc::new(point)
+        );
+    }
+
+    /// Finishes a successful run.
+    fn finish(&self) {
+        let current = mem::take(&mut *self.current.lock().unwrap());
+        *self.previous.lock().unwrap() = current;
+    }
+}
+
+
+//------------ CachedPoint ---------------------------------------------------
+
+/// A validated publication point in the point cache.
+#[derive(Debug)]
+struct CachedPoint {
+    /// The CA certificate the point was validated with.
+    ca: Arc<CaCert>,
+
+    /// The validated manifest and CRL.
+    manifest: ValidPointManifest,
+
+    /// The valid objects in manifest order.
+    objects: Vec<CachedObject>,
+
+    /// The objects the processor didn’t want.
+    unwanted: Vec<uri::Rsync>,
+
+    /// The publication metrics of the point.
+    metrics: PublicationMetrics,
+
+    /// The time when the first object of the point expires.
+    expires: Time,
+}
+
+impl CachedPoint {
+    /// Creates a new cached point from a validated point.
+    fn new(
+        ca: Arc<CaCert>,
+        manifest: ValidPointManifest,
+        record: CacheRecord,
+        metrics: PublicationMetrics,
+    ) -> Self {
+        let expires = cmp::min(
+            record.expires,
+            cmp::min(
+                cmp::min(
+                    manifest.ee_cert.validity().not_after(),
+                    manifest.content.next_update(),
+                ),
+                manifest.crl.next_update(),
+            )
+        );
+        CachedPoint {
+            ca, manifest,
+            objects: record.objects,
+            unwanted: record.unwanted,
+            metrics, expires
+        }
+    }
+
+    /// Returns whether the cached point can be used instead of `stored`.
+    fn matches(
+        &self, ca: &CaCert, stored: &StoredManifest, now: Time
+    ) -> bool {
+        now < self.expires
+            && stored.ca_repository() == ca.ca_repository()
+            && self.manifest.manifest_bytes == stored.manifest()
+            && self.manifest.crl_bytes == stored.crl()
+            && self.ca.is_same(ca)
+    }
+}
+
+
+//------------ CachedObject --------------------------------------------------
+
+/// A valid object of a cached publication point.
+#[derive(Clone, Debug)]
+enum CachedObject {
+    /// A child CA.
+    Ca(uri::Rsync, Arc<CaCert>),
+
+    /// A router certificate.
+    RouterCert(uri::Rsync, Cert),
+
+    /// A ROA.
+    Roa(uri::Rsync, ResourceCert, RouteOriginAttestation),
+
+    /// An ASPA object.
+    #[cfg(feature = "aspa")]
+    Aspa(uri::Rsync, ResourceCert, AsProviderAttestation),
+
+    /// A Ghostbuster Record.
+    Gbr(uri::Rsync, ResourceCert, Bytes),
+
+    /// A Trust Anchor Key object.
+    Tak(uri::Rsync, ResourceCert, TakContent),
+}
+
+impl CachedObject {
+    /// Returns the URI of the object.
+    fn uri(&self) -> &uri::Rsync {
+        match self {
+            CachedObject::Ca(uri, _) => uri,
+            CachedObject::RouterCert(uri, _) => uri,
+            CachedObject::Roa(uri, _, _) => uri,
+            #[cfg(feature = "aspa")]
+            CachedObject::Aspa(uri, _, _) => uri,
+            CachedObject::Gbr(uri, _, _) => uri,
+            CachedObject::Tak(uri, _, _) => uri,
+        }
+    }
+
+    /// Returns the time the object expires.
+    fn not_after(&self) -> Time {
+        match self {
+            CachedObject::Ca(_, cert) => cert.cert().validity().not_after(),
+            CachedObject::RouterCert(_, cert) => cert.validity().not_after(),
+            CachedObject::Roa(_, cert, _) => cert.validity().not_after(),
+            #[cfg(feature = "aspa")]
+            CachedObject::Aspa(_, cert, _) => cert.validity().not_after(),
+            CachedObject::Gbr(_, cert, _) => cert.validity().not_after(),
+            CachedObject::Tak(_, cert, _) => cert.validity().not_after(),
+        }
+    }
+}
+
+
+//------------ CacheRecord ---------------------------------------------------
+
+/// The valid objects collected while validating a point for the cache.
+#[derive(Debug)]
+struct CacheRecord {
+    /// The valid objects in manifest order.
+    objects: Vec<CachedObject>,
+
+    /// The objects the processor didn’t want.
+    unwanted: Vec<uri::Rsync>,
+
+    /// The time when the first of the objects expires.
+    expires: Time,
+}
+
+impl CacheRecord {
+    /// Starts a new record for a point if the engine has a point cache.
+    fn start(engine: &Engine, ca: &CaCert) -> Option<Self> {
+        engine.point_cache.as_ref().map(|_| {
+            CacheRecord {
+                objects: Vec::new(),
+                unwanted: Vec::new(),
+                expires: ca.combined_validity().not_after(),
+            }
+        })
+    }
+
+    /// Adds a valid object.
+    fn push(&mut self, object: CachedObject) {
+        self.expires = cmp::min(self.expires, object.not_after());
+        self.objects.push(object);
+    }
+}
+
+
+//------------ Validation at a Given Time ------------------------------------
+//
+// The rpki crate’s `Roa::process` and `Aspa::process` always validate at
+// the current time and it doesn’t provide variants taking a time, nor does
+// it expose the content checks. The functions below therefore validate the
+// signed object at the given time and repeat the content checks. The tests
+// make sure they agree with the crate’s functions at the current time. They
+// should be replaced by the crate’s functions once it gains them.
+
+/// Validates a signed object at the given time.
+///
+/// This is what `SignedObject::process` does but with an explicit time.
+fn validate_signed_at<F>(
+    obj: SignedObject,
+    issuer: &ResourceCert,
+    strict: bool,
+    now: Time,
+    check_crl: F,
+) -> Result<ResourceCert, ValidationError>
+where F: FnOnce(&Cert) -> Result<(), ValidationError> {
+    let cert = obj.validate_at(issuer, strict, now)?;
+    check_crl(cert.as_ref())?;
+    Ok(cert)
+}
+
+/// Processes a ROA at the given time.
+///
+/// This does the same as `Roa::process`. Since the signed object of the
+/// ROA isn’t accessible, the ROA’s `content` needs to be decoded again as
+/// a signed object.
+fn process_roa_at<F>(
+    roa: Roa,
+    content: Bytes,
+    issuer: &ResourceCert,
+    strict: bool,
+    now: Time,
+    check_crl: F,
+) -> Result<(ResourceCert, RouteOriginAttestation), ValidationError>
+where F: FnOnce(&Cert) -> Result<(), ValidationError> {
+    let cert = validate_signed_at(
+        SignedObject::decode(content, strict)?,
+        issuer, strict, now, check_crl
+    )?;
+    let route = roa.content().clone();
+    for addr in route.v4_addrs().iter() {
+        if !cert.v4_resources().contains_roa(&addr) {
+            return Err(VerificationError::new(
+                "IPv4 ROA prefix not covered by certificate"
+            ).into())
+        }
+    }
+    for addr in route.v6_addrs().iter() {
+        if !cert.v6_resources().contains_roa(&addr) {
+            return Err(VerificationError::new(
+                "IPv6 ROA prefix not covered by certificate"
+            ).into())
+        }
+    }
+    Ok((cert, route))
+}
+
+/// Processes an ASPA object at the given time.
+///
+/// This does the same as `Aspa::process`. As with ROAs, the `content` is
+/// decoded again as a signed object.
+#[cfg(feature = "aspa")]
+fn process_aspa_at<F>(
+    aspa: Aspa,
+    content: Bytes,
+    issuer: &ResourceCert,
+    strict: bool,
+    now: Time,
+    check_crl: F,
+) -> Result<(ResourceCert, AsProviderAttestation), ValidationError>
+where F: FnOnce(&Cert) -> Result<(), ValidationError> {
+    let cert = validate_signed_at(
+        SignedObject::decode(content, strict)?,
+        issuer, strict, now, check_crl
+    )?;
+    let aspa = aspa.content().clone();
+    if !cert.as_resources().contains_asn(aspa.customer_as()) {
+        return Err(VerificationError::new(
+            "customer AS not covered by certificate"
+        ).into())
+    }
+    if cert.as_cert().as_resources().is_inherited() {
+        return Err(VerificationError::new(
+            "certificate contains inherited AS resources"
+        ).into())
+    }
+    if cert.as_cert().has_ip_resources() {
+        return Err(VerificationError::new(
+            "certificate contains IP resources"
+        ).into())
+    }
+    Ok((cert, aspa))
+}
+
+
 //------------ Task ----------------------------------------------------------
 
 /// Any task that can be queued for delayed processing.
@@ -1563,6 +2726,35 @@ struct TalTask<'a> {
 }
 
 
+//------------ TalRepositories -----------------------------------------------
+
+/// The repositories admitted or refused for a TAL.
+#[derive(Debug, Default)]
+struct TalRepositories {
+    /// The repository indexes of the admitted repositories.
+    admitted: HashSet<usize>,
+
+    /// The repository indexes of the refused repositories.
+    refused: HashSet<usize>,
+}
+
+
+//------------ TalRepository -------------------------------------------------
+
+/// The state of a CA’s repository with regard to its TAL’s limit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TalRepository {
+    /// The repository can be used.
+    Admitted,
+
+    /// The repository can’t be used.
+    Refused,
+
+    /// The repository hasn’t been decided upon yet.
+    Pending,
+}
+
+
 //------------ CaTask --------------------------------------------------------
 
 /// A task for processing a single CA.
@@ -1618,6 +2810,9 @@ pub struct CaCert {
     pub(crate) // XXX
     tal: usize,
 
+    /// The constraints on the resources of the TAL.
+    constraints: Option<Arc<TalConstraints>>,
+
     /// The combined validity of the certificate.
     ///
     /// This is derived from the validity of all the parents and the
@@ -1628,9 +2823,10 @@ pub struct CaCert {
 impl CaCert {
     /// Creates a new CA cert for a trust anchor.
     pub fn root(
-        cert: ResourceCert, uri: TalUri, tal: usize
+        cert: ResourceCert, uri: TalUri, tal: usize,
+        constraints: Option<Arc<TalConstraints>>,
     ) -> Result<Arc<Self>, Failed> {
-        Self::new(cert, uri, None, 0, tal)
+        Self::new(cert, uri, None, 0, tal, constraints)
     }
 
     /// Creates a new CA cert for an issued CA.
@@ -1660,7 +2856,7 @@ impl CaCert {
         Self::new(
             cert, TalUri::Rsync(uri),
             Some(issuer.clone()), chain_len,
-            issuer.tal
+            issuer.tal, issuer.constraints.clone(),
         )
     }
 
@@ -1671,6 +2867,7 @@ impl CaCert {
         parent: Option<Arc<Self>>,
         chain_len: usize,
         tal: usize,
+        constraints: Option<Arc<TalConstraints>>,
     ) -> Result<Arc<Self>, Failed> {
         let combined_validity = match parent.as_ref() {
             Some(ca) => cert.validity().trim(ca.combined_validity()),
@@ -1705,7 +2902,7 @@ impl CaCert {
         };
         Ok(Arc::new(CaCert {
             cert, uri, ca_repository, rpki_manifest, parent, chain_len, tal,
-            combined_validity,
+            constraints, combined_validity,
         }))
     }
 
@@ -1730,6 +2927,26 @@ impl CaCert {
         }
     }
 
+    /// Returns whether `other` is the same CA certificate in the same place.
+    ///
+    /// Since an issuer uses each serial number only once, certificates are
+    /// considered the same if their issuer, serial number, and key match.
+    /// The resolved resources and combined validity are compared, too,
+    /// since they depend on the certificate’s parents.
+    fn is_same(&self, other: &CaCert) -> bool {
+        self.uri == other.uri
+            && self.tal == other.tal
+            && self.combined_validity == other.combined_validity
+            && self.cert.serial_number() == other.cert.serial_number()
+            && self.cert.subject_key_identifier()
+                == other.cert.subject_key_identifier()
+            && self.cert.authority_key_identifier()
+                == other.cert.authority_key_identifier()
+            && self.cert.v4_resources() == other.cert.v4_resources()
+            && self.cert.v6_resources() == other.cert.v6_resources()
+            && self.cert.as_resources() == other.cert.as_resources()
+    }
+
     /// Returns a reference to the resource certificate.
     pub fn cert(&self) -> &ResourceCert {
         &self.cert
@@ -1750,6 +2967,18 @@ impl CaCert {
         &self.ca_repository
     }
 
+    /// Returns the URI identifying the repository of the certificate.
+    ///
+    /// This is the RRDP notification URI if there is one or the rsync
+    /// module otherwise.
+    pub fn repository_uri(&self) -> Cow<str> {
+        self.rpki_notify().map(|uri| {
+            Cow::Borrowed(uri.as_str())
+        }).unwrap_or_else(|| {
+            self.ca_repository.canonical_module()
+        })
+    }
+
     /// Returns a reference to the rpkiManifest URI of the certificate.
     pub fn rpki_manifest(&self) -> &uri::Rsync {
         &self.rpki_manifest
@@ -1765,6 +2994,18 @@ impl CaCert {
         self.combined_validity
     }
 
+    /// Returns the constraints on the resources of the CA’s TAL, if any.
+    pub fn constraints(&self) -> Option<&Arc<TalConstraints>> {
+        self.constraints.as_ref()
+    }
+
+    /// Returns the length of the chain of certificates from a trust anchor.
+    ///
+    /// This is zero for a trust anchor certificate.
+    pub fn chain_len(&self) -> usize {
+        self.chain_len
+    }
+
     /// Returns whether the CA is in a different repository from its parent.
     ///
     /// This is just a quick check and may report a switch when in fact there
@@ -1788,6 +3029,64 @@ impl CaCert {
 } 
 
 
+//------------ TaCertChange --------------------------------------------------
+
+/// The differences between two versions of a trust anchor certificate.
+#[derive(Clone, Copy, Debug)]
+struct TaCertChange {
+    /// Has the key changed?
+    key: bool,
+
+    /// Have the resources changed?
+    resources: bool,
+
+    /// Has the validity changed?
+    validity: bool,
+}
+
+impl TaCertChange {
+    /// Determines the changes from the `old` to the `new` certificate.
+    fn new(old: &Cert, new: &Cert) -> Self {
+        TaCertChange {
+            key: {
+                old.subject_public_key_info()
+                    != new.subject_public_key_info()
+            },
+            resources: {
+                old.v4_resources() != new.v4_resources()
+                || old.v6_resources() != new.v6_resources()
+                || old.as_resources() != new.as_resources()
+            },
+            validity: old.validity() != new.validity(),
+        }
+    }
+
+    /// Returns whether the changes need to be accepted when pinning.
+    ///
+    /// Trust anchor certificates are regularly reissued with a new
+    /// validity, so only changes to key or resources are relevant.
+    fn needs_acceptance(self) -> bool {
+        self.key || self.resources
+    }
+
+    /// Logs the changes and adds them to the metrics.
+    fn apply(self, uri: &TalUri, metrics: &mut PublicationMetrics) {
+        if self.key {
+            warn!("Trust anchor {}: certificate key has changed.", uri);
+            metrics.ta_key_changes += 1;
+        }
+        if self.resources {
+            warn!("Trust anchor {}: certificate resources have changed.", uri);
+            metrics.ta_resource_changes += 1;
+        }
+        if self.validity {
+            info!("Trust anchor {}: certificate validity has changed.", uri);
+            metrics.ta_validity_changes += 1;
+        }
+    }
+}
+
+
 //------------ RunMetrics ----------------------------------------------------
 
 /// The metrics collected during a engine run.
@@ -1824,12 +3123,13 @@ impl RunMetrics {
     ///
     /// Adds a new repository if necessary.
     pub fn repository_index(&self, cert: &CaCert) -> usize {
-        let uri = cert.rpki_notify().map(|uri| {
-            Cow::Borrowed(uri.as_str())
-        }).unwrap_or_else(|| {
-            cert.ca_repository.canonical_module()
-        });
+        self.repository_index_for(cert.repository_uri())
+    }
 
+    /// Returns the index of the repository with the given URI.
+    ///
+    /// Adds a new repository if necessary.
+    pub fn repository_index_for(&self, uri: Cow<str>) -> usize {
         let mut repository_indexes = self.repository_indexes.lock().unwrap();
         if let Some(index) = repository_indexes.get(uri.as_ref()) {
             return *index
@@ -1858,6 +3158,17 @@ impl RunMetrics {
         self.publication += metrics;
     }
 
+    /// Apply publication metrics that only concern a TAL.
+    pub fn apply_tal(
+        &mut self, metrics: &PublicationMetrics, tal_index: usize
+    ) {
+        while self.tals.len() <= tal_index {
+            self.tals.push(Default::default())
+        }
+        self.tals[tal_index] += metrics;
+        self.publication += metrics;
+    }
+
     /// Prepares the final metrics.
     pub fn prepare_final(&self, target: &mut Metrics) {
         let mut indexes: Vec<_>
@@ -1891,11 +3202,93 @@ impl RunMetrics {
 }
 
 
-//------------ ProcessRun ----------------------------------------------------
+//------------ PointSource ---------------------------------------------------
 
-/// A type that can process the valid data from the RPKI.
-pub trait ProcessRun: Send + Sync {
-    /// The type processing the valid data of a single publication point.
+/// The source of the data of a publication point.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PointSource {
+    /// The data was freshly collected and has updated the store.
+    Collected,
+
+    /// The data was taken from the store.
+    Stored,
+}
+
+
+//------------ ObjectKind ----------------------------------------------------
+
+/// The kind of an object published at a publication point.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ObjectKind {
+    /// A manifest.
+    Manifest,
+
+    /// A certificate revocation list.
+    Crl,
+
+    /// A certificate that could not be decoded.
+    Cert,
+
+    /// A CA certificate.
+    CaCert,
+
+    /// A BGPsec router certificate.
+    RouterCert,
+
+    /// A ROA.
+    Roa,
+
+    /// An ASPA object.
+    Aspa,
+
+    /// A Ghostbuster record.
+    Gbr,
+
+    /// A Trust Anchor Key object.
+    Tak,
+
+    /// Any other object.
+    Other,
+}
+
+impl ObjectKind {
+    /// Returns the kind of an object based on the extension of its URI.
+    pub fn from_uri(uri: &uri::Rsync) -> Self {
+        if uri.ends_with(".mft") {
+            ObjectKind::Manifest
+        }
+        else if uri.ends_with(".crl") {
+            ObjectKind::Crl
+        }
+        else if uri.ends_with(".cer") {
+            ObjectKind::Cert
+        }
+        else if uri.ends_with(".roa") {
+            ObjectKind::Roa
+        }
+        else if uri.ends_with(".asa") {
+            ObjectKind::Aspa
+        }
+        else if uri.ends_with(".gbr") {
+            ObjectKind::Gbr
+        }
+        else if uri.ends_with(".tak") {
+            ObjectKind::Tak
+        }
+        else {
+            ObjectKind::Other
+        }
+    }
+}
+
+
+//------------ ProcessRun ----------------------------------------------------
+
+/// A type that can process the valid data from the RPKI.
+pub trait ProcessRun: Send + Sync {
+    /// The type processing the valid data of a single publication point.
     type PubPoint: ProcessPubPoint;
 
     /// Processes the given trust anchor.
@@ -1929,12 +3322,59 @@ pub trait ProcessPubPoint: Sized + Send + Sync {
         let _ = not_after;
     }
 
+    /// Sets the source of the data of the publication point.
+    ///
+    /// The method is called before the manifest and any objects of the
+    /// publication point are processed. If processing of collected data
+    /// fails and is restarted with the stored data, it is called again
+    /// after [`restart`][Self::restart].
+    fn point_source(&mut self, source: PointSource) {
+        let _ = source;
+    }
+
     /// Determines whether an object with the given URI should be processed.
     ///
     /// The object will only be processed if the method returns `Ok(true)`.
     /// If it returns `Ok(false)`, the object will be skipped quietly. If it
     /// returns an error, the entire processing run will be aborted.
     fn want(&self, uri: &uri::Rsync) -> Result<bool, Failed>;
+
+    /// Notes that an object of the publication point has been rejected.
+    ///
+    /// The method is called for the manifest and CRL as well as for every
+    /// wanted object that fails validation. The `reason` is the same
+    /// text that is logged as a warning.
+    fn rejected_object(
+        &mut self, uri: &uri::Rsync, kind: ObjectKind, reason: &str
+    ) {
+        let _ = (uri, kind, reason);
+    }
+
+    /// Notes that the publication point has been rejected.
+    ///
+    /// The method is called with a short description of the reason right
+    /// before [`cancel`][Self::cancel].
+    fn rejected_point(&mut self, reason: &str) {
+        let _ = reason;
+    }
+
+    /// Process the validated manifest and CRL of the publication point.
+    ///
+    /// The method is called before any of the objects of the publication
+    /// point are processed. It is given the URIs and content of both the
+    /// manifest and CRL as well as the manifest’s EE certificate. If it
+    /// returns an error, the entire processing run will be aborted.
+    fn process_manifest(
+        &mut self,
+        uri: &uri::Rsync,
+        ee_cert: &ResourceCert,
+        manifest: &ManifestContent,
+        crl_uri: &uri::Rsync,
+        crl: &Crl,
+    ) -> Result<(), Failed> {
+        let _ = (uri, ee_cert, manifest, crl_uri, crl);
+        Ok(())
+    }
    
     /// Process the content of a validated CA.
     ///
@@ -2032,3 +3472,758 @@ pub trait ProcessPubPoint: Sized + Send + Sync {
     }
 }
 
+
+//------------ Processing Pairs ----------------------------------------------
+
+/// Two processors can be combined into a pair to run them in the same pass.
+///
+/// Each processor of the pair sees exactly the same data it would see if
+/// it were used on its own. Trust anchors and CAs are skipped only if both
+/// processors want to skip them.
+impl<A: ProcessRun, B: ProcessRun> ProcessRun for (A, B) {
+    type PubPoint = (Option<A::PubPoint>, Option<B::PubPoint>);
+
+    fn process_ta(
+        &self, tal: &Tal, uri: &TalUri, cert: &CaCert, tal_index: usize
+    ) -> Result<Option<Self::PubPoint>, Failed> {
+        match (
+            self.0.process_ta(tal, uri, cert, tal_index)?,
+            self.1.process_ta(tal, uri, cert, tal_index)?,
+        ) {
+            (None, None) => Ok(None),
+            res => Ok(Some(res))
+        }
+    }
+}
+
+impl<A: ProcessPubPoint, B: ProcessPubPoint> ProcessPubPoint
+for (Option<A>, Option<B>) {
+    fn repository_index(&mut self, repository_index: usize) {
+        if let Some(left) = self.0.as_mut() {
+            left.repository_index(repository_index)
+        }
+        if let Some(right) = self.1.as_mut() {
+            right.repository_index(repository_index)
+        }
+    }
+
+    fn update_refresh(&mut self, not_after: Time) {
+        if let Some(left) = self.0.as_mut() {
+            left.update_refresh(not_after)
+        }
+        if let Some(right) = self.1.as_mut() {
+            right.update_refresh(not_after)
+        }
+    }
+
+    fn point_source(&mut self, source: PointSource) {
+        if let Some(left) = self.0.as_mut() {
+            left.point_source(source)
+        }
+        if let Some(right) = self.1.as_mut() {
+            right.point_source(source)
+        }
+    }
+
+    fn want(&self, uri: &uri::Rsync) -> Result<bool, Failed> {
+        Ok(
+            want_opt(self.0.as_ref(), uri)?
+            || want_opt(self.1.as_ref(), uri)?
+        )
+    }
+
+    fn rejected_object(
+        &mut self, uri: &uri::Rsync, kind: ObjectKind, reason: &str
+    ) {
+        if let Some(left) = self.0.as_mut() {
+            left.rejected_object(uri, kind, reason)
+        }
+        if let Some(right) = self.1.as_mut() {
+            right.rejected_object(uri, kind, reason)
+        }
+    }
+
+    fn rejected_point(&mut self, reason: &str) {
+        if let Some(left) = self.0.as_mut() {
+            left.rejected_point(reason)
+        }
+        if let Some(right) = self.1.as_mut() {
+            right.rejected_point(reason)
+        }
+    }
+
+    fn process_manifest(
+        &mut self,
+        uri: &uri::Rsync,
+        ee_cert: &ResourceCert,
+        manifest: &ManifestContent,
+        crl_uri: &uri::Rsync,
+        crl: &Crl,
+    ) -> Result<(), Failed> {
+        if let Some(left) = self.0.as_mut() {
+            left.process_manifest(uri, ee_cert, manifest, crl_uri, crl)?;
+        }
+        if let Some(right) = self.1.as_mut() {
+            right.process_manifest(uri, ee_cert, manifest, crl_uri, crl)?;
+        }
+        Ok(())
+    }
+
+    fn process_ca(
+        &mut self, uri: &uri::Rsync, cert: &CaCert,
+    ) -> Result<Option<Self>, Failed> {
+        let left = match self.0.as_mut() {
+            Some(left) if left.want(uri)? => left.process_ca(uri, cert)?,
+            _ => None
+        };
+        let right = match self.1.as_mut() {
+            Some(right) if right.want(uri)? => right.process_ca(uri, cert)?,
+            _ => None
+        };
+        match (left, right) {
+            (None, None) => Ok(None),
+            res => Ok(Some(res))
+        }
+    }
+
+    fn process_router_cert(
+        &mut self, uri: &uri::Rsync, cert: Cert, ca_cert: &CaCert,
+    ) -> Result<(), Failed> {
+        if let Some(left) = self.0.as_mut() {
+            if left.want(uri)? {
+                left.process_router_cert(uri, cert.clone(), ca_cert)?;
+            }
+        }
+        if let Some(right) = self.1.as_mut() {
+            if right.want(uri)? {
+                right.process_router_cert(uri, cert, ca_cert)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn process_roa(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        route: RouteOriginAttestation
+    ) -> Result<(), Failed> {
+        if let Some(left) = self.0.as_mut() {
+            if left.want(uri)? {
+                left.process_roa(uri, cert.clone(), route.clone())?;
+            }
+        }
+        if let Some(right) = self.1.as_mut() {
+            if right.want(uri)? {
+                right.process_roa(uri, cert, route)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn process_aspa(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        aspa: AsProviderAttestation,
+    ) -> Result<(), Failed> {
+        if let Some(left) = self.0.as_mut() {
+            if left.want(uri)? {
+                left.process_aspa(uri, cert.clone(), aspa.clone())?;
+            }
+        }
+        if let Some(right) = self.1.as_mut() {
+            if right.want(uri)? {
+                right.process_aspa(uri, cert, aspa)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn process_gbr(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        content: Bytes
+    ) -> Result<(), Failed> {
+        if let Some(left) = self.0.as_mut() {
+            if left.want(uri)? {
+                left.process_gbr(uri, cert.clone(), content.clone())?;
+            }
+        }
+        if let Some(right) = self.1.as_mut() {
+            if right.want(uri)? {
+                right.process_gbr(uri, cert, content)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn restart(&mut self) -> Result<(), Failed> {
+        if let Some(left) = self.0.as_mut() {
+            left.restart()?;
+        }
+        if let Some(right) = self.1.as_mut() {
+            right.restart()?;
+        }
+        Ok(())
+    }
+
+    fn commit(self) {
+        if let Some(left) = self.0 {
+            left.commit()
+        }
+        if let Some(right) = self.1 {
+            right.commit()
+        }
+    }
+
+    fn cancel(self, cert: &CaCert) {
+        if let Some(left) = self.0 {
+            left.cancel(cert)
+        }
+        if let Some(right) = self.1 {
+            right.cancel(cert)
+        }
+    }
+}
+
+/// Returns whether an optional processor wants the given object.
+fn want_opt<P: ProcessPubPoint>(
+    processor: Option<&P>, uri: &uri::Rsync
+) -> Result<bool, Failed> {
+    match processor {
+        Some(processor) => processor.want(uri),
+        None => Ok(false)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rpki::crypto::PublicKey;
+    use crate::payload::ValidationReport;
+    use crate::store::TakFile;
+    use crate::tak::ACCEPTANCE_DAYS;
+    use crate::utils::testing::{TAL_NAME, TestCa, TestRepository};
+
+    /// Performs a validation run and returns the publication metrics.
+    fn run(engine: &Engine, repo: &TestRepository) -> PublicationMetrics {
+        let (_, metrics) = ValidationReport::process(
+            engine, repo.config()
+        ).unwrap();
+        metrics.tals[0].publication.clone()
+    }
+
+    /// Creates a repository with a trust anchor and a child CA with a ROA.
+    fn ta_and_child() -> (TestRepository, TestCa, TestCa) {
+        let (repo, mut ta) = TestRepository::new();
+        let mut child = repo.child(
+            &mut ta, "child", "10.1.0.0/16", "AS64496"
+        );
+        repo.roa(&mut child, "roa", 64496, "10.1.0.0/16");
+        repo.publish(&mut ta);
+        repo.publish(&mut child);
+        (repo, ta, child)
+    }
+
+    #[test]
+    fn point_cache_unchanged() {
+        let (repo, _ta, _child) = ta_and_child();
+        let mut engine = repo.engine();
+        engine.enable_point_cache();
+        let metrics = run(&engine, &repo);
+        assert_eq!(metrics.valid_points, 2);
+        assert_eq!(metrics.valid_roas, 1);
+        assert_eq!(metrics.cached_points, 0);
+        let metrics = run(&engine, &repo);
+        assert_eq!(metrics.cached_points, 2);
+        assert_eq!(metrics.valid_roas, 1);
+    }
+
+    #[test]
+    fn point_cache_manifest_changed() {
+        let (repo, _ta, mut child) = ta_and_child();
+        let mut engine = repo.engine();
+        engine.enable_point_cache();
+        run(&engine, &repo);
+        repo.publish(&mut child);
+        let metrics = run(&engine, &repo);
+        assert_eq!(metrics.cached_points, 1);
+        assert_eq!(metrics.valid_points, 2);
+        assert_eq!(metrics.valid_roas, 1);
+    }
+
+    #[test]
+    fn point_cache_ca_cert_changed() {
+        let (repo, mut ta, mut child) = ta_and_child();
+        let mut engine = repo.engine();
+        engine.enable_point_cache();
+        run(&engine, &repo);
+        repo.reissue(&mut ta, &mut child);
+        repo.publish(&mut ta);
+        let metrics = run(&engine, &repo);
+        assert_eq!(metrics.cached_points, 0);
+        assert_eq!(metrics.valid_points, 2);
+        assert_eq!(metrics.valid_roas, 1);
+    }
+
+    #[test]
+    fn point_cache_crl_changed() {
+        let (repo, _ta, child) = ta_and_child();
+        let mut engine = repo.engine();
+        engine.enable_point_cache();
+        run(&engine, &repo);
+        let cache = engine.point_cache.as_ref().unwrap();
+        let cached = cache.get(&child.object_uri("ca.mft")).unwrap();
+        let store = repo.store();
+        let run = store.start();
+        let point = run.pub_point(child.ca()).unwrap();
+        let stored = point.manifest().unwrap();
+        assert!(cached.matches(child.ca(), stored, Time::now()));
+        let changed = StoredManifest::new(
+            stored.not_after(), None, stored.ca_repository().clone(),
+            child.object_uri("ca.mft"), stored.manifest().clone(),
+            child.object_uri("ca.crl"), Bytes::from_static(b"other"),
+        );
+        assert!(!cached.matches(child.ca(), &changed, Time::now()));
+    }
+
+    #[test]
+    fn max_manifest_entries() {
+        let (mut repo, mut ta) = TestRepository::new();
+        let mut child = repo.child(
+            &mut ta, "child", "10.1.0.0/16", "AS64496"
+        );
+        repo.roa(&mut child, "one", 64496, "10.1.0.0/24");
+        repo.roa(&mut child, "two", 64496, "10.1.1.0/24");
+        repo.roa(&mut child, "three", 64496, "10.1.2.0/24");
+        repo.publish(&mut ta);
+        repo.publish(&mut child);
+
+        // The child’s manifest has three ROAs and the CRL.
+        repo.config_mut().max_manifest_entries = Some(4);
+        let metrics = run(&repo.engine(), &repo);
+        assert_eq!(metrics.oversized_manifests, 0);
+        assert_eq!(metrics.valid_roas, 3);
+
+        repo.config_mut().max_manifest_entries = Some(3);
+        let metrics = run(&repo.engine(), &repo);
+        assert_eq!(metrics.oversized_manifests, 1);
+        assert_eq!(metrics.valid_points, 1);
+        assert_eq!(metrics.valid_roas, 0);
+    }
+
+    #[test]
+    fn max_child_cas() {
+        let (mut repo, mut ta) = TestRepository::new();
+        let mut children: Vec<_> = ["one", "two", "three"].iter().map(|name| {
+            repo.child(&mut ta, name, "10.1.0.0/16", "AS64496")
+        }).collect();
+        repo.publish(&mut ta);
+        for child in &mut children {
+            repo.publish(child);
+        }
+
+        repo.config_mut().max_child_cas = Some(3);
+        let metrics = run(&repo.engine(), &repo);
+        assert_eq!(metrics.excess_child_ca_points, 0);
+        assert_eq!(metrics.valid_points, 4);
+
+        repo.config_mut().max_child_cas = Some(2);
+        let metrics = run(&repo.engine(), &repo);
+        assert_eq!(metrics.excess_child_ca_points, 1);
+        assert_eq!(metrics.rejected_points, 1);
+        assert_eq!(metrics.valid_points, 0);
+    }
+
+    #[test]
+    fn max_tal_repositories() {
+        let (mut repo, mut ta) = TestRepository::new();
+        for (name, module) in [
+            ("one", "b.test"), ("two", "a.test"), ("three", "c.test"),
+        ] {
+            let mut child = repo.child(
+                &mut ta, name, "10.1.0.0/16", "AS64496"
+            );
+            child.set_repository(
+                &format!("rsync://{}/module/{}/", module, name)
+            );
+            repo.reissue(&mut ta, &mut child);
+            repo.publish(&mut child);
+        }
+        repo.publish(&mut ta);
+
+        // The TA’s repository and the two repositories with the smallest
+        // URIs are admitted, no matter how many threads are used.
+        repo.config_mut().max_tal_repositories = Some(3);
+        for threads in [1, 4] {
+            repo.config_mut().validation_threads = threads;
+            let (_, metrics) = ValidationReport::process(
+                &repo.engine(), repo.config()
+            ).unwrap();
+            let publication = &metrics.tals[0].publication;
+            assert_eq!(publication.excess_repository_points, 1);
+            assert_eq!(publication.valid_points, 3);
+            for repository in &metrics.repositories {
+                assert_eq!(
+                    repository.publication.excess_repository_points,
+                    u32::from(repository.uri.contains("c.test")),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn engine_now() {
+        let (repo, _ta) = TestRepository::new();
+        let mut engine = repo.engine();
+        let before = Time::now();
+        let now = engine.now();
+        assert!(before <= now && now <= Time::now());
+        let time = Time::utc(2023, 5, 1, 12, 0, 0);
+        engine.set_validation_time(time);
+        assert_eq!(engine.now(), time);
+    }
+
+    #[test]
+    fn validate_roa_at() {
+        let (repo, mut ta) = TestRepository::new();
+        repo.roa(&mut ta, "covered", 64496, "10.1.0.0/16-24");
+        repo.roa(&mut ta, "uncovered", 64496, "192.0.2.0/24");
+        let issuer = ta.ca().cert();
+        let now = Time::now();
+        for name in ["covered.roa", "uncovered.roa"] {
+            let content = ta.object(name);
+            let roa = Roa::decode(content.clone(), true).unwrap();
+            let current = roa.clone().process(issuer, true, |_| Ok(()));
+            let at = process_roa_at(
+                roa, content, issuer, true, now, |_| Ok(())
+            );
+            assert_eq!(current.is_ok(), at.is_ok());
+            if let (Ok(current), Ok(at)) = (current, at) {
+                assert_eq!(
+                    current.1.iter().collect::<Vec<_>>(),
+                    at.1.iter().collect::<Vec<_>>()
+                );
+            }
+        }
+
+        let content = ta.object("covered.roa");
+        let roa = Roa::decode(content.clone(), true).unwrap();
+        assert!(process_roa_at(
+            roa.clone(), content.clone(), issuer, true,
+            now - chrono::Duration::days(2), |_| Ok(())
+        ).is_err());
+        assert!(process_roa_at(
+            roa.clone(), content.clone(), issuer, true,
+            now + chrono::Duration::days(400), |_| Ok(())
+        ).is_err());
+        assert!(process_roa_at(
+            roa, content, issuer, true, now,
+            |_| Err(VerificationError::new("revoked").into())
+        ).is_err());
+    }
+
+    #[cfg(feature = "aspa")]
+    #[test]
+    fn validate_aspa_at() {
+        let (repo, mut ta) = TestRepository::new();
+        repo.aspa(&mut ta, "covered", 64500, &[64501, 64502]);
+        repo.aspa(&mut ta, "uncovered", 65000, &[64501]);
+        let issuer = ta.ca().cert();
+        let now = Time::now();
+        for name in ["covered.asa", "uncovered.asa"] {
+            let content = ta.object(name);
+            let aspa = Aspa::decode(content.clone(), true).unwrap();
+            let current = aspa.clone().process(issuer, true, |_| Ok(()));
+            let at = process_aspa_at(
+                aspa, content, issuer, true, now, |_| Ok(())
+            );
+            assert_eq!(current.is_ok(), at.is_ok());
+        }
+
+        let content = ta.object("covered.asa");
+        let aspa = Aspa::decode(content.clone(), true).unwrap();
+        assert!(process_aspa_at(
+            aspa.clone(), content.clone(), issuer, true, now, |_| Ok(())
+        ).is_ok());
+        assert!(process_aspa_at(
+            aspa, content, issuer, true,
+            now + chrono::Duration::days(400), |_| Ok(())
+        ).is_err());
+    }
+
+    /// Returns the encoded form of a certificate.
+    fn encode(cert: &Cert) -> Bytes {
+        cert.to_captured().into_bytes()
+    }
+
+    /// Selects the TA certificate given a downloaded certificate.
+    ///
+    /// Returns the selected certificate and the TAL’s metrics.
+    fn select_ta(
+        engine: &Engine, repo: &TestRepository, uri: &uri::Rsync,
+        downloaded: &Cert,
+    ) -> (Cert, PublicationMetrics) {
+        let report = ValidationReport::new(repo.config());
+        let run = engine.start(&report).unwrap();
+        let mut metrics = RunMetrics::default();
+        let cert = run.select_ta(
+            &TalUri::Rsync(uri.clone()), 0, &mut metrics,
+            Some(encode(downloaded))
+        ).unwrap().unwrap();
+        (cert, metrics.tals.pop().unwrap_or_default())
+    }
+
+    /// Returns the stored and pending certificates for the URI.
+    fn stored_ta(
+        repo: &TestRepository, uri: &uri::Rsync
+    ) -> (Option<Bytes>, bool) {
+        let uri = TalUri::Rsync(uri.clone());
+        let store = repo.store();
+        (
+            store.start().load_ta(&uri).unwrap(),
+            store.pending_ta_path(&uri).exists(),
+        )
+    }
+
+    /// Creates a repository and an engine with pinned TA certificates.
+    fn pinned() -> (TestRepository, TestCa, Engine) {
+        let (mut repo, ta) = TestRepository::new();
+        repo.config_mut().pin_ta_certs = true;
+        let engine = repo.engine();
+        (repo, ta, engine)
+    }
+
+    #[test]
+    fn ta_cert_first_seen() {
+        let (repo, ta, engine) = pinned();
+        let uri = ta.object_uri("other.cer");
+        let (cert, metrics) = select_ta(&engine, &repo, &uri, ta.cert());
+        assert_eq!(encode(&cert), encode(ta.cert()));
+        assert_eq!(metrics.pinned_ta_certs, 0);
+        assert_eq!(stored_ta(&repo, &uri), (Some(encode(ta.cert())), false));
+    }
+
+    #[test]
+    fn ta_cert_unchanged() {
+        let (repo, ta, engine) = pinned();
+        let (cert, metrics) = select_ta(&engine, &repo, ta.uri(), ta.cert());
+        assert_eq!(encode(&cert), encode(ta.cert()));
+        assert_eq!(metrics.ta_key_changes, 0);
+        assert_eq!(metrics.ta_resource_changes, 0);
+        assert_eq!(metrics.ta_validity_changes, 0);
+        assert_eq!(metrics.pinned_ta_certs, 0);
+    }
+
+    #[test]
+    fn ta_cert_key_changed() {
+        let (repo, ta, engine) = pinned();
+        let new = repo.ta_cert(&ta, &repo.new_key(), repo.validity());
+        let (cert, metrics) = select_ta(&engine, &repo, ta.uri(), &new);
+        assert_eq!(encode(&cert), encode(ta.cert()));
+        assert_eq!(metrics.ta_key_changes, 1);
+        assert_eq!(metrics.pinned_ta_certs, 1);
+        assert_eq!(
+            stored_ta(&repo, ta.uri()), (Some(encode(ta.cert())), true)
+        );
+
+        // Accepting promotes the pending certificate.
+        assert_eq!(
+            engine.accept_ta_certs(Some(TAL_NAME)).unwrap(),
+            vec![TalUri::Rsync(ta.uri().clone())]
+        );
+        assert_eq!(stored_ta(&repo, ta.uri()), (Some(encode(&new)), false));
+        let (cert, metrics) = select_ta(&engine, &repo, ta.uri(), &new);
+        assert_eq!(encode(&cert), encode(&new));
+        assert_eq!(metrics.pinned_ta_certs, 0);
+        assert!(engine.accept_ta_certs(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ta_cert_resources_changed() {
+        let (repo, mut ta, engine) = pinned();
+        ta.set_v4("10.0.0.0/9");
+        let new = repo.ta_cert(&ta, ta.key_info(), repo.validity());
+        let (cert, metrics) = select_ta(&engine, &repo, ta.uri(), &new);
+        assert_eq!(encode(&cert), encode(ta.cert()));
+        assert_eq!(metrics.ta_key_changes, 0);
+        assert_eq!(metrics.ta_resource_changes, 1);
+        assert_eq!(metrics.pinned_ta_certs, 1);
+        assert_eq!(
+            stored_ta(&repo, ta.uri()), (Some(encode(ta.cert())), true)
+        );
+    }
+
+    #[test]
+    fn ta_cert_validity_changed() {
+        let (repo, ta, engine) = pinned();
+        let validity = Validity::new(
+            ta.cert().validity().not_before(),
+            ta.cert().validity().not_after() + chrono::Duration::days(1),
+        );
+        let new = repo.ta_cert(&ta, ta.key_info(), validity);
+        let (cert, metrics) = select_ta(&engine, &repo, ta.uri(), &new);
+        assert_eq!(encode(&cert), encode(&new));
+        assert_eq!(metrics.ta_validity_changes, 1);
+        assert_eq!(metrics.pinned_ta_certs, 0);
+        assert_eq!(stored_ta(&repo, ta.uri()), (Some(encode(&new)), false));
+    }
+
+    /// Creates TAK content announcing `successor` for the test TA.
+    fn tak(ta: &TestCa, successor: &PublicKey) -> TakContent {
+        let key = |key_info: &PublicKey| {
+            TaKey::new(
+                Vec::new(), vec![TalUri::Rsync(ta.uri().clone())],
+                key_info.clone()
+            )
+        };
+        TakContent::new(key(ta.key_info()), None, Some(key(successor)))
+    }
+
+    #[test]
+    fn tak_successor_accepted() {
+        let (repo, ta) = TestRepository::new();
+        let (successor, cert) = repo.successor_cert(&ta);
+        let engine = repo.engine();
+        let report = ValidationReport::new(repo.config());
+        let mut run = engine.start(&report).unwrap();
+        run.taks.get_mut().unwrap().insert(0, tak(&ta, &successor));
+        let store = repo.store();
+        let pending = || {
+            store.start().load_tak(TAL_NAME, TakFile::Pending)
+                .unwrap().is_some()
+        };
+        let now = Time::now();
+        let later = now + chrono::Duration::days(ACCEPTANCE_DAYS);
+
+        run.apply_taks(now, |_| unreachable!()).unwrap();
+        assert!(pending());
+
+        // The successor certificate is needed for acceptance.
+        run.apply_taks(later, |_| None).unwrap();
+        assert!(pending());
+        run.apply_taks(later, |_| Some(encode(ta.cert()))).unwrap();
+        assert!(pending());
+        run.apply_taks(later, |_| Some(encode(&cert))).unwrap();
+        assert!(!pending());
+        drop(run);
+
+        let engine = repo.engine();
+        let report = ValidationReport::new(repo.config());
+        let run = engine.start(&report).unwrap();
+        assert_eq!(run.tals[0].key_info(), &successor);
+    }
+
+    #[test]
+    fn tak_successor_check() {
+        let (repo, ta) = TestRepository::new();
+        let (successor, cert) = repo.successor_cert(&ta);
+        let bogus = repo.ta_cert(&ta, &successor, repo.validity());
+        let engine = repo.engine();
+        let report = ValidationReport::new(repo.config());
+        let run = engine.start(&report).unwrap();
+        let key = TaKey::new(
+            Vec::new(),
+            vec![
+                TalUri::Rsync(ta.object_uri("missing.cer")),
+                TalUri::Rsync(ta.uri().clone()),
+            ],
+            successor
+        );
+        let now = Time::now();
+        let load = |cert: &Cert| {
+            let bytes = encode(cert);
+            move |uri: &TalUri| {
+                uri.as_str().ends_with("/ta.cer").then(|| bytes.clone())
+            }
+        };
+        assert!(run.check_successor(0, &key, now, load(&cert)));
+        assert!(!run.check_successor(0, &key, now, load(ta.cert())));
+        assert!(!run.check_successor(0, &key, now, load(&bogus)));
+        assert!(!run.check_successor(
+            0, &key, now + chrono::Duration::days(400), load(&cert)
+        ));
+    }
+
+    #[test]
+    fn tak_validation_time() {
+        let (mut repo, ta) = TestRepository::new();
+        repo.config_mut().disable_rrdp = true;
+        repo.config_mut().disable_rsync = true;
+        let mut engine = Engine::new(repo.config(), true).unwrap();
+        engine.set_validation_time(
+            Time::now() - chrono::Duration::days(ACCEPTANCE_DAYS)
+        );
+        let successor = repo.new_key();
+        let pending = || {
+            repo.store().start().load_tak(
+                TAL_NAME, TakFile::Pending
+            ).unwrap().is_some()
+        };
+        let report = ValidationReport::new(repo.config());
+        let mut run = engine.start(&report).unwrap();
+        run.taks.get_mut().unwrap().insert(0, tak(&ta, &successor));
+        run.update_taks().unwrap();
+        assert!(!pending());
+
+        // Without a validation time, the key is recorded.
+        let engine = Engine::new(repo.config(), true).unwrap();
+        let mut run = engine.start(&report).unwrap();
+        run.taks.get_mut().unwrap().insert(0, tak(&ta, &successor));
+        run.update_taks().unwrap();
+        assert!(pending());
+    }
+
+    #[test]
+    fn collected_manifest_rollback() {
+        let (mut repo, mut ta, mut child) = ta_and_child();
+        repo.mirror(&child);
+        repo.roa(&mut child, "new", 64496, "10.1.1.0/24");
+        repo.publish(&mut child);
+        repo.publish(&mut ta);
+
+        // The collector only has the first manifest of the child.
+        repo.config_mut().rsync_command = "true".into();
+        repo.config_mut().disable_rrdp = true;
+        let engine = Engine::new(repo.config(), true).unwrap();
+        let metrics = run(&engine, &repo);
+        assert_eq!(metrics.rollback_manifests, 1);
+        assert_eq!(metrics.valid_points, 2);
+        assert_eq!(metrics.valid_roas, 2);
+    }
+
+    #[test]
+    fn manifest_rollback() {
+        let stored = ManifestSequence {
+            number: Serial::from(10u64),
+            this_update: Time::utc(2023, 5, 1, 12, 0, 0),
+        };
+        assert!(ManifestSequence {
+            number: Serial::from(11u64),
+            this_update: Time::utc(2023, 5, 1, 13, 0, 0),
+        }.check_successor(stored).is_ok());
+
+        // Same number with different content.
+        assert!(ManifestSequence {
+            number: Serial::from(10u64),
+            this_update: Time::utc(2023, 5, 1, 13, 0, 0),
+        }.check_successor(stored).is_err());
+
+        // Replay of an older manifest.
+        assert!(ManifestSequence {
+            number: Serial::from(9u64),
+            this_update: Time::utc(2023, 4, 30, 12, 0, 0),
+        }.check_successor(stored).is_err());
+
+        // Higher number but older thisUpdate.
+        assert!(ManifestSequence {
+            number: Serial::from(11u64),
+            this_update: Time::utc(2023, 4, 30, 12, 0, 0),
+        }.check_successor(stored).is_err());
+    }
+}
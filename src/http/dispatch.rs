@@ -7,7 +7,7 @@ use crate::config::Config;
 use crate::metrics::{HttpServerMetrics, SharedRtrServerMetrics};
 use crate::payload::SharedHistory;
 use crate::process::LogOutput;
-use super::{delta, log, metrics, payload, status, validity};
+use super::{delta, log, metrics, payload, status, tree, validity};
 use super::response::Response;
 
 //------------ State ---------------------------------------------------------
@@ -19,6 +19,7 @@ pub struct State {
     metrics: Arc<HttpServerMetrics>,
     rtr_metrics: SharedRtrServerMetrics,
     notify: NotifySender,
+    tree: bool,
 }
 
 impl State {
@@ -36,6 +37,7 @@ impl State {
             metrics: Arc::new(HttpServerMetrics::default()),
             rtr_metrics,
             notify,
+            tree: config.http_tree,
         }
     }
     
@@ -84,6 +86,13 @@ impl State {
             &req, &self.history) {
             return response
         }
+        if self.tree {
+            if let Some(response) = tree::handle_get_or_head(
+                &req, &self.history
+            ) {
+                return response
+            }
+        }
 
         #[cfg(feature = "ui")]
         if let Some(response) = super::ui::handle_get_or_head(&req) {
@@ -122,6 +122,34 @@ async fn handle_metrics(
         &mut target, Group::Ta, unsafe_vrps,
         metrics.tals.iter().map(|m| (m.tal.name(), &m.payload))
     );
+    let metric = Metric::new(
+        "ta_cert_changes_total",
+        "changes of the trust anchor certificate",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for tal in &metrics.tals {
+        target.multi(metric).label("name", tal.name())
+            .label("change", "key")
+            .value(tal.publication.ta_key_changes);
+        target.multi(metric).label("name", tal.name())
+            .label("change", "resources")
+            .value(tal.publication.ta_resource_changes);
+        target.multi(metric).label("name", tal.name())
+            .label("change", "validity")
+            .value(tal.publication.ta_validity_changes);
+    }
+    let metric = Metric::new(
+        "ta_cert_pinned",
+        "changed trust anchor certificates waiting for acceptance",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for tal in &metrics.tals {
+        target.multi(metric).label("name", tal.name()).value(
+            tal.publication.pinned_ta_certs
+        );
+    }
 
     // Per-repository metrics.
     pub_point_metrics(
@@ -140,6 +168,17 @@ async fn handle_metrics(
         &mut target, Group::Repository, unsafe_vrps,
         metrics.repositories.iter().map(|m| (m.uri.as_ref(), &m.payload))
     );
+    let metric = Metric::new(
+        "repository_oversized",
+        "whether the repository exceeded the size limit",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for repository in &metrics.repositories {
+        target.multi(metric).label("uri", &repository.uri).value(
+            u8::from(repository.oversized)
+        );
+    }
 
     // Locally added VRPs
     target.single(
@@ -182,6 +221,15 @@ fn pub_point_metrics<'a>(
         target.multi(metric).label(group.label(), name)
             .label("state", "rejected")
             .value(metrics.rejected_points);
+        target.multi(metric).label(group.label(), name)
+            .label("state", "cached")
+            .value(metrics.cached_points);
+        target.multi(metric).label(group.label(), name)
+            .label("state", "excess_child_cas")
+            .value(metrics.excess_child_ca_points);
+        target.multi(metric).label(group.label(), name)
+            .label("state", "excess_repositories")
+            .value(metrics.excess_repository_points);
     }
 }
 
@@ -212,6 +260,14 @@ fn object_metrics<'a>(
             .label("type", "manifest")
             .label("state", "stale")
             .value(metrics.stale_manifests);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "manifest")
+            .label("state", "rollback")
+            .value(metrics.rollback_manifests);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "manifest")
+            .label("state", "oversized")
+            .value(metrics.oversized_manifests);
         target.multi(metric).label(group.label(), name)
             .label("type", "manifest")
             .label("state", "missing")
@@ -275,6 +331,14 @@ fn object_metrics<'a>(
             .label("type", "gbr")
             .label("state", "invalid")
             .value(metrics.invalid_gbrs);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "tak")
+            .label("state", "valid")
+            .value(metrics.valid_taks);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "tak")
+            .label("state", "invalid")
+            .value(metrics.invalid_taks);
 
         target.multi(metric).label(group.label(), name)
             .label("type", "other")
@@ -305,6 +369,11 @@ fn vrp_metrics<'a>(
         ("number of VRPs filtered out by local exceptions per ", group.help()),
         MetricType::Gauge
     );
+    let constrained_metric = Metric::with_prefix(
+        group.prefix(), "constrained_vrps_total",
+        ("number of VRPs dropped due to TAL constraints per ", group.help()),
+        MetricType::Gauge
+    );
     let duplicate_metric = Metric::with_prefix(
         group.prefix(), "duplicate_vrps_total",
         ("number of duplicate VRPs per ", group.help()),
@@ -321,6 +390,7 @@ fn vrp_metrics<'a>(
         target.header(unsafe_metric);
     }
     target.header(filtered_metric);
+    target.header(constrained_metric);
     target.header(duplicate_metric);
     target.header(contributed_metric);
     for (name, metrics) in metrics {
@@ -332,6 +402,8 @@ fn vrp_metrics<'a>(
         }
         target.multi(filtered_metric).label(group.label(), name)
             .value(metrics.locally_filtered);
+        target.multi(constrained_metric).label(group.label(), name)
+            .value(metrics.constrained);
         target.multi(duplicate_metric).label(group.label(), name)
             .value(metrics.duplicate);
         target.multi(contributed_metric).label(group.label(), name)
@@ -364,6 +436,14 @@ fn payload_metrics<'a>(
         ),
         MetricType::Gauge
     );
+    let constrained_metric = Metric::with_prefix(
+        group.prefix(), "constrained_payload_total",
+        (
+            "number of payload items dropped due to TAL constraints per ",
+            group.help()
+        ),
+        MetricType::Gauge
+    );
     let duplicate_metric = Metric::with_prefix(
         group.prefix(), "duplicate_payload_total",
         ("number of duplicate payload items per ", group.help()),
@@ -383,6 +463,7 @@ fn payload_metrics<'a>(
         target.header(unsafe_metric);
     }
     target.header(filtered_metric);
+    target.header(constrained_metric);
     target.header(duplicate_metric);
     target.header(contributed_metric);
 
@@ -408,6 +489,10 @@ fn payload_metrics<'a>(
                 .label(group.label(), name)
                 .label("type", type_name)
                 .value(metrics.locally_filtered);
+            target.multi(constrained_metric)
+                .label(group.label(), name)
+                .label("type", type_name)
+                .value(metrics.constrained);
             target.multi(duplicate_metric)
                 .label(group.label(), name)
                 .label("type", type_name)
@@ -423,6 +508,10 @@ fn payload_metrics<'a>(
                 .label(group.label(), name)
                 .label("type", "aspas")
                 .value(metrics.aspas.valid);
+            target.multi(constrained_metric)
+                .label(group.label(), name)
+                .label("type", "aspas")
+                .value(metrics.aspas.constrained);
             target.multi(duplicate_metric)
                 .label(group.label(), name)
                 .label("type", "aspas")
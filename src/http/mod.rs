@@ -23,6 +23,7 @@ mod log;
 mod metrics;
 mod payload;
 mod status;
+mod tree;
 mod ui;
 mod validity;
 
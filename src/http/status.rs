@@ -382,6 +382,7 @@ async fn handle_api_status(
                     json_publication_metrics(
                         target, &tal.publication
                     );
+                    json_ta_cert_metrics(target, &tal.publication);
                 });
             }
         });
@@ -398,6 +399,7 @@ async fn handle_api_status(
                     else {
                         target.member_str("type", "other");
                     }
+                    target.member_raw("oversized", repo.oversized);
                     json_compat_payload_metrics(target, &repo.payload);
                     json_publication_metrics(
                         target, &repo.publication
@@ -428,6 +430,7 @@ async fn handle_api_status(
                         }
                         Err(_) => target.member_raw("duration", "null")
                     }
+                    target.member_raw("oversized", metrics.oversized);
                 })
             }
         });
@@ -457,6 +460,7 @@ async fn handle_api_status(
                         }
                         Err(_) => target.member_raw("duration", "null")
                     }
+                    target.member_raw("oversized", metrics.oversized);
                     if !metrics.status().is_not_modified() {
                         match metrics.serial {
                             Some(serial) => {
@@ -579,10 +583,19 @@ fn json_publication_metrics(
 ) {
     target.member_raw("validPublicationPoints", metrics.valid_points);
     target.member_raw("rejectedPublicationPoints", metrics.rejected_points);
+    target.member_raw("cachedPublicationPoints", metrics.cached_points);
+    target.member_raw(
+        "excessChildCaPublicationPoints", metrics.excess_child_ca_points
+    );
+    target.member_raw(
+        "excessRepositoryPublicationPoints", metrics.excess_repository_points
+    );
     target.member_raw("validManifests", metrics.valid_manifests);
     target.member_raw("invalidManifests", metrics.invalid_manifests);
     target.member_raw("prematureManifests", metrics.premature_manifests);
     target.member_raw("staleManifests", metrics.stale_manifests);
+    target.member_raw("rollbackManifests", metrics.rollback_manifests);
+    target.member_raw("oversizedManifests", metrics.oversized_manifests);
     target.member_raw("missingManifests", metrics.missing_manifests);
     target.member_raw("validCRLs", metrics.valid_crls);
     target.member_raw("invalidCRLs", metrics.invalid_crls);
@@ -603,9 +616,22 @@ fn json_publication_metrics(
         target.member_raw("validASPAs", metrics.valid_aspas);
     }
     target.member_raw("invalidGBRs", metrics.invalid_gbrs);
+    target.member_raw("validTAKs", metrics.valid_taks);
+    target.member_raw("invalidTAKs", metrics.invalid_taks);
     target.member_raw("otherObjects", metrics.others);
 }
 
+fn json_ta_cert_metrics(
+    target: &mut JsonBuilder, metrics: &PublicationMetrics
+) {
+    target.member_object("taCertificate", |target| {
+        target.member_raw("keyChanges", metrics.ta_key_changes);
+        target.member_raw("resourceChanges", metrics.ta_resource_changes);
+        target.member_raw("validityChanges", metrics.ta_validity_changes);
+        target.member_raw("pinned", metrics.pinned_ta_certs);
+    });
+}
+
 fn json_compat_payload_metrics(
     target: &mut JsonBuilder, payload: &PayloadMetrics
 ) {
@@ -649,6 +675,7 @@ fn json_vrps_metrics(
         target.member_raw("unsafe", vrps.marked_unsafe);
     }
     target.member_raw("locallyFiltered", vrps.locally_filtered);
+    target.member_raw("constrained", vrps.constrained);
     target.member_raw("duplicate", vrps.duplicate);
     target.member_raw("final", vrps.contributed);
 }
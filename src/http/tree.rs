@@ -0,0 +1,309 @@
+//! Handles endpoints related to the tree of validated objects.
+
+use std::str::FromStr;
+use hyper::{Body, Method, Request};
+use rpki::repository::x509::Time;
+use serde::Serialize;
+use crate::dump::{
+    Expiring, ExpiryWindow, Provenance, ProvenanceSelection, TreeDelta,
+    TreeSelection
+};
+use crate::payload::SharedHistory;
+use super::response::{ContentType, Response, ResponseBuilder};
+
+
+//------------ handle_get_or_head --------------------------------------------
+
+pub fn handle_get_or_head(
+    req: &Request<Body>,
+    history: &SharedHistory,
+) -> Option<Response> {
+    match req.uri().path() {
+        "/api/v1/tree" => Some(handle_tree(req, history)),
+        "/api/v1/tree-changes" => Some(handle_changes(req, history)),
+        "/api/v1/provenance" => Some(handle_provenance(req, history)),
+        "/api/v1/expiring" => Some(handle_expiring(req, history)),
+        _ => None
+    }
+}
+
+
+//------------ handle_tree ---------------------------------------------------
+
+fn handle_tree(
+    req: &Request<Body>,
+    history: &SharedHistory,
+) -> Response {
+    let selection = match TreeSelection::from_query(req.uri().query()) {
+        Ok(selection) => selection,
+        Err(_) => return Response::bad_request()
+    };
+
+    let (session, serial, updated, tree) = {
+        let history = history.read();
+        (
+            history.session(),
+            history.tree_serial(),
+            history.tree_updated(),
+            history.tree(),
+        )
+    };
+    let (tree, updated) = match (tree, updated) {
+        (Some(tree), Some(updated)) => (tree, updated),
+        _ => return Response::initial_validation(),
+    };
+
+    let etag = format!("\"{:x}-t{}\"", session, serial);
+
+    if let Some(response) = Response::maybe_not_modified(
+        req, &etag, updated
+    ) {
+        return response
+    }
+
+    let res = ResponseBuilder::ok()
+        .content_type(ContentType::JSON)
+        .etag(&etag).last_modified(updated);
+    if *req.method() == Method::HEAD {
+        res.empty()
+    }
+    else {
+        let mut body = Vec::new();
+        selection.apply(&tree).write_json(&mut body).expect(
+            "writing to vec failed"
+        );
+        res.body(body)
+    }
+}
+
+
+//------------ handle_changes ------------------------------------------------
+
+fn handle_changes(
+    req: &Request<Body>,
+    history: &SharedHistory,
+) -> Response {
+    let since = match since_from_query(req.uri().query()) {
+        Ok(since) => since,
+        Err(_) => return Response::bad_request()
+    };
+
+    let (session, serial, updated, deltas) = {
+        let history = history.read();
+        (
+            history.session(),
+            history.tree_serial(),
+            history.tree_updated(),
+            history.tree_deltas_since(since),
+        )
+    };
+    let updated = match updated {
+        Some(updated) => updated,
+        None => return Response::initial_validation(),
+    };
+
+    let etag = format!("\"{:x}-t{}\"", session, serial);
+
+    if let Some(response) = Response::maybe_not_modified(
+        req, &etag, updated
+    ) {
+        return response
+    }
+
+    let res = ResponseBuilder::ok()
+        .content_type(ContentType::JSON)
+        .etag(&etag).last_modified(updated);
+    if *req.method() == Method::HEAD {
+        return res.empty()
+    }
+    let changes = TreeChanges {
+        session,
+        serial,
+        reset: deltas.is_none(),
+        changes: deltas.as_ref().map(|deltas| {
+            deltas.iter().map(AsRef::as_ref).collect()
+        }),
+    };
+    res.body(serde_json::to_vec_pretty(&changes).expect(
+        "serializing tree changes failed"
+    ))
+}
+
+/// Returns the value of the `since` query parameter if present.
+fn since_from_query(query: Option<&str>) -> Result<Option<u64>, ()> {
+    let query = match query {
+        Some(query) => query,
+        None => return Ok(None)
+    };
+    let mut since = None;
+    for (key, value) in form_urlencoded::parse(query.as_ref()) {
+        if key == "since" {
+            since = Some(u64::from_str(&value).map_err(|_| ())?);
+        }
+        else {
+            return Err(())
+        }
+    }
+    Ok(since)
+}
+
+
+//------------ handle_provenance ---------------------------------------------
+
+fn handle_provenance(
+    req: &Request<Body>,
+    history: &SharedHistory,
+) -> Response {
+    let selection = match ProvenanceSelection::from_query(req.uri().query()) {
+        Ok(selection) => selection,
+        Err(_) => return Response::bad_request()
+    };
+
+    let (session, serial, tree_serial, created, snapshot, tree) = {
+        let history = history.read();
+        (
+            history.session(),
+            history.serial(),
+            history.tree_serial(),
+            history.created(),
+            history.current(),
+            history.tree(),
+        )
+    };
+    let (snapshot, tree, created) = match (snapshot, tree, created) {
+        (Some(snapshot), Some(tree), Some(created)) => {
+            (snapshot, tree, created)
+        }
+        _ => return Response::initial_validation(),
+    };
+
+    let etag = format!("\"{:x}-{}-t{}\"", session, serial, tree_serial);
+
+    if let Some(response) = Response::maybe_not_modified(
+        req, &etag, created
+    ) {
+        return response
+    }
+
+    let res = ResponseBuilder::ok()
+        .content_type(ContentType::JSON)
+        .etag(&etag).last_modified(created);
+    if *req.method() == Method::HEAD {
+        res.empty()
+    }
+    else {
+        let mut body = Vec::new();
+        Provenance::new(&snapshot, &tree, &selection).write_json(
+            &mut body
+        ).expect("writing to vec failed");
+        res.body(body)
+    }
+}
+
+
+//------------ handle_expiring -----------------------------------------------
+
+fn handle_expiring(
+    req: &Request<Body>,
+    history: &SharedHistory,
+) -> Response {
+    let within = match ExpiryWindow::from_query(req.uri().query()) {
+        Ok(within) => within,
+        Err(_) => return Response::bad_request()
+    };
+
+    let (snapshot, tree) = {
+        let history = history.read();
+        (history.current(), history.tree())
+    };
+    let (snapshot, tree) = match (snapshot, tree) {
+        (Some(snapshot), Some(tree)) => (snapshot, tree),
+        _ => return Response::initial_validation(),
+    };
+
+    // The result depends on the current time, so there is no ETag.
+    let res = ResponseBuilder::ok().content_type(ContentType::JSON);
+    if *req.method() == Method::HEAD {
+        res.empty()
+    }
+    else {
+        let mut body = Vec::new();
+        Expiring::new(&snapshot, &tree, Time::now(), within).write_json(
+            &mut body
+        ).expect("writing to vec failed");
+        res.body(body)
+    }
+}
+
+
+//------------ TreeChanges ---------------------------------------------------
+
+/// The response to a request for tree changes.
+#[derive(Serialize)]
+struct TreeChanges<'a> {
+    /// The session ID of the server.
+    session: u64,
+
+    /// The serial number of the current tree.
+    serial: u64,
+
+    /// Whether the changes since the requested serial are not available.
+    ///
+    /// If this is `true`, the client has to fetch the full tree.
+    reset: bool,
+
+    /// The changes since the requested serial, oldest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changes: Option<Vec<&'a TreeDelta>>,
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::StatusCode;
+    use crate::config::Config;
+    use crate::dump::testing::{dump, tal};
+
+    /// Requests the tree and returns status, ETag, and Last-Modified.
+    fn get(
+        history: &SharedHistory, etag: Option<&str>
+    ) -> (StatusCode, String, String) {
+        let mut req = Request::builder().uri("/api/v1/tree");
+        if let Some(etag) = etag {
+            req = req.header("If-None-Match", etag);
+        }
+        let res = handle_get_or_head(
+            &req.body(Body::empty()).unwrap(), history
+        ).unwrap().into_hyper();
+        (
+            res.status(),
+            res.headers()["ETag"].to_str().unwrap().to_string(),
+            res.headers()["Last-Modified"].to_str().unwrap().to_string(),
+        )
+    }
+
+    #[test]
+    fn tree_etag() {
+        let history = SharedHistory::from_config(&Config::default());
+        history.update_tree(dump(vec![tal("a", 1)]));
+        let (status, etag, modified) = get(&history, None);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(get(&history, Some(&etag)).0, StatusCode::NOT_MODIFIED);
+
+        // An unchanged tree keeps ETag and Last-Modified, a changed one
+        // doesn’t. New payload doesn’t matter.
+        history.update_tree(dump(vec![tal("a", 1)]));
+        history.mark_update_done();
+        let (status, _, unchanged) = get(&history, Some(&etag));
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(modified, unchanged);
+        history.update_tree(dump(vec![tal("a", 2)]));
+        let (status, new_etag, new_modified) = get(&history, Some(&etag));
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(etag, new_etag);
+        assert_ne!(modified, new_modified);
+    }
+}
@@ -1,11 +1,18 @@
 //! Handling of endpoints related to route validity.
+//!
+//! This includes the contacts endpoint which helps finding out who to
+//! talk to about an RPKI invalid route and the ASPA validity endpoint for
+//! verifying AS paths.
 
 use std::str::FromStr;
 use std::sync::Arc;
 use hyper::{Body, Method, Request};
 use rpki::resources::{Asn, Prefix};
+use crate::contacts::{ContactList, ContactQuery};
 use crate::payload::{PayloadSnapshot, SharedHistory};
-use crate::validity::RouteValidity;
+use crate::validity::{
+    parse_as_path, AspaDirection, AspaPathValidity, RouteValidity
+};
 use super::response::{ContentType, Response, ResponseBuilder};
 
 
@@ -23,6 +30,12 @@ pub fn handle_get_or_head(
         path if path.starts_with("/api/v1/validity/") => {
             Some(handle_validity_path(head, history, &path[17..]))
         }
+        "/api/v1/aspa-validity" => {
+            Some(handle_aspa_validity(head, history, req.uri().query()))
+        }
+        "/api/v1/contacts" => {
+            Some(handle_contacts(head, history, req.uri().query()))
+        }
         _ => None
     }
 }
@@ -119,3 +132,83 @@ fn validity(
     }
 }
 
+
+//------------ handle_aspa_validity ------------------------------------------
+
+fn handle_aspa_validity(
+    head: bool,
+    history: &SharedHistory,
+    query: Option<&str>
+) -> Response {
+    let current = match validity_check(history) {
+        Ok(current) => current,
+        Err(resp) => return resp
+    };
+    let query = match query {
+        Some(query) => query.as_bytes(),
+        None => return Response::bad_request()
+    };
+
+    let mut path = None;
+    let mut direction = AspaDirection::Upstream;
+    for (key, value) in form_urlencoded::parse(query) {
+        if key == "path" {
+            path = match parse_as_path(&value) {
+                Ok(path) => Some(path),
+                Err(_) => return Response::bad_request()
+            }
+        }
+        else if key == "direction" {
+            direction = match AspaDirection::from_str(&value) {
+                Ok(direction) => direction,
+                Err(_) => return Response::bad_request()
+            }
+        }
+        else {
+            return Response::bad_request()
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => return Response::bad_request()
+    };
+    let res = ResponseBuilder::ok().content_type(ContentType::JSON);
+    if head {
+        res.empty()
+    }
+    else {
+        res.body(
+            AspaPathValidity::new(path, direction, &current)
+            .into_json(&current)
+        )
+    }
+}
+
+
+//------------ handle_contacts -----------------------------------------------
+
+fn handle_contacts(
+    head: bool,
+    history: &SharedHistory,
+    query: Option<&str>
+) -> Response {
+    let query = match ContactQuery::from_query(query) {
+        Ok(query) => query,
+        Err(_) => return Response::bad_request()
+    };
+    let current = match validity_check(history) {
+        Ok(current) => current,
+        Err(resp) => return resp
+    };
+    let res = ResponseBuilder::ok().content_type(ContentType::JSON);
+    if head {
+        res.empty()
+    }
+    else {
+        let mut body = Vec::new();
+        ContactList::new(&current, &query).write_json(
+            &mut body
+        ).expect("writing to vec failed");
+        res.body(body)
+    }
+}
@@ -0,0 +1,1022 @@
+//! Inspecting individual RPKI objects.
+//!
+//! This module allows decoding a single RPKI object given as a file with
+//! the same decoders used during validation and to show its content via
+//! [`ObjectInfo`]. Optionally, the object can be validated against the
+//! local repository via [`ObjectValidation`] which looks for the issuing CA
+//! during a validation run and validates the object against the CA’s
+//! certificate and CRL.
+
+use std::io;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use bytes::Bytes;
+use log::error;
+use rpki::uri;
+use rpki::crypto::keys::KeyIdentifier;
+use rpki::repository::aspa::Aspa;
+use rpki::repository::cert::{Cert, KeyUsage, ResourceCert};
+use rpki::repository::crl::Crl;
+use rpki::repository::error::{
+    InspectionError, ValidationError, VerificationError
+};
+use rpki::repository::manifest::{Manifest, ManifestContent};
+use rpki::repository::roa::Roa;
+use rpki::repository::sigobj::SignedObject;
+use rpki::repository::tal::{Tal, TalInfo, TalUri};
+use rpki::repository::x509::{Serial, Time};
+use serde::Serialize;
+use crate::config::Config;
+use crate::engine::{CaCert, Engine, ProcessPubPoint, ProcessRun};
+use crate::error::Failed;
+use crate::utils::date::format_iso_date;
+use crate::utils::str::append_hex;
+
+
+//------------ RpkiObject ----------------------------------------------------
+
+/// A decoded RPKI object.
+///
+/// The type of the object is determined by the file name extension just
+/// like for objects published in the repository.
+#[derive(Clone, Debug)]
+pub enum RpkiObject {
+    /// A CA or router certificate.
+    Cert(Cert),
+
+    /// A certificate revocation list.
+    Crl(Crl),
+
+    /// A manifest.
+    Manifest(Manifest),
+
+    /// A route origin authorization.
+    Roa(Roa),
+
+    /// An autonomous system provider authorization.
+    Aspa(Aspa),
+
+    /// A Ghostbuster record.
+    Gbr(SignedObject),
+}
+
+impl RpkiObject {
+    /// Decodes an object with the given file name from its content.
+    ///
+    /// Logs an error and returns `Err(Failed)` if the file name has an
+    /// unknown extension or the object cannot be decoded.
+    pub fn decode(
+        name: &str, data: Bytes, strict: bool
+    ) -> Result<Self, Failed> {
+        let res = if name.ends_with(".cer") {
+            Cert::decode(data).map(RpkiObject::Cert).map_err(|err| {
+                err.to_string()
+            })
+        }
+        else if name.ends_with(".crl") {
+            Crl::decode(data).map(RpkiObject::Crl).map_err(|err| {
+                err.to_string()
+            })
+        }
+        else if name.ends_with(".mft") {
+            Manifest::decode(data, strict).map(RpkiObject::Manifest).map_err(
+                |err| err.to_string()
+            )
+        }
+        else if name.ends_with(".roa") {
+            Roa::decode(data, strict).map(RpkiObject::Roa).map_err(|err| {
+                err.to_string()
+            })
+        }
+        else if name.ends_with(".asa") {
+            Aspa::decode(data, strict).map(RpkiObject::Aspa).map_err(|err| {
+                err.to_string()
+            })
+        }
+        else if name.ends_with(".gbr") {
+            SignedObject::decode(data, strict).map(RpkiObject::Gbr).map_err(
+                |err| err.to_string()
+            )
+        }
+        else {
+            error!(
+                "{}: unknown object type. Supported are .cer, .crl, .mft, \
+                 .roa, .asa, and .gbr files.",
+                name
+            );
+            return Err(Failed)
+        };
+        res.map_err(|err| {
+            error!("{}: failed to decode object: {}", name, err);
+            Failed
+        })
+    }
+
+    /// Returns the certificate the object is signed with.
+    ///
+    /// For certificates, this is the certificate itself. For a CRL, there
+    /// is no such certificate.
+    fn cert(&self) -> Option<&Cert> {
+        match *self {
+            RpkiObject::Cert(ref cert) => Some(cert),
+            RpkiObject::Crl(_) => None,
+            RpkiObject::Manifest(ref obj) => Some(obj.cert()),
+            RpkiObject::Roa(ref obj) => Some(obj.cert()),
+            RpkiObject::Aspa(ref obj) => Some(obj.cert()),
+            RpkiObject::Gbr(ref obj) => Some(obj.cert()),
+        }
+    }
+
+    /// Returns the key identifier of the CA that issued the object.
+    pub fn issuer(&self) -> Option<KeyIdentifier> {
+        match *self {
+            RpkiObject::Crl(ref crl) => Some(*crl.authority_key_identifier()),
+            _ => self.cert().and_then(|cert| {
+                cert.authority_key_identifier()
+            })
+        }
+    }
+
+    /// Returns whether the object is a self-signed certificate.
+    pub fn is_self_signed(&self) -> bool {
+        match *self {
+            RpkiObject::Cert(ref cert) => cert.is_self_signed(),
+            _ => false
+        }
+    }
+
+    /// Validates the object against the given issuing CA.
+    ///
+    /// The CA is given via its certificate and the URI and content of its
+    /// current CRL. Validation includes checking that the object’s
+    /// certificate has not been revoked and, for manifests and CRLs, that
+    /// they aren’t stale.
+    pub fn validate_at(
+        &self,
+        issuer: &ResourceCert,
+        crl_uri: &uri::Rsync,
+        crl: &Crl,
+        strict: bool,
+        now: Time,
+    ) -> Result<(), ValidationError> {
+        let check_crl = |cert: &Cert| check_crl(cert, crl_uri, crl);
+        match *self {
+            RpkiObject::Cert(ref cert) => {
+                if cert.key_usage() == KeyUsage::Ca {
+                    cert.clone().validate_ca_at(issuer, strict, now)?;
+                }
+                else {
+                    cert.validate_router_at(issuer, strict, now)?;
+                }
+                check_crl(cert)
+            }
+            RpkiObject::Crl(ref obj) => {
+                obj.verify_signature(issuer.subject_public_key_info())?;
+                if obj.next_update() < now {
+                    return Err(VerificationError::new("stale CRL").into())
+                }
+                Ok(())
+            }
+            RpkiObject::Manifest(ref obj) => {
+                let (cert, content) = obj.clone().validate_at(
+                    issuer, strict, now
+                )?;
+                check_crl(cert.as_cert())?;
+                if content.next_update() < now {
+                    return Err(
+                        VerificationError::new("stale manifest").into()
+                    )
+                }
+                Ok(())
+            }
+            RpkiObject::Roa(ref obj) => {
+                obj.clone().process(issuer, strict, check_crl).map(|_| ())
+            }
+            RpkiObject::Aspa(ref obj) => {
+                obj.clone().process(issuer, strict, check_crl).map(|_| ())
+            }
+            RpkiObject::Gbr(ref obj) => {
+                let cert = obj.clone().validate_at(issuer, strict, now)?;
+                check_crl(cert.as_cert())
+            }
+        }
+    }
+}
+
+/// Checks whether `cert` has been revoked by the CRL of its issuer.
+fn check_crl(
+    cert: &Cert, crl_uri: &uri::Rsync, crl: &Crl
+) -> Result<(), ValidationError> {
+    match cert.crl_uri() {
+        Some(uri) if uri == crl_uri => { }
+        Some(_) => {
+            return Err(InspectionError::new(
+                "certificate's CRL differs from issuer's"
+            ).into())
+        }
+        None => {
+            return Err(InspectionError::new(
+                "certificate has no CRL URI"
+            ).into())
+        }
+    }
+    if crl.contains(cert.serial_number()) {
+        return Err(InspectionError::new(
+            "certificate has been revoked"
+        ).into())
+    }
+    Ok(())
+}
+
+
+//------------ ObjectInfo ----------------------------------------------------
+
+/// The content of an RPKI object prepared for output.
+#[derive(Clone, Debug, Serialize)]
+pub struct ObjectInfo {
+    /// The type of the object.
+    #[serde(rename = "type")]
+    kind: &'static str,
+
+    /// The certificate or the EE certificate of a signed object.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert: Option<CertInfo>,
+
+    /// The content specific to the object type.
+    #[serde(flatten)]
+    content: ContentInfo,
+
+    /// The result of validating the object, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation: Option<ValidationResult>,
+}
+
+impl ObjectInfo {
+    /// Creates the information for the given object.
+    pub fn new(object: &RpkiObject) -> Self {
+        let (kind, content) = match *object {
+            RpkiObject::Cert(ref cert) => {
+                (
+                    if cert.key_usage() == KeyUsage::Ca {
+                        "ca-cert"
+                    }
+                    else {
+                        "router-cert"
+                    },
+                    ContentInfo::Cert { }
+                )
+            }
+            RpkiObject::Crl(ref crl) => {
+                ("crl", ContentInfo::Crl {
+                    authority_key_identifier: *crl.authority_key_identifier(),
+                    crl_number: crl.crl_number(),
+                    this_update: crl.this_update(),
+                    next_update: crl.next_update(),
+                    revoked_certs: crl.revoked_certs().iter().count(),
+                })
+            }
+            RpkiObject::Manifest(ref mft) => {
+                ("manifest", ContentInfo::Manifest {
+                    manifest_number: mft.manifest_number(),
+                    this_update: mft.this_update(),
+                    next_update: mft.next_update(),
+                    files: ManifestFile::from_content(mft),
+                })
+            }
+            RpkiObject::Roa(ref roa) => {
+                let content = roa.content();
+                ("roa", ContentInfo::Roa {
+                    asn: content.as_id().into_u32(),
+                    prefixes: content.iter().map(|addr| {
+                        format!(
+                            "{}/{}-{}",
+                            addr.address(), addr.address_length(),
+                            addr.max_length()
+                        )
+                    }).collect(),
+                })
+            }
+            RpkiObject::Aspa(ref aspa) => {
+                let content = aspa.content();
+                ("aspa", ContentInfo::Aspa {
+                    customer: content.customer_as().into_u32(),
+                    providers: content.provider_as_set().iter().map(|asn| {
+                        asn.into_u32()
+                    }).collect(),
+                })
+            }
+            RpkiObject::Gbr(ref gbr) => {
+                ("gbr", ContentInfo::Gbr {
+                    vcard: String::from_utf8_lossy(
+                        gbr.content().to_bytes().as_ref()
+                    ).into_owned()
+                })
+            }
+        };
+        ObjectInfo {
+            kind,
+            cert: object.cert().map(CertInfo::new),
+            content,
+            validation: None,
+        }
+    }
+
+    /// Adds the result of validating the object.
+    pub fn set_validation(&mut self, validation: ValidationResult) {
+        self.validation = Some(validation)
+    }
+
+    /// Writes the information as pretty-printed JSON to the given target.
+    pub fn write_json(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        serde_json::to_writer_pretty(&mut *target, self)?;
+        target.write_all(b"\n")
+    }
+
+    /// Writes the information as human-readable text to the given target.
+    pub fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(target, "Type: {}", self.kind)?;
+        if let Some(cert) = self.cert.as_ref() {
+            cert.write_text(target)?;
+        }
+        self.content.write_text(target)?;
+        if let Some(validation) = self.validation.as_ref() {
+            validation.write_text(target)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the information in the given format to the given target.
+    pub fn write(
+        &self, format: InspectFormat, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match format {
+            InspectFormat::Json => self.write_json(target),
+            InspectFormat::Text => self.write_text(target),
+        }
+    }
+}
+
+
+//------------ CertInfo ------------------------------------------------------
+
+/// The content of a certificate prepared for output.
+#[derive(Clone, Debug, Serialize)]
+struct CertInfo {
+    subject_key_identifier: KeyIdentifier,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authority_key_identifier: Option<KeyIdentifier>,
+    serial_number: Serial,
+    not_before: Time,
+    not_after: Time,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crl_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ca_repository: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rpki_manifest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rpki_notify: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signed_object: Option<String>,
+    as_resources: String,
+    v4_resources: String,
+    v6_resources: String,
+}
+
+impl CertInfo {
+    fn new(cert: &Cert) -> Self {
+        let validity = cert.validity();
+        CertInfo {
+            subject_key_identifier: cert.subject_key_identifier(),
+            authority_key_identifier: cert.authority_key_identifier(),
+            serial_number: cert.serial_number(),
+            not_before: validity.not_before(),
+            not_after: validity.not_after(),
+            crl_uri: cert.crl_uri().map(ToString::to_string),
+            ca_repository: cert.ca_repository().map(ToString::to_string),
+            rpki_manifest: cert.rpki_manifest().map(ToString::to_string),
+            rpki_notify: cert.rpki_notify().map(ToString::to_string),
+            signed_object: cert.signed_object().map(ToString::to_string),
+            as_resources: cert.as_resources().to_string(),
+            v4_resources: match cert.v4_resources().to_blocks() {
+                Ok(blocks) => blocks.as_v4().to_string(),
+                Err(_) => "inherit".into(),
+            },
+            v6_resources: match cert.v6_resources().to_blocks() {
+                Ok(blocks) => blocks.as_v6().to_string(),
+                Err(_) => "inherit".into(),
+            },
+        }
+    }
+
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        writeln!(
+            target, "Subject key identifier: {}",
+            self.subject_key_identifier
+        )?;
+        if let Some(aki) = self.authority_key_identifier.as_ref() {
+            writeln!(target, "Authority key identifier: {}", aki)?;
+        }
+        writeln!(target, "Serial number: {}", self.serial_number)?;
+        writeln!(
+            target, "Not before: {}",
+            format_iso_date(self.not_before.into())
+        )?;
+        writeln!(
+            target, "Not after: {}",
+            format_iso_date(self.not_after.into())
+        )?;
+        for (label, value) in [
+            ("CRL", &self.crl_uri),
+            ("CA repository", &self.ca_repository),
+            ("Manifest", &self.rpki_manifest),
+            ("RRDP notification", &self.rpki_notify),
+            ("Signed object", &self.signed_object),
+        ] {
+            if let Some(value) = value.as_ref() {
+                writeln!(target, "{}: {}", label, value)?;
+            }
+        }
+        for (label, value) in [
+            ("AS resources", &self.as_resources),
+            ("IPv4 resources", &self.v4_resources),
+            ("IPv6 resources", &self.v6_resources),
+        ] {
+            if value.is_empty() {
+                writeln!(target, "{}: none", label)?;
+            }
+            else {
+                writeln!(target, "{}: {}", label, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+//------------ ContentInfo ---------------------------------------------------
+
+/// The type specific content of an object prepared for output.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+enum ContentInfo {
+    Cert { },
+    Crl {
+        authority_key_identifier: KeyIdentifier,
+        crl_number: Serial,
+        this_update: Time,
+        next_update: Time,
+        revoked_certs: usize,
+    },
+    Manifest {
+        manifest_number: Serial,
+        this_update: Time,
+        next_update: Time,
+        files: Vec<ManifestFile>,
+    },
+    Roa {
+        asn: u32,
+        prefixes: Vec<String>,
+    },
+    Aspa {
+        customer: u32,
+        providers: Vec<u32>,
+    },
+    Gbr {
+        vcard: String,
+    },
+}
+
+impl ContentInfo {
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match *self {
+            ContentInfo::Cert { } => Ok(()),
+            ContentInfo::Crl {
+                ref authority_key_identifier, crl_number, this_update,
+                next_update, revoked_certs
+            } => {
+                writeln!(
+                    target, "Authority key identifier: {}",
+                    authority_key_identifier
+                )?;
+                writeln!(target, "CRL number: {}", crl_number)?;
+                writeln!(
+                    target, "This update: {}",
+                    format_iso_date(this_update.into())
+                )?;
+                writeln!(
+                    target, "Next update: {}",
+                    format_iso_date(next_update.into())
+                )?;
+                writeln!(target, "Revoked certificates: {}", revoked_certs)
+            }
+            ContentInfo::Manifest {
+                manifest_number, this_update, next_update, ref files
+            } => {
+                writeln!(target, "Manifest number: {}", manifest_number)?;
+                writeln!(
+                    target, "This update: {}",
+                    format_iso_date(this_update.into())
+                )?;
+                writeln!(
+                    target, "Next update: {}",
+                    format_iso_date(next_update.into())
+                )?;
+                writeln!(target, "Files:")?;
+                for file in files {
+                    writeln!(target, "  {} {}", file.hash, file.file)?;
+                }
+                Ok(())
+            }
+            ContentInfo::Roa { asn, ref prefixes } => {
+                writeln!(target, "ASN: AS{}", asn)?;
+                writeln!(target, "Prefixes:")?;
+                for prefix in prefixes {
+                    writeln!(target, "  {}", prefix)?;
+                }
+                Ok(())
+            }
+            ContentInfo::Aspa { customer, ref providers } => {
+                writeln!(target, "Customer: AS{}", customer)?;
+                writeln!(target, "Providers:")?;
+                for provider in providers {
+                    writeln!(target, "  AS{}", provider)?;
+                }
+                Ok(())
+            }
+            ContentInfo::Gbr { ref vcard } => {
+                writeln!(target, "vCard:")?;
+                for line in vcard.lines() {
+                    writeln!(target, "  {}", line)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+
+//------------ ManifestFile --------------------------------------------------
+
+/// A file listed on a manifest.
+#[derive(Clone, Debug, Serialize)]
+struct ManifestFile {
+    /// The file name.
+    file: String,
+
+    /// The hex-encoded hash of the file.
+    hash: String,
+}
+
+impl ManifestFile {
+    fn from_content(content: &ManifestContent) -> Vec<Self> {
+        content.iter().map(|item| {
+            let mut hash = String::new();
+            append_hex(item.hash().as_ref(), &mut hash);
+            ManifestFile {
+                file: String::from_utf8_lossy(item.file()).into_owned(),
+                hash,
+            }
+        }).collect()
+    }
+}
+
+
+//------------ InspectFormat -------------------------------------------------
+
+/// The output format for inspected objects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InspectFormat {
+    /// A JSON object.
+    Json,
+
+    /// Human-readable text.
+    Text,
+}
+
+impl InspectFormat {
+    /// All known inspect format names and their values.
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("json", InspectFormat::Json),
+        ("text", InspectFormat::Text),
+    ];
+
+    /// The default inspect format name.
+    pub const DEFAULT_VALUE: &'static str = "text";
+}
+
+
+//--- FromStr
+
+impl FromStr for InspectFormat {
+    type Err = Failed;
+
+    fn from_str(value: &str) -> Result<Self, Failed> {
+        for &(name, res) in Self::VALUES {
+            if name == value {
+                return Ok(res)
+            }
+        }
+        error!("Unknown inspect format: {}", value);
+        Err(Failed)
+    }
+}
+
+
+//------------ ValidationResult ----------------------------------------------
+
+/// The result of validating an object against the local repository.
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationResult {
+    /// Whether the object is valid.
+    valid: bool,
+
+    /// The name of the TAL the object was validated under.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tal: Option<String>,
+
+    /// The URI of the certificate of the issuing CA.
+    ///
+    /// This is `None` if the object is a trust anchor certificate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issuer: Option<String>,
+
+    /// The reason why validation failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+impl ValidationResult {
+    /// Returns whether the object is valid.
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Records a new result for an object.
+    ///
+    /// Since an object may be checked against more than one candidate
+    /// issuer, a valid result is never replaced and an invalid result
+    /// never replaces an earlier one.
+    fn record(current: &mut Option<Self>, new: Self) {
+        match current.as_ref() {
+            Some(current) if current.valid || !new.valid => { }
+            _ => *current = Some(new)
+        }
+    }
+
+    fn write_text(
+        &self, target: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        if self.valid {
+            match (self.tal.as_ref(), self.issuer.as_ref()) {
+                (Some(tal), Some(issuer)) => {
+                    writeln!(
+                        target, "Validation: valid, issued by {} (TAL {})",
+                        issuer, tal
+                    )
+                }
+                (Some(tal), None) => {
+                    writeln!(
+                        target, "Validation: valid, trust anchor of TAL {}",
+                        tal
+                    )
+                }
+                _ => writeln!(target, "Validation: valid"),
+            }
+        }
+        else {
+            writeln!(
+                target, "Validation: invalid, {}",
+                self.reason.as_deref().unwrap_or("unknown reason")
+            )
+        }
+    }
+}
+
+
+//------------ ObjectValidation ----------------------------------------------
+
+/// Validates an object against the local repository.
+///
+/// During a validation run, the object is validated against every CA with
+/// a subject key identifier matching the object’s authority key identifier
+/// using the CRL of the CA’s current manifest. Validation stops at the
+/// first CA the object validates against. Self-signed certificates are
+/// valid if they are the certificate of one of the trust anchors.
+#[derive(Debug)]
+pub struct ObjectValidation<'a> {
+    /// The object to validate.
+    object: &'a RpkiObject,
+
+    /// The key identifier of the issuing CA.
+    issuer: Option<KeyIdentifier>,
+
+    /// Whether to use strict validation.
+    strict: bool,
+
+    /// The time to validate the object at.
+    now: Time,
+
+    /// The result so far.
+    result: Mutex<Option<ValidationResult>>,
+
+    /// Whether the object has been found valid.
+    complete: AtomicBool,
+}
+
+impl<'a> ObjectValidation<'a> {
+    /// Creates a new validation for the given object.
+    ///
+    /// The object will be validated at the validation time of `engine`.
+    pub fn new(
+        object: &'a RpkiObject, engine: &Engine, config: &Config
+    ) -> Self {
+        ObjectValidation {
+            object,
+            issuer: object.issuer(),
+            strict: config.strict,
+            now: engine.now(),
+            result: Mutex::new(None),
+            complete: AtomicBool::new(false),
+        }
+    }
+
+    /// Performs a validation run with the given engine.
+    pub fn process(&self, engine: &Engine) -> Result<(), Failed> {
+        let mut run = engine.start(self)?;
+        run.process()?;
+        run.cleanup()?;
+        Ok(())
+    }
+
+    /// Returns the result of the validation.
+    pub fn finalize(self) -> ValidationResult {
+        self.result.into_inner().expect("poisoned lock").unwrap_or_else(|| {
+            ValidationResult {
+                valid: false,
+                tal: None,
+                issuer: None,
+                reason: Some("issuing CA not found".into()),
+            }
+        })
+    }
+
+    /// Validates the object against a CA.
+    fn check_ca(
+        &self,
+        tal: &TalInfo,
+        issuer: &IssuerInfo,
+        crl_uri: &uri::Rsync,
+        crl: &Crl,
+    ) {
+        let res = self.object.validate_at(
+            &issuer.cert, crl_uri, crl, self.strict, self.now
+        );
+        let mut result = self.result.lock().expect("poisoned lock");
+        match res {
+            Ok(()) => {
+                ValidationResult::record(&mut result, ValidationResult {
+                    valid: true,
+                    tal: Some(tal.name().into()),
+                    issuer: Some(issuer.uri.clone()),
+                    reason: None,
+                });
+                self.complete.store(true, Ordering::Relaxed);
+            }
+            Err(err) => {
+                ValidationResult::record(&mut result, ValidationResult {
+                    valid: false,
+                    tal: Some(tal.name().into()),
+                    issuer: Some(issuer.uri.clone()),
+                    reason: Some(err.to_string()),
+                });
+            }
+        }
+    }
+
+    /// Returns the issuer information if `cert` is the issuing CA.
+    fn issuer_info(&self, cert: &CaCert) -> Option<IssuerInfo> {
+        if self.issuer != Some(cert.cert().subject_key_identifier()) {
+            return None
+        }
+        Some(IssuerInfo {
+            uri: cert.uri().as_str().into(),
+            cert: cert.cert().clone(),
+        })
+    }
+}
+
+impl<'a, 's> ProcessRun for &'s ObjectValidation<'a> {
+    type PubPoint = InspectPoint<'a, 's>;
+
+    fn process_ta(
+        &self, tal: &Tal, _uri: &TalUri, cert: &CaCert,
+        _tal_index: usize
+    ) -> Result<Option<Self::PubPoint>, Failed> {
+        if self.complete.load(Ordering::Relaxed) {
+            return Ok(None)
+        }
+        if let RpkiObject::Cert(ref object) = *self.object {
+            if
+                self.object.is_self_signed()
+                && object.to_captured().as_slice()
+                    == cert.cert().as_cert().to_captured().as_slice()
+            {
+                ValidationResult::record(
+                    &mut self.result.lock().expect("poisoned lock"),
+                    ValidationResult {
+                        valid: true,
+                        tal: Some(tal.info().name().into()),
+                        issuer: None,
+                        reason: None,
+                    }
+                );
+                self.complete.store(true, Ordering::Relaxed);
+                return Ok(None)
+            }
+        }
+        Ok(Some(InspectPoint {
+            validation: self,
+            tal: tal.info().clone(),
+            issuer: self.issuer_info(cert),
+            crl: None,
+        }))
+    }
+}
+
+
+//------------ InspectPoint --------------------------------------------------
+
+/// Looks for the issuer of the object at a publication point.
+pub struct InspectPoint<'a, 's> {
+    /// The validation to add the result to.
+    validation: &'s ObjectValidation<'a>,
+
+    /// The TAL the publication point was derived from.
+    tal: Arc<TalInfo>,
+
+    /// The CA of the point if it may be the issuer.
+    issuer: Option<IssuerInfo>,
+
+    /// The URI and content of the point’s CRL if it may be the issuer.
+    crl: Option<(uri::Rsync, Crl)>,
+}
+
+/// Information about a possible issuer of the object.
+struct IssuerInfo {
+    /// The URI of the CA certificate.
+    uri: String,
+
+    /// The CA certificate.
+    cert: ResourceCert,
+}
+
+impl<'a, 's> ProcessPubPoint for InspectPoint<'a, 's> {
+    fn want(&self, uri: &uri::Rsync) -> Result<bool, Failed> {
+        Ok(uri.ends_with(".cer"))
+    }
+
+    fn process_manifest(
+        &mut self,
+        _uri: &uri::Rsync,
+        _ee_cert: &ResourceCert,
+        _manifest: &ManifestContent,
+        crl_uri: &uri::Rsync,
+        crl: &Crl,
+    ) -> Result<(), Failed> {
+        if self.issuer.is_some() {
+            self.crl = Some((crl_uri.clone(), crl.clone()));
+        }
+        Ok(())
+    }
+
+    fn process_ca(
+        &mut self, _uri: &uri::Rsync, cert: &CaCert,
+    ) -> Result<Option<Self>, Failed> {
+        if self.validation.complete.load(Ordering::Relaxed) {
+            return Ok(None)
+        }
+        Ok(Some(InspectPoint {
+            validation: self.validation,
+            tal: self.tal.clone(),
+            issuer: self.validation.issuer_info(cert),
+            crl: None,
+        }))
+    }
+
+    fn restart(&mut self) -> Result<(), Failed> {
+        self.crl = None;
+        Ok(())
+    }
+
+    fn commit(self) {
+        if self.validation.complete.load(Ordering::Relaxed) {
+            return
+        }
+        if let (Some(issuer), Some((crl_uri, crl))) = (
+            self.issuer.as_ref(), self.crl.as_ref()
+        ) {
+            self.validation.check_ca(&self.tal, issuer, crl_uri, crl);
+        }
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::testing::{TAL_NAME, TestCa, TestRepository};
+
+    #[test]
+    fn decode_unknown() {
+        assert!(
+            RpkiObject::decode("foo.txt", Bytes::from_static(b"foo"), false)
+                .is_err()
+        );
+        assert!(
+            RpkiObject::decode("foo.roa", Bytes::from_static(b"foo"), false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn record_keeps_best_result() {
+        fn result(valid: bool, issuer: &str) -> ValidationResult {
+            ValidationResult {
+                valid,
+                tal: None,
+                issuer: Some(issuer.into()),
+                reason: if valid { None } else { Some("bad".into()) },
+            }
+        }
+
+        let mut current = None;
+        ValidationResult::record(&mut current, result(false, "a"));
+        assert_eq!(current.as_ref().unwrap().issuer.as_deref(), Some("a"));
+        ValidationResult::record(&mut current, result(false, "b"));
+        assert_eq!(current.as_ref().unwrap().issuer.as_deref(), Some("a"));
+        ValidationResult::record(&mut current, result(true, "c"));
+        assert!(current.as_ref().unwrap().is_valid());
+        ValidationResult::record(&mut current, result(false, "d"));
+        assert!(current.as_ref().unwrap().is_valid());
+        assert_eq!(current.as_ref().unwrap().issuer.as_deref(), Some("c"));
+        ValidationResult::record(&mut current, result(true, "e"));
+        assert_eq!(current.unwrap().issuer.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn validate_against_repository() {
+        let (repo, mut ta) = TestRepository::new();
+        let mut ca = repo.child(&mut ta, "ca", "10.0.0.0/8", "AS64496");
+        repo.roa(&mut ca, "valid", 64496, "10.0.0.0/24");
+        let revoked = repo.roa(&mut ca, "revoked", 64496, "10.0.1.0/24");
+        ca.revoke(revoked);
+        repo.publish(&mut ca);
+        repo.publish(&mut ta);
+
+        // The trust anchor’s stored point doesn’t know this CA.
+        let mut unknown = repo.child(
+            &mut ta, "unknown", "10.1.0.0/16", "AS64496"
+        );
+        repo.roa(&mut unknown, "orphan", 64496, "10.1.0.0/24");
+        repo.publish(&mut unknown);
+
+        let engine = repo.engine();
+        let validate = |ca: &TestCa, name: &str| {
+            let object = RpkiObject::decode(
+                name, ca.object(name), false
+            ).unwrap();
+            let validation = ObjectValidation::new(
+                &object, &engine, repo.config()
+            );
+            validation.process(&engine).unwrap();
+            validation.finalize()
+        };
+
+        let result = validate(&ca, "valid.roa");
+        assert!(result.is_valid());
+        assert_eq!(result.tal.as_deref(), Some(TAL_NAME));
+        assert_eq!(result.issuer.as_deref(), Some(ca.uri().as_str()));
+
+        let result = validate(&ca, "revoked.roa");
+        assert!(!result.is_valid());
+        assert_eq!(
+            result.reason.as_deref(), Some("certificate has been revoked")
+        );
+
+        let result = validate(&unknown, "orphan.roa");
+        assert!(!result.is_valid());
+        assert_eq!(result.reason.as_deref(), Some("issuing CA not found"));
+    }
+}
@@ -22,12 +22,15 @@
 //! * [engine], which performs a validation run using both collector and
 //!   store.
 //!
-//! The second part currently comes in two flavours:
+//! The second part currently comes in four flavours:
 //!
 //! * [payload], which collects and processes data for distribution to
-//!   routers or local use, and
+//!   routers or local use,
+//! * [dump], which collects the tree of validated CA certificates and
+//!   ROAs,
 //! * [rta], which processes Resource Tagged Authorizations (i.e., objects
-//!   signed by resource holders).
+//!   signed by resource holders), and
+//! * [rsc], which validates RPKI Signed Checklists.
 //!
 //! Additional modules can be added in the future.
 //!
@@ -38,8 +41,9 @@
 //! * [http] provides an HTTP server with multiple endpoints for all sorts
 //!   of purposes,
 //! * [rtr] provides an RTR server which allows routers to synchronize their
-//!   RPKI filter tables, and
-//! * [validity] can be used to perform route origin validation.
+//!   RPKI filter tables,
+//! * [validity] can be used to perform route origin validation, and
+//! * [contacts] finds the contacts responsible for resources.
 //!
 //! Apart from these, there are a few more modules that support these core
 //! parts in their work.
@@ -55,19 +59,24 @@ pub use reqwest;
 
 pub mod collector;
 pub mod config;
+pub mod constraints;
+pub mod contacts;
+pub mod dump;
 pub mod engine;
 pub mod error;
 pub mod http;
+pub mod inspect;
 pub mod metrics;
 pub mod operation;
 pub mod output;
 pub mod payload;
 pub mod process;
+pub mod rsc;
 pub mod rtr;
 pub mod rta;
 pub mod slurm;
 pub mod store;
+pub mod tak;
 pub mod tals;
 pub mod utils;
-pub mod validity;
-pub mod dump;
\ No newline at end of file
+pub mod validity;
\ No newline at end of file
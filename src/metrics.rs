@@ -140,6 +140,9 @@ pub struct RrdpRepositoryMetrics {
 
     /// The duration of the last update.
     pub duration: Result<Duration, SystemTimeError>,
+
+    /// Did the update fail because the repository exceeded the size limit?
+    pub oversized: bool,
 }
 
 impl RrdpRepositoryMetrics {
@@ -151,7 +154,8 @@ impl RrdpRepositoryMetrics {
             serial: None,
             snapshot_reason: None,
             payload_status: None,
-            duration: Ok(Duration::from_secs(0))
+            duration: Ok(Duration::from_secs(0)),
+            oversized: false,
         }
     }
 
@@ -179,6 +183,9 @@ pub struct RsyncModuleMetrics {
     pub module: uri::Rsync,
     pub status: Result<process::ExitStatus, io::Error>,
     pub duration: Result<Duration, SystemTimeError>,
+
+    /// Was the module removed because it exceeded the size limit?
+    pub oversized: bool,
 }
 
 
@@ -229,6 +236,9 @@ pub struct RepositoryMetrics {
 
     /// The VRP metrics.
     pub payload: PayloadMetrics,
+
+    /// Was the repository rejected for exceeding the size limit?
+    pub oversized: bool,
 }
 
 impl RepositoryMetrics {
@@ -237,6 +247,7 @@ impl RepositoryMetrics {
             uri,
             publication: Default::default(),
             payload: Default::default(),
+            oversized: false,
         }
     }
 
@@ -257,6 +268,25 @@ pub struct PublicationMetrics {
     /// The number of rejected publication points.
     pub rejected_points: u32,
 
+    /// The number of valid publication points taken from the cache.
+    ///
+    /// These points have not changed since the previous validation run and
+    /// their validated objects were reused. They are included in
+    /// `valid_points`.
+    pub cached_points: u32,
+
+    /// The number of publication points rejected for too many child CAs.
+    ///
+    /// These points are included in `rejected_points`.
+    pub excess_child_ca_points: u32,
+
+    /// The number of publication points rejected for too many repositories.
+    ///
+    /// These are points in a repository that would have exceeded the
+    /// maximum number of repositories of their TAL. They are included in
+    /// `rejected_points`.
+    pub excess_repository_points: u32,
+
     /// The number of valid manifests.
     pub valid_manifests: u32,
 
@@ -269,6 +299,16 @@ pub struct PublicationMetrics {
     /// The number of stale manifests.
     pub stale_manifests: u32,
 
+    /// The number of collected manifests going backwards.
+    ///
+    /// These are manifests whose manifest number is not higher or whose
+    /// thisUpdate time is earlier than that of the stored manifest. They
+    /// are rejected and the stored manifest is used instead.
+    pub rollback_manifests: u32,
+
+    /// The number of manifests with too many entries.
+    pub oversized_manifests: u32,
+
     /// The number of missing manifests.
     pub missing_manifests: u32,
 
@@ -315,8 +355,29 @@ pub struct PublicationMetrics {
     /// The number of invalid ASPA objects.
     pub invalid_aspas: u32,
 
+    /// The number of valid TAK objects.
+    pub valid_taks: u32,
+
+    /// The number of invalid TAK objects.
+    pub invalid_taks: u32,
+
     /// The number of other objects.
     pub others: u32,
+
+    /// The number of trust anchor certificates with a changed key.
+    pub ta_key_changes: u32,
+
+    /// The number of trust anchor certificates with changed resources.
+    pub ta_resource_changes: u32,
+
+    /// The number of trust anchor certificates with a changed validity.
+    pub ta_validity_changes: u32,
+
+    /// The number of changed trust anchor certificates held back.
+    ///
+    /// If trust anchor certificates are pinned, a changed certificate is
+    /// not used until it has been accepted.
+    pub pinned_ta_certs: u32,
 }
 
 impl PublicationMetrics {
@@ -339,11 +400,16 @@ impl<'a> ops::AddAssign<&'a Self> for PublicationMetrics {
     fn add_assign(&mut self, other: &'a Self) {
         self.valid_points += other.valid_points;
         self.rejected_points += other.rejected_points;
+        self.cached_points += other.cached_points;
+        self.excess_child_ca_points += other.excess_child_ca_points;
+        self.excess_repository_points += other.excess_repository_points;
 
         self.valid_manifests += other.valid_manifests;
         self.invalid_manifests += other.invalid_manifests;
         self.premature_manifests += other.premature_manifests;
         self.stale_manifests += other.stale_manifests;
+        self.rollback_manifests += other.rollback_manifests;
+        self.oversized_manifests += other.oversized_manifests;
         self.missing_manifests += other.missing_manifests;
         self.valid_crls += other.valid_crls;
         self.invalid_crls += other.invalid_crls;
@@ -359,7 +425,13 @@ impl<'a> ops::AddAssign<&'a Self> for PublicationMetrics {
         self.invalid_gbrs += other.invalid_gbrs;
         self.valid_aspas += other.valid_aspas;
         self.invalid_aspas += other.invalid_aspas;
+        self.valid_taks += other.valid_taks;
+        self.invalid_taks += other.invalid_taks;
         self.others += other.others;
+        self.ta_key_changes += other.ta_key_changes;
+        self.ta_resource_changes += other.ta_resource_changes;
+        self.ta_validity_changes += other.ta_validity_changes;
+        self.pinned_ta_certs += other.pinned_ta_certs;
     }
 }
 
@@ -459,6 +531,9 @@ pub struct VrpMetrics {
     /// The number of VRPs filtered due to local exceptions.
     pub locally_filtered: u32,
 
+    /// The number of VRPs dropped because of TAL constraints.
+    pub constrained: u32,
+
     /// The number of duplicate VRPs.
     ///
     /// This number is only calculated after local filtering. If duplicates
@@ -488,6 +563,7 @@ impl<'a> ops::AddAssign<&'a Self> for VrpMetrics {
         self.valid += other.valid;
         self.marked_unsafe += other.marked_unsafe;
         self.locally_filtered += other.locally_filtered;
+        self.constrained += other.constrained;
         self.duplicate += other.duplicate;
         self.contributed += other.contributed;
     }
@@ -20,24 +20,32 @@ use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::mpsc::RecvTimeoutError;
 use std::time::{Duration, Instant};
-#[cfg(feature = "rta")] use bytes::Bytes;
+use bytes::Bytes;
 use clap::{Arg, Args, ArgAction, ArgMatches, FromArgMatches, Parser};
 use log::{error, info};
+use rpki::crypto::keys::KeyIdentifier;
 use rpki::resources::{Asn, Prefix};
+use rpki::repository::x509::Time;
 #[cfg(feature = "rta")] use rpki::repository::rta::Rta;
 use rpki::rtr::server::NotifySender;
 use tempfile::NamedTempFile;
 use tokio::sync::oneshot;
 #[cfg(feature = "rta")] use crate::rta;
-use crate::{output, validity};
+use crate::{dump, inspect, output, rsc, validity};
 use crate::config::Config;
+use crate::contacts::{ContactList, ContactQuery, ContactsFormat};
+use crate::dump::{
+    DBDump, ExpiringFormat, ExpiryWindow, ProvenanceFormat,
+    ProvenanceSelection, TreeAuditFormat, TreeDelta, TreeDiffFormat,
+    TreeFormat, TreeGraphFormat, TreeGraphOptions, TreeReport, TreeStream,
+};
 use crate::error::{ExitError, Failed};
 use crate::http::http_listener;
-use crate::metrics::{SharedRtrServerMetrics};
+use crate::metrics::{Metrics, SharedRtrServerMetrics};
 use crate::output::{Output, OutputFormat};
 use crate::payload::{PayloadSnapshot, SharedHistory, ValidationReport};
 use crate::process::Process;
-use crate::engine::Engine;
+use crate::engine::{Engine, ProcessRun};
 use crate::rtr::{rtr_listener};
 use crate::slurm::LocalExceptions;
 
@@ -65,11 +73,21 @@ pub enum Operation {
     Server(Server),
     Vrps(Vrps),
     Validate(Validate),
+    Contacts(Contacts),
     #[cfg(feature = "rta")]
     ValidateDocument(ValidateDocument),
+    ValidateChecklist(ValidateChecklist),
     Update(Update),
     PrintConfig(PrintConfig),
     Dump(Dump),
+    AcceptTa(AcceptTa),
+    Tree(Tree),
+    TreeDiff(TreeDiff),
+    TreeAudit(TreeAudit),
+    TreeGraph(TreeGraph),
+    Provenance(Provenance),
+    Expiring(Expiring),
+    Inspect(Inspect),
     Man(Man),
 }
 
@@ -86,13 +104,23 @@ impl Operation {
         let app = Server::config_args(app);
         let app = Vrps::config_args(app);
         let app = Validate::config_args(app);
+        let app = Contacts::config_args(app);
 
         #[cfg(feature = "rta")]
         let app = ValidateDocument::config_args(app);
 
+        let app = ValidateChecklist::config_args(app);
         let app = Update::config_args(app);
         let app = PrintConfig::config_args(app);
         let app = Dump::config_args(app);
+        let app = AcceptTa::config_args(app);
+        let app = Tree::config_args(app);
+        let app = TreeDiff::config_args(app);
+        let app = TreeAudit::config_args(app);
+        let app = TreeGraph::config_args(app);
+        let app = Provenance::config_args(app);
+        let app = Expiring::config_args(app);
+        let app = Inspect::config_args(app);
         Man::config_args(app)
     }
 
@@ -114,12 +142,20 @@ impl Operation {
             Some(("validate", matches)) => {
                 Operation::Validate(Validate::from_arg_matches(matches)?)
             },
+            Some(("contacts", matches)) => {
+                Operation::Contacts(Contacts::from_arg_matches(matches)?)
+            }
             #[cfg(feature = "rta")]
             Some(("rta", matches)) => {
                 Operation::ValidateDocument(
                     ValidateDocument::from_arg_matches(matches)?
                 )
             }
+            Some(("rsc", matches)) => {
+                Operation::ValidateChecklist(
+                    ValidateChecklist::from_arg_matches(matches)?
+                )
+            }
             Some(("update", matches)) => {
                 Operation::Update(Update::from_arg_matches(matches)?)
             }
@@ -131,6 +167,30 @@ impl Operation {
             Some(("dump", matches)) => {
                 Operation::Dump( Dump::from_arg_matches(matches, cur_dir)?)
             }
+            Some(("accept-ta", matches)) => {
+                Operation::AcceptTa(AcceptTa::from_arg_matches(matches)?)
+            }
+            Some(("tree", matches)) => {
+                Operation::Tree(Tree::from_arg_matches(matches)?)
+            }
+            Some(("tree-diff", matches)) => {
+                Operation::TreeDiff(TreeDiff::from_arg_matches(matches)?)
+            }
+            Some(("tree-audit", matches)) => {
+                Operation::TreeAudit(TreeAudit::from_arg_matches(matches)?)
+            }
+            Some(("tree-graph", matches)) => {
+                Operation::TreeGraph(TreeGraph::from_arg_matches(matches)?)
+            }
+            Some(("provenance", matches)) => {
+                Operation::Provenance(Provenance::from_arg_matches(matches)?)
+            }
+            Some(("expiring", matches)) => {
+                Operation::Expiring(Expiring::from_arg_matches(matches)?)
+            }
+            Some(("inspect", matches)) => {
+                Operation::Inspect(Inspect::from_arg_matches(matches)?)
+            }
             Some(("man", matches)) => {
                 Operation::Man(Man::from_arg_matches(matches)?)
             }
@@ -162,11 +222,21 @@ impl Operation {
             Operation::Server(cmd) => cmd.run(process),
             Operation::Vrps(cmd) => cmd.run(process),
             Operation::Validate(cmd) => cmd.run(process),
+            Operation::Contacts(cmd) => cmd.run(process),
             #[cfg(feature = "rta")]
             Operation::ValidateDocument(cmd) => cmd.run(process),
+            Operation::ValidateChecklist(cmd) => cmd.run(process),
             Operation::Update(cmd) => cmd.run(process),
             Operation::PrintConfig(cmd) => cmd.run(process),
             Operation::Dump(cmd) => cmd.run(process),
+            Operation::AcceptTa(cmd) => cmd.run(process),
+            Operation::Tree(cmd) => cmd.run(process),
+            Operation::TreeDiff(cmd) => cmd.run(process),
+            Operation::TreeAudit(cmd) => cmd.run(process),
+            Operation::TreeGraph(cmd) => cmd.run(process),
+            Operation::Provenance(cmd) => cmd.run(process),
+            Operation::Expiring(cmd) => cmd.run(process),
+            Operation::Inspect(cmd) => cmd.run(process),
             Operation::Man(cmd) => cmd.run(process),
         }
     }
@@ -240,6 +310,9 @@ impl Server {
         process.drop_privileges()?;
 
         let mut validation = Engine::new(process.config(), true)?;
+        if !process.config().disable_validation_cache {
+            validation.enable_point_cache();
+        }
         let runtime = process.runtime()?;
         let mut rtr = runtime.spawn(rtr);
         let mut http = runtime.spawn(http);
@@ -362,7 +435,23 @@ impl Server {
     ) -> Result<(), Failed> {
         info!("Starting a validation run.");
         history.mark_update_start();
-        let (report, metrics) = ValidationReport::process(engine, config)?;
+        let report = ValidationReport::new(config);
+
+        // We only need the tree if it is served or its changes recorded.
+        let tree = (
+            config.http_tree || config.tree_changes_file.is_some()
+        ).then(TreeReport::new);
+        let metrics = match tree.as_ref() {
+            Some(tree) => Self::run_engine(engine, (&report, tree))?,
+            None => Self::run_engine(engine, &report)?,
+        };
+        if let Some(tree) = tree {
+            if let Some(delta) = history.update_tree(tree.into_dump()) {
+                if let Some(path) = config.tree_changes_file.as_ref() {
+                    Self::append_tree_delta(path, &delta);
+                }
+            }
+        }
         let must_notify = history.update(
             report, &exceptions, metrics,
         );
@@ -386,6 +475,35 @@ impl Server {
         history.mark_update_done();
         Ok(())
     }
+
+    /// Performs a validation run with the given processor.
+    fn run_engine(
+        engine: &Engine, processor: impl ProcessRun,
+    ) -> Result<Metrics, Failed> {
+        let mut run = engine.start(processor)?;
+        run.process()?;
+        run.cleanup()?;
+        Ok(run.done())
+    }
+
+    /// Appends the tree delta as a line of JSON to the given file.
+    ///
+    /// Failing to do so is logged but otherwise ignored.
+    fn append_tree_delta(path: &Path, delta: &TreeDelta) {
+        let res = fs::OpenOptions::new().create(true).append(true).open(
+            path
+        ).and_then(|file| {
+            let mut file = io::BufWriter::new(file);
+            delta.write_json_line(&mut file)?;
+            file.flush()
+        });
+        if let Err(err) = res {
+            error!(
+                "Failed to append tree changes to '{}': {}",
+                path.display(), err
+            );
+        }
+    }
 }
 
 
@@ -410,6 +528,12 @@ pub struct Vrps {
 
     /// Return an error on incomplete update.
     complete: bool,
+
+    /// Take the payload from this tree dump instead of validating.
+    from_tree: Option<PathBuf>,
+
+    /// Evaluate the validity of objects at this time.
+    validation_time: Option<Time>,
 }
 
 /// The command line arguments for the vrps sub-command.
@@ -465,6 +589,17 @@ struct VrpsArgs {
     /// Return an error status on incomplete update
     #[arg(long)]
     complete: bool,
+
+    /// Take the payload from a tree dump instead of validating
+    #[arg(
+        long, value_name = "PATH",
+        conflicts_with_all = &["noupdate", "complete"]
+    )]
+    from_tree: Option<PathBuf>,
+
+    /// Evaluate validity at this time using only the local cache
+    #[arg(long, value_name = "TIME", conflicts_with = "from_tree")]
+    validation_time: Option<Time>,
 }
 
 impl Vrps {
@@ -534,6 +669,8 @@ impl Vrps {
             output,
             noupdate: args.noupdate,
             complete: args.complete,
+            from_tree: args.from_tree,
+            validation_time: args.validation_time,
         })
     }
 
@@ -543,16 +680,33 @@ impl Vrps {
     /// stdout if that is `None`. The format is determined by `format`.
     /// If `noupdate` is `false`, the local repository will be updated first
     /// and rsync will be enabled during validation to sync any new
-    /// publication points.
+    /// publication points. If `from_tree` is some path, the payload is
+    /// taken from the tree dump at that path instead. If `validation_time`
+    /// is some time, only the stored data is validated as of that time.
     fn run(mut self, process: Process) -> Result<(), ExitError> {
         self.output.update_from_config(process.config());
-        let mut engine = Engine::new(process.config(), !self.noupdate)?;
-        engine.ignite()?;
-        process.switch_logging(false, false)?;
+        let (report, mut metrics) = match self.from_tree {
+            Some(ref path) => {
+                let engine = Engine::new(process.config(), false)?;
+                process.switch_logging(false, false)?;
+                ValidationReport::from_tree(
+                    &DBDump::load(path)?, &engine, process.config()
+                )
+            }
+            None => {
+                let mut engine = Engine::new(
+                    process.config(),
+                    !self.noupdate && self.validation_time.is_none()
+                )?;
+                if let Some(time) = self.validation_time {
+                    engine.set_validation_time(time);
+                }
+                engine.ignite()?;
+                process.switch_logging(false, false)?;
+                ValidationReport::process(&engine, process.config())?
+            }
+        };
         let exceptions = LocalExceptions::load(process.config(), true)?;
-        let (report, mut metrics) = ValidationReport::process(
-            &engine, process.config(),
-        )?;
         let vrps = Arc::new(report.into_snapshot(&exceptions, &mut metrics));
         let rsync_complete = metrics.rsync_complete();
         let metrics = Arc::new(metrics);
@@ -621,6 +775,12 @@ pub struct Validate {
 
     /// Return an error on incomplete update.
     complete: bool,
+
+    /// Take the payload from this tree dump instead of validating.
+    from_tree: Option<PathBuf>,
+
+    /// Evaluate the validity of objects at this time.
+    validation_time: Option<Time>,
 }
 
 /// What route(s) should we validate, please?
@@ -633,6 +793,9 @@ enum ValidateWhat {
 
     /// Validate the routes provided on stdin.
     Stdin,
+
+    /// Verify the given AS path using ASPA.
+    AsPath(Vec<Asn>, validity::AspaDirection),
 }
 
 /// The command line arguments for the validate sub-command.
@@ -657,6 +820,19 @@ struct ValidateArgs {
     )]
     input: Option<PathBuf>,
 
+    /// AS path of the announcement to verify using ASPA
+    #[arg(
+        long, value_name = "PATH",
+        conflicts_with_all = &["prefix", "asn", "input"]
+    )]
+    as_path: Option<String>,
+
+    /// Direction the announcement was received from
+    #[arg(
+        long, value_name = "DIRECTION", value_enum, requires = "as_path"
+    )]
+    direction: Option<validity::AspaDirection>,
+
     /// Write output to a file
     #[arg(short, long, value_name = "PATH", default_value = "-")]
     output: PathBuf,
@@ -668,6 +844,17 @@ struct ValidateArgs {
     /// Return an error status on incomplete update
     #[arg(long)]
     complete: bool,
+
+    /// Take the payload from a tree dump instead of validating
+    #[arg(
+        long, value_name = "PATH",
+        conflicts_with_all = &["noupdate", "complete"]
+    )]
+    from_tree: Option<PathBuf>,
+
+    /// Evaluate validity at this time using only the local cache
+    #[arg(long, value_name = "TIME", conflicts_with = "from_tree")]
+    validation_time: Option<Time>,
 }
 
 impl Validate {
@@ -687,7 +874,22 @@ impl Validate {
         let args = ValidateArgs::from_arg_matches(matches).unwrap();
 
         Ok(Validate {
-            what: if let Some(path) = args.input {
+            what: if let Some(path) = args.as_path {
+                let path = match validity::parse_as_path(&path) {
+                    Ok(path) => path,
+                    Err(_) => {
+                        error!("Invalid AS path '{}'", path);
+                        return Err(Failed)
+                    }
+                };
+                ValidateWhat::AsPath(
+                    path,
+                    args.direction.unwrap_or(
+                        validity::AspaDirection::Upstream
+                    )
+                )
+            }
+            else if let Some(path) = args.input {
                 if path == Path::new("-") {
                     ValidateWhat::Stdin
                 }
@@ -724,12 +926,30 @@ impl Validate {
             },
             noupdate: args.noupdate,
             complete: args.complete,
+            from_tree: args.from_tree,
+            validation_time: args.validation_time,
         })
     }
 
 
     /// Outputs whether the given route announcement is valid.
     fn run(self, process: Process) -> Result<(), ExitError> {
+        if let ValidateWhat::AsPath(ref path, direction) = self.what {
+            let snapshot = self.get_snapshot(process)?;
+            let result = validity::AspaPathValidity::new(
+                path.clone(), direction, &snapshot
+            );
+            return write_output(
+                self.output.as_deref(), "result", |mut target| {
+                    if self.json {
+                        result.write_json(&snapshot, &mut target)
+                    }
+                    else {
+                        result.write_plain(&mut target)
+                    }
+                }
+            )
+        }
         let requests = self.read_requests()?;
         let snapshot = self.get_snapshot(process)?;
         self.output_validity(requests, snapshot)
@@ -740,6 +960,7 @@ impl Validate {
             ValidateWhat::Single(prefix, asn) => {
                 Ok(validity::RequestList::single(prefix, asn))
             }
+            ValidateWhat::AsPath(..) => Ok(Default::default()),
             ValidateWhat::File(ref path) => {
                 let mut file = match fs::File::open(path) {
                     Ok(file) => file,
@@ -800,12 +1021,27 @@ impl Validate {
     fn get_snapshot(
         &self, process: Process
     ) -> Result<PayloadSnapshot, ExitError> {
-        let mut engine = Engine::new(process.config(), !self.noupdate)?;
-        engine.ignite()?;
-        process.switch_logging(false, false)?;
-        let (report, mut metrics) = ValidationReport::process(
-            &engine, process.config(),
-        )?;
+        let (report, mut metrics) = match self.from_tree {
+            Some(ref path) => {
+                let engine = Engine::new(process.config(), false)?;
+                process.switch_logging(false, false)?;
+                ValidationReport::from_tree(
+                    &DBDump::load(path)?, &engine, process.config()
+                )
+            }
+            None => {
+                let mut engine = Engine::new(
+                    process.config(),
+                    !self.noupdate && self.validation_time.is_none()
+                )?;
+                if let Some(time) = self.validation_time {
+                    engine.set_validation_time(time);
+                }
+                engine.ignite()?;
+                process.switch_logging(false, false)?;
+                ValidationReport::process(&engine, process.config())?
+            }
+        };
         let snapshot = report.into_snapshot(
             &LocalExceptions::load(process.config(), false)?,
             &mut metrics,
@@ -825,46 +1061,123 @@ impl Validate {
         snapshot: PayloadSnapshot
     ) -> Result<(), ExitError> {
         let result = requests.validity(&snapshot);
-        match self.output.as_ref() {
-            Some(path) => {
-                let mut file = match fs::File::create(path) {
-                    Ok(file) => file,
-                    Err(err) => {
-                        error!(
-                            "Failed to open output file '{}': {}",
-                            path.display(), err
-                        );
-                        return Err(ExitError::Generic)
-                    }
-                };
-                let res = if self.json {
-                    result.write_json(&mut file)
-                }
-                else {
-                    result.write_plain(&mut file)
-                };
-                res.map_err(|err| {
-                    error!(
-                        "Failed to write to output file '{}': {}",
-                        path.display(), err
-                    );
-                    ExitError::Generic
-                })
+        write_output(self.output.as_deref(), "result", |mut target| {
+            if self.json {
+                result.write_json(&mut target)
             }
-            None => {
-                let stdout = io::stdout();
-                let mut stdout = stdout.lock();
-                let res = if self.json {
-                    result.write_json(&mut stdout)
-                }
-                else {
-                    result.write_plain(&mut stdout)
-                };
-                res.map_err(|err| {
-                    error!("Failed to write output: {}", err);
-                    ExitError::Generic
-                })
+            else {
+                result.write_plain(&mut target)
+            }
+        })
+    }
+}
+
+
+//------------ Contacts ------------------------------------------------------
+
+/// Show the contacts responsible for resources.
+pub struct Contacts {
+    /// The resources to show contacts for.
+    query: ContactQuery,
+
+    /// The destination to write the contacts to.
+    ///
+    /// If this is some path, then we write the contacts into that file.
+    /// Otherwise we just dump them to stdout.
+    path: Option<PathBuf>,
+
+    /// The desired output format.
+    format: ContactsFormat,
+
+    /// Don’t update the repository.
+    noupdate: bool,
+
+    /// Return an error on incomplete update.
+    complete: bool,
+}
+
+/// The command line arguments for the contacts sub-command.
+#[derive(Clone, Debug, Parser)]
+struct ContactsArgs {
+    /// Show contacts of CAs holding this prefix
+    #[arg(short, long)]
+    prefix: Option<Prefix>,
+
+    /// Show contacts of CAs holding this AS number
+    #[arg(short, long)]
+    asn: Option<Asn>,
+
+    /// The destination of the output
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
+    output: PathBuf,
+
+    /// The format of the output
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = ContactsFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Don't update the local cache
+    #[arg(short, long)]
+    noupdate: bool,
+
+    /// Return an error status on incomplete update
+    #[arg(long)]
+    complete: bool,
+}
+
+impl Contacts {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            ContactsArgs::augment_args(
+                clap::Command::new("contacts")
+                    .about("Shows the contacts responsible for resources")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = ContactsArgs::from_arg_matches(matches).unwrap();
+        Ok(Contacts {
+            query: ContactQuery::new(args.prefix, args.asn),
+            path: if args.output == Path::new("-") {
+                None
             }
+            else {
+                Some(args.output)
+            },
+            format: ContactsFormat::from_str(&args.format)?,
+            noupdate: args.noupdate,
+            complete: args.complete,
+        })
+    }
+
+    /// Performs a validation run and writes the selected contacts.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let mut engine = Engine::new(process.config(), !self.noupdate)?;
+        engine.ignite()?;
+        process.switch_logging(false, false)?;
+        let (report, mut metrics) = ValidationReport::process(
+            &engine, process.config()
+        )?;
+        let snapshot = report.into_snapshot(
+            &LocalExceptions::load(process.config(), false)?,
+            &mut metrics,
+        );
+        let contacts = ContactList::new(&snapshot, &self.query);
+        write_output(
+            self.path.as_deref(), "contacts",
+            |mut out| contacts.write(self.format, &mut out)
+        )?;
+        if self.complete && !metrics.rsync_complete() {
+            Err(ExitError::IncompleteUpdate)
+        }
+        else {
+            Ok(())
         }
     }
 }
@@ -1001,134 +1314,1058 @@ impl ValidateDocument {
 }
 
 
-//------------ Update --------------------------------------------------------
-
+//------------ ValidateChecklist ---------------------------------------------
 
-/// Update the local repository.
+/// Validates documents through an RPKI Signed Checklist.
 ///
-/// This will also do a validation run in order to discover possible new
-/// publication points.
+/// Performs a validation run in order to find the necessary certificates.
 #[derive(Clone, Debug, Parser)]
-pub struct Update {
-    /// Return an error on incomplete update.
+pub struct ValidateChecklist {
+    /// Path to the signed checklist.
+    #[arg(long, value_name = "PATH")]
+    signature: PathBuf,
+
+    /// Paths to the documents listed on the checklist.
+    #[arg(value_name = "DOCUMENT", required = true)]
+    documents: Vec<PathBuf>,
+
+    /// Don’t update the repository.
     #[arg(short, long)]
-    complete: bool,
+    noupdate: bool,
 }
 
-impl Update {
+impl ValidateChecklist {
     /// Adds the command configuration to a clap app.
     pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
         app.subcommand(
-            Update::augment_args(
-                clap::Command::new("update")
-                    .about("Updates the local RPKI repository")
-                    .after_help(AFTER_HELP)
+            ValidateChecklist::augment_args(
+                clap::Command::new("rsc")
+                .about("Validates documents through an RPKI Signed Checklist")
+                .after_help(AFTER_HELP)
             )
         )
     }
 
     /// Creates a command from clap matches.
-    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
-        Ok(<Update as FromArgMatches>::from_arg_matches(matches).unwrap())
+    pub fn from_arg_matches(
+        matches: &ArgMatches,
+    ) -> Result<Self, Failed> {
+        Ok(
+            <ValidateChecklist as FromArgMatches>::from_arg_matches(
+                matches
+            ).unwrap()
+        )
     }
 
-    /// Updates the repository.
+    /// Tries to validate the documents through the signed checklist.
     ///
-    /// This runs both an update of the already known publication points but
-    /// also does validation in order to discover new points.
-    ///
-    /// Which turns out is just a shortcut for `vrps` with no output.
+    /// Returns successfully if all documents are on the checklist and the
+    /// checklist is valid or with an appropriate error otherwise.
     fn run(self, process: Process) -> Result<(), ExitError> {
-        let mut engine = Engine::new(process.config(), true)?;
-        engine.ignite()?;
+        let mut validation = Engine::new(process.config(), !self.noupdate)?;
+        validation.ignite()?;
         process.switch_logging(false, false)?;
-        let (_, metrics) = ValidationReport::process(
-            &engine, process.config(),
-        )?;
-        if self.complete && !metrics.rsync_complete() {
-            Err(ExitError::IncompleteUpdate)
-        }
-        else {
-           Ok(())
-        }
-    }
-}
-
 
-//------------ Config --------------------------------------------------------
-
-
-/// Shows the current configuration.
-pub struct PrintConfig;
+        // Load and decode the signed checklist.
+        let data = match fs::read(&self.signature) {
+            Ok(data) => Bytes::from(data),
+            Err(err) => {
+                error!(
+                    "Failed to read signed checklist '{}': {}",
+                    self.signature.display(), err
+                );
+                return Err(ExitError::Generic)
+            }
+        };
+        let rsc = match rsc::SignedChecklist::decode(
+            data, process.config().strict
+        ) {
+            Ok(rsc) => rsc,
+            Err(err) => {
+                error!(
+                    "Failed to decode signed checklist '{}': {}",
+                    self.signature.display(), err
+                );
+                return Err(ExitError::Invalid)
+            }
+        };
 
-impl PrintConfig {
-    /// Adds the command configuration to a clap app.
+        // Check that all documents are on the list.
+        for document in &self.documents {
+            match rsc.content().check_file(document) {
+                Ok(true) => { }
+                Ok(false) => {
+                    error!(
+                        "Document '{}' is not on the signed checklist.",
+                        document.display()
+                    );
+                    return Err(ExitError::Invalid)
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to read document '{}': {}",
+                        document.display(), err
+                    );
+                    return Err(ExitError::Generic)
+                }
+            }
+        }
+
+        let rsc_validation = rsc::ValidationReport::new(
+            &rsc, &validation, process.config()
+        );
+        rsc_validation.process(&validation)?;
+        if rsc_validation.finalize().is_err() {
+            error!("Signed checklist did not validate.");
+            return Err(ExitError::Invalid)
+        }
+
+        let content = rsc.content();
+        for block in content.as_resources().iter() {
+            println!("{}", block);
+        }
+        for block in content.v4_resources().iter() {
+            println!("{}", block.display_v4());
+        }
+        for block in content.v6_resources().iter() {
+            println!("{}", block.display_v6());
+        }
+        Ok(())
+    }
+}
+
+
+//------------ Update --------------------------------------------------------
+
+
+/// Update the local repository.
+///
+/// This will also do a validation run in order to discover possible new
+/// publication points.
+#[derive(Clone, Debug, Parser)]
+pub struct Update {
+    /// Return an error on incomplete update.
+    #[arg(short, long)]
+    complete: bool,
+}
+
+impl Update {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            Update::augment_args(
+                clap::Command::new("update")
+                    .about("Updates the local RPKI repository")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<Update as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Updates the repository.
+    ///
+    /// This runs both an update of the already known publication points but
+    /// also does validation in order to discover new points.
+    ///
+    /// Which turns out is just a shortcut for `vrps` with no output.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let mut engine = Engine::new(process.config(), true)?;
+        engine.ignite()?;
+        process.switch_logging(false, false)?;
+        let (_, metrics) = ValidationReport::process(
+            &engine, process.config(),
+        )?;
+        if self.complete && !metrics.rsync_complete() {
+            Err(ExitError::IncompleteUpdate)
+        }
+        else {
+           Ok(())
+        }
+    }
+}
+
+
+//------------ Config --------------------------------------------------------
+
+
+/// Shows the current configuration.
+pub struct PrintConfig;
+
+impl PrintConfig {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        // config
+        app.subcommand(Config::server_args(clap::Command::new("config")
+            .about("Prints the current config and exits")
+            .after_help(AFTER_HELP)
+        ))
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(
+        matches: &ArgMatches,
+        cur_dir: &Path,
+        config: &mut Config,
+    ) -> Result<Self, Failed> {
+        config.apply_server_arg_matches(matches, cur_dir)?;
+        Ok(PrintConfig)
+    }
+
+    /// Prints the current configuration to stdout and exits.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        println!("{}", process.config());
+        Ok(())
+    }
+}
+
+
+//------------ Dump ----------------------------------------------------------
+
+/// Dumps the database content.
+#[derive(Clone, Debug, Parser)]
+pub struct Dump {
+    /// Output directory.
+    #[arg(short, long, value_name = "PATH")]
+    output: PathBuf,
+}
+
+impl Dump {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        // config
+        app.subcommand(
+            Dump::augment_args(
+                clap::Command::new("dump")
+                    .about("Writes the cache content to disk")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(
+        matches: &ArgMatches,
+        cur_dir: &Path,
+    ) -> Result<Self, Failed> {
+        let mut res =
+            <Dump as FromArgMatches>::from_arg_matches(matches).unwrap();
+        res.output = cur_dir.join(res.output);
+        Ok(res)
+    }
+
+    /// Prints the current configuration to stdout and exits.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let engine = Engine::new(process.config(), true)?;
+        process.switch_logging(false, false)?;
+        engine.dump(&self.output)?;
+        Ok(())
+    }
+}
+
+
+//------------ AcceptTa ------------------------------------------------------
+
+/// Accepts changed trust anchor certificates held back by pinning.
+#[derive(Clone, Debug, Parser)]
+pub struct AcceptTa {
+    /// Only accept certificates for the TAL with this name
+    #[arg(long, value_name = "NAME")]
+    tal: Option<String>,
+}
+
+impl AcceptTa {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            AcceptTa::augment_args(
+                clap::Command::new("accept-ta")
+                    .about("Accepts changed trust anchor certificates")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<AcceptTa as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Accepts the pending certificates and prints their URIs.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let engine = Engine::new(process.config(), false)?;
+        process.switch_logging(false, false)?;
+        let accepted = engine.accept_ta_certs(self.tal.as_deref())?;
+        if accepted.is_empty() {
+            println!("No changed trust anchor certificates pending.");
+        }
+        for uri in accepted {
+            println!("{}", uri);
+        }
+        Ok(())
+    }
+}
+
+
+//------------ Tree ----------------------------------------------------------
+
+/// Writes the tree of validated CA certificates and ROAs.
+pub struct Tree {
+    /// The destination to write the tree to.
+    ///
+    /// If this is some path, then we write the tree into that file.
+    /// Otherwise we just dump it to stdout.
+    path: Option<PathBuf>,
+
+    /// The desired output format.
+    format: TreeFormat,
+
+    /// Don’t update the repository.
+    noupdate: bool,
+
+    /// Return an error on incomplete update.
+    complete: bool,
+
+    /// Include rejected objects and publication points.
+    rejected: bool,
+
+    /// Evaluate the validity of objects at this time.
+    validation_time: Option<Time>,
+}
+
+/// The command line arguments for the tree sub-command.
+#[derive(Clone, Debug, Parser)]
+struct TreeArgs {
+    /// The destination of the tree
+    #[arg(short, long, value_name = "PATH")]
+    output: PathBuf,
+
+    /// The format of the tree
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = TreeFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Don't update the local cache
+    #[arg(short, long)]
+    noupdate: bool,
+
+    /// Return an error status on incomplete update
+    #[arg(long)]
+    complete: bool,
+
+    /// Include rejected objects and publication points
+    #[arg(long)]
+    rejected: bool,
+
+    /// Evaluate validity at this time using only the local cache
+    #[arg(long, value_name = "TIME")]
+    validation_time: Option<Time>,
+}
+
+impl Tree {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            TreeArgs::augment_args(
+                clap::Command::new("tree")
+                    .about("Writes the tree of validated CAs and ROAs")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = TreeArgs::from_arg_matches(matches).unwrap();
+        Ok(Tree {
+            path: if args.output == Path::new("-") {
+                None
+            }
+            else {
+                Some(args.output)
+            },
+            format: TreeFormat::from_str(&args.format)?,
+            noupdate: args.noupdate,
+            complete: args.complete,
+            rejected: args.rejected,
+            validation_time: args.validation_time,
+        })
+    }
+
+    /// Performs a validation run and writes the resulting tree.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let mut engine = Engine::new(
+            process.config(),
+            !self.noupdate && self.validation_time.is_none()
+        )?;
+        if let Some(time) = self.validation_time {
+            engine.set_validation_time(time);
+        }
+        engine.ignite()?;
+        process.switch_logging(false, false)?;
+        let mut report = TreeReport::new();
+        if self.rejected {
+            report = report.with_rejected();
+        }
+        if self.format == TreeFormat::Ndjson {
+            match TreeStream::new() {
+                Ok(stream) => report = report.with_stream(stream),
+                Err(err) => {
+                    error!("Failed to create temporary file: {}", err);
+                    return Err(Failed.into())
+                }
+            }
+        }
+        let (report, metrics) = report.process(&engine)?;
+        write_output(
+            self.path.as_deref(), "tree",
+            |mut out| report.write(self.format, &mut out)
+        )?;
+        if self.complete && !metrics.rsync_complete() {
+            Err(ExitError::IncompleteUpdate)
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+
+//------------ TreeDiff ------------------------------------------------------
+
+/// Compare two tree dumps.
+pub struct TreeDiff {
+    /// The path to the older tree dump.
+    old: PathBuf,
+
+    /// The path to the newer tree dump.
+    new: PathBuf,
+
+    /// The destination to write the differences to.
+    ///
+    /// If this is some path, then we write the differences into that file.
+    /// Otherwise we just dump them to stdout.
+    path: Option<PathBuf>,
+
+    /// The desired output format.
+    format: TreeDiffFormat,
+}
+
+/// The command line arguments for the tree-diff sub-command.
+#[derive(Clone, Debug, Parser)]
+struct TreeDiffArgs {
+    /// The older tree dump
+    #[arg(value_name = "OLD")]
+    old: PathBuf,
+
+    /// The newer tree dump
+    #[arg(value_name = "NEW")]
+    new: PathBuf,
+
+    /// The destination of the differences
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
+    output: PathBuf,
+
+    /// The format of the differences
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = TreeDiffFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+}
+
+impl TreeDiff {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            TreeDiffArgs::augment_args(
+                clap::Command::new("tree-diff")
+                    .about("Shows the differences between two tree dumps")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = TreeDiffArgs::from_arg_matches(matches).unwrap();
+        Ok(TreeDiff {
+            old: args.old,
+            new: args.new,
+            path: if args.output == Path::new("-") {
+                None
+            }
+            else {
+                Some(args.output)
+            },
+            format: TreeDiffFormat::from_str(&args.format)?,
+        })
+    }
+
+    /// Loads both tree dumps and writes their differences.
+    fn run(self, _process: Process) -> Result<(), ExitError> {
+        let diff = dump::TreeDiff::new(
+            &DBDump::load(&self.old)?, &DBDump::load(&self.new)?
+        );
+        write_output(
+            self.path.as_deref(), "tree differences",
+            |mut out| diff.write(self.format, &mut out)
+        )
+    }
+}
+
+
+//------------ TreeAudit -----------------------------------------------------
+
+/// Audit the resources of a tree dump.
+pub struct TreeAudit {
+    /// The path to the tree dump.
+    tree: PathBuf,
+
+    /// The destination to write the audit to.
+    ///
+    /// If this is some path, then we write the audit into that file.
+    /// Otherwise we just dump it to stdout.
+    path: Option<PathBuf>,
+
+    /// The desired output format.
+    format: TreeAuditFormat,
+
+    /// Return an error status if problems were found.
+    strict: bool,
+}
+
+/// The command line arguments for the tree-audit sub-command.
+#[derive(Clone, Debug, Parser)]
+struct TreeAuditArgs {
+    /// The tree dump to audit
+    #[arg(value_name = "TREE")]
+    tree: PathBuf,
+
+    /// The destination of the audit
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
+    output: PathBuf,
+
+    /// The format of the audit
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = TreeAuditFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Return an error status if over-claims or orphans were found
+    #[arg(long)]
+    strict: bool,
+}
+
+impl TreeAudit {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            TreeAuditArgs::augment_args(
+                clap::Command::new("tree-audit")
+                    .about("Checks the resources of a tree dump")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = TreeAuditArgs::from_arg_matches(matches).unwrap();
+        Ok(TreeAudit {
+            tree: args.tree,
+            path: if args.output == Path::new("-") {
+                None
+            }
+            else {
+                Some(args.output)
+            },
+            format: TreeAuditFormat::from_str(&args.format)?,
+            strict: args.strict,
+        })
+    }
+
+    /// Loads the tree dump and writes the audit.
+    fn run(self, _process: Process) -> Result<(), ExitError> {
+        let audit = dump::TreeAudit::new(&DBDump::load(&self.tree)?);
+        write_output(
+            self.path.as_deref(), "tree audit",
+            |mut out| audit.write(self.format, &mut out)
+        )?;
+        if self.strict && audit.has_problems() {
+            Err(ExitError::Invalid)
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+
+//------------ TreeGraph -----------------------------------------------------
+
+/// Export the CA hierarchy of a tree dump as a graph.
+pub struct TreeGraph {
+    /// The path to the tree dump.
+    tree: PathBuf,
+
+    /// The destination to write the graph to.
+    ///
+    /// If this is some path, then we write the graph into that file.
+    /// Otherwise we just dump it to stdout.
+    path: Option<PathBuf>,
+
+    /// The desired output format.
+    format: TreeGraphFormat,
+
+    /// The options for creating the graph.
+    options: TreeGraphOptions,
+}
+
+/// The command line arguments for the tree-graph sub-command.
+#[derive(Clone, Debug, Parser)]
+struct TreeGraphArgs {
+    /// The tree dump to export
+    #[arg(value_name = "TREE")]
+    tree: PathBuf,
+
+    /// The destination of the graph
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
+    output: PathBuf,
+
+    /// The format of the graph
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = TreeGraphFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Show the number of ROAs of each CA instead of the ROAs
+    #[arg(long)]
+    collapse_roas: bool,
+
+    /// Only include the given number of CA levels
+    #[arg(long, value_name = "DEPTH")]
+    max_depth: Option<usize>,
+
+    /// Only include the subtree of the CA with the given key identifier
+    #[arg(long, value_name = "SKI")]
+    subtree: Option<String>,
+}
+
+impl TreeGraph {
+    /// Adds the command configuration to a clap app.
     pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
-        // config
-        app.subcommand(Config::server_args(clap::Command::new("config")
-            .about("Prints the current config and exits")
-            .after_help(AFTER_HELP)
-        ))
+        app.subcommand(
+            TreeGraphArgs::augment_args(
+                clap::Command::new("tree-graph")
+                    .about("Exports the CA hierarchy of a tree dump")
+                    .after_help(AFTER_HELP)
+            )
+        )
     }
 
     /// Creates a command from clap matches.
-    pub fn from_arg_matches(
-        matches: &ArgMatches,
-        cur_dir: &Path,
-        config: &mut Config,
-    ) -> Result<Self, Failed> {
-        config.apply_server_arg_matches(matches, cur_dir)?;
-        Ok(PrintConfig)
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = TreeGraphArgs::from_arg_matches(matches).unwrap();
+        let mut options = TreeGraphOptions::new();
+        if args.collapse_roas {
+            options = options.with_collapsed_roas();
+        }
+        if let Some(depth) = args.max_depth {
+            options = options.with_max_depth(depth);
+        }
+        if let Some(subtree) = args.subtree {
+            match KeyIdentifier::from_str(&subtree) {
+                Ok(id) => options = options.with_subtree(id),
+                Err(_) => {
+                    error!("Invalid key identifier '{}'", subtree);
+                    return Err(Failed)
+                }
+            }
+        }
+        Ok(TreeGraph {
+            tree: args.tree,
+            path: if args.output == Path::new("-") {
+                None
+            }
+            else {
+                Some(args.output)
+            },
+            format: TreeGraphFormat::from_str(&args.format)?,
+            options,
+        })
     }
 
-    /// Prints the current configuration to stdout and exits.
+    /// Loads the tree dump and writes the graph.
+    fn run(self, _process: Process) -> Result<(), ExitError> {
+        let graph = dump::TreeGraph::new(
+            &DBDump::load(&self.tree)?, &self.options
+        )?;
+        write_output(
+            self.path.as_deref(), "tree graph",
+            |mut out| graph.write(self.format, &mut out)
+        )
+    }
+}
+
+
+//------------ Provenance ----------------------------------------------------
+
+/// Show where VRPs come from.
+pub struct Provenance {
+    /// The VRPs to show.
+    selection: ProvenanceSelection,
+
+    /// The destination to write the provenance to.
+    ///
+    /// If this is some path, then we write the provenance into that file.
+    /// Otherwise we just dump it to stdout.
+    path: Option<PathBuf>,
+
+    /// The desired output format.
+    format: ProvenanceFormat,
+
+    /// Don’t update the repository.
+    noupdate: bool,
+
+    /// Return an error on incomplete update.
+    complete: bool,
+}
+
+/// The command line arguments for the provenance sub-command.
+#[derive(Clone, Debug, Parser)]
+struct ProvenanceArgs {
+    /// Show VRPs covering the given prefix
+    #[arg(
+        short, long, value_name = "PREFIX",
+        required_unless_present = "asn",
+    )]
+    prefix: Option<Prefix>,
+
+    /// Show VRPs for the given origin AS number
+    #[arg(short, long, value_name = "ASN")]
+    asn: Option<Asn>,
+
+    /// Also show VRPs for more specific prefixes
+    #[arg(short, long, requires = "prefix")]
+    more_specifics: bool,
+
+    /// The destination of the output
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
+    output: PathBuf,
+
+    /// The format of the output
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = ProvenanceFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Don't update the local cache
+    #[arg(short, long)]
+    noupdate: bool,
+
+    /// Return an error status on incomplete update
+    #[arg(long)]
+    complete: bool,
+}
+
+impl Provenance {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            ProvenanceArgs::augment_args(
+                clap::Command::new("provenance")
+                    .about("Shows the certificate chains of VRPs")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = ProvenanceArgs::from_arg_matches(matches).unwrap();
+        Ok(Provenance {
+            selection: ProvenanceSelection::new(
+                args.prefix, args.asn, args.more_specifics
+            ),
+            path: if args.output == Path::new("-") {
+                None
+            }
+            else {
+                Some(args.output)
+            },
+            format: ProvenanceFormat::from_str(&args.format)?,
+            noupdate: args.noupdate,
+            complete: args.complete,
+        })
+    }
+
+    /// Performs a validation run and writes the provenance of the VRPs.
     fn run(self, process: Process) -> Result<(), ExitError> {
-        println!("{}", process.config());
-        Ok(())
+        let mut engine = Engine::new(process.config(), !self.noupdate)?;
+        engine.ignite()?;
+        process.switch_logging(false, false)?;
+        let exceptions = LocalExceptions::load(process.config(), true)?;
+        let report = ValidationReport::new(process.config());
+        let tree = TreeReport::new();
+        let mut metrics = {
+            let mut run = engine.start((&report, &tree))?;
+            run.process()?;
+            run.cleanup()?;
+            run.done()
+        };
+        let snapshot = report.into_snapshot(&exceptions, &mut metrics);
+        let provenance = dump::Provenance::new(
+            &snapshot, &tree.into_dump(), &self.selection
+        );
+        write_output(
+            self.path.as_deref(), "provenance",
+            |mut out| provenance.write(self.format, &mut out)
+        )?;
+        if self.complete && !metrics.rsync_complete() {
+            Err(ExitError::IncompleteUpdate)
+        }
+        else {
+            Ok(())
+        }
     }
 }
 
 
-//------------ Dump ----------------------------------------------------------
+//------------ Expiring ------------------------------------------------------
 
-/// Dumps the database content.
+/// Show objects about to expire.
+pub struct Expiring {
+    /// How far into the future to look.
+    within: ExpiryWindow,
+
+    /// The destination to write the objects to.
+    ///
+    /// If this is some path, then we write the objects into that file.
+    /// Otherwise we just dump them to stdout.
+    path: Option<PathBuf>,
+
+    /// The desired output format.
+    format: ExpiringFormat,
+
+    /// Don’t update the repository.
+    noupdate: bool,
+
+    /// Return an error on incomplete update.
+    complete: bool,
+}
+
+/// The command line arguments for the expiring sub-command.
 #[derive(Clone, Debug, Parser)]
-pub struct Dump {
-    /// Output directory.
-    #[arg(short, long, value_name = "PATH")]
+struct ExpiringArgs {
+    /// Show objects expiring within this time, e.g., 48h or 7d
+    #[arg(
+        short, long, value_name = "TIME",
+        default_value = ExpiryWindow::DEFAULT_VALUE,
+    )]
+    within: String,
+
+    /// The destination of the output
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
     output: PathBuf,
+
+    /// The format of the output
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = ExpiringFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Don't update the local cache
+    #[arg(short, long)]
+    noupdate: bool,
+
+    /// Return an error status on incomplete update
+    #[arg(long)]
+    complete: bool,
 }
 
-impl Dump {
+impl Expiring {
     /// Adds the command configuration to a clap app.
     pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
-        // config
         app.subcommand(
-            Dump::augment_args(
-                clap::Command::new("dump")
-                    .about("Writes the cache content to disk")
+            ExpiringArgs::augment_args(
+                clap::Command::new("expiring")
+                    .about("Shows objects that will expire soon")
                     .after_help(AFTER_HELP)
             )
         )
     }
 
     /// Creates a command from clap matches.
-    pub fn from_arg_matches(
-        matches: &ArgMatches,
-        cur_dir: &Path,
-    ) -> Result<Self, Failed> {
-        let mut res =
-            <Dump as FromArgMatches>::from_arg_matches(matches).unwrap();
-        res.output = cur_dir.join(res.output);
-        Ok(res)
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = ExpiringArgs::from_arg_matches(matches).unwrap();
+        Ok(Expiring {
+            within: match ExpiryWindow::from_str(&args.within) {
+                Ok(within) => within,
+                Err(_) => {
+                    error!("Invalid time window '{}'.", args.within);
+                    return Err(Failed)
+                }
+            },
+            path: if args.output == Path::new("-") {
+                None
+            }
+            else {
+                Some(args.output)
+            },
+            format: ExpiringFormat::from_str(&args.format)?,
+            noupdate: args.noupdate,
+            complete: args.complete,
+        })
     }
 
-    /// Prints the current configuration to stdout and exits.
+    /// Performs a validation run and writes the objects about to expire.
     fn run(self, process: Process) -> Result<(), ExitError> {
-        let engine = Engine::new(process.config(), true)?;
+        let mut engine = Engine::new(process.config(), !self.noupdate)?;
+        engine.ignite()?;
         process.switch_logging(false, false)?;
-        engine.dump(&self.output)?;
-        Ok(())
+        let exceptions = LocalExceptions::load(process.config(), true)?;
+        let report = ValidationReport::new(process.config());
+        let tree = TreeReport::new();
+        let mut metrics = {
+            let mut run = engine.start((&report, &tree))?;
+            run.process()?;
+            run.cleanup()?;
+            run.done()
+        };
+        let snapshot = report.into_snapshot(&exceptions, &mut metrics);
+        let expiring = dump::Expiring::new(
+            &snapshot, &tree.into_dump(), Time::now(), self.within
+        );
+        write_output(
+            self.path.as_deref(), "expiring objects",
+            |mut out| expiring.write(self.format, &mut out)
+        )?;
+        if self.complete && !metrics.rsync_complete() {
+            Err(ExitError::IncompleteUpdate)
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+
+//------------ Inspect -------------------------------------------------------
+
+/// Show the content of a single RPKI object.
+pub struct Inspect {
+    /// The path to the object.
+    file: PathBuf,
+
+    /// Validate the object against the local repository.
+    validate: bool,
+
+    /// The desired output format.
+    format: inspect::InspectFormat,
+
+    /// Don’t update the repository.
+    noupdate: bool,
+}
+
+/// The command line arguments for the inspect sub-command.
+#[derive(Clone, Debug, Parser)]
+struct InspectArgs {
+    /// The object file to inspect
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    /// Validate the object against the local repository
+    #[arg(long)]
+    validate: bool,
+
+    /// The format of the output
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = inspect::InspectFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Don't update the local cache before validating
+    #[arg(short, long, requires = "validate")]
+    noupdate: bool,
+}
+
+impl Inspect {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            InspectArgs::augment_args(
+                clap::Command::new("inspect")
+                    .about("Shows the content of an RPKI object")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        let args = InspectArgs::from_arg_matches(matches).unwrap();
+        Ok(Inspect {
+            file: args.file,
+            validate: args.validate,
+            format: inspect::InspectFormat::from_str(&args.format)?,
+            noupdate: args.noupdate,
+        })
+    }
+
+    /// Decodes the object, validates it if requested, and shows it.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let data = match fs::read(&self.file) {
+            Ok(data) => Bytes::from(data),
+            Err(err) => {
+                error!(
+                    "Failed to read object '{}': {}",
+                    self.file.display(), err
+                );
+                return Err(ExitError::Generic)
+            }
+        };
+        let object = inspect::RpkiObject::decode(
+            &self.file.to_string_lossy(), data, process.config().strict
+        ).map_err(|_| ExitError::Invalid)?;
+        let mut info = inspect::ObjectInfo::new(&object);
+
+        let valid = if self.validate {
+            let mut engine = Engine::new(process.config(), !self.noupdate)?;
+            engine.ignite()?;
+            process.switch_logging(false, false)?;
+            let validation = inspect::ObjectValidation::new(
+                &object, &engine, process.config()
+            );
+            validation.process(&engine)?;
+            let result = validation.finalize();
+            let valid = result.is_valid();
+            info.set_validation(result);
+            valid
+        }
+        else {
+            true
+        };
+
+        write_output(
+            None, "object", |mut out| info.write(self.format, &mut out)
+        )?;
+        if valid {
+            Ok(())
+        }
+        else {
+            Err(ExitError::Invalid)
+        }
     }
 }
 
@@ -1255,6 +2492,50 @@ impl Man {
 }
 
 
+//------------ write_output --------------------------------------------------
+
+/// Writes the output produced by `op` to a file or stdout.
+///
+/// If `path` is `None`, the output goes to stdout. Failures are logged
+/// using `what` to describe the output, except for a broken pipe on
+/// stdout which happens when the user pipes the output into something
+/// like `head`.
+fn write_output(
+    path: Option<&Path>,
+    what: &str,
+    op: impl FnOnce(&mut dyn io::Write) -> Result<(), io::Error>,
+) -> Result<(), ExitError> {
+    let res = match path {
+        Some(path) => {
+            let mut file = match fs::File::create(path) {
+                Ok(file) => io::BufWriter::new(file),
+                Err(err) => {
+                    error!(
+                        "Failed to open output file '{}': {}",
+                        path.display(), err
+                    );
+                    return Err(ExitError::Generic)
+                }
+            };
+            op(&mut file).and_then(|_| file.flush())
+        }
+        None => {
+            let out = io::stdout();
+            let mut out = out.lock();
+            op(&mut out)
+        }
+    };
+    if let Err(err) = res {
+        // Surpress an error message for broken pipe on stdout.
+        if path.is_some() || err.kind() != io::ErrorKind::BrokenPipe {
+            error!("Failed to output {}: {}", what, err);
+        }
+        return Err(ExitError::Generic)
+    }
+    Ok(())
+}
+
+
 //------------ SignalListener --------------------------------------------------
 
 #[allow(dead_code)]
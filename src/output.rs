@@ -1020,14 +1020,17 @@ impl ExtendedJson {
                     \"validity\": {{ \"notBefore\": \"{}\", \
                     \"notAfter\": \"{}\" }}, \
                     \"chainValidity\": {{ \"notBefore\": \"{}\", \
-                    \"notAfter\": \"{}\" }} \
-                    }}",
+                    \"notAfter\": \"{}\" }}",
                     json_str(roa.tal.name()),
                     format_iso_date(roa.roa_validity.not_before().into()),
                     format_iso_date(roa.roa_validity.not_after().into()),
                     format_iso_date(roa.chain_validity.not_before().into()),
                     format_iso_date(roa.chain_validity.not_after().into()),
                 )?;
+                if roa.constrained {
+                    write!(target, ", \"constrained\": true")?;
+                }
+                write!(target, " }}")?;
             }
             if let Some(exc) = item.exception_info() {
                 if !first {
@@ -0,0 +1,319 @@
+//! Contact information published via Ghostbuster records.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+//!
+//! Ghostbuster records as defined in RFC 6493 contain a vCard with contact
+//! information for the operator of a CA. We keep a small subset of the
+//! vCard together with the resources of the CA that published the record
+//! in a [`Contact`].
+
+use std::sync::Arc;
+use rpki::uri;
+use rpki::repository::resources::{AsBlocks, IpBlocks, Prefix};
+use rpki::repository::tal::{TalInfo, TalUri};
+use rpki::repository::x509::Validity;
+use rpki::resources::Asn;
+use serde::Serialize;
+use crate::engine::CaCert;
+
+
+//------------ Contact -------------------------------------------------------
+
+/// The contact information of a CA.
+#[derive(Clone, Debug)]
+pub struct Contact {
+    /// The CA that published the Ghostbuster record.
+    ca: Arc<ContactCa>,
+
+    /// The rsync URI of the Ghostbuster record.
+    uri: uri::Rsync,
+
+    /// The validity of the record’s validation chain.
+    validity: Validity,
+
+    /// The content of the vCard.
+    vcard: VCard,
+}
+
+impl Contact {
+    /// Creates a new contact from its parts.
+    pub(super) fn new(
+        ca: Arc<ContactCa>, uri: uri::Rsync, validity: Validity, vcard: VCard,
+    ) -> Self {
+        Contact { ca, uri, validity, vcard }
+    }
+
+    /// Returns the name of the TAL the contact was derived from.
+    pub fn tal_name(&self) -> &str {
+        self.ca.tal.name()
+    }
+
+    /// Returns the URI of the CA certificate.
+    pub fn ca_uri(&self) -> &TalUri {
+        &self.ca.uri
+    }
+
+    /// Returns the number of certificates between the CA and its TA.
+    ///
+    /// This is zero for contacts published by trust anchor CAs.
+    pub fn chain_len(&self) -> usize {
+        self.ca.chain_len
+    }
+
+    /// Returns the IPv4 resources of the CA.
+    pub fn v4_resources(&self) -> &IpBlocks {
+        &self.ca.v4
+    }
+
+    /// Returns the IPv6 resources of the CA.
+    pub fn v6_resources(&self) -> &IpBlocks {
+        &self.ca.v6
+    }
+
+    /// Returns the AS resources of the CA.
+    pub fn as_resources(&self) -> &AsBlocks {
+        &self.ca.asns
+    }
+
+    /// Returns the rsync URI of the Ghostbuster record.
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    /// Returns the validity of the record’s validation chain.
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+
+    /// Returns the vCard of the contact.
+    pub fn vcard(&self) -> &VCard {
+        &self.vcard
+    }
+
+    /// Returns whether the CA holds the given prefix.
+    pub fn holds_prefix(&self, prefix: rpki::resources::Prefix) -> bool {
+        let raw = Prefix::new(prefix.addr(), prefix.len());
+        if prefix.is_v4() {
+            self.ca.v4.contains_block(raw)
+        }
+        else {
+            self.ca.v6.contains_block(raw)
+        }
+    }
+
+    /// Returns whether the CA holds the given AS number.
+    pub fn holds_asn(&self, asn: Asn) -> bool {
+        self.ca.asns.contains_asn(asn)
+    }
+}
+
+
+//------------ ContactCa -----------------------------------------------------
+
+/// Information about a CA that publishes contact information.
+#[derive(Clone, Debug)]
+pub(super) struct ContactCa {
+    /// The TAL the CA was derived from.
+    tal: Arc<TalInfo>,
+
+    /// The URI of the CA certificate.
+    uri: TalUri,
+
+    /// The length of the chain from the trust anchor.
+    chain_len: usize,
+
+    /// The IPv4 resources of the CA.
+    v4: IpBlocks,
+
+    /// The IPv6 resources of the CA.
+    v6: IpBlocks,
+
+    /// The AS resources of the CA.
+    asns: AsBlocks,
+}
+
+impl ContactCa {
+    /// Creates the information from a CA certificate.
+    pub fn new(cert: &CaCert) -> Self {
+        ContactCa {
+            tal: cert.cert().tal().clone(),
+            uri: cert.uri().clone(),
+            chain_len: cert.chain_len(),
+            v4: cert.cert().v4_resources().clone(),
+            v6: cert.cert().v6_resources().clone(),
+            asns: cert.cert().as_resources().clone(),
+        }
+    }
+}
+
+
+//------------ VCard ---------------------------------------------------------
+
+/// The relevant content of the vCard of a Ghostbuster record.
+///
+/// RFC 6493 limits the vCard to a profile with only a handful of
+/// properties. Of these, we keep the formatted name, the organization, and
+/// any addresses, phone numbers, and email addresses.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VCard {
+    /// The formatted name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_name: Option<String>,
+
+    /// The organization.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+
+    /// The postal addresses.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<String>,
+
+    /// The phone numbers.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub phones: Vec<String>,
+
+    /// The email addresses.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<String>,
+}
+
+impl VCard {
+    /// Parses the vCard from the content of a Ghostbuster record.
+    ///
+    /// Returns `None` if the data is not a vCard at all. Unknown properties
+    /// are ignored.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = std::str::from_utf8(data).ok()?;
+
+        // Unfold the lines first: a line starting with white space
+        // continues the previous line.
+        let mut lines: Vec<String> = Vec::new();
+        for line in data.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if let Some(cont) = line.strip_prefix([' ', '\t']) {
+                lines.last_mut()?.push_str(cont);
+            }
+            else if !line.is_empty() {
+                lines.push(line.into());
+            }
+        }
+
+        let mut lines = lines.iter();
+        if !lines.next()?.eq_ignore_ascii_case("BEGIN:VCARD") {
+            return None
+        }
+        let mut res = VCard::default();
+        for line in lines {
+            let (name, value) = split_property(line)?;
+            match name.to_ascii_uppercase().as_str() {
+                "END" if value.eq_ignore_ascii_case("VCARD") => {
+                    return Some(res)
+                }
+                "FN" => res.full_name = Some(unescape(value)),
+                "ORG" => res.org = Some(join_components(value)),
+                "ADR" => res.addresses.push(join_components(value)),
+                "TEL" => res.phones.push(unescape(value)),
+                "EMAIL" => res.emails.push(unescape(value)),
+                _ => { }
+            }
+        }
+
+        // We never saw END:VCARD.
+        None
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Splits a content line into the property name and the value.
+///
+/// Drops the group and any parameters from the name. Returns `None` if
+/// there is no value.
+fn split_property(line: &str) -> Option<(&str, &str)> {
+    // The colon may appear in quoted parameter values.
+    let mut quoted = false;
+    let colon = line.char_indices().find(|&(_, ch)| {
+        if ch == '"' {
+            quoted = !quoted;
+        }
+        ch == ':' && !quoted
+    })?.0;
+    let (name, value) = (&line[..colon], &line[colon + 1..]);
+    let name = name.split(';').next().unwrap_or(name);
+    let name = name.rsplit('.').next().unwrap_or(name);
+    Some((name, value))
+}
+
+/// Unescapes a text value.
+fn unescape(value: &str) -> String {
+    split_components(value).join(";")
+}
+
+/// Unescapes a structured value and joins its non-empty components.
+fn join_components(value: &str) -> String {
+    split_components(value).into_iter().filter(|item| {
+        !item.is_empty()
+    }).collect::<Vec<_>>().join(", ")
+}
+
+/// Splits a structured value into its unescaped components.
+fn split_components(value: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                match chars.next() {
+                    Some('n') | Some('N') => current.push('\n'),
+                    Some(ch) => current.push(ch),
+                    None => current.push('\\'),
+                }
+            }
+            ';' => res.push(std::mem::take(&mut current)),
+            ch => current.push(ch),
+        }
+    }
+    res.push(current);
+    res
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_vcard() {
+        let vcard = VCard::parse(
+            b"BEGIN:VCARD\r\n\
+              VERSION:4.0\r\n\
+              FN:Human's Name\r\n\
+              ORG:Organizational Entity\r\n\
+              ADR;TYPE=WORK:;;42 Twisty Passage;Deep Cavern;WA;98666;\r\n \
+              U.S.A.\r\n\
+              TEL;TYPE=VOICE,TEXT,WORK;VALUE=uri:tel:+1-666-555-1212\r\n\
+              TEL;TYPE=FAX,WORK;VALUE=uri:tel:+1-666-555-1213\r\n\
+              EMAIL:human@example.com\r\n\
+              END:VCARD\r\n"
+        ).unwrap();
+        assert_eq!(vcard.full_name.as_deref(), Some("Human's Name"));
+        assert_eq!(vcard.org.as_deref(), Some("Organizational Entity"));
+        assert_eq!(
+            vcard.addresses,
+            ["42 Twisty Passage, Deep Cavern, WA, 98666, U.S.A."]
+        );
+        assert_eq!(
+            vcard.phones, ["tel:+1-666-555-1212", "tel:+1-666-555-1213"]
+        );
+        assert_eq!(vcard.emails, ["human@example.com"]);
+
+        assert_eq!(unescape("a\\, b\\;c\\nd"), "a, b;c\nd");
+        assert!(VCard::parse(b"FN:Foo\r\n").is_none());
+        assert!(VCard::parse(b"BEGIN:VCARD\r\nFN:Foo\r\n").is_none());
+    }
+}
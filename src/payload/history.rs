@@ -12,6 +12,7 @@ use log::info;
 use rpki::rtr::{Serial, State, Timing};
 use rpki::rtr::server::PayloadSource;
 use crate::config::{Config, FilterPolicy};
+use crate::dump::{DBDump, TreeDelta, TreeDiff};
 use crate::metrics::Metrics;
 use crate::slurm::LocalExceptions;
 use super::delta::{DeltaArcIter, PayloadDelta};
@@ -96,6 +97,53 @@ impl SharedHistory {
         }
     }
 
+    /// Updates the tree of validated objects.
+    ///
+    /// Always replaces the current tree since the diff ignores some of its
+    /// parts such as manifests and CRLs. If the tree differs from the
+    /// current one, increases the tree serial and, if there was a previous
+    /// tree, adds the changes to the history and returns them.
+    pub fn update_tree(&self, tree: DBDump) -> Option<Arc<TreeDelta>> {
+        let (current, serial) = {
+            let read = self.read();
+            (read.tree(), read.tree_serial())
+        };
+        let (changed, delta) = match current {
+            Some(current) => {
+                let diff = TreeDiff::new(&current, &tree);
+                if diff.is_empty() {
+                    (false, None)
+                }
+                else {
+                    (
+                        true,
+                        Some(Arc::new(TreeDelta::new(serial + 1, diff)))
+                    )
+                }
+            }
+            None => (true, None)
+        };
+        let mut history = self.write();
+        history.tree = Some(tree.into());
+        if !changed {
+            return None
+        }
+        history.tree_serial = serial + 1;
+        history.tree_updated = Some(
+            unique_second(history.tree_updated, Utc::now())
+        );
+        if let Some(delta) = delta.as_ref() {
+            info!(
+                "Tree changes with serial {}.", delta.serial()
+            );
+            if history.tree_deltas.len() == history.keep {
+                let _ = history.tree_deltas.pop_back();
+            }
+            history.tree_deltas.push_front(delta.clone());
+        }
+        delta
+    }
+
     /// Marks the beginning of an update cycle.
     pub fn mark_update_start(&self) {
         self.write().last_update_start = Utc::now();
@@ -119,24 +167,28 @@ impl SharedHistory {
                 locked.next_update_start = refresh;
             }
         }
-        locked.created = {
-            if let Some(created) = locked.created {
-                // Since we increase the time, the created time may
-                // actually have moved into the future.
-                if now.timestamp() <= created.timestamp() {
-                    Some(created + chrono::Duration::seconds(1))
-                }
-                else {
-                    Some(now)
-                }
-            }
-            else {
-                Some(now)
-            }
-        };
+        locked.created = Some(unique_second(locked.created, now));
     }
 }
 
+/// Returns a time in a different second than the previous time.
+///
+/// The time used in conditional HTTP requests only has second-resolution,
+/// so if `now` is within the same second as `previous`, moves it to the
+/// next second.
+fn unique_second(
+    previous: Option<DateTime<Utc>>, now: DateTime<Utc>
+) -> DateTime<Utc> {
+    if let Some(previous) = previous {
+        // Since we increase the time, the previous time may
+        // actually have moved into the future.
+        if now.timestamp() <= previous.timestamp() {
+            return previous + chrono::Duration::seconds(1)
+        }
+    }
+    now
+}
+
 
 //--- PayloadSource
 
@@ -203,6 +255,25 @@ pub struct PayloadHistory {
     /// The current metrics.
     metrics: Option<Arc<Metrics>>,
 
+    /// The current tree of validated objects.
+    tree: Option<Arc<DBDump>>,
+
+    /// The serial number of the current tree.
+    ///
+    /// This is increased every time the tree changes.
+    tree_serial: u64,
+
+    /// The time the tree serial was last increased.
+    ///
+    /// Like `created`, this is moved to the next second if necessary so
+    /// that conditional HTTP requests can tell two trees apart.
+    tree_updated: Option<DateTime<Utc>>,
+
+    /// A queue with a number of tree deltas.
+    ///
+    /// The newest delta will be at the front of the queue.
+    tree_deltas: VecDeque<Arc<TreeDelta>>,
+
     /// The session ID.
     session: u64,
 
@@ -246,6 +317,10 @@ impl PayloadHistory {
             current: None,
             deltas: VecDeque::with_capacity(config.history_size),
             metrics: None,
+            tree: None,
+            tree_serial: 0,
+            tree_updated: None,
+            tree_deltas: VecDeque::with_capacity(config.history_size),
             session: {
                 SystemTime::now()
                     .duration_since(SystemTime::UNIX_EPOCH).unwrap()
@@ -400,6 +475,50 @@ impl PayloadHistory {
         self.metrics.clone()
     }
 
+    /// Returns the current tree of validated objects if available yet.
+    pub fn tree(&self) -> Option<Arc<DBDump>> {
+        self.tree.clone()
+    }
+
+    /// Returns the serial number of the current tree.
+    pub fn tree_serial(&self) -> u64 {
+        self.tree_serial
+    }
+
+    /// Returns the time the tree serial was last increased.
+    pub fn tree_updated(&self) -> Option<DateTime<Utc>> {
+        self.tree_updated
+    }
+
+    /// Returns the tree deltas since the given serial number.
+    ///
+    /// The deltas are returned oldest first. If `serial` is `None`, all
+    /// available deltas are returned. If the history doesn’t have all the
+    /// deltas necessary to get from `serial` to the current tree or if
+    /// the serial is in the future, returns `None`.
+    pub fn tree_deltas_since(
+        &self, serial: Option<u64>
+    ) -> Option<Vec<Arc<TreeDelta>>> {
+        let res: Vec<_> = match serial {
+            Some(serial) => {
+                if serial > self.tree_serial {
+                    return None
+                }
+                self.tree_deltas.iter().rev().filter(|delta| {
+                    delta.serial() > serial
+                }).cloned().collect()
+            }
+            None => self.tree_deltas.iter().rev().cloned().collect()
+        };
+        if let Some(serial) = serial {
+            let needed = self.tree_serial - serial;
+            if u64::try_from(res.len()).unwrap_or(u64::MAX) != needed {
+                return None
+            }
+        }
+        Some(res)
+    }
+
     /// Returns the time the last update was started.
     pub fn last_update_start(&self) -> DateTime<Utc> {
         self.last_update_start
@@ -430,3 +549,65 @@ impl PayloadHistory {
     }
 }
 
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dump::testing::{dump, tal};
+
+    #[test]
+    fn update_tree() {
+        let history = SharedHistory::from_config(&Config::default());
+        assert!(history.read().tree().is_none());
+        assert_eq!(history.read().tree_serial(), 0);
+
+        // The first tree has no changes.
+        assert!(history.update_tree(dump(vec![tal("a", 1)])).is_none());
+        assert!(history.read().tree().is_some());
+        assert_eq!(history.read().tree_serial(), 1);
+        let updated = history.read().tree_updated().unwrap();
+
+        // An unchanged tree keeps the serial but still replaces the tree.
+        let first = history.read().tree().unwrap();
+        assert!(history.update_tree(dump(vec![tal("a", 1)])).is_none());
+        assert_eq!(history.read().tree_serial(), 1);
+        assert!(!Arc::ptr_eq(&first, &history.read().tree().unwrap()));
+        assert_eq!(history.read().tree_updated(), Some(updated));
+
+        let delta = history.update_tree(
+            dump(vec![tal("a", 1), tal("b", 2)])
+        ).unwrap();
+        assert_eq!(delta.serial(), 2);
+        assert_eq!(history.read().tree_serial(), 2);
+        assert_eq!(history.read().tree().unwrap().tals().len(), 2);
+        assert!(history.read().tree_updated().unwrap() > updated);
+    }
+
+    #[test]
+    fn tree_deltas_since() {
+        let history = SharedHistory::from_config(&Config {
+            history_size: 2, .. Default::default()
+        });
+        for id in 1..5 {
+            history.update_tree(dump(vec![tal("a", id)]));
+        }
+        let history = history.read();
+        assert_eq!(history.tree_serial(), 4);
+
+        // Only the last two deltas are kept.
+        let serials = |since| {
+            history.tree_deltas_since(since).map(|deltas| {
+                deltas.iter().map(|delta| delta.serial()).collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(serials(None), Some(vec![3, 4]));
+        assert_eq!(serials(Some(2)), Some(vec![3, 4]));
+        assert_eq!(serials(Some(3)), Some(vec![4]));
+        assert_eq!(serials(Some(4)), Some(vec![]));
+        assert_eq!(serials(Some(1)), None);
+        assert_eq!(serials(Some(5)), None);
+    }
+}
@@ -144,6 +144,9 @@ pub struct PublishInfo {
 
     /// The validity of the validation chain.
     pub chain_validity: Validity,
+
+    /// Was part of the object’s payload dropped due to TAL constraints?
+    pub constrained: bool,
 }
 
 impl PublishInfo {
@@ -156,6 +159,7 @@ impl PublishInfo {
             }),
             roa_validity: cert.validity(),
             chain_validity: cert.validity().trim(ca_validity),
+            constrained: false,
         }
     }
 
@@ -166,7 +170,8 @@ impl PublishInfo {
             tal: ca_cert.cert().tal().clone(),
             uri: Some(uri.clone()),
             roa_validity: cert.validity(),
-            chain_validity: cert.validity().trim(ca_cert.combined_validity())
+            chain_validity: cert.validity().trim(ca_cert.combined_validity()),
+            constrained: false,
         }
     }
 
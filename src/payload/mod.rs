@@ -6,7 +6,11 @@
 //! between consecutive such snapshots available as [`PayloadDelta`]. A
 //! collection of the two plus additional information is the
 //! [`PayloadHistory`] or, wrapped in an arc, [`SharedHistory`].
+//!
+//! The snapshot also keeps the contact information published by CAs via
+//! Ghostbuster records as a list of [`Contact`]s.
 
+pub use self::contact::{Contact, VCard};
 pub use self::delta::{DeltaArcIter, PayloadDelta};
 pub use self::history::{PayloadHistory, SharedHistory};
 pub use self::info::PayloadInfo;
@@ -16,6 +20,7 @@ pub use self::snapshot::{
 };
 pub use self::validation::ValidationReport;
 
+mod contact;
 mod delta;
 mod history;
 mod info;
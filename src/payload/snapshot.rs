@@ -11,6 +11,7 @@ use rpki::rtr::payload::{
     Aspa, PayloadRef, PayloadType, RouteOrigin, RouterKey
 };
 use rpki::rtr::server::PayloadSet;
+use super::contact::Contact;
 use super::info::PayloadInfo;
 
 
@@ -29,6 +30,12 @@ pub struct PayloadSnapshot {
     /// The AS providers,
     aspas: PayloadCollection<Aspa>,
 
+    /// The contacts from Ghostbuster records.
+    ///
+    /// These are ordered by the URI of the Ghostbuster record.
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    contacts: Vec<Contact>,
+
     /// The time when this snapshot was created.
     created: DateTime<Utc>,
 
@@ -45,6 +52,7 @@ impl Default for PayloadSnapshot {
             origins: Default::default(),
             router_keys: Default::default(),
             aspas: Default::default(),
+            contacts: Default::default(),
             created: Utc::now(),
             refresh: None
         }
@@ -57,12 +65,17 @@ impl PayloadSnapshot {
         origins: impl Iterator<Item = (RouteOrigin, PayloadInfo)>,
         router_keys: impl Iterator<Item = (RouterKey, PayloadInfo)>,
         aspas: impl Iterator<Item = (Aspa, PayloadInfo)>,
+        mut contacts: Vec<Contact>,
         refresh: Option<Time>
     ) -> Self {
+        contacts.sort_unstable_by(|left, right| {
+            left.uri().as_str().cmp(right.uri().as_str())
+        });
         Self {
             origins: PayloadCollection::from_iter(origins),
             router_keys: PayloadCollection::from_iter(router_keys),
             aspas: PayloadCollection::from_iter(aspas),
+            contacts,
             created: Utc::now(),
             refresh,
         }
@@ -140,6 +153,11 @@ impl PayloadSnapshot {
         self.aspas.iter_payload()
     }
 
+    /// Returns the contacts published via Ghostbuster records.
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
     /// Returns an iterator over the payload of a shared snapshot.
     pub fn arc_iter(self: Arc<Self>) -> SnapshotArcIter {
         SnapshotArcIter::new(self)
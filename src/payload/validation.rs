@@ -17,6 +17,7 @@ use std::cmp;
 use std::collections::hash_map;
 use std::collections::HashMap;
 use std::sync::Arc;
+use bytes::Bytes;
 use crossbeam_queue::SegQueue;
 use log::{info, warn};
 use rpki::uri;
@@ -27,16 +28,19 @@ use rpki::repository::resources::{
     AsBlock, AsBlocks, IpBlock, IpBlocks, IpBlocksBuilder,
 };
 use rpki::repository::roa::RouteOriginAttestation;
-use rpki::repository::tal::{Tal, TalUri};
+use rpki::repository::tal::{Tal, TalInfo, TalUri};
 use rpki::repository::x509::{Time, Validity};
 use rpki::resources::{Asn, Prefix, SmallAsnSet};
 use rpki::rtr::payload::{Aspa, RouteOrigin, RouterKey};
 use rpki::rtr::pdu::{ProviderAsns, RouterKeyInfo};
 use crate::config::{Config, FilterPolicy};
+use crate::constraints::TalConstraints;
+use crate::dump::DBDump;
 use crate::engine::{CaCert, Engine, ProcessPubPoint, ProcessRun};
 use crate::error::Failed;
-use crate::metrics::{Metrics, PayloadMetrics, VrpMetrics};
+use crate::metrics::{Metrics, PayloadMetrics, TalMetrics, VrpMetrics};
 use crate::slurm::LocalExceptions;
+use super::contact::{Contact, ContactCa, VCard};
 use super::info::{PayloadInfo, PublishInfo};
 use super::snapshot::PayloadSnapshot;
 
@@ -104,6 +108,151 @@ impl ValidationReport {
         Ok((report, metrics))
     }
 
+    /// Creates a new validation report from a tree dump.
+    ///
+    /// Instead of running the engine, the payload is taken from the ROAs,
+    /// router certificates, and ASPA objects of the tree. Since the tree
+    /// only contains objects that were valid when it was written, they
+    /// are not validated again. The validity of each object’s chain is
+    /// derived from the certificates in the tree. Rejected publication
+    /// points are not recorded in the tree with their resources, so no
+    /// VRPs will be considered unsafe.
+    ///
+    /// The constraints `engine` has for a TAL of the same name are applied
+    /// to the payload just as during a validation run.
+    pub fn from_tree(
+        tree: &DBDump, engine: &Engine, config: &Config,
+    ) -> (Self, Metrics) {
+        let report = Self::new(config);
+        let mut metrics = Metrics::new();
+        let mut tals = HashMap::new();
+        let mut tal_infos = Vec::new();
+        let mut constraints = Vec::new();
+        for tal in tree.tals() {
+            let info = TalInfo::from_name(tal.name().into()).into_arc();
+            tals.insert(tal.name(), tal_infos.len());
+            tal_infos.push(info.clone());
+            constraints.push(engine.tal_constraints(tal.name()));
+            metrics.tals.push(TalMetrics::new(info));
+        }
+        let ta_validity: HashMap<_, _> = tree.tals().iter().map(|tal| {
+            (tal.id(), tal.validity())
+        }).collect();
+        let cas: HashMap<_, _> = tree.ca_certs().iter().map(|cert| {
+            (cert.id(), (cert.parent(), cert.validity()))
+        }).collect();
+
+        // Trims `validity` to that of all the CAs from `id` upwards. The
+        // number of steps is limited since a broken tree may have loops.
+        let chain_validity = |mut id: KeyIdentifier, mut validity: Validity| {
+            for _ in 0..=cas.len() {
+                match cas.get(&id) {
+                    Some((parent, ca_validity)) => {
+                        validity = validity.trim(*ca_validity);
+                        id = *parent;
+                    }
+                    None => {
+                        if let Some(Some(ta)) = ta_validity.get(&id) {
+                            validity = validity.trim(*ta);
+                        }
+                        break
+                    }
+                }
+            }
+            validity
+        };
+
+        // Returns the TAL index and the publish info for an object.
+        let publish_info = |
+            parent: KeyIdentifier, tal: &str,
+            uri: &uri::Rsync, validity: Validity, constrained: bool,
+        | {
+            let tal_index = tals.get(tal).copied()?;
+            Some((tal_index, Arc::new(PublishInfo {
+                tal: tal_infos[tal_index].clone(),
+                uri: Some(uri.clone()),
+                roa_validity: validity,
+                chain_validity: chain_validity(parent, validity),
+                constrained,
+            })))
+        };
+
+        // We collect one publication point per CA.
+        let mut points: HashMap<KeyIdentifier, PubPoint> = HashMap::new();
+        for roa in tree.roas() {
+            let (tal_index, info) = match publish_info(
+                roa.parent(), roa.point().tal(), roa.uri(), roa.validity(),
+                roa.constrained(),
+            ) {
+                Some(some) => some,
+                None => continue,
+            };
+            PubPoint::tree_point(
+                &mut points, roa.parent(), tal_index,
+                info.chain_validity.not_after(),
+                &constraints[tal_index],
+            ).add_roa(
+                roa.origins(), info,
+                report.limit_v4_len, report.limit_v6_len,
+            );
+        }
+        if report.enable_bgpsec {
+            for cert in tree.router_certs() {
+                let key = match RouterKeyInfo::new(
+                    cert.pubkey().to_info_bytes()
+                ) {
+                    Ok(key) => key,
+                    Err(_) => continue,
+                };
+                let (tal_index, info) = match publish_info(
+                    cert.parent(), cert.point().tal(),
+                    cert.uri(), cert.validity(), cert.constrained(),
+                ) {
+                    Some(some) => some,
+                    None => continue,
+                };
+                PubPoint::tree_point(
+                    &mut points, cert.parent(), tal_index,
+                    info.chain_validity.not_after(),
+                    &constraints[tal_index],
+                ).add_router_key(
+                    cert.as_resources().clone(), cert.id(), key, info
+                );
+            }
+        }
+        if report.enable_aspa {
+            for aspa in tree.aspas() {
+                let (tal_index, info) = match publish_info(
+                    aspa.parent(), aspa.point().tal(),
+                    aspa.uri(), aspa.validity(), false,
+                ) {
+                    Some(some) => some,
+                    None => continue,
+                };
+                PubPoint::tree_point(
+                    &mut points, aspa.parent(), tal_index,
+                    info.chain_validity.not_after(),
+                    &constraints[tal_index],
+                ).add_aspa(
+                    aspa.customer_as(), aspa.provider_as_set().collect(),
+                    info,
+                );
+            }
+        }
+
+        // The refresh time also depends on the manifests.
+        for manifest in tree.manifests() {
+            if let Some(point) = points.get_mut(&manifest.parent()) {
+                point.update_refresh(manifest.next_update());
+            }
+        }
+
+        for (_, point) in points {
+            report.pub_points.push(point);
+        }
+        (report, metrics)
+    }
+
     /// Converts the report into a payload snapshot.
     pub fn into_snapshot(
         self,
@@ -134,6 +283,7 @@ impl<'a> ProcessRun for &'a ValidationReport {
                 report: self,
                 pub_point: PubPoint::new_ta(cert, tal_index),
                 validity: cert.combined_validity(),
+                ca: Arc::new(ContactCa::new(cert)),
             }
         ))
     }
@@ -157,6 +307,9 @@ pub struct PubPointProcessor<'a> {
 
     /// The (combined) validity of the CA certificate.
     validity: Validity,
+
+    /// The CA information for contacts published by the CA.
+    ca: Arc<ContactCa>,
 }
 
 impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
@@ -184,6 +337,7 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
                 report: self.report,
                 pub_point: PubPoint::new_ca(&self.pub_point, cert),
                 validity: cert.combined_validity(),
+                ca: Arc::new(ContactCa::new(cert)),
             }
         ))
     }
@@ -245,7 +399,8 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
         route: RouteOriginAttestation
     ) -> Result<(), Failed> {
         if self.pub_point.add_roa(
-            route, Arc::new(PublishInfo::signed_object(&cert, self.validity)),
+            route.iter_origins(),
+            Arc::new(PublishInfo::signed_object(&cert, self.validity)),
             self.report.limit_v4_len, self.report.limit_v6_len,
         ) {
             self.pub_point.update_refresh(cert.validity().not_after());
@@ -264,11 +419,32 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
         }
         self.pub_point.update_refresh(cert.validity().not_after());
         self.pub_point.add_aspa(
-            aspa, Arc::new(PublishInfo::signed_object(&cert, self.validity))
+            aspa.customer_as(), aspa.provider_as_set().to_set(),
+            Arc::new(PublishInfo::signed_object(&cert, self.validity))
         );
         Ok(())
     }
 
+    fn process_gbr(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        content: Bytes
+    ) -> Result<(), Failed> {
+        let vcard = match VCard::parse(content.as_ref()) {
+            Some(vcard) => vcard,
+            None => {
+                warn!("{}: Ghostbuster record contains invalid vCard.", uri);
+                return Ok(())
+            }
+        };
+        self.pub_point.contacts.push(Contact::new(
+            self.ca.clone(), uri.clone(),
+            cert.validity().trim(self.validity), vcard
+        ));
+        Ok(())
+    }
+
     fn restart(&mut self) -> Result<(), Failed> {
         self.pub_point.restart();
         Ok(())
@@ -318,6 +494,9 @@ pub struct PubPoint {
     /// The list of valid ASPA payload.
     aspas: Vec<PubAspa>,
 
+    /// The list of contacts from valid Ghostbuster records.
+    contacts: Vec<Contact>,
+
     /// The time when the publication point needs to be refreshed.
     refresh: Time,
 
@@ -331,25 +510,40 @@ pub struct PubPoint {
 
     /// The index of the repository containing the payload in the metrics.
     repository_index: Option<usize>,
+
+    /// The constraints on the resources of the TAL.
+    constraints: Option<Arc<TalConstraints>>,
+
+    /// The metrics for payload dropped because of the constraints.
+    constrained: PayloadMetrics,
 }
 
 impl PubPoint {
     /// Creates a new publication point.
-    fn new(refresh: Time, tal_index: usize) -> Self {
+    fn new(
+        refresh: Time, tal_index: usize,
+        constraints: Option<Arc<TalConstraints>>,
+    ) -> Self {
         PubPoint {
             origins: Vec::new(),
             router_keys: Vec::new(),
             aspas: Vec::new(),
+            contacts: Vec::new(),
             refresh,
             orig_refresh: refresh,
             tal_index,
             repository_index: None,
+            constraints,
+            constrained: Default::default(),
         }
     }
 
     /// Creates a new publication point for a trust anchor CA.
     fn new_ta(cert: &CaCert, tal_index: usize) -> Self {
-        Self::new(cert.cert().validity().not_after(), tal_index)
+        Self::new(
+            cert.cert().validity().not_after(), tal_index,
+            cert.constraints().cloned(),
+        )
     }
 
     /// Creates a new publication for a regular CA.
@@ -359,14 +553,32 @@ impl PubPoint {
                 parent.refresh, cert.cert().validity().not_after()
             ),
             parent.tal_index,
+            parent.constraints.clone(),
         )
     }
 
+    /// Returns the point for `parent` from a map of points from a tree.
+    ///
+    /// Creates the point with the TAL’s `constraints` if it doesn’t exist
+    /// yet and makes sure its refresh time is no later than `refresh`.
+    fn tree_point<'a>(
+        points: &'a mut HashMap<KeyIdentifier, PubPoint>,
+        parent: KeyIdentifier, tal_index: usize, refresh: Time,
+        constraints: &Option<Arc<TalConstraints>>,
+    ) -> &'a mut PubPoint {
+        let point = points.entry(parent).or_insert_with(|| {
+            Self::new(refresh, tal_index, constraints.clone())
+        });
+        point.update_refresh(refresh);
+        point
+    }
+
     /// Returns whether there is nothing published via this point.
     pub fn is_empty(&self) -> bool {
         self.origins.is_empty()
         && self.router_keys.is_empty()
         && self.aspas.is_empty()
+        && self.contacts.is_empty()
     }
 
     /// Updates the refresh time to be no later than the given time.
@@ -379,19 +591,70 @@ impl PubPoint {
         self.origins.clear();
         self.router_keys.clear();
         self.aspas.clear();
+        self.contacts.clear();
         self.refresh = self.orig_refresh;
+        self.constrained = Default::default();
     }
 
-    /// Adds the content of a ROA to the payload.
+    /// Adds the route origins of a ROA to the payload.
+    ///
+    /// Route origins for prefixes outside the TAL’s constraints are
+    /// dropped.
     fn add_roa(
         &mut self,
-        roa: RouteOriginAttestation,
+        roa: impl Iterator<Item = RouteOrigin>,
+        mut info: Arc<PublishInfo>,
+        limit_v4_len: Option<u8>,
+        limit_v6_len: Option<u8>,
+    ) -> bool {
+        let mut origins = Vec::new();
+        for origin in roa {
+            if self.allows_origin(origin) {
+                origins.push(origin)
+            }
+            else {
+                Arc::make_mut(&mut info).constrained = true;
+            }
+        }
+        self.add_origins(
+            origins.into_iter(), info, limit_v4_len, limit_v6_len
+        )
+    }
+
+    /// Returns whether a route origin is allowed by the constraints.
+    ///
+    /// Updates the metrics for constrained payload if it isn’t.
+    fn allows_origin(&mut self, origin: RouteOrigin) -> bool {
+        let constraints = match self.constraints.as_ref() {
+            Some(constraints) => constraints,
+            None => return true,
+        };
+        if constraints.allows_prefix(origin.prefix.prefix()) {
+            return true
+        }
+        let metrics = if origin.is_v4() {
+            &mut self.constrained.v4_origins
+        }
+        else {
+            &mut self.constrained.v6_origins
+        };
+        metrics.valid += 1;
+        metrics.constrained += 1;
+        false
+    }
+
+    /// Adds route origins to the payload.
+    ///
+    /// Returns whether any origins were actually added.
+    fn add_origins(
+        &mut self,
+        origins: impl Iterator<Item = RouteOrigin>,
         info: Arc<PublishInfo>,
         limit_v4_len: Option<u8>,
         limit_v6_len: Option<u8>,
     ) -> bool {
         let mut any = false;
-        for origin in roa.iter_origins() {
+        for origin in origins {
             let limit = if origin.prefix.prefix().is_v4() {
                 limit_v4_len
             }
@@ -410,31 +673,56 @@ impl PubPoint {
     }
 
     /// Adds the content of a router key to the payload.
+    ///
+    /// The AS numbers of the key are clipped to the TAL’s constraints.
     fn add_router_key(
         &mut self,
         asns: AsBlocks,
         key_id: KeyIdentifier,
         key_info: RouterKeyInfo,
-        info: Arc<PublishInfo>,
+        mut info: Arc<PublishInfo>,
     ) {
+        let asns = match self.constraints.as_ref() {
+            Some(constraints) => {
+                let clipped = constraints.clip_asns(&asns);
+                let dropped = asns.asn_count().saturating_sub(
+                    clipped.asn_count()
+                );
+                if dropped > 0 {
+                    self.constrained.router_keys.valid += dropped;
+                    self.constrained.router_keys.constrained += dropped;
+                    Arc::make_mut(&mut info).constrained = true;
+                }
+                clipped
+            }
+            None => asns
+        };
+        if asns.is_empty() {
+            return
+        }
         self.router_keys.push(
             PubRouterKey { asns, key_id, key_info, info }
         );
     }
 
     /// Adds the content of an ASPA object to the payload.
+    ///
+    /// The object is rejected if its customer AS is outside the TAL’s
+    /// constraints.
     fn add_aspa(
         &mut self,
-        aspa: AsProviderAttestation,
+        customer: Asn,
+        providers: SmallAsnSet,
         info: Arc<PublishInfo>,
     ) {
-        self.aspas.push(
-            PubAspa {
-                customer: aspa.customer_as(),
-                providers: aspa.provider_as_set().to_set(),
-                info
+        if let Some(constraints) = self.constraints.as_ref() {
+            if !constraints.allows_asn(customer) {
+                self.constrained.aspas.valid += 1;
+                self.constrained.aspas.constrained += 1;
+                return
             }
-        )
+        }
+        self.aspas.push(PubAspa { customer, providers, info })
     }
 }
 
@@ -579,6 +867,9 @@ struct SnapshotBuilder<'a> {
     /// The key is the customer ASN.
     aspas: HashMap<Asn, (SmallAsnSet, PayloadInfo)>,
 
+    /// The collected contacts.
+    contacts: Vec<Contact>,
+
     /// The list of rejected resources.
     rejected: RejectedResources,
 
@@ -605,6 +896,7 @@ impl<'a> SnapshotBuilder<'a> {
             origins: Default::default(),
             router_keys: Default::default(),
             aspas: Default::default(),
+            contacts: Default::default(),
             rejected,
             unsafe_vrps,
             unsafe_vrps_present: false,
@@ -621,6 +913,7 @@ impl<'a> SnapshotBuilder<'a> {
             metrics, point.tal_index, point.repository_index,
         );
         self.update_refresh(point.refresh);
+        metrics.update(|m| *m += &point.constrained);
         point.origins.into_iter().for_each(|item| {
             self.process_origin(item, &mut metrics)
         });
@@ -630,6 +923,7 @@ impl<'a> SnapshotBuilder<'a> {
         point.aspas.into_iter().for_each(|item| {
             self.process_aspa(item, &mut metrics)
         });
+        self.contacts.extend(point.contacts);
     }
 
     /// Updates the refresh time.
@@ -831,6 +1125,7 @@ impl<'a> SnapshotBuilder<'a> {
                     }
                 }
             ),
+            self.contacts,
             self.refresh,
         )
     }
@@ -880,3 +1175,74 @@ impl<'a> AllVrpMetrics<'a> {
     }
 }
 
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use crate::dump::TreeReport;
+    use crate::utils::testing::{TAL_NAME, TestRepository};
+
+    /// Returns the sorted route origins and ASPAs of a report.
+    fn payload(
+        report: ValidationReport, mut metrics: Metrics
+    ) -> (Vec<RouteOrigin>, Vec<Aspa>) {
+        let snapshot = report.into_snapshot(
+            &LocalExceptions::empty(), &mut metrics
+        );
+        let mut origins: Vec<_> = snapshot.origins().map(|(origin, _)| {
+            origin
+        }).collect();
+        origins.sort();
+        let mut aspas: Vec<_> = snapshot.aspas().map(|(aspa, _)| {
+            aspa.clone()
+        }).collect();
+        aspas.sort();
+        (origins, aspas)
+    }
+
+    #[test]
+    fn from_tree_round_trip() {
+        let (mut repo, mut ta) = TestRepository::new();
+        repo.config_mut().enable_aspa = true;
+        fs::write(
+            repo.config().extra_tals_dir.as_ref().unwrap().join(
+                format!("{}.constraints", TAL_NAME)
+            ),
+            "allow 10.0.0.0/20\nallow AS64496-AS64497\n"
+        ).unwrap();
+        let mut ca = repo.child(
+            &mut ta, "ca", "10.0.0.0/16", "AS64496-AS64499"
+        );
+        repo.roa(&mut ca, "a", 64496, "10.0.0.0/24");
+        repo.roa(&mut ca, "b", 64497, "10.0.1.0/24-26");
+        repo.roa(&mut ca, "c", 64498, "10.0.128.0/24");
+        repo.aspa(&mut ca, "d", 64496, &[64497, 64498]);
+        repo.aspa(&mut ca, "e", 64499, &[64496]);
+        repo.publish(&mut ca);
+        repo.publish(&mut ta);
+
+        let engine = repo.engine();
+        let report = ValidationReport::new(repo.config());
+        let tree = TreeReport::new();
+        let mut run = engine.start((&report, &tree)).unwrap();
+        run.process().unwrap();
+        let metrics = run.done();
+
+        let mut json = Vec::new();
+        tree.into_dump().write_json(&mut json).unwrap();
+        let dump = DBDump::read_json(json.as_slice()).unwrap();
+        let (tree_report, tree_metrics) = ValidationReport::from_tree(
+            &dump, &engine, repo.config()
+        );
+
+        let validated = payload(report, metrics);
+        assert_eq!(validated.0.len(), 2);
+        #[cfg(feature = "aspa")]
+        assert_eq!(validated.1.len(), 1);
+        assert_eq!(validated, payload(tree_report, tree_metrics));
+    }
+}
@@ -0,0 +1,659 @@
+//! Validating RPKI Signed Checklists.
+//!
+//! An RPKI Signed Checklist as defined in [RFC 9323] is a signed object
+//! that lists the digests of a number of files and attests them with a
+//! set of resources. Unlike other signed objects it is not published in
+//! an RPKI repository, so its EE certificate is validated by looking for
+//! the issuing CA during a regular validation run.
+//!
+//! [RFC 9323]: https://tools.ietf.org/html/rfc9323
+
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use bcder::{decode, Mode, OctetString, Oid, Tag};
+use bcder::decode::{DecodeError, IntoSource, Source};
+use bcder::string::Ia5String;
+use bytes::Bytes;
+use rpki::uri;
+use rpki::crypto::{
+    DigestAlgorithm, KeyIdentifier, RpkiSignature, RpkiSignatureAlgorithm,
+};
+use rpki::repository::cert::{Cert, ResourceCert};
+use rpki::repository::crl::Crl;
+use rpki::repository::error::{ValidationError, VerificationError};
+use rpki::repository::manifest::ManifestContent;
+use rpki::repository::resources::{AddressFamily, AsBlocks, IpBlocks};
+use rpki::repository::sigobj::{MessageDigest, SignedAttrs};
+use rpki::repository::tal::{Tal, TalUri};
+use rpki::repository::x509::Time;
+use crate::config::Config;
+use crate::engine::{CaCert, Engine, ProcessPubPoint, ProcessRun};
+use crate::error::Failed;
+
+
+//------------ Constants -----------------------------------------------------
+
+/// The object identifier of the PKCS#7 signed data content type.
+const SIGNED_DATA: Oid<&[u8]> = Oid(&[42, 134, 72, 134, 247, 13, 1, 7, 2]);
+
+/// The object identifier of the signed checklist content type.
+///
+/// This is `id-ct-signedChecklist`, 1.2.840.113549.1.9.16.1.48.
+const CT_SIGNED_CHECKLIST: Oid<&[u8]>
+    = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 48]);
+
+
+//------------ SignedChecklist -----------------------------------------------
+
+/// An RPKI Signed Checklist.
+#[derive(Clone, Debug)]
+pub struct SignedChecklist {
+    /// The decoded content of the object.
+    content: Checklist,
+
+    /// The raw content of the object.
+    raw_content: OctetString,
+
+    /// The digest algorithm used for the message digest.
+    digest_algorithm: DigestAlgorithm,
+
+    /// The EE certificate the object is signed with.
+    cert: Cert,
+
+    /// The subject key identifier given in the signer info.
+    sid: KeyIdentifier,
+
+    /// The signed attributes.
+    signed_attrs: SignedAttrs,
+
+    /// The message digest from the signed attributes.
+    message_digest: MessageDigest,
+
+    /// The signature.
+    signature: RpkiSignature,
+}
+
+impl SignedChecklist {
+    /// Decodes a signed checklist from the given source.
+    pub fn decode<S: IntoSource>(
+        source: S,
+        strict: bool
+    ) -> Result<Self, DecodeError<<S::Source as Source>::Error>> {
+        if strict {
+            Mode::Der
+        }
+        else {
+            Mode::Ber
+        }.decode(source.into_source(), Self::take_from)
+    }
+
+    /// Takes a signed checklist from an encoded constructed value.
+    ///
+    /// This follows the profile for signed objects of RFC 6488 with the
+    /// content type of a signed checklist.
+    fn take_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>
+    ) -> Result<Self, DecodeError<S::Error>> {
+        cons.take_sequence(|cons| { // ContentInfo
+            SIGNED_DATA.skip_if(cons)?; // contentType
+            cons.take_constructed_if(Tag::CTX_0, |cons| { // content
+                cons.take_sequence(|cons| { // SignedData
+                    cons.skip_u8_if(3)?; // version -- must be 3
+                    let digest_algorithm =
+                        DigestAlgorithm::take_set_from(cons)?;
+                    let raw_content = cons.take_sequence(|cons| {
+                        CT_SIGNED_CHECKLIST.skip_if(cons)?;
+                        cons.take_constructed_if(
+                            Tag::CTX_0, OctetString::take_from
+                        )
+                    })?;
+                    let content = Mode::Der.decode(
+                        raw_content.clone(), Checklist::take_from
+                    ).map_err(|_| {
+                        cons.content_err("invalid signed checklist content")
+                    })?;
+                    let cert = cons.take_constructed_if( // certificates
+                        Tag::CTX_0, Cert::take_from
+                    )?;
+                    // no crls
+                    let (sid, attrs, signature) = { // signerInfos
+                        cons.take_set(|cons| {
+                            cons.take_sequence(|cons| {
+                                cons.skip_u8_if(3)?;
+                                let sid = cons.take_value_if(
+                                    Tag::CTX_0, KeyIdentifier::from_content
+                                )?;
+                                let alg = DigestAlgorithm::take_from(cons)?;
+                                if alg != digest_algorithm {
+                                    return Err(cons.content_err(
+                                        "digest algorithm mismatch"
+                                    ))
+                                }
+                                let attrs = SignedAttrs::take_from(cons)?;
+                                if attrs.2 != CT_SIGNED_CHECKLIST {
+                                    return Err(cons.content_err(
+                                        "content type in signed attributes \
+                                         differs"
+                                    ))
+                                }
+                                let signature = RpkiSignature::new(
+                                    RpkiSignatureAlgorithm::cms_take_from(
+                                        cons
+                                    )?,
+                                    OctetString::take_from(cons)?.into_bytes()
+                                );
+                                // no unsignedAttributes
+                                Ok((sid, attrs, signature))
+                            })
+                        })?
+                    };
+                    Ok(SignedChecklist {
+                        content,
+                        raw_content,
+                        digest_algorithm,
+                        cert,
+                        sid,
+                        signed_attrs: attrs.0,
+                        message_digest: attrs.1,
+                        signature,
+                    })
+                })
+            })
+        })
+    }
+
+    /// Returns the content of the signed checklist.
+    pub fn content(&self) -> &Checklist {
+        &self.content
+    }
+
+    /// Returns the EE certificate of the signed checklist.
+    pub fn cert(&self) -> &Cert {
+        &self.cert
+    }
+
+    /// Validates the signed checklist against a possible issuer.
+    ///
+    /// Checks that the object is correctly signed by its EE certificate,
+    /// that the certificate was issued by `issuer`, is valid at `now`, and
+    /// has not been revoked by the issuer’s current CRL `crl`, and that the
+    /// resources of the checklist are covered by the certificate. Upon
+    /// success, returns the validated EE certificate.
+    pub fn validate_at(
+        &self,
+        issuer: &ResourceCert,
+        crl: &Crl,
+        strict: bool,
+        now: Time,
+    ) -> Result<ResourceCert, ValidationError> {
+        if self.sid != self.cert.subject_key_identifier() {
+            return Err(VerificationError::new(
+                "Subject Key Identifier mismatch in signed checklist"
+            ).into())
+        }
+        let digest = {
+            let mut context = self.digest_algorithm.start();
+            self.raw_content.iter().for_each(|x| context.update(x));
+            context.finish()
+        };
+        if digest.as_ref() != self.message_digest.as_ref() {
+            return Err(VerificationError::new(
+                "message digest mismatch in signed checklist"
+            ).into())
+        }
+        self.cert.subject_public_key_info().verify(
+            &self.signed_attrs.encode_verify(), &self.signature
+        ).map_err(VerificationError::from)?;
+
+        // The EE certificate doesn’t have a signed object SIA since the
+        // object isn’t published, so it needs to be validated as a
+        // detached EE certificate.
+        let cert = self.cert.clone().validate_detached_ee_at(
+            issuer, strict, now
+        )?;
+        if crl.contains(cert.serial_number()) {
+            return Err(VerificationError::new(
+                "signed checklist EE certificate has been revoked"
+            ).into())
+        }
+        if
+            !cert.as_resources().contains(&self.content.as_resources)
+            || !cert.v4_resources().contains(&self.content.v4_resources)
+            || !cert.v6_resources().contains(&self.content.v6_resources)
+        {
+            return Err(VerificationError::new(
+                "signed checklist resources not covered by EE certificate"
+            ).into())
+        }
+        Ok(cert)
+    }
+}
+
+
+//------------ Checklist -----------------------------------------------------
+
+/// The content of a signed checklist.
+#[derive(Clone, Debug)]
+pub struct Checklist {
+    /// The attested IPv4 resources.
+    v4_resources: IpBlocks,
+
+    /// The attested IPv6 resources.
+    v6_resources: IpBlocks,
+
+    /// The attested AS resources.
+    as_resources: AsBlocks,
+
+    /// The algorithm used for the digests of the files.
+    digest_algorithm: DigestAlgorithm,
+
+    /// The files on the list.
+    items: Vec<ChecklistItem>,
+}
+
+impl Checklist {
+    /// Returns the attested IPv4 resources.
+    pub fn v4_resources(&self) -> &IpBlocks {
+        &self.v4_resources
+    }
+
+    /// Returns the attested IPv6 resources.
+    pub fn v6_resources(&self) -> &IpBlocks {
+        &self.v6_resources
+    }
+
+    /// Returns the attested AS resources.
+    pub fn as_resources(&self) -> &AsBlocks {
+        &self.as_resources
+    }
+
+    /// Returns the files on the list.
+    pub fn items(&self) -> &[ChecklistItem] {
+        &self.items
+    }
+
+    /// Checks whether the given file is on the list.
+    ///
+    /// A file is on the list if there is an item with the file’s digest
+    /// and either no file name or the file name of the path.
+    pub fn check_file(&self, path: &Path) -> Result<bool, io::Error> {
+        let digest = self.digest_algorithm.digest_file(path)?;
+        let name = path.file_name().and_then(|name| name.to_str());
+        Ok(self.items.iter().any(|item| {
+            item.hash.as_ref() == digest.as_ref()
+            && match item.file_name.as_ref() {
+                Some(item_name) => name == Some(item_name.as_str()),
+                None => true,
+            }
+        }))
+    }
+
+    /// Takes the content from an encoded constructed value.
+    fn take_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>
+    ) -> Result<Self, DecodeError<S::Error>> {
+        cons.take_sequence(|cons| {
+            // version [0] INTEGER DEFAULT 0 -- must be 0.
+            cons.take_opt_constructed_if(Tag::CTX_0, |c| c.skip_u8_if(0))?;
+            let (v4_resources, v6_resources, as_resources) =
+                Self::take_resources_from(cons)?;
+            let digest_algorithm = DigestAlgorithm::take_from(cons)?;
+            let items = cons.take_sequence(|cons| {
+                let mut items = Vec::new();
+                while let Some(item) = ChecklistItem::take_opt_from(cons)? {
+                    items.push(item)
+                }
+                Ok(items)
+            })?;
+            if items.is_empty() {
+                return Err(cons.content_err("empty signed checklist"))
+            }
+            Ok(Checklist {
+                v4_resources, v6_resources, as_resources,
+                digest_algorithm, items
+            })
+        })
+    }
+
+    /// Takes the resource block.
+    ///
+    /// Unlike in certificates, the resources are given explicitly tagged
+    /// and without any option to inherit them.
+    fn take_resources_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>
+    ) -> Result<(IpBlocks, IpBlocks, AsBlocks), DecodeError<S::Error>> {
+        cons.take_sequence(|cons| {
+            let asres = cons.take_opt_constructed_if(Tag::CTX_0, |cons| {
+                cons.take_sequence(|cons| {
+                    cons.take_constructed_if(Tag::CTX_0, AsBlocks::take_from)
+                })
+            })?;
+
+            let mut v4 = None;
+            let mut v6 = None;
+            cons.take_opt_constructed_if(Tag::CTX_1, |cons| {
+                cons.take_sequence(|cons| {
+                    while let Some(()) = cons.take_opt_sequence(|cons| {
+                        let family = AddressFamily::take_from(cons)?;
+                        let target = match family {
+                            AddressFamily::Ipv4 => &mut v4,
+                            AddressFamily::Ipv6 => &mut v6,
+                        };
+                        if target.is_some() {
+                            return Err(cons.content_err(
+                                "duplicate address family in signed checklist"
+                            ))
+                        }
+                        *target = Some(
+                            IpBlocks::take_from_with_family(cons, family)?
+                        );
+                        Ok(())
+                    })? { }
+                    Ok(())
+                })
+            })?;
+
+            if asres.is_none() && v4.is_none() && v6.is_none() {
+                return Err(cons.content_err(
+                    "no resources in signed checklist"
+                ))
+            }
+            Ok((
+                v4.unwrap_or_default(),
+                v6.unwrap_or_default(),
+                asres.unwrap_or_default(),
+            ))
+        })
+    }
+}
+
+
+//------------ ChecklistItem -------------------------------------------------
+
+/// A file on a signed checklist.
+#[derive(Clone, Debug)]
+pub struct ChecklistItem {
+    /// The optional name of the file.
+    file_name: Option<String>,
+
+    /// The digest of the file.
+    hash: Bytes,
+}
+
+impl ChecklistItem {
+    /// Returns the file name if present.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Returns the digest of the file.
+    pub fn hash(&self) -> &[u8] {
+        self.hash.as_ref()
+    }
+
+    /// Takes an optional item from an encoded constructed value.
+    fn take_opt_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>
+    ) -> Result<Option<Self>, DecodeError<S::Error>> {
+        cons.take_opt_sequence(|cons| {
+            let file_name = cons.take_opt_value_if(
+                Tag::IA5_STRING, Ia5String::from_content
+            )?;
+            let file_name = match file_name {
+                Some(name) => {
+                    let name = String::from_utf8(
+                        name.into_bytes().to_vec()
+                    ).map_err(|_| cons.content_err("invalid file name"))?;
+                    if !is_portable_file_name(&name) {
+                        return Err(cons.content_err("invalid file name"))
+                    }
+                    Some(name)
+                }
+                None => None
+            };
+            let hash = OctetString::take_from(cons)?.into_bytes();
+            Ok(ChecklistItem { file_name, hash })
+        })
+    }
+}
+
+/// Returns whether a file name only uses the portable character set.
+///
+/// RFC 9323 restricts file names to the characters of the POSIX portable
+/// filename character set and doesn’t allow them to start with a hyphen.
+fn is_portable_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('-')
+    && name.bytes().all(|ch| {
+        ch.is_ascii_alphanumeric() || ch == b'.' || ch == b'_' || ch == b'-'
+    })
+}
+
+
+//------------ ValidationReport ----------------------------------------------
+
+/// The result of validating a signed checklist during a validation run.
+///
+/// The EE certificate of the checklist is validated against all CA
+/// certificates with a matching subject key identifier encountered during
+/// the run and the CRL of their publication point. Validation stops at the
+/// first of them the certificate validates against.
+#[derive(Debug)]
+pub struct ValidationReport<'a> {
+    /// The signed checklist.
+    rsc: &'a SignedChecklist,
+
+    /// The authority key identifier of the EE certificate.
+    issuer: Option<KeyIdentifier>,
+
+    /// Whether to use strict validation.
+    strict: bool,
+
+    /// The time to validate the EE certificate at.
+    now: Time,
+
+    /// The validated EE certificate once found.
+    cert: Mutex<Option<ResourceCert>>,
+
+    /// Whether we have found the EE certificate’s issuer.
+    complete: AtomicBool,
+}
+
+impl<'a> ValidationReport<'a> {
+    /// Creates a new report for the given signed checklist.
+    ///
+    /// The EE certificate will be validated at the validation time of
+    /// `engine`.
+    pub fn new(
+        rsc: &'a SignedChecklist, engine: &Engine, config: &Config
+    ) -> Self {
+        ValidationReport {
+            rsc,
+            issuer: rsc.cert().authority_key_identifier(),
+            strict: config.strict,
+            now: engine.now(),
+            cert: Mutex::new(None),
+            complete: AtomicBool::new(false),
+        }
+    }
+
+    /// Performs a validation run with the given engine.
+    pub fn process(&self, engine: &Engine) -> Result<(), Failed> {
+        let mut run = engine.start(self)?;
+        run.process()?;
+        run.cleanup()?;
+        Ok(())
+    }
+
+    /// Returns the validated EE certificate if validation succeeded.
+    pub fn finalize(self) -> Result<ResourceCert, Failed> {
+        self.cert.into_inner().expect("poisoned lock").ok_or(Failed)
+    }
+
+    /// Returns whether the CA certificate could have issued the checklist.
+    fn is_candidate(&self, ca: &ResourceCert) -> bool {
+        self.issuer == Some(ca.subject_key_identifier())
+    }
+
+    /// Tries to validate the checklist against a CA certificate and its CRL.
+    fn check_ca(&self, ca: &ResourceCert, crl: &Crl) {
+        let res = self.rsc.validate_at(ca, crl, self.strict, self.now);
+        if let Ok(cert) = res {
+            *self.cert.lock().expect("poisoned lock") = Some(cert);
+            self.complete.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<'a, 's> ProcessRun for &'s ValidationReport<'a> {
+    type PubPoint = ValidateCa<'a, 's>;
+
+    fn process_ta(
+        &self, _tal: &Tal, _uri: &TalUri, cert: &CaCert,
+        _tal_index: usize
+    ) -> Result<Option<Self::PubPoint>, Failed> {
+        if self.complete.load(Ordering::Relaxed) {
+            Ok(None)
+        }
+        else {
+            Ok(Some(ValidateCa::new(self, cert)))
+        }
+    }
+}
+
+
+//------------ ValidateCa ----------------------------------------------------
+
+/// Looks for the issuer of the checklist at a publication point.
+///
+/// If the CA certificate of the publication point could have issued the
+/// checklist, it is checked together with the CRL of the point once the
+/// point has been processed successfully.
+pub struct ValidateCa<'a, 's> {
+    /// The report to eventually add the result to.
+    report: &'s ValidationReport<'a>,
+
+    /// The CA certificate if it could have issued the checklist.
+    candidate: Option<ResourceCert>,
+
+    /// The CRL of the publication point if the CA is a candidate.
+    crl: Option<Crl>,
+}
+
+impl<'a, 's> ValidateCa<'a, 's> {
+    fn new(report: &'s ValidationReport<'a>, cert: &CaCert) -> Self {
+        ValidateCa {
+            report,
+            candidate: if report.is_candidate(cert.cert()) {
+                Some(cert.cert().clone())
+            }
+            else {
+                None
+            },
+            crl: None,
+        }
+    }
+}
+
+impl<'a, 's> ProcessPubPoint for ValidateCa<'a, 's> {
+    fn want(&self, uri: &uri::Rsync) -> Result<bool, Failed> {
+        Ok(uri.ends_with(".cer"))
+    }
+
+    fn process_manifest(
+        &mut self,
+        _uri: &uri::Rsync,
+        _ee_cert: &ResourceCert,
+        _manifest: &ManifestContent,
+        _crl_uri: &uri::Rsync,
+        crl: &Crl,
+    ) -> Result<(), Failed> {
+        if self.candidate.is_some() {
+            self.crl = Some(crl.clone());
+        }
+        Ok(())
+    }
+
+    fn process_ca(
+        &mut self, _uri: &uri::Rsync, cert: &CaCert,
+    ) -> Result<Option<Self>, Failed> {
+        if self.report.complete.load(Ordering::Relaxed) {
+            return Ok(None)
+        }
+        Ok(Some(Self::new(self.report, cert)))
+    }
+
+    fn restart(&mut self) -> Result<(), Failed> {
+        self.crl = None;
+        Ok(())
+    }
+
+    fn commit(self) {
+        if self.report.complete.load(Ordering::Relaxed) {
+            return
+        }
+        if let (Some(cert), Some(crl)) = (self.candidate, self.crl) {
+            self.report.check_ca(&cert, &crl);
+        }
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The content of a checklist for AS64496 and 192.0.2.0/24.
+    ///
+    /// It lists a file `hello.txt` containing "hello\n" and an unnamed
+    /// file.
+    const CONTENT: &str = "\
+        308183301fa00b3009a0073005020300fbf0a110300e300c040200013006030400\
+        c00002300b06096086480165030402013053302d160968656c6c6f2e7478740420\
+        5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be0330\
+        2204200101010101010101010101010101010101010101010101010101010101\
+        01010101\
+    ";
+
+    fn content() -> Checklist {
+        let data: Vec<u8> = (0..CONTENT.len()).step_by(2).map(|i| {
+            u8::from_str_radix(&CONTENT[i..i + 2], 16).unwrap()
+        }).collect();
+        Mode::Der.decode(Bytes::from(data), Checklist::take_from).unwrap()
+    }
+
+    #[test]
+    fn decode_content() {
+        let content = content();
+        assert!(content.as_resources().contains_asn(64496.into()));
+        assert_eq!(content.v4_resources().iter().count(), 1);
+        assert!(content.v6_resources().is_empty());
+        assert_eq!(content.items().len(), 2);
+        assert_eq!(content.items()[0].file_name(), Some("hello.txt"));
+        assert_eq!(content.items()[1].file_name(), None);
+    }
+
+    #[test]
+    fn check_file() {
+        let content = content();
+        let dir = tempfile::tempdir().unwrap();
+        let hello = dir.path().join("hello.txt");
+        std::fs::write(&hello, b"hello\n").unwrap();
+        assert!(content.check_file(&hello).unwrap());
+        let renamed = dir.path().join("world.txt");
+        std::fs::write(&renamed, b"hello\n").unwrap();
+        assert!(!content.check_file(&renamed).unwrap());
+    }
+
+    #[test]
+    fn portable_file_name() {
+        assert!(is_portable_file_name("document-1.0_final.txt"));
+        assert!(!is_portable_file_name(""));
+        assert!(!is_portable_file_name("-rf"));
+        assert!(!is_portable_file_name("../etc/passwd"));
+        assert!(!is_portable_file_name("a b"));
+    }
+}
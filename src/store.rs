@@ -27,10 +27,10 @@
 //! The store uses the file system to store its data. It has its dedicated
 //! directory within the RPKI repository directory, normally named `stored`
 //! (this is because an earlier version used `store` already). Within this
-//! directory are four sub-directories: `rrdp` and `rsync` contain the data
+//! directory are five sub-directories: `rrdp` and `rsync` contain the data
 //! for each stored publication point; `ta` contains the downloaded trust
-//! anchor certificates; and `tmp` is a directory for storing files as they
-//! are constructed.
+//! anchor certificates; `tak` contains the key rollover state of TALs; and
+//! `tmp` is a directory for storing files as they are constructed.
 //!
 //! All publication points that do not support RRDP are stored under `rsync`.
 //! Each has a file stored at a path and file name derived from the
@@ -55,7 +55,14 @@
 //! directory structure derived from the URI the certificate is retrieved
 //! from. The first level is the scheme, `https` or `rsync`, the second
 //! level is the authority (i.e., hostname), and the third is the SHA-256
-//! hash of the full URI with an extension of `.cer` added.
+//! hash of the full URI with an extension of `.cer` added. A changed
+//! certificate that is held back because trust anchor certificates are
+//! pinned is kept alongside with an extension of `.pending` instead.
+//!
+//! The key rollover state of a TAL is kept under `tak` in files named
+//! after the TAL with an extension of `.tal` for an accepted TAL and
+//! `.pending` for a pending successor key. The format of these files is
+//! described with [`TakState`][crate::tak::TakState].
 //!
 //! Finally, the `tmp` directory is used to build the publication point files
 //! in so they can be constructed without yet knowing whether the update is
@@ -337,6 +344,38 @@ impl Store {
         }
     }
 
+    /// Returns the path to use for a pending trust anchor certificate.
+    pub(crate) fn pending_ta_path(&self, uri: &TalUri) -> PathBuf {
+        match *uri {
+            TalUri::Rsync(ref uri) => {
+                self.path.join(
+                    uri.unique_path("ta/rsync", ".pending")
+                )
+            }
+            TalUri::Https(ref uri) => {
+                self.path.join(
+                    uri.unique_path("ta/https", ".pending")
+                )
+            }
+        }
+    }
+
+    /// Returns the path to use for a key rollover state file of a TAL.
+    ///
+    /// Characters in the TAL name that may not be safe in a file name are
+    /// replaced by underscores.
+    fn tak_path(&self, name: &str, file: TakFile) -> PathBuf {
+        let name: String = name.chars().map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            }
+            else {
+                '_'
+            }
+        }).collect();
+        self.path.join("tak").join(format!("{}.{}", name, file.extension()))
+    }
+
     /// The name of the directory where all the RRDP repositories go.
     const RRDP_BASE: &'static str = "rrdp";
 
@@ -453,6 +492,65 @@ impl<'a> Run<'a> {
         fatal::write_file(&path, content)
     }
 
+    /// Stores a changed trust anchor certificate pending acceptance.
+    pub fn update_pending_ta(
+        &self, uri: &TalUri, content: &[u8]
+    ) -> Result<(), Failed> {
+        let path = self.store.pending_ta_path(uri);
+        if let Some(dir) = path.parent() {
+            fatal::create_dir_all(dir)?;
+        }
+        fatal::write_file(&path, content)
+    }
+
+    /// Removes a pending trust anchor certificate if it exists.
+    pub fn remove_pending_ta(&self, uri: &TalUri) -> Result<(), Failed> {
+        fatal::remove_file(&self.store.pending_ta_path(uri))
+    }
+
+    /// Replaces a stored trust anchor certificate with the pending one.
+    ///
+    /// Returns whether there was a pending certificate.
+    pub fn accept_pending_ta(&self, uri: &TalUri) -> Result<bool, Failed> {
+        let path = self.store.pending_ta_path(uri);
+        let content = match fatal::read_existing_file(&path)? {
+            Some(content) => content,
+            None => return Ok(false)
+        };
+        self.update_ta(uri, &content)?;
+        fatal::remove_file(&path)?;
+        Ok(true)
+    }
+
+    /// Loads a key rollover state file for the TAL with the given name.
+    pub fn load_tak(
+        &self, name: &str, file: TakFile
+    ) -> Result<Option<Bytes>, Failed> {
+        fatal::read_existing_file(
+            &self.store.tak_path(name, file)
+        ).map(|maybe| {
+            maybe.map(Into::into)
+        })
+    }
+
+    /// Updates or inserts a key rollover state file for a TAL.
+    pub fn update_tak(
+        &self, name: &str, file: TakFile, content: &[u8]
+    ) -> Result<(), Failed> {
+        let path = self.store.tak_path(name, file);
+        if let Some(dir) = path.parent() {
+            fatal::create_dir_all(dir)?;
+        }
+        fatal::write_file(&path, content)
+    }
+
+    /// Removes a key rollover state file for a TAL if it exists.
+    pub fn remove_tak(
+        &self, name: &str, file: TakFile
+    ) -> Result<(), Failed> {
+        fatal::remove_file(&self.store.tak_path(name, file))
+    }
+
     /// Accesses the repository for the provided RPKI CA.
     ///
     /// If the CA’s rpkiNotify URI is present, the RRDP repository identified
@@ -560,6 +658,29 @@ impl<'a> Run<'a> {
 }
 
 
+//------------ TakFile -------------------------------------------------------
+
+/// The kind of key rollover state file of a TAL.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TakFile {
+    /// The TAL for an accepted successor key.
+    Accepted,
+
+    /// A successor key waiting for acceptance.
+    Pending,
+}
+
+impl TakFile {
+    /// Returns the file extension for the kind of file.
+    fn extension(self) -> &'static str {
+        match self {
+            TakFile::Accepted => "tal",
+            TakFile::Pending => "pending",
+        }
+    }
+}
+
+
 //------------ Repository ----------------------------------------------------
 
 /// Access to a single repository during a validation run.
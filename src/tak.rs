@@ -0,0 +1,528 @@
+//! Trust anchor key rollover via Trust Anchor Key objects.
+//!
+//! [RFC 9691] allows a trust anchor to announce changes to its key and the
+//! URIs of its certificate through a signed Trust Anchor Key (TAK) object
+//! published in its repository. The object contains the current key of the
+//! trust anchor and, optionally, its predecessor and successor keys.
+//!
+//! When a valid TAK object announces a successor key, it is remembered as
+//! pending in the store. If the same successor is still announced after the
+//! acceptance time of 30 days and a valid trust anchor certificate for it
+//! can be fetched from one of its URIs, it is accepted: a TAL for the
+//! successor key is written to the store and used instead of the configured
+//! TAL from the next validation run on. The accepted TAL is only used as
+//! long as the configured TAL still has the key the rollover started from,
+//! so updating the configured TAL overrides any earlier rollover.
+//!
+//! The state is only updated by runs that fetch repository data and don’t
+//! use a fixed validation time.
+//!
+//! The type [`TakContent`] represents the decoded content of a TAK object
+//! while [`TakState`] provides access to the rollover state of a TAL.
+//!
+//! [RFC 9691]: https://tools.ietf.org/html/rfc9691
+
+use std::str::FromStr;
+use bcder::{decode, Oid, Tag};
+use bcder::decode::DecodeError;
+use bcder::string::{Ia5String, Utf8String};
+use log::{info, warn};
+use rpki::crypto::{KeyIdentifier, PublicKey};
+use rpki::repository::error::{InspectionError, ValidationError};
+use rpki::repository::sigobj::SignedObject;
+use rpki::repository::tal::{Tal, TalUri};
+use rpki::repository::x509::Time;
+use rpki::util::base64;
+use crate::error::Failed;
+use crate::store::{self, TakFile};
+
+
+//------------ Constants -----------------------------------------------------
+
+/// The object identifier of the TAK content type.
+///
+/// This is `id-ct-signedTAL`, 1.2.840.113549.1.9.16.1.50.
+pub const CT_SIGNED_TAL: Oid<&[u8]>
+    = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 50]);
+
+/// The number of days a successor key needs to be announced.
+pub const ACCEPTANCE_DAYS: i64 = 30;
+
+
+//------------ TakContent ----------------------------------------------------
+
+/// The content of a Trust Anchor Key object.
+#[derive(Clone, Debug)]
+pub struct TakContent {
+    /// The current key of the trust anchor.
+    current: TaKey,
+
+    /// The previous key of the trust anchor.
+    predecessor: Option<TaKey>,
+
+    /// The next key of the trust anchor.
+    successor: Option<TaKey>,
+}
+
+impl TakContent {
+    /// Creates new content from its parts.
+    pub fn new(
+        current: TaKey,
+        predecessor: Option<TaKey>,
+        successor: Option<TaKey>,
+    ) -> Self {
+        TakContent { current, predecessor, successor }
+    }
+
+    /// Decodes the content of a signed object.
+    ///
+    /// Fails if the object has the wrong content type or the content is
+    /// invalid.
+    pub fn from_signed_object(
+        obj: &SignedObject
+    ) -> Result<Self, ValidationError> {
+        if CT_SIGNED_TAL != *obj.content_type() {
+            return Err(InspectionError::new("invalid content type").into())
+        }
+        obj.decode_content(Self::take_from).map_err(Into::into)
+    }
+
+    /// Returns the current key.
+    pub fn current(&self) -> &TaKey {
+        &self.current
+    }
+
+    /// Returns the predecessor key if present.
+    pub fn predecessor(&self) -> Option<&TaKey> {
+        self.predecessor.as_ref()
+    }
+
+    /// Returns the successor key if present.
+    pub fn successor(&self) -> Option<&TaKey> {
+        self.successor.as_ref()
+    }
+
+    /// Takes the content from an encoded constructed value.
+    fn take_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>
+    ) -> Result<Self, DecodeError<S::Error>> {
+        cons.take_sequence(|cons| {
+            // version INTEGER DEFAULT 0 -- must be 0.
+            if let Some(version) = cons.take_opt_u8()? {
+                if version != 0 {
+                    return Err(cons.content_err("invalid TAK version"))
+                }
+            }
+            let current = TaKey::take_from(cons)?;
+            let predecessor = cons.take_opt_constructed_if(
+                Tag::CTX_0, TaKey::take_from
+            )?;
+            let successor = cons.take_opt_constructed_if(
+                Tag::CTX_1, TaKey::take_from
+            )?;
+            Ok(TakContent { current, predecessor, successor })
+        })
+    }
+}
+
+
+//------------ TaKey ---------------------------------------------------------
+
+/// A trust anchor key as announced in a TAK object.
+#[derive(Clone, Debug)]
+pub struct TaKey {
+    /// Free-form comments describing the key.
+    comments: Vec<String>,
+
+    /// The URIs of the trust anchor certificate for the key.
+    uris: Vec<TalUri>,
+
+    /// The key itself.
+    key_info: PublicKey,
+}
+
+impl TaKey {
+    /// Creates a new key from its parts.
+    pub fn new(
+        comments: Vec<String>, uris: Vec<TalUri>, key_info: PublicKey
+    ) -> Self {
+        TaKey { comments, uris, key_info }
+    }
+
+    /// Returns the comments.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Returns the certificate URIs.
+    pub fn uris(&self) -> &[TalUri] {
+        &self.uris
+    }
+
+    /// Returns the key.
+    pub fn key_info(&self) -> &PublicKey {
+        &self.key_info
+    }
+
+    /// Takes a key from an encoded constructed value.
+    fn take_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>
+    ) -> Result<Self, DecodeError<S::Error>> {
+        cons.take_sequence(|cons| {
+            let comments = cons.take_sequence(|cons| {
+                let mut res = Vec::new();
+                while let Some(comment) = cons.take_opt_value_if(
+                    Tag::UTF8_STRING, Utf8String::from_content
+                )? {
+                    res.push(comment.to_string())
+                }
+                Ok(res)
+            })?;
+            let uris = cons.take_sequence(|cons| {
+                let mut res = Vec::new();
+                while let Some(uri) = cons.take_opt_value_if(
+                    Tag::IA5_STRING, Ia5String::from_content
+                )? {
+                    match TalUri::from_bytes(uri.into_bytes()) {
+                        Ok(uri) => res.push(uri),
+                        Err(_) => {
+                            return Err(cons.content_err(
+                                "invalid certificate URI"
+                            ))
+                        }
+                    }
+                }
+                Ok(res)
+            })?;
+            if uris.is_empty() {
+                return Err(cons.content_err("missing certificate URIs"))
+            }
+            let key_info = PublicKey::take_from(cons)?;
+            Ok(TaKey { comments, uris, key_info })
+        })
+    }
+
+    /// Returns a TAL file for the key with the given header lines.
+    ///
+    /// The header lines and the comments are included as comment lines.
+    fn to_tal_file(&self, header: &[String]) -> String {
+        let mut res = String::new();
+        for line in header.iter().chain(self.comments.iter()) {
+            for line in line.lines() {
+                res.push_str("# ");
+                res.push_str(line);
+                res.push('\n');
+            }
+        }
+        for uri in &self.uris {
+            res.push_str(uri.as_str());
+            res.push('\n');
+        }
+        res.push('\n');
+        res.push_str(&base64::Xml.encode(&self.key_info.to_info_bytes()));
+        res.push('\n');
+        res
+    }
+}
+
+
+//------------ TakState ------------------------------------------------------
+
+/// The rollover state of a TAL kept in the store.
+///
+/// Both the pending successor and the accepted TAL are stored as TAL files
+/// with some extra information in header comments: the key identifier of
+/// the configured key the rollover started from in a line
+/// `# base: <key identifier>` and, for a pending successor, the time it
+/// was first seen in a line `# since: <time>`.
+pub struct TakState<'a> {
+    /// The store to keep the state in.
+    store: &'a store::Run<'a>,
+
+    /// The configured TAL.
+    tal: &'a Tal,
+}
+
+impl<'a> TakState<'a> {
+    /// Creates access to the state of the configured TAL `tal`.
+    pub fn new(store: &'a store::Run<'a>, tal: &'a Tal) -> Self {
+        TakState { store, tal }
+    }
+
+    /// Returns the TAL to use for validation.
+    ///
+    /// This is the accepted TAL if there is one for the configured key or
+    /// a copy of the configured TAL otherwise.
+    pub fn effective_tal(&self) -> Result<Tal, Failed> {
+        if let Some(content) = self.load(TakFile::Accepted)? {
+            match Tal::read_named(
+                self.tal.info().name().into(), &mut content.as_bytes()
+            ) {
+                Ok(mut tal) => {
+                    tal.prefer_https();
+                    return Ok(tal)
+                }
+                Err(err) => {
+                    warn!(
+                        "Ignoring broken stored TAL for {}: {}",
+                        self.tal.info().name(), err
+                    );
+                }
+            }
+        }
+        Ok(self.tal.clone())
+    }
+
+    /// Updates the state from a valid TAK object.
+    ///
+    /// The TAK object must have been issued under the trust anchor of the
+    /// effective TAL `current`. The time `now` is used as the current time.
+    /// It is recorded as the time a new successor key was first seen, so it
+    /// must be the actual time and not a validation time.
+    ///
+    /// Before a successor key is accepted, `verify` is called with it and
+    /// must return whether a valid trust anchor certificate for the key
+    /// is available. If it returns `false`, the key stays pending.
+    pub fn update(
+        &self, current: &Tal, tak: &TakContent, now: Time,
+        verify: impl FnOnce(&TaKey) -> bool,
+    ) -> Result<(), Failed> {
+        let successor = match tak.successor() {
+            Some(successor) if successor.key_info() != current.key_info() => {
+                successor
+            }
+            _ => {
+                return self.store.remove_tak(
+                    self.tal.info().name(), TakFile::Pending
+                )
+            }
+        };
+        let since = match self.pending_since(successor)? {
+            Some(since) => since,
+            None => {
+                info!(
+                    "Trust anchor for TAL {} announced successor key {}. \
+                     Accepting it in {} days.",
+                    self.tal.info().name(),
+                    successor.key_info().key_identifier(),
+                    ACCEPTANCE_DAYS
+                );
+                let header = [
+                    format!("base: {}", self.base()),
+                    format!("since: {}", now.to_rfc3339()),
+                ];
+                return self.store.update_tak(
+                    self.tal.info().name(), TakFile::Pending,
+                    successor.to_tal_file(&header).as_bytes()
+                )
+            }
+        };
+        if now < since + chrono::Duration::days(ACCEPTANCE_DAYS) {
+            return Ok(())
+        }
+        if !verify(successor) {
+            warn!(
+                "No valid trust anchor certificate for successor key {} \
+                 of TAL {}. Keeping it pending.",
+                successor.key_info().key_identifier(),
+                self.tal.info().name()
+            );
+            return Ok(())
+        }
+        info!(
+            "Accepting successor key {} for TAL {}.",
+            successor.key_info().key_identifier(),
+            self.tal.info().name()
+        );
+        self.store.update_tak(
+            self.tal.info().name(), TakFile::Accepted,
+            successor.to_tal_file(
+                &[format!("base: {}", self.base())]
+            ).as_bytes()
+        )?;
+        self.store.remove_tak(self.tal.info().name(), TakFile::Pending)
+    }
+
+    /// Returns the time a pending successor key was first seen.
+    ///
+    /// Returns `None` if there is no pending key or it is a different key.
+    fn pending_since(
+        &self, successor: &TaKey
+    ) -> Result<Option<Time>, Failed> {
+        let content = match self.load(TakFile::Pending)? {
+            Some(content) => content,
+            None => return Ok(None)
+        };
+        let tal = match Tal::read_named(
+            self.tal.info().name().into(), &mut content.as_bytes()
+        ) {
+            Ok(tal) => tal,
+            Err(_) => return Ok(None)
+        };
+        if tal.key_info() != successor.key_info() {
+            return Ok(None)
+        }
+        Ok(header_value(&content, "since").and_then(|since| {
+            Time::from_str(since).ok()
+        }))
+    }
+
+    /// Loads a state file if it belongs to the configured key.
+    fn load(&self, file: TakFile) -> Result<Option<String>, Failed> {
+        let content = match self.store.load_tak(
+            self.tal.info().name(), file
+        )? {
+            Some(content) => content,
+            None => return Ok(None)
+        };
+        let content = match String::from_utf8(content.to_vec()) {
+            Ok(content) => content,
+            Err(_) => return Ok(None)
+        };
+        let base = header_value(&content, "base").and_then(|base| {
+            KeyIdentifier::from_str(base).ok()
+        });
+        if base != Some(self.tal.key_info().key_identifier()) {
+            return Ok(None)
+        }
+        Ok(Some(content))
+    }
+
+    /// Returns the key identifier of the configured key.
+    fn base(&self) -> KeyIdentifier {
+        self.tal.key_info().key_identifier()
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Returns the value of a header comment line of a TAL file.
+fn header_value<'s>(content: &'s str, key: &str) -> Option<&'s str> {
+    content.lines().take_while(|line| {
+        line.starts_with('#')
+    }).find_map(|line| {
+        line.strip_prefix("# ")?.strip_prefix(key)?.strip_prefix(": ")
+    }).map(str::trim)
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use chrono::Duration;
+    use crate::utils::testing::{TAL_NAME, TestRepository};
+
+    /// Reads the TAL of the test repository.
+    fn tal(repo: &TestRepository) -> Tal {
+        let path = repo.config().extra_tals_dir.as_ref().unwrap().join(
+            format!("{}.tal", TAL_NAME)
+        );
+        Tal::read_named(
+            TAL_NAME.into(), &mut fs::File::open(path).unwrap()
+        ).unwrap()
+    }
+
+    /// Creates TAK content for the TAL’s key and an optional successor.
+    fn tak(tal: &Tal, successor: Option<&PublicKey>) -> TakContent {
+        let key = |key_info: &PublicKey| {
+            TaKey::new(
+                Vec::new(), tal.uris().cloned().collect(), key_info.clone()
+            )
+        };
+        TakContent::new(key(tal.key_info()), None, successor.map(key))
+    }
+
+    /// Returns whether there is a state file.
+    fn has_file(run: &store::Run, file: TakFile) -> bool {
+        run.load_tak(TAL_NAME, file).unwrap().is_some()
+    }
+
+    #[test]
+    fn successor_accepted() {
+        let (repo, _) = TestRepository::new();
+        let tal = tal(&repo);
+        let successor = repo.new_key();
+        let tak = tak(&tal, Some(&successor));
+        let store = repo.store();
+        let run = store.start();
+        let state = TakState::new(&run, &tal);
+        let now = Time::now();
+
+        state.update(&tal, &tak, now, |_| unreachable!()).unwrap();
+        assert!(has_file(&run, TakFile::Pending));
+        assert!(!has_file(&run, TakFile::Accepted));
+
+        // Seeing the key again doesn’t restart the acceptance time.
+        let later = now + Duration::days(ACCEPTANCE_DAYS - 1);
+        state.update(&tal, &tak, later, |_| unreachable!()).unwrap();
+        assert!(has_file(&run, TakFile::Pending));
+        assert_eq!(state.effective_tal().unwrap().key_info(), tal.key_info());
+
+        // Without a valid certificate, the key stays pending.
+        let later = now + Duration::days(ACCEPTANCE_DAYS);
+        state.update(&tal, &tak, later, |_| false).unwrap();
+        assert!(has_file(&run, TakFile::Pending));
+        assert!(!has_file(&run, TakFile::Accepted));
+
+        state.update(&tal, &tak, later, |key| {
+            key.key_info() == &successor
+        }).unwrap();
+        assert!(!has_file(&run, TakFile::Pending));
+        assert!(has_file(&run, TakFile::Accepted));
+        assert_eq!(state.effective_tal().unwrap().key_info(), &successor);
+    }
+
+    #[test]
+    fn successor_withdrawn() {
+        let (repo, _) = TestRepository::new();
+        let tal = tal(&repo);
+        let successor = repo.new_key();
+        let store = repo.store();
+        let run = store.start();
+        let state = TakState::new(&run, &tal);
+        let now = Time::now();
+
+        state.update(
+            &tal, &tak(&tal, Some(&successor)), now, |_| unreachable!()
+        ).unwrap();
+        assert!(has_file(&run, TakFile::Pending));
+        state.update(&tal, &tak(&tal, None), now, |_| unreachable!()).unwrap();
+        assert!(!has_file(&run, TakFile::Pending));
+
+        // Announcing the current key as successor reverts the rollover, too.
+        state.update(
+            &tal, &tak(&tal, Some(&successor)), now, |_| unreachable!()
+        ).unwrap();
+        assert!(has_file(&run, TakFile::Pending));
+        state.update(
+            &tal, &tak(&tal, Some(tal.key_info())), now, |_| unreachable!()
+        ).unwrap();
+        assert!(!has_file(&run, TakFile::Pending));
+
+        // A new pending key starts over.
+        let later = now + Duration::days(ACCEPTANCE_DAYS);
+        let tak = tak(&tal, Some(&successor));
+        state.update(&tal, &tak, later, |_| unreachable!()).unwrap();
+        state.update(&tal, &tak, later, |_| unreachable!()).unwrap();
+        assert!(!has_file(&run, TakFile::Accepted));
+    }
+
+    #[test]
+    fn header_values() {
+        let content = "# base: 0011\n# since: 2024-01-01T00:00:00+00:00\n\
+                       # some comment\nhttps://example.com/ta.cer\n\n";
+        assert_eq!(header_value(content, "base"), Some("0011"));
+        assert_eq!(
+            header_value(content, "since"),
+            Some("2024-01-01T00:00:00+00:00")
+        );
+        assert_eq!(header_value(content, "uri"), None);
+        assert!(
+            Time::from_str(header_value(content, "since").unwrap()).is_ok()
+        );
+    }
+}
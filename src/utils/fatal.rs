@@ -348,6 +348,31 @@ pub fn copy_dir_all(source: &Path, target: &Path) -> Result<(), Failed> {
 }
 
 
+//------------ dir_size ------------------------------------------------------
+
+/// Returns the combined size of all files under a directory.
+///
+/// Returns zero if the directory doesn’t exist. Errors out if anything
+/// else goes wrong.
+pub fn dir_size(path: &Path) -> Result<u64, Failed> {
+    let dir = match read_existing_dir(path)? {
+        Some(dir) => dir,
+        None => return Ok(0),
+    };
+    let mut res = 0u64;
+    for entry in dir {
+        let entry = entry?;
+        if entry.is_file() {
+            res = res.saturating_add(entry.len());
+        }
+        else if entry.is_dir() {
+            res = res.saturating_add(dir_size(entry.path())?);
+        }
+    }
+    Ok(res)
+}
+
+
 //------------ IoErrorDisplay ------------------------------------------------
 
 struct IoErrorDisplay(io::Error);
@@ -9,6 +9,7 @@ pub mod json;
 pub mod net;
 pub mod str;
 pub mod sync;
+#[cfg(test)] pub mod testing;
 pub mod tls;
 pub mod uri;
 
@@ -0,0 +1,596 @@
+//! Building RPKI repositories for tests.
+//!
+//! A [`TestRepository`] creates a trust anchor and any number of CAs with
+//! real keys and writes their publication points directly into the store
+//! of a temporary cache directory. An engine created via
+//! [`TestRepository::engine`] doesn’t have a collector and will validate
+//! exactly this data.
+
+use std::fs;
+use std::cell::Cell;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use std::sync::Arc;
+use bcder::{Mode, Oid};
+use bcder::encode::Values;
+use bytes::Bytes;
+use chrono::Duration;
+use rpki::uri;
+use rpki::crypto::{
+    DigestAlgorithm, PublicKey, PublicKeyFormat, RpkiSignatureAlgorithm,
+    Signer,
+};
+use rpki::crypto::softsigner::{KeyId, OpenSslSigner};
+use rpki::repository::aspa::AspaBuilder;
+use rpki::repository::cert::{
+    Cert, KeyUsage, Overclaim, TbsCert
+};
+use rpki::repository::crl::{CrlEntry, TbsCertList};
+use rpki::repository::manifest::{FileAndHash, ManifestContent, ManifestHash};
+use rpki::repository::resources::{
+    AsBlocks, AsResources, Asn, IpBlocks, IpResources,
+};
+use rpki::repository::roa::RoaBuilder;
+use rpki::repository::sigobj::SignedObjectBuilder;
+use rpki::repository::tal::{TalInfo, TalUri};
+use rpki::repository::x509::{Serial, Time, Validity};
+use rpki::util::base64;
+use tempfile::TempDir;
+use crate::config::Config;
+use crate::engine::{CaCert, Engine};
+use crate::store::{Store, StoredManifest, StoredObject};
+
+
+//------------ Constants -----------------------------------------------------
+
+/// The name of the TAL of the test repository.
+pub const TAL_NAME: &str = "test";
+
+/// The object identifier of the Ghostbuster Record content type.
+const CT_RPKI_GHOSTBUSTERS: Oid<&[u8]>
+    = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 35]);
+
+/// The maximum CA depth used when chaining CA certificates.
+const MAX_DEPTH: usize = 32;
+
+
+//------------ TestRepository ------------------------------------------------
+
+/// A temporary RPKI repository for tests.
+pub struct TestRepository {
+    /// The temporary directory holding TAL and cache directories.
+    ///
+    /// This is only here to keep the directory alive.
+    _dir: TempDir,
+
+    /// The configuration to use with the repository.
+    config: Config,
+
+    /// The signer holding all the keys.
+    signer: OpenSslSigner,
+
+    /// The serial number for the next certificate.
+    serial: Cell<u64>,
+}
+
+impl TestRepository {
+    /// Creates a new repository and returns it and its trust anchor.
+    ///
+    /// The trust anchor holds 10.0.0.0/8 and AS64496 to AS64511. Its
+    /// certificate is stored and its TAL written, but its publication
+    /// point only appears once it has been published.
+    pub fn new() -> (Self, TestCa) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            cache_dir: dir.path().join("cache"),
+            no_rir_tals: true,
+            extra_tals_dir: Some(dir.path().join("tals")),
+            validation_threads: 1,
+            .. Default::default()
+        };
+        fs::create_dir_all(&config.cache_dir).unwrap();
+        fs::create_dir_all(dir.path().join("tals")).unwrap();
+        let repo = TestRepository {
+            _dir: dir,
+            config,
+            signer: OpenSslSigner::new(),
+            serial: Cell::new(1),
+        };
+        let ta = repo.make_ta();
+        (repo, ta)
+    }
+
+    /// Returns the configuration for the repository.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the configuration for the repository for modification.
+    ///
+    /// Changes only affect engines created afterwards.
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Creates an engine without collector for the repository.
+    pub fn engine(&self) -> Engine {
+        Engine::new(&self.config, false).unwrap()
+    }
+
+    /// Returns a new store for the repository’s cache.
+    pub fn store(&self) -> Store {
+        Store::new(&self.config).unwrap()
+    }
+
+    /// Creates a new key and returns its public key.
+    pub fn new_key(&self) -> PublicKey {
+        let key = self.signer.create_key(PublicKeyFormat::Rsa).unwrap();
+        self.signer.get_key_info(&key).unwrap()
+    }
+
+    /// Adds a child CA with the given resources to `parent`.
+    ///
+    /// The resources are given as a comma separated list of IPv4 blocks
+    /// and of AS blocks in the form `AS64496-AS64499`. The CA certificate
+    /// is added to the parent’s objects as `<name>.cer`.
+    pub fn child(
+        &self, parent: &mut TestCa, name: &str, v4: &str, asns: &str,
+    ) -> TestCa {
+        let key = self.signer.create_key(PublicKeyFormat::Rsa).unwrap();
+        let key_info = self.signer.get_key_info(&key).unwrap();
+        let mut child = TestCa {
+            key, key_info,
+            uri: parent.object_uri(&format!("{}.cer", name)),
+            repository: uri::Rsync::from_string(
+                format!("rsync://example.test/repo/{}/", name)
+            ).unwrap(),
+            v4: IpBlocks::from_str(v4).unwrap(),
+            asns: AsBlocks::from_str(asns).unwrap(),
+            cert: None,
+            ca: None,
+            objects: Vec::new(),
+            revoked: Vec::new(),
+            manifest_number: 0,
+            published: Vec::new(),
+        };
+        self.issue(parent, &mut child);
+        child
+    }
+
+    /// Reissues the certificate of `child` with a new serial number.
+    pub fn reissue(&self, parent: &mut TestCa, child: &mut TestCa) {
+        self.issue(parent, child);
+    }
+
+    /// Reissues the certificate of `child` for a new key.
+    ///
+    /// Objects already signed by the child keep using the old key and need
+    /// to be reissued, too.
+    pub fn rekey(&self, parent: &mut TestCa, child: &mut TestCa) {
+        child.key = self.signer.create_key(PublicKeyFormat::Rsa).unwrap();
+        child.key_info = self.signer.get_key_info(&child.key).unwrap();
+        self.issue(parent, child);
+    }
+
+    /// Adds a ROA for a single IPv4 prefix to the CA’s objects.
+    ///
+    /// The prefix is given as `address/len` with an optional max-length
+    /// appended as `-max_len`. Returns the serial number of the ROA’s EE
+    /// certificate.
+    pub fn roa(
+        &self, ca: &mut TestCa, name: &str, asn: u32, prefix: &str,
+    ) -> Serial {
+        let (prefix, max_len) = match prefix.split_once('-') {
+            Some((prefix, max_len)) => {
+                (prefix, Some(u8::from_str(max_len).unwrap()))
+            }
+            None => (prefix, None)
+        };
+        let (addr, len) = prefix.split_once('/').unwrap();
+        let mut builder = RoaBuilder::new(Asn::from_u32(asn));
+        builder.push_v4_addr(
+            Ipv4Addr::from_str(addr).unwrap(),
+            u8::from_str(len).unwrap(),
+            max_len,
+        );
+        let name = format!("{}.roa", name);
+        let (sigobj, serial) = self.sigobj(ca, &name);
+        let roa = builder.finalize(sigobj, &self.signer, &ca.key).unwrap();
+        ca.add_object(&name, roa.to_captured().into_bytes());
+        serial
+    }
+
+    /// Adds an ASPA object to the CA’s objects.
+    pub fn aspa(
+        &self, ca: &mut TestCa, name: &str, customer: u32, providers: &[u32]
+    ) -> Serial {
+        let builder = AspaBuilder::new(
+            Asn::from_u32(customer),
+            providers.iter().copied().map(Asn::from_u32).collect::<Vec<_>>()
+        ).unwrap();
+        let name = format!("{}.asa", name);
+        let (sigobj, serial) = self.sigobj(ca, &name);
+        let aspa = builder.finalize(sigobj, &self.signer, &ca.key).unwrap();
+        ca.add_object(&name, aspa.to_captured().into_bytes());
+        serial
+    }
+
+    /// Adds a Ghostbuster Record with the given vCard to the CA’s objects.
+    pub fn gbr(&self, ca: &mut TestCa, name: &str, vcard: &str) -> Serial {
+        let name = format!("{}.gbr", name);
+        let (mut sigobj, serial) = self.sigobj(ca, &name);
+        sigobj.set_v4_resources_inherit();
+        sigobj.set_v6_resources_inherit();
+        sigobj.set_as_resources_inherit();
+        let obj = sigobj.finalize(
+            Oid(Bytes::from_static(CT_RPKI_GHOSTBUSTERS.0)),
+            Bytes::copy_from_slice(vcard.as_bytes()),
+            &self.signer, &ca.key
+        ).unwrap();
+        ca.add_object(
+            &name, obj.encode_ref().to_captured(Mode::Der).into_bytes()
+        );
+        serial
+    }
+
+    /// Publishes the current objects of the CA.
+    ///
+    /// Creates a new CRL and manifest with the next manifest number and
+    /// replaces the CA’s stored publication point.
+    pub fn publish(&self, ca: &mut TestCa) {
+        ca.manifest_number += 1;
+        let now = Time::now();
+        let next_update = now + Duration::days(1);
+
+        let crl_name = "ca.crl";
+        let crl = TbsCertList::new(
+            RpkiSignatureAlgorithm::default(),
+            ca.key_info.to_subject_name(),
+            now - Duration::hours(1),
+            next_update,
+            ca.revoked.iter().map(|serial| {
+                CrlEntry::new(*serial, now - Duration::hours(1))
+            }).collect::<Vec<_>>(),
+            ca.key_info.key_identifier(),
+            Serial::from(ca.manifest_number),
+        ).into_crl(&self.signer, &ca.key).unwrap();
+        let crl = crl.to_captured().into_bytes();
+
+        let digest = DigestAlgorithm::default();
+        let mut files = vec![
+            FileAndHash::new(
+                Bytes::from_static(crl_name.as_bytes()),
+                Bytes::copy_from_slice(digest.digest(&crl).as_ref()),
+            )
+        ];
+        files.extend(ca.objects.iter().map(|(name, content)| {
+            FileAndHash::new(
+                Bytes::copy_from_slice(name.as_bytes()),
+                Bytes::copy_from_slice(digest.digest(content).as_ref()),
+            )
+        }));
+        let mft_name = "ca.mft";
+        let (sigobj, _) = self.sigobj(ca, mft_name);
+        let manifest = ManifestContent::new(
+            Serial::from(ca.manifest_number),
+            now - Duration::hours(1),
+            next_update,
+            digest,
+            files.iter(),
+        ).into_manifest(sigobj, &self.signer, &ca.key).unwrap();
+        let not_after = manifest.cert().validity().not_after();
+        let manifest = manifest.to_captured().into_bytes();
+
+        ca.published = ca.objects.clone();
+        ca.published.push((mft_name.into(), manifest.clone()));
+        ca.published.push((crl_name.into(), crl.clone()));
+
+        let store = self.store();
+        let run = store.start();
+        let mut point = run.pub_point(ca.ca()).unwrap();
+        let mut objects = ca.objects.iter().map(|(name, content)| {
+            StoredObject::new(
+                ca.object_uri(name),
+                content.clone(),
+                Some(ManifestHash::new(
+                    Bytes::copy_from_slice(digest.digest(content).as_ref()),
+                    digest,
+                ))
+            )
+        });
+        point.update(
+            StoredManifest::new(
+                not_after, None, ca.repository.clone(),
+                ca.object_uri(mft_name), manifest,
+                ca.object_uri(crl_name), crl,
+            ),
+            || Ok(objects.next())
+        ).unwrap();
+    }
+
+    /// Writes the last published point of the CA to the rsync collector.
+    ///
+    /// An engine with a collector whose rsync command doesn’t touch any
+    /// files, such as `true`, will find the point as it was published at
+    /// the time of the call.
+    pub fn mirror(&self, ca: &TestCa) {
+        for (name, content) in &ca.published {
+            let uri = ca.object_uri(name);
+            let path = self.config.cache_dir.join("rsync")
+                .join(uri.canonical_authority().as_ref())
+                .join(uri.module_name())
+                .join(uri.path());
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    /// Creates the trust anchor, stores its certificate and writes the TAL.
+    fn make_ta(&self) -> TestCa {
+        let key = self.signer.create_key(PublicKeyFormat::Rsa).unwrap();
+        let key_info = self.signer.get_key_info(&key).unwrap();
+        let mut ta = TestCa {
+            key, key_info,
+            uri: uri::Rsync::from_str(
+                "rsync://example.test/repo/ta.cer"
+            ).unwrap(),
+            repository: uri::Rsync::from_str(
+                "rsync://example.test/repo/ta/"
+            ).unwrap(),
+            v4: IpBlocks::from_str("10.0.0.0/8").unwrap(),
+            asns: AsBlocks::from_str("AS64496-AS64511").unwrap(),
+            cert: None,
+            ca: None,
+            objects: Vec::new(),
+            revoked: Vec::new(),
+            manifest_number: 0,
+            published: Vec::new(),
+        };
+        let cert = self.ta_cert(&ta, &ta.key_info, self.validity());
+        let store = self.store();
+        store.start().update_ta(
+            &TalUri::Rsync(ta.uri.clone()),
+            &cert.to_captured().into_bytes(),
+        ).unwrap();
+        let rcert = cert.clone().validate_ta_at(
+            TalInfo::from_name(TAL_NAME.into()).into_arc(), false, Time::now()
+        ).unwrap();
+        ta.ca = Some(
+            CaCert::root(rcert, TalUri::Rsync(ta.uri.clone()), 0, None)
+                .unwrap()
+        );
+        ta.cert = Some(cert);
+        fs::write(
+            self.config.extra_tals_dir.as_ref().unwrap().join(
+                format!("{}.tal", TAL_NAME)
+            ),
+            format!(
+                "{}\n\n{}\n", ta.uri,
+                base64::Xml.encode(&ta.key_info.to_info_bytes())
+            )
+        ).unwrap();
+        ta
+    }
+
+    /// Creates a self-signed trust anchor certificate for `ta`.
+    ///
+    /// The certificate is signed with the trust anchor’s key but contains
+    /// `key_info` as its public key. This allows creating bogus trust
+    /// anchor certificates.
+    pub fn ta_cert(
+        &self, ta: &TestCa, key_info: &PublicKey, validity: Validity,
+    ) -> Cert {
+        self.sign_ta(ta, &ta.key, key_info, validity)
+    }
+
+    /// Creates a trust anchor certificate for a new key of `ta`.
+    ///
+    /// Returns the new public key and the certificate self-signed with it.
+    pub fn successor_cert(&self, ta: &TestCa) -> (PublicKey, Cert) {
+        let key = self.signer.create_key(PublicKeyFormat::Rsa).unwrap();
+        let key_info = self.signer.get_key_info(&key).unwrap();
+        let cert = self.sign_ta(ta, &key, &key_info, self.validity());
+        (key_info, cert)
+    }
+
+    /// Creates a trust anchor certificate for `ta` signed with `key`.
+    fn sign_ta(
+        &self, ta: &TestCa, key: &KeyId, key_info: &PublicKey,
+        validity: Validity,
+    ) -> Cert {
+        let mut tbs = TbsCert::new(
+            self.next_serial(),
+            key_info.to_subject_name(),
+            validity,
+            None,
+            key_info.clone(),
+            KeyUsage::Ca,
+            Overclaim::Trim,
+        );
+        tbs.set_basic_ca(Some(true));
+        tbs.set_ca_repository(Some(ta.repository.clone()));
+        tbs.set_rpki_manifest(Some(ta.object_uri("ca.mft")));
+        tbs.set_v4_resources(IpResources::blocks(ta.v4.clone()));
+        tbs.set_as_resources(AsResources::blocks(ta.asns.clone()));
+        Self::reencode(tbs.into_cert(&self.signer, key).unwrap())
+    }
+
+    /// Issues a certificate for `child` and adds it to `parent`.
+    fn issue(&self, parent: &mut TestCa, child: &mut TestCa) {
+        let mut tbs = TbsCert::new(
+            self.next_serial(),
+            parent.key_info.to_subject_name(),
+            self.validity(),
+            None,
+            child.key_info.clone(),
+            KeyUsage::Ca,
+            Overclaim::Trim,
+        );
+        tbs.set_basic_ca(Some(true));
+        tbs.set_authority_key_identifier(
+            Some(parent.key_info.key_identifier())
+        );
+        tbs.set_crl_uri(Some(parent.object_uri("ca.crl")));
+        tbs.set_ca_issuer(Some(parent.uri.clone()));
+        tbs.set_ca_repository(Some(child.repository.clone()));
+        tbs.set_rpki_manifest(Some(child.object_uri("ca.mft")));
+        tbs.set_v4_resources(IpResources::blocks(child.v4.clone()));
+        tbs.set_as_resources(AsResources::blocks(child.asns.clone()));
+        let cert = Self::reencode(
+            tbs.into_cert(&self.signer, &parent.key).unwrap()
+        );
+        let name = child.uri.path().rsplit('/').next().unwrap().to_string();
+        parent.add_object(&name, cert.to_captured().into_bytes());
+        let rcert = cert.clone().validate_ca_at(
+            parent.ca().cert(), false, Time::now()
+        ).unwrap();
+        child.cert = Some(cert);
+        child.ca = Some(
+            CaCert::chain(parent.ca(), child.uri.clone(), rcert, MAX_DEPTH)
+                .unwrap()
+        );
+    }
+
+    /// Returns the certificate as it would be decoded from its encoding.
+    ///
+    /// The times of a new certificate have a higher precision than their
+    /// encoded form, so certificates are only equal to those loaded by the
+    /// engine after a round trip.
+    fn reencode(cert: Cert) -> Cert {
+        Cert::decode(cert.to_captured().into_bytes()).unwrap()
+    }
+
+    /// Returns a signed object builder for an object of the CA.
+    fn sigobj(
+        &self, ca: &TestCa, name: &str
+    ) -> (SignedObjectBuilder, Serial) {
+        let serial = self.next_serial();
+        let mut res = SignedObjectBuilder::new(
+            serial,
+            self.validity(),
+            ca.object_uri("ca.crl"),
+            ca.uri.clone(),
+            ca.object_uri(name),
+        );
+        res.set_issuer(Some(ca.key_info.to_subject_name()));
+        (res, serial)
+    }
+
+    /// Returns the validity for new certificates.
+    pub fn validity(&self) -> Validity {
+        let now = Time::now();
+        Validity::new(now - Duration::days(1), now + Duration::days(365))
+    }
+
+    /// Returns the next serial number.
+    fn next_serial(&self) -> Serial {
+        let res = self.serial.get();
+        self.serial.set(res + 1);
+        Serial::from(res)
+    }
+}
+
+
+//------------ TestCa --------------------------------------------------------
+
+/// A CA of a test repository.
+pub struct TestCa {
+    /// The CA’s key.
+    key: KeyId,
+
+    /// The public key of the CA’s key.
+    key_info: PublicKey,
+
+    /// The URI of the CA certificate.
+    uri: uri::Rsync,
+
+    /// The URI of the CA’s repository directory.
+    repository: uri::Rsync,
+
+    /// The IPv4 resources of the CA.
+    v4: IpBlocks,
+
+    /// The AS resources of the CA.
+    asns: AsBlocks,
+
+    /// The current CA certificate.
+    cert: Option<Cert>,
+
+    /// The current CA certificate as validated by the engine.
+    ca: Option<Arc<CaCert>>,
+
+    /// The objects of the publication point, except manifest and CRL.
+    objects: Vec<(String, Bytes)>,
+
+    /// The serial numbers of revoked certificates.
+    revoked: Vec<Serial>,
+
+    /// The number of the last manifest published.
+    manifest_number: u64,
+
+    /// The files of the last published point, including manifest and CRL.
+    published: Vec<(String, Bytes)>,
+}
+
+impl TestCa {
+    /// Returns the URI of the CA certificate.
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    /// Returns the current CA certificate.
+    pub fn cert(&self) -> &Cert {
+        self.cert.as_ref().unwrap()
+    }
+
+    /// Returns the public key of the CA’s key.
+    pub fn key_info(&self) -> &PublicKey {
+        &self.key_info
+    }
+
+    /// Changes the IPv4 resources for future certificates of the CA.
+    pub fn set_v4(&mut self, v4: &str) {
+        self.v4 = IpBlocks::from_str(v4).unwrap()
+    }
+
+    /// Changes the repository directory for future certificates of the CA.
+    pub fn set_repository(&mut self, uri: &str) {
+        self.repository = uri::Rsync::from_str(uri).unwrap()
+    }
+
+    /// Returns the validated CA certificate.
+    pub fn ca(&self) -> &Arc<CaCert> {
+        self.ca.as_ref().unwrap()
+    }
+
+    /// Returns the key identifier of the CA’s key.
+    pub fn key_id(&self) -> rpki::crypto::KeyIdentifier {
+        self.key_info.key_identifier()
+    }
+
+    /// Returns the URI of an object published by the CA.
+    pub fn object_uri(&self, name: &str) -> uri::Rsync {
+        self.repository.join(name.as_bytes()).unwrap()
+    }
+
+    /// Returns the content of the object with the given file name.
+    pub fn object(&self, name: &str) -> Bytes {
+        self.objects.iter().find(|(item, _)| item == name).unwrap().1.clone()
+    }
+
+    /// Removes the object with the given file name.
+    pub fn remove_object(&mut self, name: &str) {
+        self.objects.retain(|(item, _)| item != name)
+    }
+
+    /// Marks the certificate with the given serial number as revoked.
+    pub fn revoke(&mut self, serial: Serial) {
+        self.revoked.push(serial)
+    }
+
+    /// Adds or replaces an object.
+    fn add_object(&mut self, name: &str, content: Bytes) {
+        self.remove_object(name);
+        self.objects.push((name.into(), content))
+    }
+}
@@ -1,10 +1,15 @@
 //! Checking for validity of route announcements.
+//!
+//! Route origin validation of a prefix and origin AS number is provided by
+//! [`RouteValidity`] while [`AspaPathValidity`] verifies an AS path using
+//! ASPA payload.
 
 use std::{fmt, io};
 use std::str::FromStr;
 use chrono::{DateTime, Utc};
 use rpki::resources::{Asn, Prefix};
-use rpki::rtr::payload::RouteOrigin;
+use rpki::resources::asn::ParseAsnError;
+use rpki::rtr::payload::{Aspa, RouteOrigin};
 use serde::Deserialize;
 use crate::payload::{PayloadInfo, PayloadSnapshot};
 use crate::utils::date::format_iso_date;
@@ -353,6 +358,330 @@ impl fmt::Display for RouteState {
 }
 
 
+//------------ AspaPathValidity ----------------------------------------------
+
+/// Information about the ASPA validity of an AS path.
+///
+/// The AS path is verified using the procedure defined in
+/// [draft-ietf-sidrops-aspa-verification][draft]. The path is given in the
+/// order of the AS_PATH attribute, i.e., the first AS number is the neighbor
+/// the route was received from and the last AS number is the origin. Only
+/// AS_SEQUENCE segments are supported. Prepending is removed before
+/// verification.
+///
+/// [draft]: https://tools.ietf.org/html/draft-ietf-sidrops-aspa-verification
+#[derive(Clone, Debug)]
+pub struct AspaPathValidity<'a> {
+    /// The AS path as given.
+    path: Vec<Asn>,
+
+    /// The direction the route was received from.
+    direction: AspaDirection,
+
+    /// The resulting state.
+    state: AspaState,
+
+    /// The hop that caused the path to not be valid.
+    ///
+    /// The first element is the customer AS number, the second the AS
+    /// number that should have been its provider.
+    failed_hop: Option<(Asn, Asn)>,
+
+    /// The ASPAs for the AS numbers of the path.
+    aspas: Vec<(&'a Aspa, &'a PayloadInfo)>,
+}
+
+impl<'a> AspaPathValidity<'a> {
+    pub fn new(
+        path: Vec<Asn>,
+        direction: AspaDirection,
+        snapshot: &'a PayloadSnapshot
+    ) -> Self {
+        let aspas: Vec<_> = snapshot.aspas().filter(|item| {
+            path.contains(&item.0.customer)
+        }).collect();
+
+        // The procedure wants the path starting from the origin.
+        let mut hops: Vec<_> = path.iter().rev().copied().collect();
+        hops.dedup();
+        let (state, failed_hop) = verify_path(
+            &hops, direction, |customer, provider| {
+                let mut res = Hop::NoAttestation;
+                for item in &aspas {
+                    if item.0.customer == customer {
+                        if item.0.providers.iter().any(|asn| asn == provider) {
+                            return Hop::ProviderPlus
+                        }
+                        res = Hop::NotProviderPlus
+                    }
+                }
+                res
+            }
+        );
+        AspaPathValidity { path, direction, state, failed_hop, aspas }
+    }
+
+    pub fn path(&self) -> &[Asn] {
+        &self.path
+    }
+
+    pub fn direction(&self) -> AspaDirection {
+        self.direction
+    }
+
+    pub fn state(&self) -> AspaState {
+        self.state
+    }
+
+    pub fn failed_hop(&self) -> Option<(Asn, Asn)> {
+        self.failed_hop
+    }
+
+    pub fn aspas(&self) -> &[(&'a Aspa, &'a PayloadInfo)] {
+        &self.aspas
+    }
+
+    pub fn write_plain<W: io::Write>(
+        &self,
+        target: &mut W
+    ) -> Result<(), io::Error> {
+        for asn in &self.path {
+            write!(target, "{} ", asn)?;
+        }
+        write!(target, "({}): {}", self.direction, self.state)?;
+        if let Some((customer, provider)) = self.failed_hop {
+            write!(target, " at {} => {}", customer, provider)?;
+        }
+        writeln!(target)
+    }
+
+    pub fn into_json(self, current: &PayloadSnapshot) -> Vec<u8> {
+        let mut res = Vec::new();
+        self.write_json(current, &mut res).unwrap();
+        res
+    }
+
+    pub fn write_json<W: io::Write>(
+        &self,
+        current: &PayloadSnapshot,
+        target: &mut W
+    ) -> Result<(), io::Error> {
+        write!(target,
+            "{{\n  \"validated_path\": {{\n    \"path\": [",
+        )?;
+        let mut first = true;
+        for asn in &self.path {
+            if first {
+                first = false;
+            }
+            else {
+                write!(target, ", ")?;
+            }
+            write!(target, "\"{}\"", asn)?;
+        }
+        writeln!(target,
+            "],\n    \"direction\": \"{}\",\n    \"validity\": {{\n      \
+             \"state\": \"{}\",",
+            self.direction, self.state,
+        )?;
+        if let Some((customer, provider)) = self.failed_hop {
+            writeln!(target,
+                "      \"failed_hop\": {{\n        \
+                 \"customer\": \"{}\",\n        \
+                 \"provider\": \"{}\"\n      }},",
+                customer, provider
+            )?;
+        }
+        write!(target, "      \"ASPAs\": [")?;
+        let mut first = true;
+        for item in &self.aspas {
+            if first {
+                first = false;
+            }
+            else {
+                write!(target, ",")?;
+            }
+            write!(target,
+                "\n        {{\n          \"customer\": \"{}\",\n          \
+                 \"providers\": [",
+                item.0.customer
+            )?;
+            let mut first = true;
+            for asn in item.0.providers.iter() {
+                if first {
+                    first = false;
+                }
+                else {
+                    write!(target, ", ")?;
+                }
+                write!(target, "\"{}\"", asn)?;
+            }
+            write!(target, "]\n        }}")?;
+        }
+        writeln!(target,
+            "\n      ]\n    }}\n  }},\
+            \n  \"generatedTime\": \"{}\"\
+            \n}}",
+            format_iso_date(current.created()),
+        )
+    }
+}
+
+
+//------------ AspaDirection -------------------------------------------------
+
+/// The direction a route was received from for AS path verification.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum AspaDirection {
+    /// The route was received from a customer or lateral peer.
+    Upstream,
+
+    /// The route was received from a provider.
+    Downstream,
+}
+
+impl FromStr for AspaDirection {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upstream" => Ok(AspaDirection::Upstream),
+            "downstream" => Ok(AspaDirection::Downstream),
+            _ => Err("expected 'upstream' or 'downstream'")
+        }
+    }
+}
+
+impl fmt::Display for AspaDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            AspaDirection::Upstream => "upstream",
+            AspaDirection::Downstream => "downstream",
+        })
+    }
+}
+
+
+//------------ AspaState -----------------------------------------------------
+
+/// The ASPA state of an AS path.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AspaState {
+    /// ASPA Valid.
+    ///
+    /// All hops of the path are attested by ASPAs.
+    Valid,
+
+    /// ASPA Invalid.
+    ///
+    /// At least one hop of the path contradicts an ASPA.
+    Invalid,
+
+    /// ASPA Unknown.
+    ///
+    /// The path could not be verified because of missing ASPAs.
+    Unknown,
+}
+
+impl fmt::Display for AspaState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            AspaState::Valid => "valid",
+            AspaState::Invalid => "invalid",
+            AspaState::Unknown => "unknown",
+        })
+    }
+}
+
+
+//------------ Hop -----------------------------------------------------------
+
+/// The result of checking a single hop of an AS path.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Hop {
+    /// The second AS is a provider of the first AS.
+    ProviderPlus,
+
+    /// The first AS has an ASPA that doesn’t include the second AS.
+    NotProviderPlus,
+
+    /// The first AS has no ASPA.
+    NoAttestation,
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Parses an AS path from a string.
+///
+/// The AS numbers can be separated by white space or commas.
+pub fn parse_as_path(s: &str) -> Result<Vec<Asn>, ParseAsnError> {
+    s.split(|ch: char| ch.is_whitespace() || ch == ',').filter(|item| {
+        !item.is_empty()
+    }).map(Asn::from_str).collect()
+}
+
+/// Verifies an AS path.
+///
+/// The path must start with the origin AS and must not contain prepending.
+/// The closure `hop` checks whether its second argument is a provider of
+/// its first argument.
+///
+/// Returns the state and, if the path isn’t valid, the hop at the end of
+/// the up-ramp as a pair of customer and provider.
+fn verify_path(
+    path: &[Asn],
+    direction: AspaDirection,
+    hop: impl Fn(Asn, Asn) -> Hop,
+) -> (AspaState, Option<(Asn, Asn)>) {
+    let len = path.len();
+    if len == 0 {
+        return (AspaState::Invalid, None)
+    }
+    let (min_up, max_up) = ramp(
+        path.windows(2).map(|pair| hop(pair[0], pair[1])), len
+    );
+    let (min_down, max_down) = match direction {
+        AspaDirection::Upstream => (0, 0),
+        AspaDirection::Downstream => {
+            ramp(path.windows(2).rev().map(|pair| hop(pair[1], pair[0])), len)
+        }
+    };
+    let failed_hop = |idx: usize| Some((path[idx - 1], path[idx]));
+    if max_up + max_down < len {
+        (AspaState::Invalid, failed_hop(max_up))
+    }
+    else if min_up + min_down < len {
+        (AspaState::Unknown, failed_hop(min_up))
+    }
+    else {
+        (AspaState::Valid, None)
+    }
+}
+
+/// Determines the minimum and maximum length of a ramp.
+///
+/// The ramp consists of the given hops. If all hops are attested, both
+/// lengths are `len`.
+fn ramp(hops: impl Iterator<Item = Hop>, len: usize) -> (usize, usize) {
+    let mut min = None;
+    for (idx, hop) in hops.enumerate() {
+        match hop {
+            Hop::ProviderPlus => { }
+            Hop::NoAttestation => {
+                if min.is_none() {
+                    min = Some(idx + 1)
+                }
+            }
+            Hop::NotProviderPlus => {
+                return (min.unwrap_or(idx + 1), idx + 1)
+            }
+        }
+    }
+    (min.unwrap_or(len), len)
+}
+
+
 //------------ RequestList ---------------------------------------------------
 
 /// A list of requests for route validity checks.
@@ -521,8 +850,125 @@ const DESCRIPTION_NOT_FOUND: &str = "No VRP Covers the Route Prefix";
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
     use super::*;
 
+    fn asns(path: &[u32]) -> Vec<Asn> {
+        path.iter().copied().map(Asn::from_u32).collect()
+    }
+
+    fn verify(
+        path: &[u32], direction: AspaDirection, aspas: &[(u32, &[u32])]
+    ) -> (AspaState, Option<(Asn, Asn)>) {
+        let aspas: HashMap<_, _> = aspas.iter().map(|(customer, providers)| {
+            (Asn::from_u32(*customer), asns(providers))
+        }).collect();
+        verify_path(&asns(path), direction, |customer, provider| {
+            match aspas.get(&customer) {
+                Some(providers) if providers.contains(&provider) => {
+                    Hop::ProviderPlus
+                }
+                Some(_) => Hop::NotProviderPlus,
+                None => Hop::NoAttestation,
+            }
+        })
+    }
+
+    fn hop(customer: u32, provider: u32) -> Option<(Asn, Asn)> {
+        Some((Asn::from_u32(customer), Asn::from_u32(provider)))
+    }
+
+    #[test]
+    fn verify_upstream() {
+        use AspaDirection::Upstream;
+
+        let aspas: &[(u32, &[u32])] = &[(1, &[2]), (2, &[3])];
+        assert_eq!(
+            verify(&[1, 2, 3], Upstream, aspas), (AspaState::Valid, None)
+        );
+        assert_eq!(verify(&[1], Upstream, &[]), (AspaState::Valid, None));
+        assert_eq!(verify(&[], Upstream, &[]), (AspaState::Invalid, None));
+        assert_eq!(
+            verify(&[1, 2, 3], Upstream, &[(1, &[2])]),
+            (AspaState::Unknown, hop(2, 3))
+        );
+        assert_eq!(
+            verify(&[1, 2, 3], Upstream, &[(1, &[4]), (2, &[3])]),
+            (AspaState::Invalid, hop(1, 2))
+        );
+    }
+
+    #[test]
+    fn verify_downstream() {
+        use AspaDirection::Downstream;
+
+        // Up from 1 to 3, then down to 5.
+        let aspas: &[(u32, &[u32])] = &[
+            (1, &[2]), (2, &[3]), (4, &[3]), (5, &[4])
+        ];
+        assert_eq!(
+            verify(&[1, 2, 3, 4, 5], Downstream, aspas),
+            (AspaState::Valid, None)
+        );
+
+        // The peak doesn’t need to be attested.
+        assert_eq!(
+            verify(
+                &[1, 2, 3, 4, 5], Downstream,
+                &[(1, &[2]), (2, &[3]), (5, &[4])]
+            ),
+            (AspaState::Valid, None)
+        );
+
+        // Same without the ASPA for 5.
+        assert_eq!(
+            verify(
+                &[1, 2, 3, 4, 5], Downstream,
+                &[(1, &[2]), (2, &[3])]
+            ),
+            (AspaState::Unknown, hop(3, 4))
+        );
+
+        // A valley at 3.
+        let aspas: &[(u32, &[u32])] = &[
+            (1, &[2]), (2, &[9]), (4, &[9]), (5, &[4])
+        ];
+        assert_eq!(
+            verify(&[1, 2, 3, 4, 5], Downstream, aspas),
+            (AspaState::Invalid, hop(2, 3))
+        );
+
+        // Short paths are always valid.
+        assert_eq!(
+            verify(&[1, 2], Downstream, &[(1, &[3]), (2, &[3])]),
+            (AspaState::Valid, None)
+        );
+    }
+
+    #[test]
+    fn aspa_path_validity_json() {
+        let snapshot = PayloadSnapshot::default();
+        let validity = AspaPathValidity::new(
+            asns(&[64500, 64501, 64501, 64502]), AspaDirection::Downstream,
+            &snapshot
+        );
+        assert_eq!(validity.state(), AspaState::Unknown);
+        let json: serde_json::Value = serde_json::from_slice(
+            &validity.into_json(&snapshot)
+        ).unwrap();
+        assert_eq!(json["validated_path"]["validity"]["state"], "unknown");
+        assert_eq!(json["validated_path"]["path"][1], "AS64501");
+    }
+
+    #[test]
+    fn as_path_parsing() {
+        assert_eq!(
+            parse_as_path("AS64500 64501,64502").unwrap(),
+            asns(&[64500, 64501, 64502])
+        );
+        assert!(parse_as_path("64500 foo").is_err());
+    }
+
     #[test]
     fn request_list_from_json_reader() {
         let _ = RequestList::from_json_reader(